@@ -0,0 +1,116 @@
+//! Admin handlers for managing API tokens and caches on a running server
+
+use axum::{
+    extract::State,
+    http::StatusCode,
+    response::{IntoResponse, Response},
+    Json,
+};
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::sync::Arc;
+use std::time::Duration;
+use tracing::info;
+
+use super::handlers::{
+    budgeted_request, context_error_response, error_response, validation_error_response, ApiJson, AppState,
+    SearchContextRequest,
+};
+use crate::middleware::{auth::TokenScope, AuthMiddleware, Permission};
+
+/// Request to add an API token
+#[derive(Debug, Deserialize)]
+pub struct AddTokenRequest {
+    pub token: String,
+    #[serde(default)]
+    pub scope: TokenScope,
+    /// Seconds until the token expires (never if unset)
+    #[serde(default)]
+    pub ttl_secs: Option<u64>,
+    /// Operations the token may perform (read and write if unset)
+    #[serde(default)]
+    pub permissions: Option<HashSet<Permission>>,
+}
+
+/// Request to revoke an API token, sent as a body to keep the token out of
+/// URLs and access logs
+#[derive(Debug, Deserialize)]
+pub struct RemoveTokenRequest {
+    pub token: String,
+}
+
+/// Number of tokens currently accepted; tokens themselves are never returned
+#[derive(Debug, Serialize, Deserialize)]
+pub struct TokenCountResponse {
+    pub token_count: usize,
+}
+
+/// Add a token
+pub async fn add_token(
+    State(auth): State<Arc<AuthMiddleware>>,
+    Json(req): Json<AddTokenRequest>,
+) -> Response {
+    if req.token.trim().is_empty() || req.token.chars().any(char::is_whitespace) {
+        return error_response(StatusCode::BAD_REQUEST, "Token must be non-empty and contain no whitespace".to_string());
+    }
+    if req.ttl_secs == Some(0) {
+        return error_response(StatusCode::BAD_REQUEST, "ttl_secs must be greater than 0".to_string());
+    }
+    
+    auth.add_scoped_token(req.token, req.scope, req.ttl_secs.map(Duration::from_secs), req.permissions).await;
+    info!("API token added ({:?} scope)", req.scope);
+    
+    let token_count = auth.token_count().await;
+    (StatusCode::CREATED, Json(TokenCountResponse { token_count })).into_response()
+}
+
+/// Revoke a token
+pub async fn remove_token(
+    State(auth): State<Arc<AuthMiddleware>>,
+    Json(req): Json<RemoveTokenRequest>,
+) -> Response {
+    if auth.remove_token(&req.token).await {
+        info!("API token revoked");
+        StatusCode::NO_CONTENT.into_response()
+    } else {
+        error_response(StatusCode::NOT_FOUND, "Unknown token".to_string())
+    }
+}
+
+/// Report how many tokens are accepted
+pub async fn token_count(State(auth): State<Arc<AuthMiddleware>>) -> Json<TokenCountResponse> {
+    Json(TokenCountResponse {
+        token_count: auth.token_count().await,
+    })
+}
+
+/// Number of contexts evicted by a compaction
+#[derive(Debug, Serialize, Deserialize)]
+pub struct CompactL1Response {
+    pub evicted: usize,
+}
+
+/// Trim the L1 cache to its current size limit and drop expired entries
+pub async fn compact_l1(State(state): State<AppState>) -> Response {
+    match state.context_manager.compact_l1().await {
+        Ok(evicted) => Json(CompactL1Response { evicted }).into_response(),
+        Err(e) => context_error_response(e),
+    }
+}
+
+/// Run a search and explain how each candidate was scored and why it was
+/// returned or left out
+pub async fn explain_retrieval(
+    State(state): State<AppState>,
+    ApiJson(req): ApiJson<SearchContextRequest>,
+) -> Response {
+    let (context_req, _) = match budgeted_request(state.token_budget, req) {
+        Ok(budgeted) => budgeted,
+        Err(e) => return validation_error_response(e.to_string(), &e),
+    };
+    
+    match state.context_manager.explain_retrieval(context_req).await {
+        Ok(explanation) => Json(explanation).into_response(),
+        Err(e) => context_error_response(e),
+    }
+}
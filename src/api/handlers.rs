@@ -1,17 +1,26 @@
 //! API request handlers
 
 use axum::{
-    extract::State,
+    async_trait,
+    extract::{rejection::JsonRejection, FromRequest, Path, Query, Request, State},
     http::StatusCode,
-    response::IntoResponse,
+    response::{
+        sse::{Event, KeepAlive, Sse},
+        IntoResponse, Response,
+    },
     Json,
 };
+use futures::StreamExt;
+use std::convert::Infallible;
 use serde::{Deserialize, Serialize};
 use std::sync::Arc;
 use uuid::Uuid;
 
 use crate::{
-    hirag::{ContextManager, ContextRequest, Priority},
+    config::{Distance, EmptyResultPolicy, ServerConfig, TokenBudgetPolicy},
+    error::{ContextError, HiRAGError},
+    hirag::{Context, ContextManager, ContextRequest, ContextResponse, Priority, SortOrder, StoreOptions},
+    middleware::{ValidationDetail, ValidationError},
     vector_db::{ContextLevel, circuit_breaker::CircuitBreaker},
 };
 
@@ -25,25 +34,52 @@ pub struct AppState {
     pub vector_db: Arc<dyn VectorStore>,
     pub health_checker: Arc<HealthChecker>,
     pub circuit_breaker: Option<Arc<CircuitBreaker>>,
+    pub token_budget: TokenBudget,
+    pub empty_result_policy: EmptyResultPolicy,
+}
+
+/// Server-side cap on the token budget of search requests
+#[derive(Debug, Clone, Copy)]
+pub struct TokenBudget {
+    pub max_tokens: usize,
+    pub policy: TokenBudgetPolicy,
+}
+
+impl TokenBudget {
+    pub fn from_config(config: &ServerConfig) -> Self {
+        Self {
+            max_tokens: config.max_allowed_tokens,
+            policy: config.token_budget_policy,
+        }
+    }
 }
 
 /// Request to store a context
 #[derive(Debug, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
 pub struct StoreContextRequest {
     pub text: String,
     pub level: ContextLevel,
     #[serde(default)]
     pub metadata: std::collections::HashMap<String, serde_json::Value>,
+    /// Agent owning the context (`"default"` if unset)
+    #[serde(default)]
+    pub agent_id: Option<String>,
+    /// Session the context belongs to
+    #[serde(default)]
+    pub session_id: Option<String>,
 }
 
 /// Response from storing a context
 #[derive(Debug, Serialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
 pub struct StoreContextResponse {
     pub id: Uuid,
 }
 
 /// Request to search contexts
 #[derive(Debug, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
 pub struct SearchContextRequest {
     pub query: String,
     pub max_tokens: usize,
@@ -52,98 +88,438 @@ pub struct SearchContextRequest {
     #[serde(default)]
     pub priority: Priority,
     pub session_id: Option<String>,
+    #[serde(default)]
+    pub agent_id: Option<String>,
+    #[serde(default)]
+    pub sort_order: SortOrder,
+    #[serde(default)]
+    pub rescore_metric: Option<Distance>,
+    #[serde(default)]
+    pub include_vectors: bool,
+    /// Overrides the server's `empty_result_policy` for this request
+    #[serde(default)]
+    pub empty_result: Option<EmptyResultPolicy>,
+}
+
+/// Request to search contexts for several queries at once
+#[derive(Debug, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct SearchBatchRequest {
+    pub queries: Vec<SearchContextRequest>,
+}
+
+/// Results of a batch search, in query order
+#[derive(Debug, Serialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct SearchBatchResponse {
+    pub results: Vec<ContextResponse>,
+}
+
+/// Query parameters for listing the most recent contexts
+#[derive(Debug, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::IntoParams))]
+#[cfg_attr(feature = "openapi", into_params(parameter_in = Query))]
+pub struct RecentContextsQuery {
+    pub level: ContextLevel,
+    /// Number of contexts to return (at most 100)
+    #[serde(default = "default_recent_limit")]
+    pub limit: usize,
+    /// Agent the contexts are listed for, checked against their ACLs when ACLs are enforced
+    #[serde(default)]
+    pub agent_id: Option<String>,
+}
+
+/// Query parameters for fetching a context by ID
+#[derive(Debug, Default, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::IntoParams))]
+#[cfg_attr(feature = "openapi", into_params(parameter_in = Query))]
+pub struct GetContextQuery {
+    /// Agent the context is fetched for, checked against its ACL when ACLs are enforced
+    #[serde(default)]
+    pub agent_id: Option<String>,
+}
+
+fn default_recent_limit() -> usize {
+    10
+}
+
+/// Largest `limit` accepted by the recent contexts route
+const MAX_RECENT_LIMIT: usize = 100;
+
+/// Most recent contexts, newest first
+#[derive(Debug, Serialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct RecentContextsResponse {
+    pub contexts: Vec<Context>,
 }
 
 /// Request to delete a context
 #[derive(Debug, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
 pub struct DeleteContextRequest {
     pub id: Uuid,
 }
 
 /// Generic success response
 #[derive(Debug, Serialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
 pub struct SuccessResponse {
     pub message: String,
 }
 
 /// Generic error response
 #[derive(Debug, Serialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
 pub struct ErrorResponse {
     pub error: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub detail: Option<ValidationDetail>,
+}
+
+/// Build a JSON error response
+pub(super) fn error_response(status: StatusCode, error: String) -> Response {
+    (status, Json(ErrorResponse { error, detail: None })).into_response()
+}
+
+/// Build a 400 response carrying the structured validation detail
+pub(super) fn validation_error_response(error: String, validation: &ValidationError) -> Response {
+    (
+        StatusCode::BAD_REQUEST,
+        Json(ErrorResponse {
+            error,
+            detail: Some(validation.to_detail()),
+        }),
+    ).into_response()
+}
+
+/// JSON body extractor that reports malformed bodies as structured 400s
+///
+/// Axum's `Json` rejects bodies that don't match the target type (for example
+/// an unknown `ContextLevel`) with a plain-text 422. This keeps serde's
+/// message, which lists the accepted values, but returns it as an
+/// `ErrorResponse` with code `INVALID_BODY`. Other rejections such as a
+/// missing content type or an oversized body keep their status.
+pub struct ApiJson<T>(pub T);
+
+#[async_trait]
+impl<S, T> FromRequest<S> for ApiJson<T>
+where
+    Json<T>: FromRequest<S, Rejection = JsonRejection>,
+    S: Send + Sync,
+{
+    type Rejection = Response;
+    
+    async fn from_request(req: Request, state: &S) -> Result<Self, Self::Rejection> {
+        match Json::<T>::from_request(req, state).await {
+            Ok(Json(value)) => Ok(ApiJson(value)),
+            Err(rejection @ (JsonRejection::JsonDataError(_) | JsonRejection::JsonSyntaxError(_))) => {
+                let e = ValidationError::InvalidBody(rejection.body_text());
+                Err(validation_error_response(e.to_string(), &e))
+            }
+            Err(rejection) => Err(rejection.into_response()),
+        }
+    }
+}
+
+/// Map a context manager error to an HTTP response
+pub(super) fn context_error_response(e: ContextError) -> Response {
+    match &e {
+        ContextError::Validation(validation) => validation_error_response(e.to_string(), validation),
+        ContextError::RateLimit(_) => error_response(StatusCode::TOO_MANY_REQUESTS, e.to_string()),
+        ContextError::ServiceUnavailable(_) => error_response(StatusCode::SERVICE_UNAVAILABLE, e.to_string()),
+        _ => error_response(StatusCode::INTERNAL_SERVER_ERROR, e.to_string()),
+    }
+}
+
+/// Answer for a search that retrieved nothing under [`EmptyResultPolicy::NotFound`]
+fn no_matches_response() -> Response {
+    error_response(StatusCode::NOT_FOUND, "No matching contexts".to_string())
 }
 
 /// Store a new context
+#[cfg_attr(feature = "openapi", utoipa::path(
+    post,
+    path = "/api/v1/contexts",
+    request_body = StoreContextRequest,
+    responses(
+        (status = 201, description = "Context stored", body = StoreContextResponse),
+        (status = 400, description = "Invalid request", body = ErrorResponse),
+        (status = 429, description = "Rate limited", body = ErrorResponse),
+        (status = 503, description = "Embedding and vector database unavailable", body = ErrorResponse),
+    ),
+    security(("bearer_auth" = [])),
+))]
 pub async fn store_context(
     State(state): State<AppState>,
-    Json(req): Json<StoreContextRequest>,
+    ApiJson(req): ApiJson<StoreContextRequest>,
 ) -> impl IntoResponse {
     // Validate metadata before storing
     use crate::middleware::validator::InputValidator;
     for (key, value) in &req.metadata {
         if let Err(e) = InputValidator::validate_metadata_key(key) {
-            return (
-                StatusCode::BAD_REQUEST,
-                Json(ErrorResponse {
-                    error: format!("Invalid metadata key '{}': {}", key, e),
-                }),
-            ).into_response();
+            return validation_error_response(format!("Invalid metadata key '{}': {}", key, e), &e);
         }
         if let Err(e) = InputValidator::validate_metadata_value(value) {
-            return (
-                StatusCode::BAD_REQUEST,
-                Json(ErrorResponse {
-                    error: format!("Invalid metadata value for key '{}': {}", key, e),
-                }),
-            ).into_response();
+            return validation_error_response(format!("Invalid metadata value for key '{}': {}", key, e), &e);
         }
     }
     
-    match state.context_manager.store_context(&req.text, req.level, req.metadata).await {
+    let options = StoreOptions { agent_id: req.agent_id, session_id: req.session_id };
+    match state.context_manager.store_context_with(&req.text, req.level, req.metadata, options).await {
         Ok(id) => (
             StatusCode::CREATED,
             Json(StoreContextResponse { id }),
         ).into_response(),
-        Err(e) => (
-            StatusCode::INTERNAL_SERVER_ERROR,
-            Json(ErrorResponse {
-                error: e.to_string(),
-            }),
-        ).into_response(),
+        Err(e) => context_error_response(e),
     }
 }
 
-/// Search for contexts
-pub async fn search_contexts(
-    State(state): State<AppState>,
-    Json(req): Json<SearchContextRequest>,
-) -> impl IntoResponse {
+/// Build the retrieval request, enforcing the server-side token budget
+///
+/// Returns the request and the clamped budget if clamping applied.
+pub(super) fn budgeted_request(
+    budget: TokenBudget,
+    req: SearchContextRequest,
+) -> Result<(ContextRequest, Option<usize>), ValidationError> {
+    let mut clamped_max_tokens = None;
+    let max_tokens = if req.max_tokens > budget.max_tokens {
+        match budget.policy {
+            TokenBudgetPolicy::Clamp => {
+                clamped_max_tokens = Some(budget.max_tokens);
+                budget.max_tokens
+            }
+            TokenBudgetPolicy::Reject => {
+                return Err(ValidationError::TokenLimitExceeded {
+                    count: req.max_tokens,
+                    max: budget.max_tokens,
+                });
+            }
+        }
+    } else {
+        req.max_tokens
+    };
+    
     let context_req = ContextRequest {
         query: req.query,
-        max_tokens: req.max_tokens,
+        max_tokens,
         levels: req.levels,
         filters: None,
         priority: req.priority,
         session_id: req.session_id,
+        agent_id: req.agent_id,
+        sort_order: req.sort_order,
+        rescore_metric: req.rescore_metric,
+        include_vectors: req.include_vectors,
     };
+    Ok((context_req, clamped_max_tokens))
+}
 
+/// Search for contexts
+#[cfg_attr(feature = "openapi", utoipa::path(
+    post,
+    path = "/api/v1/contexts/search",
+    request_body = SearchContextRequest,
+    responses(
+        (status = 200, description = "Retrieved contexts", body = ContextResponse),
+        (status = 400, description = "Invalid request", body = ErrorResponse),
+        (status = 404, description = "Nothing matched and the empty-result policy is `not_found`", body = ErrorResponse),
+        (status = 429, description = "Rate limited", body = ErrorResponse),
+        (status = 503, description = "Embedding and vector database unavailable", body = ErrorResponse),
+    ),
+    security(("bearer_auth" = [])),
+))]
+pub async fn search_contexts(
+    State(state): State<AppState>,
+    ApiJson(req): ApiJson<SearchContextRequest>,
+) -> impl IntoResponse {
+    let empty_result = req.empty_result.unwrap_or(state.empty_result_policy);
+    let (context_req, clamped_max_tokens) = match budgeted_request(state.token_budget, req) {
+        Ok(budgeted) => budgeted,
+        Err(e) => return validation_error_response(e.to_string(), &e),
+    };
+    
     match state.context_manager.retrieve_context(context_req).await {
-        Ok(response) => (
-            StatusCode::OK,
-            Json(response),
-        ).into_response(),
-        Err(e) => (
-            StatusCode::INTERNAL_SERVER_ERROR,
-            Json(ErrorResponse {
-                error: e.to_string(),
-            }),
-        ).into_response(),
+        Ok(response) if response.contexts.is_empty() && empty_result == EmptyResultPolicy::NotFound => {
+            no_matches_response()
+        }
+        Ok(mut response) => {
+            response.metadata.clamped_max_tokens = clamped_max_tokens;
+            (StatusCode::OK, Json(response)).into_response()
+        }
+        Err(e) => context_error_response(e),
     }
 }
 
+/// Search for contexts for several queries, embedding them in one call
+///
+/// The token budget applies to each query separately. The batch is a 404 only
+/// when no query matched and every query's empty-result policy is `not_found`.
+#[cfg_attr(feature = "openapi", utoipa::path(
+    post,
+    path = "/api/v1/contexts/search/batch",
+    request_body = SearchBatchRequest,
+    responses(
+        (status = 200, description = "Retrieved contexts per query", body = SearchBatchResponse),
+        (status = 400, description = "Invalid request", body = ErrorResponse),
+        (status = 404, description = "No query matched and every empty-result policy is `not_found`", body = ErrorResponse),
+        (status = 429, description = "Rate limited", body = ErrorResponse),
+        (status = 503, description = "Embedding and vector database unavailable", body = ErrorResponse),
+    ),
+    security(("bearer_auth" = [])),
+))]
+pub async fn search_contexts_batch(
+    State(state): State<AppState>,
+    ApiJson(req): ApiJson<SearchBatchRequest>,
+) -> impl IntoResponse {
+    let mut requests = Vec::with_capacity(req.queries.len());
+    let mut clamped = Vec::with_capacity(req.queries.len());
+    let not_found_when_empty = !req.queries.is_empty() && req.queries.iter().all(|query| {
+        query.empty_result.unwrap_or(state.empty_result_policy) == EmptyResultPolicy::NotFound
+    });
+    for query in req.queries {
+        match budgeted_request(state.token_budget, query) {
+            Ok((context_req, clamped_max_tokens)) => {
+                requests.push(context_req);
+                clamped.push(clamped_max_tokens);
+            }
+            Err(e) => return validation_error_response(e.to_string(), &e),
+        }
+    }
+    
+    match state.context_manager.retrieve_batch(requests).await {
+        Ok(results) if not_found_when_empty && results.iter().all(|response| response.contexts.is_empty()) => {
+            no_matches_response()
+        }
+        Ok(mut results) => {
+            for (response, clamped_max_tokens) in results.iter_mut().zip(clamped) {
+                response.metadata.clamped_max_tokens = clamped_max_tokens;
+            }
+            (StatusCode::OK, Json(SearchBatchResponse { results })).into_response()
+        }
+        Err(e) => context_error_response(e),
+    }
+}
+
+/// Summary sent as the final event of a streamed search
+#[derive(Debug, Serialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct SearchStreamSummary {
+    /// IDs of the contexts in the final response, in response order;
+    /// streamed contexts missing here lost out in deduplication, ranking or
+    /// the token budget
+    pub context_ids: Vec<Uuid>,
+    pub total_contexts: usize,
+    pub total_tokens: usize,
+    pub retrieval_time_ms: u64,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub clamped_max_tokens: Option<usize>,
+}
+
+/// Search for contexts, streaming the results as Server-Sent Events
+///
+/// Each level's candidates are sent as `context` events as soon as that level
+/// has been searched, followed by a single `summary` event naming the contexts
+/// of the final response. Errors before any level returns candidates are
+/// regular JSON error responses, as is an empty result under the `not_found`
+/// policy; later errors end the stream with an `error` event.
+#[cfg_attr(feature = "openapi", utoipa::path(
+    post,
+    path = "/api/v1/contexts/search/stream",
+    request_body = SearchContextRequest,
+    responses(
+        (status = 200, description = "`context` events (Context) followed by one `summary` event (SearchStreamSummary)", content_type = "text/event-stream"),
+        (status = 400, description = "Invalid request", body = ErrorResponse),
+        (status = 404, description = "Nothing matched and the empty-result policy is `not_found`", body = ErrorResponse),
+    ),
+    security(("bearer_auth" = [])),
+))]
+pub async fn search_contexts_stream(
+    State(state): State<AppState>,
+    ApiJson(req): ApiJson<SearchContextRequest>,
+) -> Response {
+    let empty_result = req.empty_result.unwrap_or(state.empty_result_policy);
+    let (context_req, clamped_max_tokens) = match budgeted_request(state.token_budget, req) {
+        Ok(budgeted) => budgeted,
+        Err(e) => return validation_error_response(e.to_string(), &e),
+    };
+    
+    // One slot per level, so no level waits on a slow client
+    let (candidates, mut received) = tokio::sync::mpsc::channel(3);
+    let manager = state.context_manager.clone();
+    let retrieval = tokio::spawn(async move {
+        manager.retrieve_context_streaming(context_req, candidates).await
+    });
+    let finished = |joined: std::result::Result<_, tokio::task::JoinError>| {
+        joined.unwrap_or_else(|e| Err(HiRAGError::RetrievalError(format!("Retrieval task failed: {}", e)).into()))
+    };
+    
+    // Retrieval that ends before sending anything may still be a plain error
+    let first = match received.recv().await {
+        Some(first) => first,
+        None => match finished(retrieval.await) {
+            Ok(response) if response.contexts.is_empty() && empty_result == EmptyResultPolicy::NotFound => {
+                return no_matches_response();
+            }
+            Ok(response) => return stream_summary(response, clamped_max_tokens).into_response(),
+            Err(e) => return context_error_response(e),
+        },
+    };
+    
+    let candidates = futures::stream::iter(std::iter::once(first))
+        .chain(futures::stream::unfold(received, |mut received| async move {
+            received.recv().await.map(|batch| (batch, received))
+        }))
+        .flat_map(futures::stream::iter)
+        .map(|context| Event::default().event("context").json_data(context));
+    let end = futures::stream::once(async move {
+        match finished(retrieval.await) {
+            Ok(response) => Event::default().event("summary").json_data(summary_of(response, clamped_max_tokens)),
+            Err(e) => Ok(Event::default().event("error").data(e.to_string())),
+        }
+    });
+    let events = candidates.chain(end).map(|event| Ok::<_, Infallible>(event.unwrap_or_else(|e| {
+        Event::default().event("error").data(e.to_string())
+    })));
+    
+    Sse::new(events)
+        .keep_alive(KeepAlive::default())
+        .into_response()
+}
+
+/// Final event of a streamed search
+fn summary_of(response: ContextResponse, clamped_max_tokens: Option<usize>) -> SearchStreamSummary {
+    SearchStreamSummary {
+        context_ids: response.contexts.iter().map(|context| context.id).collect(),
+        total_contexts: response.contexts.len(),
+        total_tokens: response.total_tokens,
+        retrieval_time_ms: response.retrieval_time_ms,
+        clamped_max_tokens,
+    }
+}
+
+/// Stream holding only the summary, for searches without candidates
+fn stream_summary(response: ContextResponse, clamped_max_tokens: Option<usize>) -> Sse<impl futures::Stream<Item = std::result::Result<Event, Infallible>>> {
+    let event = Event::default()
+        .event("summary")
+        .json_data(summary_of(response, clamped_max_tokens))
+        .unwrap_or_else(|e| Event::default().event("error").data(e.to_string()));
+    Sse::new(futures::stream::iter([Ok(event)])).keep_alive(KeepAlive::default())
+}
+
 /// Delete a context
+#[cfg_attr(feature = "openapi", utoipa::path(
+    post,
+    path = "/api/v1/contexts/delete",
+    request_body = DeleteContextRequest,
+    responses(
+        (status = 200, description = "Context deleted", body = SuccessResponse),
+        (status = 500, description = "Deletion failed", body = ErrorResponse),
+    ),
+    security(("bearer_auth" = [])),
+))]
 pub async fn delete_context(
     State(state): State<AppState>,
-    Json(req): Json<DeleteContextRequest>,
+    ApiJson(req): ApiJson<DeleteContextRequest>,
 ) -> impl IntoResponse {
     match state.context_manager.delete_context(req.id).await {
         Ok(_) => (
@@ -152,19 +528,73 @@ pub async fn delete_context(
                 message: format!("Context {} deleted", req.id),
             }),
         ).into_response(),
-        Err(e) => (
-            StatusCode::INTERNAL_SERVER_ERROR,
-            Json(ErrorResponse {
-                error: e.to_string(),
-            }),
-        ).into_response(),
+        Err(e) => context_error_response(e),
+    }
+}
+
+/// Fetch a single context by ID
+#[cfg_attr(feature = "openapi", utoipa::path(
+    get,
+    path = "/api/v1/contexts/{id}",
+    params(("id" = Uuid, Path, description = "Context ID"), GetContextQuery),
+    responses(
+        (status = 200, description = "The context", body = Context),
+        (status = 400, description = "Invalid context ID"),
+        (status = 404, description = "No context with this ID visible to the agent", body = ErrorResponse),
+    ),
+    security(("bearer_auth" = [])),
+))]
+pub async fn get_context(
+    State(state): State<AppState>,
+    Path(id): Path<Uuid>,
+    Query(query): Query<GetContextQuery>,
+) -> impl IntoResponse {
+    match state.context_manager.get_context(id, query.agent_id.as_deref()).await {
+        Ok(Some(context)) => (StatusCode::OK, Json(context)).into_response(),
+        Ok(None) => error_response(StatusCode::NOT_FOUND, format!("Context {} not found", id)),
+        Err(e) => context_error_response(e),
+    }
+}
+
+/// List the most recent contexts in a level without a query
+///
+/// `limit` is clamped to 100.
+#[cfg_attr(feature = "openapi", utoipa::path(
+    get,
+    path = "/api/v1/contexts/recent",
+    params(RecentContextsQuery),
+    responses(
+        (status = 200, description = "Most recent contexts, newest first", body = RecentContextsResponse),
+        (status = 400, description = "Invalid query parameters"),
+        (status = 429, description = "Rate limited", body = ErrorResponse),
+    ),
+    security(("bearer_auth" = [])),
+))]
+pub async fn recent_contexts(
+    State(state): State<AppState>,
+    Query(query): Query<RecentContextsQuery>,
+) -> impl IntoResponse {
+    let limit = query.limit.min(MAX_RECENT_LIMIT);
+    match state.context_manager.recent(query.level, limit, query.agent_id.as_deref()).await {
+        Ok(contexts) => (StatusCode::OK, Json(RecentContextsResponse { contexts })).into_response(),
+        Err(e) => context_error_response(e),
     }
 }
 
 /// Clear contexts by level
+#[cfg_attr(feature = "openapi", utoipa::path(
+    post,
+    path = "/api/v1/contexts/clear",
+    request_body = ContextLevel,
+    responses(
+        (status = 200, description = "Level cleared", body = SuccessResponse),
+        (status = 500, description = "Clearing failed", body = ErrorResponse),
+    ),
+    security(("bearer_auth" = [])),
+))]
 pub async fn clear_level(
     State(state): State<AppState>,
-    Json(level): Json<ContextLevel>,
+    ApiJson(level): ApiJson<ContextLevel>,
 ) -> impl IntoResponse {
     match state.context_manager.clear_level(level).await {
         Ok(_) => (
@@ -173,11 +603,351 @@ pub async fn clear_level(
                 message: format!("Level {:?} cleared", level),
             }),
         ).into_response(),
-        Err(e) => (
-            StatusCode::INTERNAL_SERVER_ERROR,
-            Json(ErrorResponse {
-                error: e.to_string(),
-            }),
-        ).into_response(),
+        Err(e) => context_error_response(e),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::{test_app_state, test_manager};
+    use std::collections::HashMap;
+    
+    async fn body_json(response: Response) -> serde_json::Value {
+        let bytes = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        serde_json::from_slice(&bytes).unwrap()
+    }
+    
+    #[tokio::test]
+    async fn test_store_rejects_long_metadata_key_with_detail() {
+        let mut metadata = HashMap::new();
+        metadata.insert("k".repeat(300), serde_json::json!("value"));
+        let req = StoreContextRequest {
+            text: "hello".to_string(),
+            level: ContextLevel::ShortTerm,
+            metadata,
+            agent_id: None,
+            session_id: None,
+        };
+        
+        let response = store_context(State(test_app_state().await), ApiJson(req)).await.into_response();
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+        
+        let body = body_json(response).await;
+        assert_eq!(body["detail"]["code"], "METADATA_KEY_TOO_LONG");
+        assert_eq!(body["detail"]["field"], "metadata.key");
+        assert_eq!(body["detail"]["max"], 256);
     }
-}
\ No newline at end of file
+    
+    #[tokio::test]
+    async fn test_store_rejects_empty_text_with_detail() {
+        let req = StoreContextRequest {
+            text: "   ".to_string(),
+            level: ContextLevel::ShortTerm,
+            metadata: HashMap::new(),
+            agent_id: None,
+            session_id: None,
+        };
+        
+        let response = store_context(State(test_app_state().await), ApiJson(req)).await.into_response();
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+        
+        let body = body_json(response).await;
+        assert_eq!(body["detail"]["code"], "EMPTY_INPUT");
+        assert_eq!(body["detail"]["field"], "text");
+        assert!(body["detail"].get("max").is_none());
+    }
+    
+    async fn budget_state(policy: TokenBudgetPolicy) -> AppState {
+        let (manager, _, _) = test_manager().await;
+        manager.store_context("dark mode preference", ContextLevel::ShortTerm, HashMap::new()).await.unwrap();
+        AppState {
+            context_manager: manager,
+            token_budget: TokenBudget { max_tokens: 1000, policy },
+            ..test_app_state().await
+        }
+    }
+    
+    fn search_request(max_tokens: usize) -> SearchContextRequest {
+        SearchContextRequest {
+            query: "dark mode".to_string(),
+            max_tokens,
+            levels: Vec::new(),
+            priority: Priority::Normal,
+            session_id: None,
+            agent_id: None,
+            sort_order: SortOrder::Relevance,
+            rescore_metric: None,
+            include_vectors: false,
+            empty_result: None,
+        }
+    }
+    
+    #[tokio::test]
+    async fn test_recent_returns_newest_first() {
+        let (manager, store, _) = test_manager().await;
+        let mut ids = Vec::new();
+        for text in ["first", "second", "third"] {
+            ids.push(manager.store_context(text, ContextLevel::ShortTerm, HashMap::new()).await.unwrap());
+        }
+        // Spread the timestamps so the order doesn't depend on clock resolution
+        let collection = crate::vector_db::CollectionNaming::default().collection(ContextLevel::ShortTerm);
+        for (offset, id) in ids.iter().enumerate() {
+            let mut point = store.point(&collection, *id).unwrap();
+            point.payload.timestamp = 1_000 + offset as i64;
+            store.insert_points(&collection, vec![point]).await.unwrap();
+        }
+        let state = AppState {
+            context_manager: manager,
+            ..test_app_state().await
+        };
+        
+        let query = RecentContextsQuery { level: ContextLevel::ShortTerm, limit: 2, agent_id: None };
+        let response = recent_contexts(State(state), Query(query)).await.into_response();
+        assert_eq!(response.status(), StatusCode::OK);
+        
+        let body = body_json(response).await;
+        let texts: Vec<_> = body["contexts"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|c| c["text"].as_str().unwrap().to_string())
+            .collect();
+        assert_eq!(texts, vec!["third", "second"]);
+    }
+    
+    #[tokio::test]
+    async fn test_store_records_agent_and_session() {
+        let (manager, store, _) = test_manager().await;
+        let state = AppState {
+            context_manager: manager,
+            ..test_app_state().await
+        };
+        let req: StoreContextRequest = serde_json::from_value(serde_json::json!({
+            "text": "owned note",
+            "level": "ShortTerm",
+            "agent_id": "agent-7",
+            "session_id": "session-42",
+        })).unwrap();
+        
+        let response = store_context(State(state), ApiJson(req)).await.into_response();
+        assert_eq!(response.status(), StatusCode::CREATED);
+        let id: Uuid = serde_json::from_value(body_json(response).await["id"].clone()).unwrap();
+        
+        let collection = crate::vector_db::CollectionNaming::default().collection(ContextLevel::ShortTerm);
+        let payload = store.point(&collection, id).unwrap().payload;
+        assert_eq!(payload.agent_id, "agent-7");
+        assert_eq!(payload.session_id.as_deref(), Some("session-42"));
+    }
+    
+    #[tokio::test]
+    async fn test_get_context_by_id() {
+        let (manager, _, _) = test_manager().await;
+        let id = manager.store_context("fetched", ContextLevel::ShortTerm, HashMap::new()).await.unwrap();
+        let state = AppState {
+            context_manager: manager,
+            ..test_app_state().await
+        };
+        
+        let response = get_context(State(state.clone()), Path(id), Query(GetContextQuery::default())).await.into_response();
+        assert_eq!(response.status(), StatusCode::OK);
+        let body = body_json(response).await;
+        assert_eq!(body["id"], id.to_string());
+        assert_eq!(body["text"], "fetched");
+        assert!(body["token_count"].as_u64().unwrap() > 0);
+        
+        let response = get_context(State(state), Path(Uuid::new_v4()), Query(GetContextQuery::default())).await.into_response();
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+    }
+    
+    #[tokio::test]
+    async fn test_empty_search_returns_200_by_default() {
+        let response = search_contexts(State(test_app_state().await), ApiJson(search_request(1000)))
+            .await
+            .into_response();
+        assert_eq!(response.status(), StatusCode::OK);
+        
+        let body = body_json(response).await;
+        assert!(body["contexts"].as_array().unwrap().is_empty());
+    }
+    
+    #[tokio::test]
+    async fn test_empty_search_returns_404_when_configured() {
+        let state = AppState {
+            empty_result_policy: EmptyResultPolicy::NotFound,
+            ..test_app_state().await
+        };
+        let response = search_contexts(State(state.clone()), ApiJson(search_request(1000)))
+            .await
+            .into_response();
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+        assert_eq!(body_json(response).await["error"], "No matching contexts");
+        
+        // The request can opt back into an empty 200
+        let req = SearchContextRequest {
+            empty_result: Some(EmptyResultPolicy::Empty),
+            ..search_request(1000)
+        };
+        let response = search_contexts(State(state), ApiJson(req)).await.into_response();
+        assert_eq!(response.status(), StatusCode::OK);
+    }
+    
+    #[tokio::test]
+    async fn test_empty_batch_and_stream_searches_follow_the_policy() {
+        let state = AppState {
+            empty_result_policy: EmptyResultPolicy::NotFound,
+            ..test_app_state().await
+        };
+        
+        let req = SearchBatchRequest { queries: vec![search_request(1000), search_request(500)] };
+        let response = search_contexts_batch(State(state.clone()), ApiJson(req)).await.into_response();
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+        assert_eq!(body_json(response).await["error"], "No matching contexts");
+        
+        // One query opting into an empty 200 keeps the batch a 200
+        let opted_in = SearchContextRequest {
+            empty_result: Some(EmptyResultPolicy::Empty),
+            ..search_request(500)
+        };
+        let req = SearchBatchRequest { queries: vec![search_request(1000), opted_in] };
+        let response = search_contexts_batch(State(state.clone()), ApiJson(req)).await.into_response();
+        assert_eq!(response.status(), StatusCode::OK);
+        
+        let response = search_contexts_stream(State(state), ApiJson(search_request(1000))).await;
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+        assert_eq!(body_json(response).await["error"], "No matching contexts");
+    }
+    
+    #[tokio::test]
+    async fn test_search_rejects_empty_query_with_detail() {
+        let req = SearchContextRequest {
+            query: "  ".to_string(),
+            ..search_request(1000)
+        };
+        
+        let response = search_contexts(State(test_app_state().await), ApiJson(req)).await.into_response();
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+        
+        let body = body_json(response).await;
+        assert_eq!(body["error"], "Validation error: Search query is empty");
+        assert_eq!(body["detail"]["code"], "EMPTY_QUERY");
+        assert_eq!(body["detail"]["field"], "query");
+    }
+    
+    #[tokio::test]
+    async fn test_search_batch_returns_results_in_query_order() {
+        let state = budget_state(TokenBudgetPolicy::Clamp).await;
+        let mut other = search_request(500);
+        other.query = "something unrelated".to_string();
+        let req = SearchBatchRequest { queries: vec![search_request(50_000), other] };
+        
+        let response = search_contexts_batch(State(state), ApiJson(req)).await.into_response();
+        assert_eq!(response.status(), StatusCode::OK);
+        
+        let body = body_json(response).await;
+        let results = body["results"].as_array().unwrap();
+        assert_eq!(results.len(), 2);
+        assert_eq!(results[0]["metadata"]["clamped_max_tokens"], 1000);
+        assert!(results[1]["metadata"].get("clamped_max_tokens").is_none());
+    }
+    
+    #[tokio::test]
+    async fn test_search_clamps_token_budget() {
+        let state = budget_state(TokenBudgetPolicy::Clamp).await;
+        
+        let response = search_contexts(State(state.clone()), ApiJson(search_request(50_000))).await.into_response();
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(body_json(response).await["metadata"]["clamped_max_tokens"], 1000);
+        
+        let response = search_contexts(State(state), ApiJson(search_request(500))).await.into_response();
+        assert_eq!(response.status(), StatusCode::OK);
+        assert!(body_json(response).await["metadata"].get("clamped_max_tokens").is_none());
+    }
+    
+    #[tokio::test]
+    async fn test_search_rejects_token_budget() {
+        let state = budget_state(TokenBudgetPolicy::Reject).await;
+        
+        let response = search_contexts(State(state), ApiJson(search_request(50_000))).await.into_response();
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+        
+        let body = body_json(response).await;
+        assert_eq!(body["detail"]["code"], "TOKEN_LIMIT_EXCEEDED");
+        assert_eq!(body["detail"]["max"], 1000);
+    }
+    
+    #[tokio::test]
+    async fn test_search_stream_emits_contexts_then_summary() {
+        let state = budget_state(TokenBudgetPolicy::Clamp).await;
+        state.context_manager
+            .store_context("dark theme in the editor", ContextLevel::ShortTerm, HashMap::new())
+            .await
+            .unwrap();
+        
+        let response = search_contexts_stream(State(state), ApiJson(search_request(50_000))).await;
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(response.headers()["content-type"], "text/event-stream");
+        
+        let bytes = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        let body = String::from_utf8(bytes.to_vec()).unwrap();
+        let events: Vec<(&str, serde_json::Value)> = body
+            .split("\n\n")
+            .filter_map(|frame| {
+                let event = frame.lines().find_map(|l| l.strip_prefix("event: "))?;
+                let data = frame.lines().find_map(|l| l.strip_prefix("data: "))?;
+                Some((event, serde_json::from_str(data).unwrap()))
+            })
+            .collect();
+        
+        let (last, contexts) = events.split_last().unwrap();
+        assert_eq!(contexts.len(), 2);
+        assert!(contexts.iter().all(|(event, data)| *event == "context" && data["text"].is_string()));
+        assert_eq!(last.0, "summary");
+        assert_eq!(last.1["total_contexts"], 2);
+        let streamed: Vec<_> = contexts.iter().map(|(_, data)| data["id"].clone()).collect();
+        assert_eq!(last.1["context_ids"].as_array().unwrap().len(), 2);
+        assert!(last.1["context_ids"].as_array().unwrap().iter().all(|id| streamed.contains(id)));
+        assert_eq!(last.1["clamped_max_tokens"], 1000);
+    }
+    
+    #[tokio::test]
+    async fn test_search_stream_rejects_before_streaming() {
+        let state = budget_state(TokenBudgetPolicy::Reject).await;
+        
+        let response = search_contexts_stream(State(state), ApiJson(search_request(50_000))).await;
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+        assert_eq!(body_json(response).await["detail"]["code"], "TOKEN_LIMIT_EXCEEDED");
+    }
+    
+    async fn post_json(router: axum::Router, uri: &str, body: &str) -> Response {
+        use tower::ServiceExt;
+        let request = axum::http::Request::post(uri)
+            .header("content-type", "application/json")
+            .body(axum::body::Body::from(body.to_string()))
+            .unwrap();
+        router.oneshot(request).await.unwrap()
+    }
+    
+    #[tokio::test]
+    async fn test_invalid_level_is_rejected_with_valid_options() {
+        let router = axum::Router::new()
+            .route("/clear", axum::routing::post(clear_level))
+            .route("/store", axum::routing::post(store_context))
+            .with_state(test_app_state().await);
+        
+        let response = post_json(router.clone(), "/clear", r#""Forever""#).await;
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+        let body = body_json(response).await;
+        assert_eq!(body["detail"]["code"], "INVALID_BODY");
+        let error = body["error"].as_str().unwrap();
+        assert!(error.contains("Forever"));
+        for level in ["Immediate", "ShortTerm", "LongTerm"] {
+            assert!(error.contains(level), "missing {} in {}", level, error);
+        }
+        
+        let response = post_json(router, "/store", r#"{"text": "hello", "level": "L4"}"#).await;
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+        let body = body_json(response).await;
+        assert!(body["error"].as_str().unwrap().contains("ShortTerm"));
+    }
+}
@@ -1,7 +1,10 @@
 //! API handlers for context management
 
+pub mod admin;
 pub mod handlers;
 pub mod routes;
+#[cfg(feature = "openapi")]
+pub mod openapi;
 
 pub use handlers::*;
 pub use routes::build_router;
\ No newline at end of file
@@ -0,0 +1,64 @@
+//! OpenAPI description of the HTTP API
+
+use axum::Json;
+use utoipa::openapi::security::{HttpAuthScheme, HttpBuilder, SecurityScheme};
+use utoipa::{Modify, OpenApi};
+
+use super::handlers::{
+    DeleteContextRequest, ErrorResponse, RecentContextsResponse, SearchBatchRequest, SearchBatchResponse,
+    SearchContextRequest, SearchStreamSummary, StoreContextRequest, StoreContextResponse,
+    SuccessResponse,
+};
+use crate::config::{Distance, EmptyResultPolicy};
+use crate::hirag::{models::ResponseMetadata, Context, ContextResponse, Priority, SortOrder};
+use crate::middleware::ValidationDetail;
+use crate::observability::{ComponentHealth, HealthStatus, SystemHealth};
+use crate::vector_db::ContextLevel;
+
+/// OpenAPI 3 document for the public and `/api/v1` routes
+#[derive(OpenApi)]
+#[openapi(
+    paths(
+        super::handlers::store_context,
+        super::handlers::search_contexts,
+        super::handlers::search_contexts_stream,
+        super::handlers::search_contexts_batch,
+        super::handlers::recent_contexts,
+        super::handlers::get_context,
+        super::handlers::delete_context,
+        super::handlers::clear_level,
+        super::routes::health_handler,
+        super::routes::liveness_handler,
+        super::routes::readiness_handler,
+        super::routes::metrics_handler,
+    ),
+    components(schemas(
+        StoreContextRequest, StoreContextResponse, SearchContextRequest, SearchBatchRequest,
+        SearchBatchResponse, RecentContextsResponse, DeleteContextRequest,
+        SuccessResponse, ErrorResponse, SearchStreamSummary, ValidationDetail,
+        Context, ContextResponse, ResponseMetadata, ContextLevel, Priority, SortOrder, Distance,
+        EmptyResultPolicy,
+        SystemHealth, ComponentHealth, HealthStatus,
+    )),
+    modifiers(&BearerAuth),
+)]
+pub struct ApiDoc;
+
+/// Registers the bearer token scheme referenced by the `/api/v1` routes
+struct BearerAuth;
+
+impl Modify for BearerAuth {
+    fn modify(&self, openapi: &mut utoipa::openapi::OpenApi) {
+        if let Some(components) = openapi.components.as_mut() {
+            components.add_security_scheme(
+                "bearer_auth",
+                SecurityScheme::Http(HttpBuilder::new().scheme(HttpAuthScheme::Bearer).build()),
+            );
+        }
+    }
+}
+
+/// Serve the OpenAPI document
+pub async fn openapi_json() -> Json<utoipa::openapi::OpenApi> {
+    Json(ApiDoc::openapi())
+}
@@ -10,14 +10,15 @@ use tower_http::trace::TraceLayer;
 
 use crate::{
     middleware::{
-        auth::AuthMiddleware,
-        rate_limiter::RateLimiter,
+        auth::{AuthMiddleware, Permission, Permissions, TokenScope},
+        rate_limiter::{RateLimitError, RateLimiter},
         BodyLimiter,
     },
     observability::{HealthChecker, MetricsCollector},
 };
 use tower_http::limit::RequestBodyLimitLayer;
 
+use super::admin;
 use super::handlers::{self, AppState};
 
 
@@ -39,30 +40,81 @@ pub fn build_router(
         .route("/health/ready", get(readiness_handler))
         .route("/metrics", get(metrics_handler))
         .with_state((app_state.clone(), metrics.clone()));
+    
+    #[cfg(feature = "openapi")]
+    let public_routes = public_routes.route("/openapi.json", get(super::openapi::openapi_json));
 
-    // Protected API routes (with auth + rate limiting + body size limit)
-    let api_routes = Router::new()
-        .route("/api/v1/contexts", post(handlers::store_context))
+    // Protected API routes (with auth + rate limiting + body size limit), each
+    // requiring the token to hold a permission
+    let read_routes = Router::new()
+        .route("/api/v1/contexts/recent", get(handlers::recent_contexts))
+        .route("/api/v1/contexts/:id", get(handlers::get_context))
         .route("/api/v1/contexts/search", post(handlers::search_contexts))
+        .route("/api/v1/contexts/search/stream", post(handlers::search_contexts_stream))
+        .route("/api/v1/contexts/search/batch", post(handlers::search_contexts_batch))
+        .route_layer(axum::middleware::from_fn_with_state(Permission::Read, require_scope));
+    let write_routes = Router::new()
+        .route("/api/v1/contexts", post(handlers::store_context))
+        .route_layer(axum::middleware::from_fn_with_state(Permission::Write, require_scope));
+    let destructive_routes = Router::new()
         .route("/api/v1/contexts/delete", post(handlers::delete_context))
         .route("/api/v1/contexts/clear", post(handlers::clear_level))
+        .route_layer(axum::middleware::from_fn_with_state(Permission::Admin, require_scope));
+    let api_routes = read_routes
+        .merge(write_routes)
+        .merge(destructive_routes)
         .layer(RequestBodyLimitLayer::new(body_limiter.max_body_size()))
         .layer(
             ServiceBuilder::new()
+                .layer(axum::middleware::from_fn_with_state(
+                    metrics.clone(),
+                    connection_tracking_middleware,
+                ))
                 .layer(TraceLayer::new_for_http())
                 .layer(axum::middleware::from_fn_with_state(
-                    rate_limiter,
+                    rate_limiter.clone(),
                     rate_limit_middleware,
                 ))
                 .layer(axum::middleware::from_fn_with_state(
-                    auth_middleware,
+                    auth_middleware.clone(),
                     auth_middleware_fn,
                 ))
         )
-        .with_state(app_state);
+        .with_state(app_state.clone());
+
+    // Admin routes for rotating tokens, compacting caches and explaining
+    // retrievals (admin-scoped auth + rate limiting)
+    let admin_routes = Router::new()
+        .route(
+            "/admin/tokens",
+            get(admin::token_count).post(admin::add_token).delete(admin::remove_token),
+        )
+        .with_state(auth_middleware.clone())
+        .merge(
+            Router::new()
+                .route("/admin/l1/compact", post(admin::compact_l1))
+                .route("/api/v1/contexts/explain", post(admin::explain_retrieval))
+                .with_state(app_state),
+        )
+        .layer(
+            ServiceBuilder::new()
+                .layer(axum::middleware::from_fn_with_state(
+                    metrics,
+                    connection_tracking_middleware,
+                ))
+                .layer(TraceLayer::new_for_http())
+                .layer(axum::middleware::from_fn_with_state(
+                    rate_limiter,
+                    rate_limit_middleware,
+                ))
+                .layer(axum::middleware::from_fn_with_state(
+                    auth_middleware.clone(),
+                    admin_auth_middleware_fn,
+                ))
+        );
 
     // Combine routes
-    public_routes.merge(api_routes)
+    public_routes.merge(api_routes).merge(admin_routes)
 }
 
 /// Root handler
@@ -76,6 +128,14 @@ async fn root_handler() -> impl axum::response::IntoResponse {
 }
 
 /// Health check handler
+#[cfg_attr(feature = "openapi", utoipa::path(
+    get,
+    path = "/health",
+    responses(
+        (status = 200, description = "Healthy or degraded", body = crate::observability::SystemHealth),
+        (status = 503, description = "Unhealthy", body = crate::observability::SystemHealth),
+    ),
+))]
 async fn health_handler(
     axum::extract::State((app_state, _)): axum::extract::State<(AppState, Arc<MetricsCollector>)>,
 ) -> impl axum::response::IntoResponse {
@@ -92,6 +152,11 @@ async fn health_handler(
 }
 
 /// Liveness probe handler - always returns 200
+#[cfg_attr(feature = "openapi", utoipa::path(
+    get,
+    path = "/health/live",
+    responses((status = 200, description = "Process is alive")),
+))]
 async fn liveness_handler() -> impl axum::response::IntoResponse {
     use axum::{http::StatusCode, Json};
     use serde_json::json;
@@ -100,6 +165,14 @@ async fn liveness_handler() -> impl axum::response::IntoResponse {
 }
 
 /// Readiness probe handler - checks if service is ready to serve traffic
+#[cfg_attr(feature = "openapi", utoipa::path(
+    get,
+    path = "/health/ready",
+    responses(
+        (status = 200, description = "Ready to serve traffic"),
+        (status = 503, description = "Not ready"),
+    ),
+))]
 async fn readiness_handler(
     axum::extract::State((app_state, _)): axum::extract::State<(AppState, Arc<MetricsCollector>)>,
 ) -> impl axum::response::IntoResponse {
@@ -122,6 +195,11 @@ async fn readiness_handler(
 }
 
 /// Metrics handler
+#[cfg_attr(feature = "openapi", utoipa::path(
+    get,
+    path = "/metrics",
+    responses((status = 200, description = "Prometheus text exposition", body = String, content_type = "text/plain")),
+))]
 async fn metrics_handler(
     axum::extract::State((app_state, metrics)): axum::extract::State<(AppState, Arc<MetricsCollector>)>,
 ) -> impl axum::response::IntoResponse {
@@ -139,11 +217,17 @@ async fn metrics_handler(
 }
 
 /// Rate limiting middleware
+///
+/// Every response carries `X-RateLimit-Limit`, `X-RateLimit-Remaining` and
+/// `Retry-After` (whole seconds until another request would be allowed) so
+/// clients can pace themselves before they are throttled.
 async fn rate_limit_middleware(
     axum::extract::State(rate_limiter): axum::extract::State<Arc<RateLimiter>>,
     req: axum::http::Request<axum::body::Body>,
     next: axum::middleware::Next,
-) -> Result<axum::response::Response, axum::http::StatusCode> {
+) -> axum::response::Response {
+    use axum::response::IntoResponse;
+    
     // Extract client ID from IP or header
     let client_id = req
         .headers()
@@ -158,18 +242,57 @@ async fn rate_limit_middleware(
         .unwrap_or_else(|| "unknown".to_string());
 
     match rate_limiter.check_rate_limit(&client_id).await {
-        Ok(_) => Ok(next.run(req).await),
-        Err(e) => {
+        Ok(_) => {
+            let usage = rate_limiter.get_usage(&client_id).await;
+            let retry_after = rate_limiter.retry_after(&client_id).await;
+            let mut response = next.run(req).await;
+            // Nothing is tracked for clients the limiter doesn't apply to
+            if let Some((count, _)) = usage {
+                let limit = rate_limiter.client_limit(&client_id).max_requests;
+                set_rate_limit_headers(response.headers_mut(), limit, limit.saturating_sub(count), retry_after);
+            }
+            response
+        }
+        Err(e @ RateLimitError::LimitExceeded { retry_after, limit }) => {
             tracing::warn!("Rate limit exceeded for {}: {}", client_id, e);
-            Err(axum::http::StatusCode::TOO_MANY_REQUESTS)
+            let mut response = axum::http::StatusCode::TOO_MANY_REQUESTS.into_response();
+            set_rate_limit_headers(response.headers_mut(), limit, 0, retry_after);
+            response
         }
     }
 }
 
+fn set_rate_limit_headers(
+    headers: &mut axum::http::HeaderMap,
+    limit: usize,
+    remaining: usize,
+    retry_after: std::time::Duration,
+) {
+    // Round up so clients never retry before the limiter would allow it
+    let retry_after_secs = retry_after.as_secs() + u64::from(retry_after.subsec_nanos() > 0);
+    headers.insert("x-ratelimit-limit", limit.into());
+    headers.insert("x-ratelimit-remaining", remaining.into());
+    headers.insert(axum::http::header::RETRY_AFTER, retry_after_secs.into());
+}
+
+/// Count the request as an active connection until it completes or is dropped
+async fn connection_tracking_middleware(
+    axum::extract::State(metrics): axum::extract::State<Arc<MetricsCollector>>,
+    req: axum::http::Request<axum::body::Body>,
+    next: axum::middleware::Next,
+) -> axum::response::Response {
+    let _connection = metrics.track_connection();
+    next.run(req).await
+}
+
 /// Authentication middleware
+///
+/// The token's [`Permissions`] and, for a verified JWT, its
+/// [`AuthClaims`](crate::middleware::AuthClaims) are added to the request
+/// extensions.
 async fn auth_middleware_fn(
     axum::extract::State(auth): axum::extract::State<Arc<AuthMiddleware>>,
-    req: axum::http::Request<axum::body::Body>,
+    mut req: axum::http::Request<axum::body::Body>,
     next: axum::middleware::Next,
 ) -> Result<axum::response::Response, axum::http::StatusCode> {
     // Extract token from Authorization header
@@ -180,17 +303,324 @@ async fn auth_middleware_fn(
         .and_then(|s| s.strip_prefix("Bearer "));
 
     match token {
-        Some(token) => {
-            if auth.validate_token(token) {
+        Some(token) => match auth.verify_token(token, TokenScope::Api).await {
+            Ok(claims) => {
+                if let Some(permissions) = auth.permissions(token, claims.as_ref()).await {
+                    req.extensions_mut().insert(permissions);
+                }
+                if let Some(claims) = claims {
+                    req.extensions_mut().insert(claims);
+                }
                 Ok(next.run(req).await)
-            } else {
-                tracing::warn!("Invalid authentication token");
+            }
+            Err(e) => {
+                tracing::warn!("Invalid authentication token: {}", e);
                 Err(axum::http::StatusCode::UNAUTHORIZED)
             }
+        },
+        None => {
+            auth.reject_missing_token();
+            Err(axum::http::StatusCode::UNAUTHORIZED)
         }
+    }
+}
+
+/// Reject requests whose token wasn't granted `permission` with 403, as
+/// opposed to the 401 for a missing or invalid token
+async fn require_scope(
+    axum::extract::State(permission): axum::extract::State<Permission>,
+    req: axum::http::Request<axum::body::Body>,
+    next: axum::middleware::Next,
+) -> Result<axum::response::Response, axum::http::StatusCode> {
+    // Nothing is granted when authentication is disabled, so nothing is checked
+    match req.extensions().get::<Permissions>() {
+        Some(granted) if !granted.allows(permission) => {
+            tracing::warn!("Token lacks the {:?} permission", permission);
+            Err(axum::http::StatusCode::FORBIDDEN)
+        }
+        _ => Ok(next.run(req).await),
+    }
+}
+
+/// Admin authentication middleware; only admin-scoped tokens are accepted
+async fn admin_auth_middleware_fn(
+    axum::extract::State(auth): axum::extract::State<Arc<AuthMiddleware>>,
+    mut req: axum::http::Request<axum::body::Body>,
+    next: axum::middleware::Next,
+) -> Result<axum::response::Response, axum::http::StatusCode> {
+    let token = req
+        .headers()
+        .get("authorization")
+        .and_then(|h| h.to_str().ok())
+        .and_then(|s| s.strip_prefix("Bearer "));
+
+    match token {
+        Some(token) => match auth.verify_token(token, TokenScope::Admin).await {
+            Ok(claims) => {
+                if let Some(claims) = claims {
+                    req.extensions_mut().insert(claims);
+                }
+                Ok(next.run(req).await)
+            }
+            Err(e) => {
+                tracing::warn!("Invalid admin authentication token: {}", e);
+                Err(axum::http::StatusCode::UNAUTHORIZED)
+            }
+        },
         None => {
-            tracing::warn!("Missing authentication token");
+            auth.reject_missing_token();
             Err(axum::http::StatusCode::UNAUTHORIZED)
         }
     }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::middleware::{
+        auth::AuthConfig,
+        rate_limiter::RateLimitConfig,
+        BodyLimitConfig,
+    };
+    use crate::test_support::test_app_state;
+    use axum::{body::Body, http::{Request, StatusCode}};
+    use std::time::Duration;
+    use tower::ServiceExt;
+    
+    async fn test_router(metrics: Arc<MetricsCollector>, max_requests: usize) -> Router {
+        let app_state = test_app_state().await;
+        let health_checker = app_state.health_checker.clone();
+        let rate_limiter = RateLimiter::new(RateLimitConfig {
+            max_requests,
+            window_duration: Duration::from_secs(60),
+            enabled: true,
+            ..Default::default()
+        })
+        .with_metrics(metrics.clone());
+        let auth = AuthMiddleware::new(AuthConfig {
+            valid_tokens: ["test-token".to_string(), "admin-token".to_string()].into_iter().collect(),
+            admin_tokens: ["admin-token".to_string()].into_iter().collect(),
+            token_permissions: [("test-token".to_string(), [Permission::Admin].into_iter().collect())].into_iter().collect(),
+            ..Default::default()
+        })
+        .with_metrics(metrics.clone());
+        
+        build_router(
+            app_state,
+            health_checker,
+            metrics,
+            Arc::new(rate_limiter),
+            Arc::new(auth),
+            Arc::new(BodyLimiter::new(BodyLimitConfig::default())),
+        )
+    }
+    
+    fn clear_request(token: Option<&str>) -> Request<Body> {
+        let mut builder = Request::post("/api/v1/contexts/clear")
+            .header("content-type", "application/json");
+        if let Some(token) = token {
+            builder = builder.header("authorization", format!("Bearer {}", token));
+        }
+        builder.body(Body::from(r#""ShortTerm""#)).unwrap()
+    }
+    
+    #[tokio::test]
+    async fn test_auth_failures_are_counted() {
+        let metrics = Arc::new(MetricsCollector::new());
+        let router = test_router(metrics.clone(), 100).await;
+        
+        let response = router.clone().oneshot(clear_request(Some("wrong-token"))).await.unwrap();
+        assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+        let response = router.clone().oneshot(clear_request(None)).await.unwrap();
+        assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+        let response = router.oneshot(clear_request(Some("test-token"))).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        
+        assert_eq!(metrics.auth_failures_total(), 2);
+        assert_eq!(metrics.rate_limited_total(), 0);
+        // Rejected and completed requests alike release their connection
+        assert_eq!(metrics.get_metrics().active_connections, 0);
+    }
+    
+    #[tokio::test]
+    async fn test_rate_limited_requests_are_counted() {
+        let metrics = Arc::new(MetricsCollector::new());
+        let router = test_router(metrics.clone(), 1).await;
+        
+        let response = router.clone().oneshot(clear_request(Some("test-token"))).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let response = router.oneshot(clear_request(Some("test-token"))).await.unwrap();
+        assert_eq!(response.status(), StatusCode::TOO_MANY_REQUESTS);
+        
+        assert_eq!(metrics.rate_limited_total(), 1);
+    }
+    
+    #[tokio::test]
+    async fn test_jwt_claims_reach_handlers() {
+        use crate::middleware::{AuthClaims, JwtAuth};
+        
+        let auth = Arc::new(AuthMiddleware::new(AuthConfig {
+            jwt: Some(JwtAuth::hs256(b"secret")),
+            ..Default::default()
+        }));
+        let router = Router::new()
+            .route("/whoami", get(|axum::Extension(claims): axum::Extension<AuthClaims>| async move {
+                claims.subject.unwrap_or_default()
+            }))
+            .layer(axum::middleware::from_fn_with_state(auth, auth_middleware_fn));
+        let token = jsonwebtoken::encode(
+            &jsonwebtoken::Header::default(),
+            &serde_json::json!({ "sub": "agent-7", "exp": chrono::Utc::now().timestamp() + 600 }),
+            &jsonwebtoken::EncodingKey::from_secret(b"secret"),
+        )
+        .unwrap();
+        
+        let request = |token: &str| {
+            Request::get("/whoami")
+                .header("authorization", format!("Bearer {}", token))
+                .body(Body::empty())
+                .unwrap()
+        };
+        let response = router.clone().oneshot(request(&token)).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        assert_eq!(&body[..], b"agent-7");
+        
+        let response = router.oneshot(request("not-a-jwt")).await.unwrap();
+        assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+    }
+    
+    #[tokio::test]
+    async fn test_rate_limit_headers_are_returned() {
+        let router = test_router(Arc::new(MetricsCollector::new()), 2).await;
+        let header = |response: &axum::response::Response, name: &str| {
+            response.headers().get(name).unwrap().to_str().unwrap().to_string()
+        };
+        
+        let response = router.clone().oneshot(clear_request(Some("test-token"))).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(header(&response, "x-ratelimit-limit"), "2");
+        assert_eq!(header(&response, "x-ratelimit-remaining"), "1");
+        assert_eq!(header(&response, "retry-after"), "0");
+        
+        let response = router.clone().oneshot(clear_request(Some("test-token"))).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(header(&response, "x-ratelimit-remaining"), "0");
+        assert_eq!(header(&response, "retry-after"), "60");
+        
+        let response = router.oneshot(clear_request(Some("test-token"))).await.unwrap();
+        assert_eq!(response.status(), StatusCode::TOO_MANY_REQUESTS);
+        assert_eq!(header(&response, "x-ratelimit-limit"), "2");
+        assert_eq!(header(&response, "x-ratelimit-remaining"), "0");
+        let retry_after: u64 = header(&response, "retry-after").parse().unwrap();
+        assert!((59..=60).contains(&retry_after));
+    }
+    
+    fn admin_request(method: &str, uri: &str, token: &str, body: Option<serde_json::Value>) -> Request<Body> {
+        Request::builder()
+            .method(method)
+            .uri(uri)
+            .header("authorization", format!("Bearer {}", token))
+            .header("content-type", "application/json")
+            .body(body.map_or_else(Body::empty, |body| Body::from(body.to_string())))
+            .unwrap()
+    }
+    
+    #[tokio::test]
+    async fn test_tokens_rotate_through_admin_routes() {
+        let router = test_router(Arc::new(MetricsCollector::new()), 100).await;
+        let add = serde_json::json!({"token": "rotated-token"});
+        
+        // API tokens can't manage tokens
+        let response = router.clone()
+            .oneshot(admin_request("POST", "/admin/tokens", "test-token", Some(add.clone())))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+        
+        let response = router.clone()
+            .oneshot(admin_request("POST", "/admin/tokens", "admin-token", Some(add)))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::CREATED);
+        // Added tokens need an explicit admin grant to clear a level
+        let response = router.clone().oneshot(clear_request(Some("rotated-token"))).await.unwrap();
+        assert_eq!(response.status(), StatusCode::FORBIDDEN);
+        
+        let response = router.clone()
+            .oneshot(admin_request("GET", "/admin/tokens", "admin-token", None))
+            .await
+            .unwrap();
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        assert_eq!(body.as_ref(), br#"{"token_count":3}"#);
+        
+        let remove = serde_json::json!({"token": "rotated-token"});
+        let response = router.clone()
+            .oneshot(admin_request("DELETE", "/admin/tokens", "admin-token", Some(remove.clone())))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::NO_CONTENT);
+        let response = router.clone().oneshot(clear_request(Some("rotated-token"))).await.unwrap();
+        assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+        
+        let response = router
+            .oneshot(admin_request("DELETE", "/admin/tokens", "admin-token", Some(remove)))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+    }
+    
+    #[tokio::test]
+    async fn test_missing_permissions_are_forbidden() {
+        let router = test_router(Arc::new(MetricsCollector::new()), 100).await;
+        let add = serde_json::json!({"token": "reader", "permissions": ["read"]});
+        let response = router.clone()
+            .oneshot(admin_request("POST", "/admin/tokens", "admin-token", Some(add)))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::CREATED);
+        
+        let response = router.clone()
+            .oneshot(admin_request("GET", "/api/v1/contexts/recent?level=ShortTerm", "reader", None))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        let search = serde_json::json!({"query": "anything", "max_tokens": 100});
+        let response = router.clone()
+            .oneshot(admin_request("POST", "/api/v1/contexts/search", "reader", Some(search)))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        
+        let store = serde_json::json!({"text": "note", "level": "ShortTerm"});
+        let response = router.clone()
+            .oneshot(admin_request("POST", "/api/v1/contexts", "reader", Some(store)))
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::FORBIDDEN);
+        let response = router.clone().oneshot(clear_request(Some("reader"))).await.unwrap();
+        assert_eq!(response.status(), StatusCode::FORBIDDEN);
+        
+        // A bad token is still unauthorized rather than forbidden
+        let response = router.oneshot(clear_request(Some("wrong-token"))).await.unwrap();
+        assert_eq!(response.status(), StatusCode::UNAUTHORIZED);
+    }
+    
+    #[cfg(feature = "openapi")]
+    #[tokio::test]
+    async fn test_openapi_document_is_served() {
+        let router = test_router(Arc::new(MetricsCollector::new()), 100).await;
+        
+        let response = router
+            .oneshot(Request::get("/openapi.json").body(Body::empty()).unwrap())
+            .await
+            .unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        let document: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert!(document["openapi"].as_str().unwrap().starts_with("3."));
+        assert!(document["paths"].get("/api/v1/contexts").is_some());
+        assert!(document["components"]["schemas"].get("ContextResponse").is_some());
+    }
+}
@@ -4,21 +4,22 @@
 //! It sets up the Axum web server with full CRUD API, authentication, and rate limiting.
 
 use context_manager::{
-    api::{handlers::AppState, routes::build_router},
-    config::Config,
-    v2::{EmbeddingClientV2 as EmbeddingClient, HiRAGManagerV2 as HiRAGManager},
-    vector_db::VectorDbClient,
+    api::{handlers::{AppState, TokenBudget}, routes::build_router},
+    config::{Config, VectorDbConfig},
+    embedding,
+    v2::HiRAGManagerV2 as HiRAGManager,
+    vector_db::{ContextLevel, VectorDbClient},
     middleware::{
-        auth::{AuthMiddleware, AuthConfig},
+        auth::{AuthMiddleware, AuthConfig, JwtAuth, Permission},
         rate_limiter::{RateLimiter, RateLimitConfig},
         BodyLimiter, BodyLimitConfig,
     },
     observability::{HealthChecker, MetricsCollector},
     hirag::ContextManager,
+    server::serve_with_shutdown_timeout,
+    shutdown::ShutdownCoordinator,
 };
 use std::{net::SocketAddr, sync::Arc, time::Duration};
-use tokio::signal;
-use tracing::info;
 
 #[tokio::main]
 async fn main() -> Result<(), Box<dyn std::error::Error>> {
@@ -66,21 +67,30 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
         }
     }
 
-    use tracing::info;
+    use tracing::{info, warn};
     info!("Starting Context Manager Server");
     info!("Configuration loaded and validated from {}", config_path);
     info!("Logging initialized with config settings");
 
     // Initialize metrics
-    let metrics = Arc::new(MetricsCollector::new());
+    let metrics = Arc::new(
+        MetricsCollector::new().with_label_cardinality_limit(config.server.metrics_label_limit),
+    );
 
     // Initialize embedding client
-    let embedding_client = Arc::new(EmbeddingClient::new(config.embedding.clone())?);
-    info!("Embedding client initialized");
+    let embedding_cache = embedding::build_cache(&config.embedding);
+    if let (Some(cache), Some(path)) = (&embedding_cache, &config.embedding.cache_snapshot_path) {
+        if let Err(e) = cache.load(path).await {
+            warn!("Failed to load embedding cache snapshot: {}", e);
+        }
+    }
+    let embedding_client = embedding::build_provider_with_cache(&config.embedding, embedding_cache.clone())?;
+    info!("Embedding client initialized ({:?} provider)", config.embedding.provider);
 
     // Initialize vector database
     let vector_db = Arc::new(VectorDbClient::new(config.vector_db.clone()).await?);
     vector_db.initialize_collections().await?;
+    let naming = vector_db.naming().clone();
     info!("Vector database initialized");
 
     // Initialize HiRAG manager
@@ -89,18 +99,37 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
         embedding_client.clone(),
         vector_db.clone(),
     )
-    .await?;
+    .await?
+    .with_collection_naming(naming.clone())
+    .with_metrics(metrics.clone())
+    .with_init_concurrency(config.vector_db.max_concurrent_collection_creates);
+    let hirag_manager_impl = if config.hirag.record_embedding_model {
+        hirag_manager_impl.with_embedding_model(config.embedding.model_id())
+    } else {
+        hirag_manager_impl
+    };
+    let hirag_manager_impl = match &config.vector_db.read_replica_url {
+        Some(url) => {
+            let replica_config = VectorDbConfig { url: url.clone(), ..config.vector_db.clone() };
+            let replica = Arc::new(VectorDbClient::new(replica_config).await?);
+            info!("Searches served from read replica {}", url);
+            hirag_manager_impl.with_read_replica(replica)
+        }
+        None => hirag_manager_impl,
+    };
     hirag_manager_impl.initialize().await?;
     
     let hirag_manager: Arc<dyn ContextManager> = Arc::new(hirag_manager_impl);
     info!("HiRAG manager initialized");
 
     // Initialize health checker
-    let health_checker = Arc::new(
-        HealthChecker::new()
-            .with_vector_db(vector_db.clone())
-            .with_embedding_client(embedding_client.clone()),
-    );
+    let mut health_checker = HealthChecker::new()
+        .with_vector_db(vector_db.clone())
+        .with_embedding_client(embedding_client.clone());
+    if let Some(cache) = &embedding_cache {
+        health_checker = health_checker.with_cache(cache.clone());
+    }
+    let health_checker = Arc::new(health_checker);
     info!("Health checker initialized");
 
     // Initialize rate limiter
@@ -108,10 +137,12 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
         max_requests: 100,
         window_duration: Duration::from_secs(60),
         enabled: true,
-    }));
+        ..Default::default()
+    }).with_metrics(metrics.clone()));
     
     // Start background cleanup task for rate limiter
-    rate_limiter.clone().start_cleanup_task();
+    let shutdown = Arc::new(ShutdownCoordinator::new());
+    let rate_limiter_cleanup = rate_limiter.clone().start_cleanup_task(shutdown.subscribe());
     info!("Rate limiter initialized with cleanup task");
 
     // Initialize body size limiter with the smaller of server or protocol limits
@@ -128,40 +159,101 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
           config.protocol.max_message_size_mb);
 
     // Initialize authentication
+    // Admin tokens may manage API tokens at runtime and also access the API
+    let admin_tokens: std::collections::HashSet<String> = std::env::var("ADMIN_TOKENS")
+        .unwrap_or_default()
+        .split(',')
+        .map(|s| s.trim().to_string())
+        .filter(|s| !s.is_empty())
+        .collect();
+    // Read-only tokens may only fetch and search contexts
+    let read_only_tokens: Vec<String> = std::env::var("READ_ONLY_TOKENS")
+        .unwrap_or_default()
+        .split(',')
+        .map(|s| s.trim().to_string())
+        .filter(|s| !s.is_empty())
+        .collect();
     let auth_config = AuthConfig {
         enabled: true,
         valid_tokens: std::env::var("API_TOKENS")
             .unwrap_or_else(|_| "default-token".to_string())
             .split(',')
             .map(|s| s.trim().to_string()) // Trim whitespace from tokens
+            .chain(admin_tokens.iter().cloned())
+            .chain(read_only_tokens.iter().cloned())
             .collect(),
         token_prefix: "Bearer".to_string(),
+        jwt: jwt_auth_from_env()?,
+        // Other API tokens may read and write; only admin tokens may delete
+        token_permissions: admin_tokens
+            .iter()
+            .map(|token| (token.clone(), [Permission::Admin].into_iter().collect()))
+            .chain(read_only_tokens.into_iter().map(|token| (token, [Permission::Read].into_iter().collect())))
+            .collect(),
+        admin_tokens,
+        ..Default::default()
     };
-    let auth_middleware = Arc::new(AuthMiddleware::new(auth_config));
+    let auth_middleware = Arc::new(AuthMiddleware::new(auth_config).with_metrics(metrics.clone()));
     info!("Authentication middleware initialized");
 
     // No circuit breaker available in VectorDbClient
     let circuit_breaker = None;
 
-    // Initialize background GC task if enabled
-    if config.hirag.gc_enabled {
+    // Initialize background GC and re-embedding tasks if enabled
+    let reembed = config.hirag.defer_embedding_on_failure || config.hirag.reembed_enabled;
+    let mut background_tasks = Vec::new();
+    if config.hirag.gc_enabled || config.hirag.soft_delete || reembed {
         use context_manager::hirag::background::BackgroundTaskManager;
         use std::time::Duration;
         
-        let background_manager = Arc::new(BackgroundTaskManager::new(
+        let mut background_manager = BackgroundTaskManager::new(
             vector_db.clone(),
             Duration::from_secs(config.hirag.gc_interval_secs),
             config.hirag.l2_ttl_secs,
-            format!("{}_shortterm", config.vector_db.collection_prefix), // L2 collection name
-            format!("{}_longterm", config.vector_db.collection_prefix), // L3 collection name
+            config.hirag.l3_ttl_secs,
+            naming.collection(ContextLevel::ShortTerm), // L2 collection name
+            naming.collection(ContextLevel::LongTerm), // L3 collection name
             config.vector_db.vector_size,
-        ));
+        )
+        .with_max_gc_backoff(Duration::from_secs(config.hirag.gc_max_backoff_secs))
+        .with_gc(config.hirag.gc_enabled)
+        .with_l3_gc(config.hirag.l3_enabled && config.hirag.l3_gc_enabled)
+        .with_metrics(metrics.clone());
+        if let Some(tolerance) = config.hirag.gc_future_timestamp_tolerance_secs {
+            background_manager = background_manager.with_future_timestamp_tolerance(Duration::from_secs(tolerance));
+        }
+        if config.hirag.soft_delete {
+            let collections = [ContextLevel::Immediate, ContextLevel::ShortTerm, ContextLevel::LongTerm]
+                .into_iter()
+                .map(|level| naming.collection(level))
+                .collect();
+            background_manager = background_manager.with_tombstone_purge(
+                collections,
+                Duration::from_secs(config.hirag.soft_delete_grace_secs),
+            );
+        }
+        if reembed {
+            let collections = [ContextLevel::Immediate, ContextLevel::ShortTerm, ContextLevel::LongTerm]
+                .into_iter()
+                .map(|level| naming.collection(level))
+                .collect();
+            background_manager = background_manager
+                .with_reembedding(embedding_client.clone(), collections)
+                .with_reembed_rate(
+                    config.hirag.reembed_batch_size,
+                    Duration::from_secs(config.hirag.reembed_interval_secs),
+                );
+        }
+        if config.hirag.reembed_enabled {
+            background_manager = background_manager.with_embedding_model(config.embedding.model_id());
+        }
+        let background_manager = Arc::new(background_manager);
         
-        background_manager.clone().start();
+        background_tasks = background_manager.clone().start(shutdown.subscribe());
         
-        info!("Background GC task started with {}s interval", config.hirag.gc_interval_secs);
+        info!("Background tasks started");
     } else {
-        info!("Background GC task is disabled");
+        info!("Background tasks are disabled");
     }
 
     // Create application state
@@ -170,6 +262,8 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
         vector_db,
         health_checker: health_checker.clone(),
         circuit_breaker,
+        token_budget: TokenBudget::from_config(&config.server),
+        empty_result_policy: config.server.empty_result_policy,
     };
 
     // Build router with all middleware
@@ -186,44 +280,73 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
     let addr = SocketAddr::from(([0, 0, 0, 0], config.server.port));
     info!("Server listening on {}", addr);
 
-    // Start server with graceful shutdown
+    // Start server with graceful shutdown, bounded by the configured timeout.
+    // The shutdown signal also stops the background tasks.
     let listener = tokio::net::TcpListener::bind(addr).await?;
-    axum::serve(listener, app)
-        .with_graceful_shutdown(shutdown_signal())
-        .await?;
+    let signal = {
+        let shutdown = shutdown.clone();
+        async move {
+            shutdown.wait_for_signal().await;
+            info!("Starting graceful shutdown");
+        }
+    };
+    let dropped = serve_with_shutdown_timeout(
+        listener,
+        app,
+        signal,
+        Duration::from_secs(config.server.shutdown_timeout_secs),
+    )
+    .await?;
+    if dropped > 0 {
+        info!("Dropped {} in-flight request(s) at shutdown", dropped);
+    }
+
+    if let Err(e) = rate_limiter_cleanup.await {
+        warn!("Rate limiter cleanup task failed: {}", e);
+    }
+    for task in background_tasks {
+        if let Err(e) = task.await {
+            warn!("Background task failed: {}", e);
+        }
+    }
+
+    if let (Some(cache), Some(path)) = (&embedding_cache, &config.embedding.cache_snapshot_path) {
+        if let Err(e) = cache.save(path).await {
+            warn!("Failed to save embedding cache snapshot: {}", e);
+        }
+    }
 
     info!("Server shutdown complete");
 
     Ok(())
 }
 
-/// Graceful shutdown signal handler
-async fn shutdown_signal() {
-    let ctrl_c = async {
-        signal::ctrl_c()
-            .await
-            .expect("failed to install Ctrl+C handler");
-    };
+/// Shortest `JWT_SECRET` accepted, the HS256 key size recommended by RFC 7518
+const MIN_JWT_SECRET_LEN: usize = 32;
 
-    #[cfg(unix)]
-    let terminate = async {
-        signal::unix::signal(signal::unix::SignalKind::terminate())
-            .expect("failed to install signal handler")
-            .recv()
-            .await;
+/// JWT verification configured through the environment, replacing the static
+/// API tokens when `JWT_SECRET` (HS256) or `JWT_PUBLIC_KEY_PATH` (RS256 PEM) is set
+fn jwt_auth_from_env() -> Result<Option<JwtAuth>, Box<dyn std::error::Error>> {
+    let jwt = if let Ok(secret) = std::env::var("JWT_SECRET") {
+        // An empty or short secret would make every token trivially forgeable
+        if secret.len() < MIN_JWT_SECRET_LEN {
+            return Err(format!("JWT_SECRET must be at least {} bytes long", MIN_JWT_SECRET_LEN).into());
+        }
+        JwtAuth::hs256(secret.as_bytes())
+    } else if let Ok(path) = std::env::var("JWT_PUBLIC_KEY_PATH") {
+        JwtAuth::rs256_pem(&std::fs::read(path)?)?
+    } else {
+        return Ok(None);
     };
-
-    #[cfg(not(unix))]
-    let terminate = std::future::pending::<()>();
-
-    tokio::select! {
-        _ = ctrl_c => {
-            info!("Received Ctrl+C signal");
-        },
-        _ = terminate => {
-            info!("Received terminate signal");
-        },
-    }
-
-    info!("Starting graceful shutdown");
-}
\ No newline at end of file
+    
+    let jwt = match std::env::var("JWT_ISSUER") {
+        Ok(issuer) => jwt.with_issuer(&issuer),
+        Err(_) => jwt,
+    };
+    let jwt = match std::env::var("JWT_AUDIENCE") {
+        Ok(audience) => jwt.with_audience(&audience),
+        Err(_) => jwt,
+    };
+    tracing::info!("JWT authentication enabled");
+    Ok(Some(jwt))
+}
@@ -0,0 +1,102 @@
+//! Clock abstraction for time-dependent logic
+//!
+//! Components that measure windows, timeouts or TTL cutoffs take an
+//! `Arc<dyn Clock>` so tests can advance time deterministically instead of
+//! sleeping.
+
+use chrono::{DateTime, Utc};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+/// Source of the current time
+pub trait Clock: Send + Sync {
+    /// Current monotonic instant, for measuring elapsed time
+    fn now(&self) -> Instant;
+
+    /// Current wall-clock time, for timestamps and TTL cutoffs
+    fn now_utc(&self) -> DateTime<Utc>;
+}
+
+/// Clock backed by the system time
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now(&self) -> Instant {
+        Instant::now()
+    }
+
+    fn now_utc(&self) -> DateTime<Utc> {
+        Utc::now()
+    }
+}
+
+/// The default clock used when none is injected
+pub fn system_clock() -> Arc<dyn Clock> {
+    Arc::new(SystemClock)
+}
+
+/// Clock that only moves when advanced, for tests
+#[derive(Debug)]
+pub struct FakeClock {
+    start: Instant,
+    start_utc: DateTime<Utc>,
+    elapsed: Mutex<Duration>,
+}
+
+impl FakeClock {
+    /// Create a fake clock frozen at the current time
+    pub fn new() -> Self {
+        Self::at(Utc::now())
+    }
+
+    /// Create a fake clock frozen at the given wall-clock time
+    pub fn at(start_utc: DateTime<Utc>) -> Self {
+        Self {
+            start: Instant::now(),
+            start_utc,
+            elapsed: Mutex::new(Duration::ZERO),
+        }
+    }
+
+    /// Move the clock forward
+    pub fn advance(&self, duration: Duration) {
+        *self.elapsed.lock().unwrap() += duration;
+    }
+
+    fn elapsed(&self) -> Duration {
+        *self.elapsed.lock().unwrap()
+    }
+}
+
+impl Default for FakeClock {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Clock for FakeClock {
+    fn now(&self) -> Instant {
+        self.start + self.elapsed()
+    }
+
+    fn now_utc(&self) -> DateTime<Utc> {
+        self.start_utc + chrono::Duration::from_std(self.elapsed()).unwrap_or(chrono::Duration::MAX)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_fake_clock_only_moves_when_advanced() {
+        let clock = FakeClock::new();
+        let (instant, utc) = (clock.now(), clock.now_utc());
+        assert_eq!(clock.now(), instant);
+
+        clock.advance(Duration::from_secs(90));
+        assert_eq!(clock.now() - instant, Duration::from_secs(90));
+        assert_eq!((clock.now_utc() - utc).num_seconds(), 90);
+    }
+}
@@ -21,9 +21,18 @@ pub struct Config {
 /// Configuration for the embedding service
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct EmbeddingConfig {
-    /// Chutes API endpoint URL
+    /// Embedding provider backing `api_url`, which selects the request and
+    /// response format (also accepted as `api_format`)
+    #[serde(default, alias = "api_format")]
+    pub provider: EmbeddingProviderType,
+    
+    /// Embedding API endpoint URL
     pub api_url: String,
     
+    /// Model identifier sent with each request (required for OpenAI)
+    #[serde(default)]
+    pub model: Option<String>,
+    
     /// API authentication token (secured)
     #[serde(serialize_with = "serialize_secret", deserialize_with = "deserialize_secret")]
     pub api_token: Secret<String>,
@@ -32,14 +41,34 @@ pub struct EmbeddingConfig {
     #[serde(default = "default_batch_size")]
     pub batch_size: usize,
     
-    /// Request timeout in seconds
+    /// Maximum estimated payload size of a batch request in bytes
+    #[serde(default = "default_max_batch_bytes")]
+    pub max_batch_bytes: usize,
+    
+    /// Request timeout in seconds, applied to each attempt separately
     #[serde(default = "default_timeout")]
     pub timeout_secs: u64,
     
+    /// Deadline in seconds for a whole embedding operation, including every
+    /// retry and backoff (unbounded if unset)
+    #[serde(default)]
+    pub total_timeout_secs: Option<u64>,
+    
+    /// Time allowed to receive the response body once headers have arrived,
+    /// in seconds (defaults to `timeout_secs`)
+    #[serde(default)]
+    pub response_timeout_secs: Option<u64>,
+    
     /// Maximum retry attempts
     #[serde(default = "default_max_retries")]
     pub max_retries: u32,
     
+    /// Retries after a successful response that can't be parsed. Kept apart
+    /// from `max_retries` because a malformed body usually means a persistent
+    /// API mismatch rather than a transient failure.
+    #[serde(default = "default_max_parse_retries")]
+    pub max_parse_retries: u32,
+    
     /// Enable caching
     #[serde(default = "default_cache_enabled")]
     pub cache_enabled: bool,
@@ -48,10 +77,20 @@ pub struct EmbeddingConfig {
     #[serde(default = "default_cache_ttl")]
     pub cache_ttl_secs: u64,
     
+    /// Cache TTL for search query embeddings in seconds. Queries are often
+    /// one-offs, so they are evicted sooner than stored passages.
+    #[serde(default = "default_query_cache_ttl")]
+    pub query_cache_ttl_secs: u64,
+    
     /// Cache maximum size
     #[serde(default = "default_cache_size")]
     pub cache_size: usize,
     
+    /// File the embedding cache is loaded from at startup and saved to on
+    /// graceful shutdown (not persisted if unset)
+    #[serde(default)]
+    pub cache_snapshot_path: Option<String>,
+    
     /// Enable TLS for embedding API connection
     #[serde(default)]
     pub tls_enabled: bool,
@@ -59,6 +98,80 @@ pub struct EmbeddingConfig {
     /// Verify TLS certificates
     #[serde(default = "default_tls_verify")]
     pub tls_verify: bool,
+    
+    /// Detect the embedding dimension from the first successful response
+    /// instead of assuming the model default. A later response with a
+    /// different dimension is rejected.
+    #[serde(default)]
+    pub auto_detect_dimension: bool,
+    
+    /// Embedding dimension. Filled in from the built-in registry when `model`
+    /// is a known model; set it explicitly for models the registry doesn't
+    /// know. When resolved it also determines `vector_db.vector_size`.
+    #[serde(default)]
+    pub dimension: Option<usize>,
+    
+    /// Maximum number of embedding API requests in flight at once across
+    /// every client built from this configuration (unlimited if unset)
+    #[serde(default)]
+    pub max_concurrent_requests: Option<usize>,
+    
+    /// Normalization applied to text before it is embedded and hashed
+    #[serde(default)]
+    pub normalization: TextNormalization,
+    
+    /// Upper bound on the L2 norm of returned embeddings. A larger vector
+    /// usually means a broken provider and would dominate similarity scores
+    /// (unchecked if unset).
+    #[serde(default)]
+    pub max_vector_norm: Option<f32>,
+    
+    /// What to do with an embedding whose norm exceeds `max_vector_norm`
+    #[serde(default)]
+    pub excessive_norm_policy: ExcessiveNormPolicy,
+}
+
+/// Handling of embeddings whose norm exceeds `max_vector_norm`
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum ExcessiveNormPolicy {
+    /// Fail the request with an API error
+    #[default]
+    Reject,
+    /// Scale the vector to unit length
+    Normalize,
+}
+
+/// Text normalization applied before embedding
+///
+/// Only the text sent to the embedding API and used for cache keys is
+/// normalized; stored contexts keep their original text.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct TextNormalization {
+    /// Trim, collapse runs of whitespace to a single space and apply Unicode
+    /// NFC normalization
+    ///
+    /// Off by default, since enabling it changes the embeddings and cache keys
+    /// of existing deployments.
+    #[serde(default)]
+    pub enabled: bool,
+    
+    /// Also lowercase the text
+    #[serde(default)]
+    pub lowercase: bool,
+}
+
+/// Supported embedding providers
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum EmbeddingProviderType {
+    /// Chutes-hosted embedding endpoint
+    #[default]
+    Chutes,
+    /// OpenAI-compatible `/v1/embeddings` endpoint
+    OpenAI,
+    /// Provider supplied programmatically by the embedding application
+    Custom,
 }
 
 /// Configuration for Qdrant vector database
@@ -97,10 +210,86 @@ pub struct VectorDbConfig {
     /// Verify TLS certificates
     #[serde(default = "default_tls_verify")]
     pub tls_verify: bool,
+    
+    /// Wait for Qdrant to apply upserts and deletes before returning.
+    ///
+    /// When enabled, writes are immediately visible to subsequent searches at
+    /// the cost of higher write latency. When disabled, writes are acknowledged
+    /// as soon as Qdrant accepts them and may briefly be invisible to reads.
+    #[serde(default)]
+    pub wait_for_indexing: bool,
+    
+    /// Default HNSW beam size for searches; larger values improve recall at
+    /// the cost of latency (Qdrant's default if unset)
+    #[serde(default)]
+    pub search_hnsw_ef: Option<usize>,
+    
+    /// Default to exhaustive (exact) search instead of the HNSW index.
+    /// Only practical for small collections.
+    #[serde(default)]
+    pub search_exact: Option<bool>,
+    
+    /// Qdrant read replica URL. Searches are sent here while writes go to
+    /// `url`; every other setting is shared with the primary.
+    #[serde(default)]
+    pub read_replica_url: Option<String>,
+    
+    /// Maximum number of collections created at once during initialization
+    #[serde(default = "default_max_concurrent_collection_creates")]
+    pub max_concurrent_collection_creates: usize,
+    
+    /// Retries of inserts and searches that fail because a collection is
+    /// still initializing (e.g. right after it was created)
+    #[serde(default = "default_not_ready_retries")]
+    pub not_ready_retries: u32,
+    
+    /// Backoff before the first such retry in milliseconds, doubling on each
+    /// further attempt
+    #[serde(default = "default_not_ready_backoff_ms")]
+    pub not_ready_backoff_ms: u64,
+    
+    /// Quantization Qdrant applies to collections created by this client
+    /// (full-precision vectors only if unset)
+    ///
+    /// Quantized vectors take a fraction of the memory and speed up search,
+    /// at some cost in recall. Qdrant keeps the original vectors and rescores
+    /// the top candidates with them, which recovers most of the loss. Only
+    /// applies to newly created collections.
+    #[serde(default)]
+    pub quantization: Option<Quantization>,
+}
+
+/// Vector quantization modes supported by Qdrant
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+#[serde(tag = "type")]
+pub enum Quantization {
+    /// One int8 per component (4x smaller). Recall is usually within a
+    /// percent or two of full precision.
+    Scalar {
+        /// Fraction of component values used to pick the int8 range, clipping
+        /// outliers (Qdrant's default of all values if unset)
+        #[serde(default)]
+        quantile: Option<f32>,
+    },
+    /// Product quantization. Compresses much further than scalar
+    /// quantization but loses noticeably more recall, especially at the
+    /// higher ratios.
+    Product { compression: ProductCompression },
+}
+
+/// Compression ratio for product quantization
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq)]
+pub enum ProductCompression {
+    X4,
+    X8,
+    X16,
+    X32,
+    X64,
 }
 
 /// Distance metrics supported
 #[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
 pub enum Distance {
     #[default]
     Cosine,
@@ -137,6 +326,10 @@ pub struct HiRAGConfig {
     #[serde(default)]
     pub token_estimator: TokenEstimator,
     
+    /// Token estimation methods for individual levels, overriding `token_estimator`
+    #[serde(default)]
+    pub level_token_estimators: LevelTokenEstimators,
+    
     /// Retrieval strategy
     #[serde(default)]
     pub retrieval_strategy: RetrievalStrategy,
@@ -145,6 +338,31 @@ pub struct HiRAGConfig {
     #[serde(default)]
     pub ranking_weights: RankingWeights,
     
+    /// Reject ranking weights that give vector similarity too little weight
+    /// instead of only warning at startup
+    #[serde(default)]
+    pub strict_weights: bool,
+    
+    /// Age in seconds at which a context's recency score has halved
+    #[serde(default = "default_recency_half_life")]
+    pub recency_half_life_secs: u64,
+    
+    /// Re-rank results with maximal marginal relevance, trading relevance
+    /// (1.0) against diversity (0.0) so near-duplicates don't crowd out other
+    /// contexts (disabled if unset)
+    #[serde(default)]
+    pub mmr_lambda: Option<f32>,
+    
+    /// Search a single requested vector-db level inline, skipping the
+    /// parallel tasks and cross-level deduplication
+    #[serde(default = "default_single_level_fast_path")]
+    pub single_level_fast_path: bool,
+    
+    /// Most conditions a retrieval request's filters may contain, across
+    /// `must`, `should`, `must_not` and nested groups
+    #[serde(default = "default_max_filter_conditions")]
+    pub max_filter_conditions: usize,
+    
     /// Enable background garbage collection
     #[serde(default = "default_gc_enabled")]
     pub gc_enabled: bool,
@@ -153,6 +371,16 @@ pub struct HiRAGConfig {
     #[serde(default = "default_gc_interval")]
     pub gc_interval_secs: u64,
     
+    /// Upper bound in seconds on the GC interval while it backs off after
+    /// consecutive failures
+    #[serde(default = "default_gc_max_backoff")]
+    pub gc_max_backoff_secs: u64,
+    
+    /// Treat contexts timestamped more than this many seconds in the future
+    /// as corrupt and let GC remove them (kept forever if unset)
+    #[serde(default)]
+    pub gc_future_timestamp_tolerance_secs: Option<u64>,
+    
     /// L2 context TTL in seconds
     #[serde(default = "default_l2_ttl")]
     pub l2_ttl_secs: i64,
@@ -160,14 +388,93 @@ pub struct HiRAGConfig {
     /// L3 context TTL in seconds
     #[serde(default = "default_l3_ttl")]
     pub l3_ttl_secs: i64,
+    
+    /// Let GC delete L3 contexts older than `l3_ttl_secs`; long-term
+    /// contexts are kept forever unless this is set
+    #[serde(default)]
+    pub l3_gc_enabled: bool,
+    
+    /// Maximum contexts kept per level; the oldest are evicted on store
+    /// once exceeded (unbounded when unset)
+    #[serde(default)]
+    pub max_contexts_per_level: Option<usize>,
+    
+    /// Keep up to this many previous versions of a context's metadata in its
+    /// `_history` metadata entry on each update (no history when unset)
+    #[serde(default)]
+    pub history_max_entries: Option<usize>,
+    
+    /// Which copy to keep when the same context ID is retrieved from
+    /// several levels
+    #[serde(default)]
+    pub duplicate_policy: DuplicatePolicy,
+    
+    /// Only return contexts whose `acl` metadata lists the requesting agent,
+    /// or that carry no `acl` at all
+    #[serde(default)]
+    pub enforce_acl: bool,
+    
+    /// Mark deleted contexts with a `_deleted_at` tombstone, hiding them from
+    /// retrieval, instead of removing them right away
+    #[serde(default)]
+    pub soft_delete: bool,
+    
+    /// Seconds a tombstoned context can still be restored before GC removes it
+    #[serde(default = "default_soft_delete_grace")]
+    pub soft_delete_grace_secs: u64,
+    
+    /// Create a level's collection on the first store to it instead of
+    /// requiring `initialize` to have run
+    #[serde(default)]
+    pub create_collections_on_store: bool,
+    
+    /// Store contexts with a zero vector when the embedding service fails,
+    /// flagging them for the background task to re-embed once it recovers
+    #[serde(default)]
+    pub defer_embedding_on_failure: bool,
+    
+    /// Re-embed contexts in the background whose stored model tag differs
+    /// from the current model (deferred contexts are re-embedded whenever
+    /// `defer_embedding_on_failure` is set)
+    #[serde(default)]
+    pub reembed_enabled: bool,
+    
+    /// Seconds between background re-embedding passes
+    #[serde(default = "default_reembed_interval")]
+    pub reembed_interval_secs: u64,
+    
+    /// Maximum contexts re-embedded per pass, bounding the load on the
+    /// embedding API
+    #[serde(default = "default_reembed_batch_size")]
+    pub reembed_batch_size: usize,
+    
+    /// Tag stored contexts with the embedding model id, and flag retrieved
+    /// contexts that were embedded with a different model
+    #[serde(default = "default_record_embedding_model")]
+    pub record_embedding_model: bool,
+}
+
+/// Resolution of a context ID found in more than one level
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum DuplicatePolicy {
+    /// Keep the copy from the most durable level (LongTerm, then ShortTerm, then Immediate)
+    HighestLevel,
+    /// Keep the copy with the highest relevance score
+    #[default]
+    HighestRelevance,
+    /// Keep the copy with the newest timestamp
+    Newest,
 }
 
 /// Token estimation methods
-#[derive(Debug, Clone, Copy, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(tag = "type")]
 pub enum TokenEstimator {
     CharacterBased { chars_per_token: f32 },
     WordBased { words_per_token: f32 },
+    /// Exact BPE token counts for an OpenAI model, e.g. `gpt-4o`
+    Tiktoken { model: String },
 }
 
 impl Default for TokenEstimator {
@@ -176,6 +483,19 @@ impl Default for TokenEstimator {
     }
 }
 
+/// Per-level token estimation methods; unset levels use the global estimator
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct LevelTokenEstimators {
+    #[serde(default)]
+    pub l1: Option<TokenEstimator>,
+    
+    #[serde(default)]
+    pub l2: Option<TokenEstimator>,
+    
+    #[serde(default)]
+    pub l3: Option<TokenEstimator>,
+}
+
 /// Context retrieval strategy
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct RetrievalStrategy {
@@ -268,6 +588,51 @@ pub struct ServerConfig {
     /// Maximum request body size in MB (0 = unlimited)
     #[serde(default = "default_max_body_size")]
     pub max_body_size_mb: usize,
+    
+    /// Largest token budget a search request may ask for
+    #[serde(default = "default_max_allowed_tokens")]
+    pub max_allowed_tokens: usize,
+    
+    /// How search requests over `max_allowed_tokens` are handled
+    #[serde(default)]
+    pub token_budget_policy: TokenBudgetPolicy,
+    
+    /// Response to a search that matches nothing (requests may override it)
+    #[serde(default)]
+    pub empty_result_policy: EmptyResultPolicy,
+    
+    /// Seconds to wait for in-flight requests after a shutdown signal
+    /// before the remaining connections are dropped
+    #[serde(default = "default_shutdown_timeout")]
+    pub shutdown_timeout_secs: u64,
+    
+    /// Maximum distinct label values tracked per labeled metric; further
+    /// values are counted under `other`
+    #[serde(default = "default_metrics_label_limit")]
+    pub metrics_label_limit: usize,
+}
+
+/// Handling of search requests whose token budget exceeds the server cap
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum TokenBudgetPolicy {
+    /// Lower the budget to the cap and flag it in the response
+    #[default]
+    Clamp,
+    /// Reject the request with a validation error
+    Reject,
+}
+
+/// Response to a search request that retrieves no contexts
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+#[serde(rename_all = "snake_case")]
+pub enum EmptyResultPolicy {
+    /// 200 with an empty `contexts` array
+    #[default]
+    Empty,
+    /// 404 with an error body
+    NotFound,
 }
 
 /// Codec types for message serialization
@@ -293,20 +658,27 @@ pub struct LoggingConfig {
 
 // Default value functions
 fn default_batch_size() -> usize { 32 }
+fn default_max_batch_bytes() -> usize { 1024 * 1024 }
 fn default_timeout() -> u64 { 30 }
 
 fn default_tls_verify() -> bool { true }
 fn default_max_retries() -> u32 { 3 }
+fn default_max_parse_retries() -> u32 { 1 }
 fn default_cache_enabled() -> bool { true }
 fn default_cache_ttl() -> u64 { 3600 }
+fn default_query_cache_ttl() -> u64 { 300 }
 fn default_cache_size() -> usize { 1000 }
 fn default_collection_prefix() -> String { "contexts".to_string() }
 fn default_vector_size() -> usize { 1024 }
+fn default_max_concurrent_collection_creates() -> usize { 4 }
+fn default_not_ready_retries() -> u32 { 5 }
+fn default_not_ready_backoff_ms() -> u64 { 100 }
 fn default_l1_size() -> usize { 10 }
 fn default_l2_size() -> usize { 100 }
 fn default_l3_enabled() -> bool { true }
 fn default_max_context_tokens() -> usize { 4000 }
 fn default_relevance_threshold() -> f32 { 0.7 }
+fn default_recency_half_life() -> u64 { 59888 } // 1 day * ln 2, as the earlier exp(-age_hours / 24) decay
 fn default_l1_allocation() -> f32 { 0.3 }
 fn default_l2_allocation() -> f32 { 0.4 }
 fn default_l3_allocation() -> f32 { 0.3 }
@@ -325,27 +697,85 @@ fn default_server_host() -> String { "0.0.0.0".to_string() }
 // GC configuration defaults
 fn default_gc_enabled() -> bool { false }
 fn default_gc_interval() -> u64 { 300 } // 5 minutes
+fn default_gc_max_backoff() -> u64 { 3600 } // 1 hour
+fn default_reembed_interval() -> u64 { 60 }
+fn default_reembed_batch_size() -> usize { 64 }
+fn default_record_embedding_model() -> bool { true }
+fn default_single_level_fast_path() -> bool { true }
+fn default_max_filter_conditions() -> usize { 64 }
+fn default_soft_delete_grace() -> u64 { 604800 } // 7 days
 fn default_l2_ttl() -> i64 { 3600 } // 1 hour
 fn default_l3_ttl() -> i64 { 86400 } // 24 hours
 
 // Server configuration defaults
 fn default_max_body_size() -> usize { 10 } // 10 MB default
+fn default_max_allowed_tokens() -> usize { 16000 }
+fn default_shutdown_timeout() -> u64 { 30 }
+fn default_metrics_label_limit() -> usize { 100 }
+
+impl EmbeddingConfig {
+    /// Identifier of the configured model: `model`, or the API URL when no
+    /// model is set
+    pub fn model_id(&self) -> String {
+        self.model.clone().unwrap_or_else(|| self.api_url.clone())
+    }
+}
 
 impl Config {
     /// Load configuration from a TOML file
     pub fn from_file<P: AsRef<Path>>(path: P) -> crate::error::Result<Self> {
-        let config = loader::load_config(path)?;
+        let mut config = loader::load_config(path)?;
+        config.resolve_embedding_dimension()?;
         validation::validate_config(&config)?;
         Ok(config)
     }
     
     /// Load configuration with environment variable overrides
     pub fn from_file_with_env<P: AsRef<Path>>(path: P) -> crate::error::Result<Self> {
-        let config = loader::load_config_with_env(path)?;
+        let mut config = loader::load_config_with_env(path)?;
+        config.resolve_embedding_dimension()?;
         validation::validate_config(&config)?;
         Ok(config)
     }
     
+    /// Resolve the embedding dimension and size the vector collections to match
+    ///
+    /// A known `embedding.model` determines the dimension, and an explicit
+    /// `embedding.dimension` that disagrees with it is an error. For unknown
+    /// models the explicit dimension is used as-is. Once a dimension is
+    /// resolved, `vector_db.vector_size` is set to it.
+    pub fn resolve_embedding_dimension(&mut self) -> crate::error::Result<()> {
+        let model = self.embedding.model.as_deref();
+        let known = model.and_then(crate::embedding::registry::known_dimension);
+        
+        let dimension = match (known, self.embedding.dimension) {
+            (Some(known), Some(explicit)) if known != explicit => {
+                return Err(crate::error::ContextError::Config(format!(
+                    "Embedding dimension {} does not match model '{}' ({} dimensions)",
+                    explicit,
+                    model.unwrap_or_default(),
+                    known
+                )));
+            }
+            (Some(known), _) => Some(known),
+            (None, explicit) => explicit,
+        };
+        
+        if let Some(dimension) = dimension {
+            if self.vector_db.vector_size != dimension {
+                tracing::info!(
+                    "Setting vector size to {} to match the embedding dimension (was {})",
+                    dimension,
+                    self.vector_db.vector_size
+                );
+            }
+            self.embedding.dimension = Some(dimension);
+            self.vector_db.vector_size = dimension;
+        }
+        
+        Ok(())
+    }
+    
     /// Validate this configuration
     pub fn validate(&self) -> crate::error::Result<()> {
         validation::validate_config(self)
@@ -355,16 +785,30 @@ impl Config {
     pub fn default_config() -> Self {
         Self {
             embedding: EmbeddingConfig {
+                provider: EmbeddingProviderType::default(),
                 api_url: "https://chutes-intfloat-multilingual-e5-large.chutes.ai/v1/embeddings".to_string(),
+                model: None,
                 api_token: Secret::new(std::env::var("CHUTES_API_TOKEN").unwrap_or_default()),
                 batch_size: default_batch_size(),
+                max_batch_bytes: default_max_batch_bytes(),
                 timeout_secs: default_timeout(),
+                total_timeout_secs: None,
+                response_timeout_secs: None,
                 max_retries: default_max_retries(),
+                max_parse_retries: default_max_parse_retries(),
                 cache_enabled: default_cache_enabled(),
                 cache_ttl_secs: default_cache_ttl(),
+                query_cache_ttl_secs: default_query_cache_ttl(),
                 cache_size: default_cache_size(),
+                cache_snapshot_path: None,
                 tls_enabled: false,
                 tls_verify: true,
+                auto_detect_dimension: false,
+                dimension: None,
+                max_concurrent_requests: None,
+                normalization: TextNormalization::default(),
+                max_vector_norm: None,
+                excessive_norm_policy: ExcessiveNormPolicy::default(),
             },
             vector_db: VectorDbConfig {
                 url: "http://localhost:6334".to_string(),
@@ -376,6 +820,14 @@ impl Config {
                 tls_enabled: false,
                 tls_cert_path: None,
                 tls_verify: true,
+                wait_for_indexing: false,
+                search_hnsw_ef: None,
+                search_exact: None,
+                read_replica_url: None,
+                max_concurrent_collection_creates: default_max_concurrent_collection_creates(),
+                not_ready_retries: default_not_ready_retries(),
+                not_ready_backoff_ms: default_not_ready_backoff_ms(),
+                quantization: None,
             },
             hirag: HiRAGConfig {
                 l1_size: default_l1_size(),
@@ -384,12 +836,33 @@ impl Config {
                 max_context_tokens: default_max_context_tokens(),
                 relevance_threshold: default_relevance_threshold(),
                 token_estimator: TokenEstimator::default(),
+                level_token_estimators: LevelTokenEstimators::default(),
                 retrieval_strategy: RetrievalStrategy::default(),
                 ranking_weights: RankingWeights::default(),
+                strict_weights: false,
+                recency_half_life_secs: default_recency_half_life(),
+                mmr_lambda: None,
+                single_level_fast_path: default_single_level_fast_path(),
+                max_filter_conditions: default_max_filter_conditions(),
                 gc_enabled: default_gc_enabled(),
                 gc_interval_secs: default_gc_interval(),
+                gc_max_backoff_secs: default_gc_max_backoff(),
+                gc_future_timestamp_tolerance_secs: None,
                 l2_ttl_secs: default_l2_ttl(),
                 l3_ttl_secs: default_l3_ttl(),
+                l3_gc_enabled: false,
+                max_contexts_per_level: None,
+                history_max_entries: None,
+                duplicate_policy: DuplicatePolicy::default(),
+                enforce_acl: false,
+                soft_delete: false,
+                soft_delete_grace_secs: default_soft_delete_grace(),
+                create_collections_on_store: false,
+                defer_embedding_on_failure: false,
+                reembed_enabled: false,
+                reembed_interval_secs: default_reembed_interval(),
+                reembed_batch_size: default_reembed_batch_size(),
+                record_embedding_model: default_record_embedding_model(),
             },
             protocol: ProtocolConfig {
                 version: default_protocol_version(),
@@ -404,6 +877,11 @@ impl Config {
                 port: default_server_port(),
                 host: default_server_host(),
                 max_body_size_mb: default_max_body_size(),
+                max_allowed_tokens: default_max_allowed_tokens(),
+                token_budget_policy: TokenBudgetPolicy::default(),
+                empty_result_policy: EmptyResultPolicy::default(),
+                shutdown_timeout_secs: default_shutdown_timeout(),
+                metrics_label_limit: default_metrics_label_limit(),
             },
         }
     }
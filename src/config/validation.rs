@@ -3,6 +3,9 @@
 use super::*;
 use crate::error::{ContextError, Result};
 
+/// Smallest similarity ranking weight accepted without a warning
+const MIN_SIMILARITY_WEIGHT: f32 = 0.2;
+
 /// Validate complete configuration
 pub fn validate_config(config: &Config) -> Result<()> {
     validate_embedding_config(&config.embedding)?;
@@ -10,6 +13,16 @@ pub fn validate_config(config: &Config) -> Result<()> {
     validate_hirag_config(&config.hirag)?;
     validate_protocol_config(&config.protocol)?;
     validate_server_config(&config.server)?;
+    
+    if let Some(dimension) = config.embedding.dimension {
+        if dimension != config.vector_db.vector_size {
+            return Err(ContextError::Config(format!(
+                "Embedding dimension {} does not match vector size {}",
+                dimension, config.vector_db.vector_size
+            )));
+        }
+    }
+    
     Ok(())
 }
 
@@ -28,6 +41,21 @@ fn validate_embedding_config(config: &EmbeddingConfig) -> Result<()> {
         ));
     }
     
+    // Validate provider-specific settings
+    if config.provider == EmbeddingProviderType::Custom {
+        return Err(ContextError::Config(
+            "Custom embedding providers can't be configured; construct them programmatically".to_string()
+        ));
+    }
+    
+    if config.provider == EmbeddingProviderType::OpenAI
+        && config.model.as_deref().is_none_or(|m| m.trim().is_empty())
+    {
+        return Err(ContextError::Config(
+            "Embedding model is required for the OpenAI provider".to_string()
+        ));
+    }
+    
     // Validate API token
     if config.api_token.expose_secret().is_empty() {
         return Err(ContextError::Config(
@@ -48,6 +76,18 @@ fn validate_embedding_config(config: &EmbeddingConfig) -> Result<()> {
         ));
     }
     
+    if config.max_batch_bytes == 0 {
+        return Err(ContextError::Config(
+            "Embedding max batch bytes must be greater than 0".to_string()
+        ));
+    }
+    
+    if config.max_concurrent_requests == Some(0) {
+        return Err(ContextError::Config(
+            "Embedding max concurrent requests must be greater than 0".to_string()
+        ));
+    }
+    
     // Validate timeout
     if config.timeout_secs == 0 {
         return Err(ContextError::Config(
@@ -61,6 +101,50 @@ fn validate_embedding_config(config: &EmbeddingConfig) -> Result<()> {
         ));
     }
     
+    if config.total_timeout_secs == Some(0) {
+        return Err(ContextError::Config(
+            "Embedding total timeout must be greater than 0".to_string()
+        ));
+    }
+    
+    if config.response_timeout_secs == Some(0) {
+        return Err(ContextError::Config(
+            "Embedding response timeout must be greater than 0".to_string()
+        ));
+    }
+    
+    if let Some(max_norm) = config.max_vector_norm {
+        if !(max_norm.is_finite() && max_norm > 0.0) {
+            return Err(ContextError::Config(
+                "Embedding max vector norm must be a positive number".to_string()
+            ));
+        }
+    }
+    
+    // Validate dimension against the model registry
+    if config.dimension == Some(0) {
+        return Err(ContextError::Config(
+            "Embedding dimension must be greater than 0".to_string()
+        ));
+    }
+    
+    if config.dimension.is_some_and(|dimension| dimension > 4096) {
+        return Err(ContextError::Config(
+            "Embedding dimension too large (max: 4096)".to_string()
+        ));
+    }
+    
+    if let (Some(model), Some(dimension)) = (config.model.as_deref(), config.dimension) {
+        if let Some(known) = crate::embedding::registry::known_dimension(model) {
+            if known != dimension {
+                return Err(ContextError::Config(format!(
+                    "Embedding dimension {} does not match model '{}' ({} dimensions)",
+                    dimension, model, known
+                )));
+            }
+        }
+    }
+    
     // Validate max retries
     if config.max_retries > 10 {
         return Err(ContextError::Config(
@@ -81,6 +165,12 @@ fn validate_embedding_config(config: &EmbeddingConfig) -> Result<()> {
                 "Cache TTL must be greater than 0 when cache is enabled".to_string()
             ));
         }
+        
+        if config.query_cache_ttl_secs == 0 {
+            return Err(ContextError::Config(
+                "Query cache TTL must be greater than 0 when cache is enabled".to_string()
+            ));
+        }
     }
     
     Ok(())
@@ -141,6 +231,26 @@ fn validate_vector_db_config(config: &VectorDbConfig) -> Result<()> {
         ));
     }
     
+    if config.search_hnsw_ef == Some(0) {
+        return Err(ContextError::Config(
+            "search_hnsw_ef must be greater than 0 when set".to_string()
+        ));
+    }
+    
+    if config.max_concurrent_collection_creates == 0 {
+        return Err(ContextError::Config(
+            "max_concurrent_collection_creates must be greater than 0".to_string()
+        ));
+    }
+    
+    if let Some(Quantization::Scalar { quantile: Some(quantile) }) = config.quantization {
+        if !(0.5..=1.0).contains(&quantile) {
+            return Err(ContextError::Config(
+                "Scalar quantization quantile must be between 0.5 and 1.0".to_string()
+            ));
+        }
+    }
+    
     // Fatal error if TLS verify disabled in release mode
     #[cfg(not(debug_assertions))]
     {
@@ -182,6 +292,18 @@ fn validate_hirag_config(config: &HiRAGConfig) -> Result<()> {
         ));
     }
     
+    if config.max_contexts_per_level == Some(0) {
+        return Err(ContextError::Config(
+            "Max contexts per level must be greater than 0".to_string()
+        ));
+    }
+    
+    if config.history_max_entries == Some(0) {
+        return Err(ContextError::Config(
+            "History max entries must be greater than 0".to_string()
+        ));
+    }
+    
     // Validate max context tokens
     if config.max_context_tokens == 0 {
         return Err(ContextError::Config(
@@ -202,6 +324,26 @@ fn validate_hirag_config(config: &HiRAGConfig) -> Result<()> {
         ));
     }
     
+    if let Some(lambda) = config.mmr_lambda {
+        if !(0.0..=1.0).contains(&lambda) {
+            return Err(ContextError::Config(
+                "MMR lambda must be between 0.0 and 1.0".to_string()
+            ));
+        }
+    }
+    
+    if config.recency_half_life_secs == 0 {
+        return Err(ContextError::Config(
+            "Recency half-life must be greater than 0".to_string()
+        ));
+    }
+    
+    if config.max_filter_conditions == 0 {
+        return Err(ContextError::Config(
+            "Max filter conditions must be greater than 0".to_string()
+        ));
+    }
+    
     // Validate ranking weights
     let weights = &config.ranking_weights;
     if weights.similarity_weight < 0.0 || weights.similarity_weight > 1.0 {
@@ -236,6 +378,23 @@ fn validate_hirag_config(config: &HiRAGConfig) -> Result<()> {
         ));
     }
     
+    if let Some(warning) = check_similarity_weight(config)? {
+        tracing::warn!("{}", warning);
+    }
+    
+    // Validate re-embedding rate
+    if config.reembed_batch_size == 0 {
+        return Err(ContextError::Config(
+            "Re-embed batch size must be greater than 0".to_string()
+        ));
+    }
+    
+    if config.reembed_interval_secs == 0 {
+        return Err(ContextError::Config(
+            "Re-embed interval must be greater than 0".to_string()
+        ));
+    }
+    
     Ok(())
 }
 
@@ -264,6 +423,26 @@ fn validate_protocol_config(config: &ProtocolConfig) -> Result<()> {
     Ok(())
 }
 
+/// Check that ranking leans on vector similarity enough for retrieval to work
+///
+/// Returns a warning to log when `similarity_weight` is below
+/// [`MIN_SIMILARITY_WEIGHT`], or an error instead with `strict_weights` set.
+fn check_similarity_weight(config: &HiRAGConfig) -> Result<Option<String>> {
+    let similarity = config.ranking_weights.similarity_weight;
+    if similarity >= MIN_SIMILARITY_WEIGHT {
+        return Ok(None);
+    }
+    
+    let message = format!(
+        "Similarity weight {:.2} is below {:.2}; retrieval will rank mostly by recency, level and frequency",
+        similarity, MIN_SIMILARITY_WEIGHT
+    );
+    if config.strict_weights {
+        return Err(ContextError::Config(message));
+    }
+    Ok(Some(message))
+}
+
 /// Validate server configuration
 pub fn validate_server_config(config: &ServerConfig) -> Result<()> {
     // Validate port range
@@ -280,6 +459,18 @@ pub fn validate_server_config(config: &ServerConfig) -> Result<()> {
         ));
     }
     
+    if config.max_allowed_tokens == 0 {
+        return Err(ContextError::Config(
+            "Server max allowed tokens must be greater than 0".to_string()
+        ));
+    }
+    
+    if config.metrics_label_limit == 0 {
+        return Err(ContextError::Config(
+            "Metrics label limit must be greater than 0".to_string()
+        ));
+    }
+    
     Ok(())
 }
 
@@ -317,6 +508,59 @@ mod tests {
         assert!(validate_embedding_config(&config.embedding).is_err());
     }
     
+    #[test]
+    fn test_openai_provider_requires_model() {
+        let mut config = Config::default_config();
+        config.embedding.api_token = Secret::new("test_token".to_string());
+        config.embedding.provider = EmbeddingProviderType::OpenAI;
+        
+        assert!(validate_embedding_config(&config.embedding).is_err());
+        
+        config.embedding.model = Some("text-embedding-3-small".to_string());
+        assert!(validate_embedding_config(&config.embedding).is_ok());
+        
+        config.embedding.provider = EmbeddingProviderType::Custom;
+        assert!(validate_embedding_config(&config.embedding).is_err());
+    }
+    
+    #[test]
+    fn test_known_model_sets_dimension() {
+        let mut config = Config::default_config();
+        config.embedding.api_token = Secret::new("test_token".to_string());
+        config.embedding.model = Some("BAAI/bge-small-en-v1.5".to_string());
+        
+        config.resolve_embedding_dimension().unwrap();
+        assert_eq!(config.embedding.dimension, Some(384));
+        assert_eq!(config.vector_db.vector_size, 384);
+        assert!(validate_config(&config).is_ok());
+    }
+    
+    #[test]
+    fn test_mismatched_dimension_for_known_model_is_rejected() {
+        let mut config = Config::default_config();
+        config.embedding.api_token = Secret::new("test_token".to_string());
+        config.embedding.model = Some("text-embedding-3-small".to_string());
+        config.embedding.dimension = Some(1024);
+        
+        assert!(config.resolve_embedding_dimension().is_err());
+        assert!(validate_embedding_config(&config.embedding).is_err());
+    }
+    
+    #[test]
+    fn test_unknown_model_uses_explicit_dimension() {
+        let mut config = Config::default_config();
+        config.embedding.api_token = Secret::new("test_token".to_string());
+        config.embedding.model = Some("in-house-embedder".to_string());
+        config.embedding.dimension = Some(640);
+        
+        config.resolve_embedding_dimension().unwrap();
+        assert_eq!(config.vector_db.vector_size, 640);
+        assert!(validate_config(&config).is_ok());
+        
+        config.embedding.dimension = Some(8192);
+        assert!(validate_embedding_config(&config.embedding).is_err());
+    }
+    
     #[test]
     fn test_invalid_vector_size() {
         let mut config = Config::default_config();
@@ -325,6 +569,16 @@ mod tests {
         assert!(validate_vector_db_config(&config.vector_db).is_err());
     }
     
+    #[test]
+    fn test_invalid_quantization_quantile() {
+        let mut config = Config::default_config();
+        config.vector_db.quantization = Some(Quantization::Scalar { quantile: Some(0.99) });
+        assert!(validate_vector_db_config(&config.vector_db).is_ok());
+        
+        config.vector_db.quantization = Some(Quantization::Scalar { quantile: Some(0.2) });
+        assert!(validate_vector_db_config(&config.vector_db).is_err());
+    }
+    
     #[test]
     fn test_invalid_relevance_threshold() {
         let mut config = Config::default_config();
@@ -343,4 +597,22 @@ mod tests {
         
         assert!(validate_hirag_config(&config.hirag).is_err());
     }
+    
+    #[test]
+    fn test_zero_similarity_weight_warns() {
+        let mut config = Config::default_config();
+        assert!(check_similarity_weight(&config.hirag).unwrap().is_none());
+        
+        config.hirag.ranking_weights.similarity_weight = 0.0;
+        config.hirag.ranking_weights.recency_weight = 1.0;
+        config.hirag.ranking_weights.level_weight = 0.0;
+        config.hirag.ranking_weights.frequency_weight = 0.0;
+        
+        // Valid, but flagged
+        assert!(check_similarity_weight(&config.hirag).unwrap().is_some());
+        assert!(validate_hirag_config(&config.hirag).is_ok());
+        
+        config.hirag.strict_weights = true;
+        assert!(validate_hirag_config(&config.hirag).is_err());
+    }
 }
\ No newline at end of file
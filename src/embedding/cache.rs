@@ -1,8 +1,15 @@
 //! High-performance caching layer for embeddings using moka
 
+use crate::error::{EmbeddingError, Result};
 use moka::future::Cache;
-use std::time::Duration;
-use tracing::{debug, info};
+use moka::Expiry;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::Path;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{Duration, Instant};
+use tokio::time::Instant as TokioInstant;
+use tracing::{debug, info, warn};
 
 /// Statistics about cache performance
 #[derive(Debug, Clone)]
@@ -13,42 +20,116 @@ pub struct CacheStats {
     pub hit_rate: f64,
 }
 
+/// A cached embedding together with the time it may live in the cache
+#[derive(Debug, Clone)]
+pub struct CachedEmbedding {
+    pub embedding: Vec<f32>,
+    pub ttl: Duration,
+    pub stored_at: TokioInstant,
+}
+
+impl CachedEmbedding {
+    /// Whether the entry has outlived its TTL on the runtime clock
+    fn is_expired(&self) -> bool {
+        self.stored_at.elapsed() >= self.ttl
+    }
+}
+
+/// Expires each entry after its own TTL
+struct PerEntryTtl;
+
+impl Expiry<String, CachedEmbedding> for PerEntryTtl {
+    fn expire_after_create(&self, _key: &String, value: &CachedEmbedding, _created_at: Instant) -> Option<Duration> {
+        Some(value.ttl)
+    }
+    
+    fn expire_after_update(
+        &self,
+        _key: &String,
+        value: &CachedEmbedding,
+        _updated_at: Instant,
+        _duration_until_expiry: Option<Duration>,
+    ) -> Option<Duration> {
+        Some(value.ttl)
+    }
+}
+
 /// High-performance async cache for embeddings using moka
 pub struct EmbeddingCache {
-    cache: Cache<String, Vec<f32>>,
+    cache: Cache<String, CachedEmbedding>,
+    ttl: Duration,
+    model_id: Option<String>,
+    hits: AtomicU64,
+    misses: AtomicU64,
+}
+
+/// On-disk representation of the cache contents
+#[derive(Debug, Serialize, Deserialize)]
+struct CacheSnapshot {
+    model: Option<String>,
+    entries: HashMap<String, Vec<f32>>,
 }
 
 impl EmbeddingCache {
-    /// Create a new cache with specified capacity and TTL
+    /// Create a new cache with specified capacity and default TTL
     pub fn new(max_size: usize, ttl: Duration) -> Self {
         info!("Initializing embedding cache with max_size={}, ttl={:?}", max_size, ttl);
         
         let cache = Cache::builder()
             .max_capacity(max_size as u64)
-            .time_to_live(ttl)
-            .time_to_idle(ttl / 2) // Evict if not accessed for half the TTL
+            .expire_after(PerEntryTtl)
             .build();
         
-        Self { cache }
+        Self {
+            cache,
+            ttl,
+            model_id: None,
+            hits: AtomicU64::new(0),
+            misses: AtomicU64::new(0),
+        }
+    }
+    
+    /// Record the model the cached embeddings were produced by
+    ///
+    /// Snapshots written for a different model are ignored by [`load`](Self::load).
+    pub fn with_model_id(mut self, model_id: impl Into<String>) -> Self {
+        self.model_id = Some(model_id.into());
+        self
     }
     
     /// Get embedding from cache
+    ///
+    /// Entries past their TTL count as misses even before moka evicts them.
     pub async fn get(&self, key: &str) -> Option<Vec<f32>> {
-        let result = self.cache.get(key).await;
+        let result = match self.cache.get(key).await {
+            Some(entry) if entry.is_expired() => {
+                self.cache.invalidate(key).await;
+                None
+            }
+            entry => entry.map(|entry| entry.embedding),
+        };
         
         if result.is_some() {
+            self.hits.fetch_add(1, Ordering::Relaxed);
             debug!("Cache hit for key: {}", key);
         } else {
+            self.misses.fetch_add(1, Ordering::Relaxed);
             debug!("Cache miss for key: {}", key);
         }
         
         result
     }
     
-    /// Store embedding in cache
+    /// Store embedding in cache with the default TTL
     pub async fn put(&self, key: String, embedding: Vec<f32>) {
-        self.cache.insert(key.clone(), embedding).await;
-        debug!("Cached embedding for key: {}", key);
+        self.put_with_ttl(key, embedding, self.ttl).await;
+    }
+    
+    /// Store embedding in cache, expiring after `ttl` instead of the default
+    pub async fn put_with_ttl(&self, key: String, embedding: Vec<f32>, ttl: Duration) {
+        let entry = CachedEmbedding { embedding, ttl, stored_at: TokioInstant::now() };
+        self.cache.insert(key.clone(), entry).await;
+        debug!("Cached embedding for key: {} (ttl={:?})", key, ttl);
     }
     
     /// Clear expired entries (moka handles this automatically)
@@ -65,25 +146,87 @@ impl EmbeddingCache {
         info!("Cache cleared");
     }
     
+    /// Write the cache contents to `path` as JSON
+    pub async fn save(&self, path: impl AsRef<Path>) -> Result<()> {
+        let path = path.as_ref();
+        let snapshot = CacheSnapshot {
+            model: self.model_id.clone(),
+            entries: self.cache.iter()
+                .map(|(key, entry)| (key.as_ref().clone(), entry.embedding))
+                .collect(),
+        };
+        
+        let bytes = serde_json::to_vec(&snapshot)
+            .map_err(|e| EmbeddingError::CacheError(format!("Failed to serialize cache snapshot: {}", e)))?;
+        tokio::fs::write(path, bytes).await
+            .map_err(|e| EmbeddingError::CacheError(format!("Failed to write {}: {}", path.display(), e)))?;
+        
+        info!("Saved {} cached embeddings to {}", snapshot.entries.len(), path.display());
+        Ok(())
+    }
+    
+    /// Populate the cache from a snapshot written by [`save`](Self::save)
+    ///
+    /// A missing file is not an error. A snapshot recorded for a different
+    /// model is skipped, since its embeddings would not match new ones.
+    /// Loaded entries start a fresh TTL.
+    pub async fn load(&self, path: impl AsRef<Path>) -> Result<()> {
+        let path = path.as_ref();
+        let bytes = match tokio::fs::read(path).await {
+            Ok(bytes) => bytes,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+                debug!("No embedding cache snapshot at {}", path.display());
+                return Ok(());
+            }
+            Err(e) => {
+                return Err(EmbeddingError::CacheError(format!("Failed to read {}: {}", path.display(), e)).into());
+            }
+        };
+        
+        let snapshot: CacheSnapshot = serde_json::from_slice(&bytes)
+            .map_err(|e| EmbeddingError::CacheError(format!("Invalid cache snapshot {}: {}", path.display(), e)))?;
+        
+        if snapshot.model != self.model_id {
+            warn!(
+                "Ignoring embedding cache snapshot {} recorded for model {:?} (current: {:?})",
+                path.display(), snapshot.model, self.model_id
+            );
+            return Ok(());
+        }
+        
+        let count = snapshot.entries.len();
+        for (key, embedding) in snapshot.entries {
+            self.put(key, embedding).await;
+        }
+        
+        info!("Loaded {} cached embeddings from {}", count, path.display());
+        Ok(())
+    }
+    
     /// Get cache statistics
     pub async fn stats(&self) -> CacheStats {
         // Run pending tasks to update stats
         self.cache.run_pending_tasks().await;
         
         let entry_count = self.cache.entry_count();
+        let hits = self.hits.load(Ordering::Relaxed);
+        let misses = self.misses.load(Ordering::Relaxed);
+        let hit_rate = if hits + misses > 0 {
+            hits as f64 / (hits + misses) as f64
+        } else {
+            0.0
+        };
         
-        // Moka doesn't expose hit/miss counts directly in the current version
-        // We'll return approximate stats based on entry count
         CacheStats {
             size: entry_count as usize,
-            hits: 0, // Not available in current moka version
-            misses: 0, // Not available in current moka version
-            hit_rate: 0.0, // Not available in current moka version
+            hits,
+            misses,
+            hit_rate,
         }
     }
     
     /// Get the underlying cache for advanced operations
-    pub fn inner(&self) -> &Cache<String, Vec<f32>> {
+    pub fn inner(&self) -> &Cache<String, CachedEmbedding> {
         &self.cache
     }
 }
@@ -111,19 +254,32 @@ mod tests {
         assert_eq!(result, None);
     }
     
-    #[tokio::test]
+    #[tokio::test(start_paused = true)]
     async fn test_cache_ttl() {
-        let cache = EmbeddingCache::new(10, Duration::from_millis(100));
+        let cache = EmbeddingCache::new(10, Duration::from_secs(60));
         let embedding = vec![1.0, 2.0, 3.0];
         
         cache.put("test".to_string(), embedding.clone()).await;
-        tokio::time::sleep(Duration::from_millis(150)).await;
+        tokio::time::advance(Duration::from_secs(59)).await;
+        assert_eq!(cache.get("test").await, Some(embedding));
         
-        // Run pending tasks to process expirations
-        cache.cache.run_pending_tasks().await;
+        tokio::time::advance(Duration::from_secs(1)).await;
+        assert_eq!(cache.get("test").await, None);
+    }
+    
+    #[tokio::test(start_paused = true)]
+    async fn test_short_ttl_entry_expires_first() {
+        let cache = EmbeddingCache::new(10, Duration::from_secs(600));
         
-        let result = cache.get("test").await;
-        assert_eq!(result, None);
+        cache.put_with_ttl("query".to_string(), vec![1.0], Duration::from_secs(60)).await;
+        cache.put("passage".to_string(), vec![2.0]).await;
+        
+        tokio::time::advance(Duration::from_secs(45)).await;
+        assert_eq!(cache.get("query").await, Some(vec![1.0]));
+        tokio::time::advance(Duration::from_secs(15)).await;
+        
+        assert_eq!(cache.get("query").await, None);
+        assert_eq!(cache.get("passage").await, Some(vec![2.0]));
     }
     
     #[tokio::test]
@@ -136,8 +292,53 @@ mod tests {
         cache.get("nonexistent").await;
         
         let stats = cache.stats().await;
-        // Moka doesn't expose hit/miss counts in the current version
-        // Just verify we can get stats without errors
         assert!(stats.size <= 10);
+        assert_eq!(stats.hits, 1);
+        assert_eq!(stats.misses, 1);
     }
-}
\ No newline at end of file
+    
+    #[tokio::test]
+    async fn test_cache_hit_rate() {
+        let cache = EmbeddingCache::new(10, Duration::from_secs(60));
+        cache.put("a".to_string(), vec![1.0]).await;
+        cache.put("b".to_string(), vec![2.0]).await;
+        
+        for key in ["a", "b", "a", "missing"] {
+            cache.get(key).await;
+        }
+        
+        let stats = cache.stats().await;
+        assert_eq!(stats.size, 2);
+        assert_eq!(stats.hits, 3);
+        assert_eq!(stats.misses, 1);
+        assert!((stats.hit_rate - 0.75).abs() < f64::EPSILON);
+    }
+    
+    fn snapshot_path() -> std::path::PathBuf {
+        std::env::temp_dir().join(format!("embedding-cache-{}.json", uuid::Uuid::new_v4()))
+    }
+    
+    #[tokio::test]
+    async fn test_snapshot_roundtrip() {
+        let path = snapshot_path();
+        let cache = EmbeddingCache::new(10, Duration::from_secs(60)).with_model_id("model-a");
+        cache.put("one".to_string(), vec![1.0, 2.0]).await;
+        cache.put("two".to_string(), vec![3.0, 4.0]).await;
+        cache.save(&path).await.unwrap();
+        
+        let restored = EmbeddingCache::new(10, Duration::from_secs(60)).with_model_id("model-a");
+        restored.load(&path).await.unwrap();
+        assert_eq!(restored.get("one").await, Some(vec![1.0, 2.0]));
+        assert_eq!(restored.get("two").await, Some(vec![3.0, 4.0]));
+        
+        // A different model must not reuse the snapshot
+        let other = EmbeddingCache::new(10, Duration::from_secs(60)).with_model_id("model-b");
+        other.load(&path).await.unwrap();
+        assert_eq!(other.get("one").await, None);
+        
+        tokio::fs::remove_file(&path).await.unwrap();
+        
+        // Loading a missing snapshot is a no-op
+        restored.load(&path).await.unwrap();
+    }
+}
@@ -1,6 +1,8 @@
 //! Embedding client for Chutes API
 
-use super::{EmbeddingProvider, EmbeddingCache, models::*};
+use super::{EmbeddingProvider, EmbeddingCache, Jitter, models::*};
+use super::magnitude::check_magnitude;
+use super::normalize::normalize_text;
 use crate::config::EmbeddingConfig;
 use crate::error::{EmbeddingError, Result};
 use async_trait::async_trait;
@@ -15,6 +17,7 @@ pub struct EmbeddingClient {
     config: EmbeddingConfig,
     http_client: Client,
     cache: Option<Arc<EmbeddingCache>>,
+    jitter: Jitter,
 }
 
 impl EmbeddingClient {
@@ -54,6 +57,7 @@ impl EmbeddingClient {
             config,
             http_client,
             cache,
+            jitter: Jitter::default(),
         })
     }
     
@@ -72,6 +76,7 @@ impl EmbeddingClient {
             config,
             http_client,
             cache,
+            jitter: Jitter::default(),
         })
     }
     
@@ -81,6 +86,25 @@ impl EmbeddingClient {
         self
     }
     
+    /// Use a specific jitter source for retry backoff
+    pub fn with_jitter(mut self, jitter: Jitter) -> Self {
+        self.jitter = jitter;
+        self
+    }
+    
+    /// Backoff before retrying after the given (1-based) failed attempt
+    fn retry_delay(&self, attempts: u32) -> Duration {
+        // Exponential backoff with jitter
+        let base_delay = 100 * 2_u64.pow(attempts - 1);
+        let max_delay = 30_000; // Cap at 30 seconds
+        let delay = base_delay.min(max_delay);
+        
+        // Add jitter (±25%)
+        let jitter = self.jitter.sample()
+            .map_or(0, |fraction| (delay as f64 * 0.25 * (fraction - 0.5)) as i64);
+        Duration::from_millis((delay as i64 + jitter).max(0) as u64)
+    }
+    
     /// Generate cache key for text
     fn cache_key(&self, text: &str) -> String {
         use std::collections::hash_map::DefaultHasher;
@@ -91,8 +115,19 @@ impl EmbeddingClient {
         format!("emb_{:x}", hasher.finish())
     }
     
-    /// Make API request with retry logic
+    /// Make API request with retry logic, bounded by `total_timeout_secs`
     async fn make_request(&self, request: &EmbeddingRequest) -> Result<EmbeddingResponse> {
+        let Some(total_timeout) = self.config.total_timeout_secs else {
+            return self.retry_request(request).await;
+        };
+        
+        tokio::time::timeout(Duration::from_secs(total_timeout), self.retry_request(request))
+            .await
+            .unwrap_or(Err(EmbeddingError::Timeout(total_timeout).into()))
+    }
+    
+    /// Make API request with retry logic
+    async fn retry_request(&self, request: &EmbeddingRequest) -> Result<EmbeddingResponse> {
         let mut attempts = 0;
         let mut last_error = None;
         
@@ -109,15 +144,7 @@ impl EmbeddingClient {
                     last_error = Some(e);
                     
                     if attempts < self.config.max_retries {
-                        // Exponential backoff with jitter
-                        let base_delay = 100 * 2_u64.pow(attempts - 1);
-                        let max_delay = 30_000; // Cap at 30 seconds
-                        let delay = base_delay.min(max_delay);
-                        
-                        // Add jitter (±25%)
-                        let jitter = (delay as f64 * 0.25 * (rand::random::<f64>() - 0.5)) as u64;
-                        let final_delay = Duration::from_millis((delay as i64 + jitter as i64).max(0) as u64);
-                        
+                        let final_delay = self.retry_delay(attempts);
                         debug!("Retrying after {}ms", final_delay.as_millis());
                         tokio::time::sleep(final_delay).await;
                     }
@@ -171,11 +198,9 @@ impl EmbeddingClient {
             }
         }
     }
-}
-
-#[async_trait]
-impl EmbeddingProvider for EmbeddingClient {
-    async fn embed_single(&self, text: &str) -> Result<Vec<f32>> {
+    
+    /// Embed one text, caching the result for `ttl`
+    async fn embed_one(&self, text: &str, ttl: Duration) -> Result<Vec<f32>> {
         if text.is_empty() {
             return Err(EmbeddingError::InvalidInput("Text cannot be empty".to_string()).into());
         }
@@ -185,6 +210,8 @@ impl EmbeddingProvider for EmbeddingClient {
                 format!("Text too long: {} characters (max 8192)", text.len())
             ).into());
         }
+        let normalized = normalize_text(text, &self.config.normalization);
+        let text = normalized.as_ref();
         
         // Check cache first
         if let Some(cache) = &self.cache {
@@ -203,18 +230,20 @@ impl EmbeddingProvider for EmbeddingClient {
             return Err(EmbeddingError::ApiError("No embeddings returned".to_string()).into());
         }
         
-        let embedding = response.data[0].embedding.clone();
+        let mut embedding = response.data[0].embedding.clone();
+        check_magnitude(&mut embedding, &self.config)?;
         
         // Cache the result
         if let Some(cache) = &self.cache {
             let key = self.cache_key(text);
-            cache.put(key, embedding.clone()).await;
+            cache.put_with_ttl(key, embedding.clone(), ttl).await;
         }
         
         Ok(embedding)
     }
     
-    async fn embed_batch(&self, texts: &[String]) -> Result<Vec<Vec<f32>>> {
+    /// Embed several texts, caching new results for `ttl`
+    async fn embed_many(&self, texts: &[String], ttl: Duration) -> Result<Vec<Vec<f32>>> {
         if texts.is_empty() {
             return Err(EmbeddingError::InvalidInput("Texts cannot be empty".to_string()).into());
         }
@@ -224,6 +253,11 @@ impl EmbeddingProvider for EmbeddingClient {
                 format!("Batch size {} exceeds maximum {}", texts.len(), self.config.batch_size)
             ).into());
         }
+        let normalized: Vec<String> = texts
+            .iter()
+            .map(|text| normalize_text(text, &self.config.normalization).into_owned())
+            .collect();
+        let texts = normalized.as_slice();
         
         // Check cache for all texts
         let mut results = Vec::with_capacity(texts.len());
@@ -257,11 +291,12 @@ impl EmbeddingProvider for EmbeddingClient {
             // Cache and store results
             for (i, embedding_data) in response.data.iter().enumerate() {
                 let original_index = uncached_indices[i];
-                let embedding = embedding_data.embedding.clone();
+                let mut embedding = embedding_data.embedding.clone();
+                check_magnitude(&mut embedding, &self.config)?;
                 
                 if let Some(cache) = &self.cache {
                     let key = self.cache_key(&uncached_texts[i]);
-                    cache.put(key, embedding.clone()).await;
+                    cache.put_with_ttl(key, embedding.clone(), ttl).await;
                 }
                 
                 results[original_index] = Some(embedding);
@@ -275,9 +310,30 @@ impl EmbeddingProvider for EmbeddingClient {
             ))
             .collect()
     }
+}
+
+#[async_trait]
+impl EmbeddingProvider for EmbeddingClient {
+    async fn embed_single(&self, text: &str) -> Result<Vec<f32>> {
+        self.embed_one(text, Duration::from_secs(self.config.cache_ttl_secs)).await
+    }
+    
+    async fn embed_query(&self, text: &str) -> Result<Vec<f32>> {
+        self.embed_one(text, Duration::from_secs(self.config.query_cache_ttl_secs)).await
+    }
+    
+    async fn embed_batch(&self, texts: &[String]) -> Result<Vec<Vec<f32>>> {
+        self.embed_many(texts, Duration::from_secs(self.config.cache_ttl_secs)).await
+    }
+    
+    async fn embed_query_batch(&self, texts: &[String]) -> Result<Vec<Vec<f32>>> {
+        self.embed_many(texts, Duration::from_secs(self.config.query_cache_ttl_secs)).await
+    }
     
     fn embedding_dimension(&self) -> usize {
-        1024 // intfloat/multilingual-e5-large produces 1024-dimensional embeddings
+        // Without a configured dimension assume intfloat/multilingual-e5-large,
+        // which produces 1024-dimensional embeddings
+        self.config.dimension.unwrap_or(1024)
     }
 }
 
@@ -288,16 +344,30 @@ mod tests {
     #[test]
     fn test_cache_key_generation() {
         let config = EmbeddingConfig {
+            provider: crate::config::EmbeddingProviderType::Chutes,
             api_url: "http://test".to_string(),
+            model: None,
             api_token: secrecy::Secret::new("test".to_string()),
             batch_size: 32,
+            max_batch_bytes: 1024 * 1024,
             timeout_secs: 30,
             max_retries: 3,
             cache_enabled: false,
             cache_ttl_secs: 3600,
+            query_cache_ttl_secs: 300,
             cache_size: 1000,
             tls_enabled: false,
             tls_verify: true,
+            auto_detect_dimension: false,
+            dimension: None,
+            max_concurrent_requests: None,
+            normalization: Default::default(),
+            max_vector_norm: None,
+            excessive_norm_policy: Default::default(),
+            total_timeout_secs: None,
+            response_timeout_secs: None,
+            cache_snapshot_path: None,
+            max_parse_retries: 1,
         };
         
         let client = EmbeddingClient::new(config).unwrap();
@@ -1,14 +1,18 @@
 //! Enhanced embedding client with improved cache handling and error recovery
 
-use super::{EmbeddingProvider, EmbeddingCache, models::*};
-use crate::config::EmbeddingConfig;
+use super::{EmbeddingProvider, EmbeddingCache, Jitter, models::*};
+use super::magnitude::check_magnitude;
+use super::normalize::normalize_text;
+use crate::config::{EmbeddingConfig, EmbeddingProviderType};
 use crate::error::{EmbeddingError, Result, ContextError};
 use crate::middleware::InputValidator;
 use crate::vector_db::{CircuitBreaker, CircuitBreakerConfig};
 use async_trait::async_trait;
-use reqwest::{Client, StatusCode};
-use std::sync::Arc;
+use chrono::{DateTime, Utc};
+use reqwest::{header::RETRY_AFTER, Client, StatusCode};
+use std::sync::{Arc, OnceLock};
 use std::time::Duration;
+use tokio::sync::{Semaphore, SemaphorePermit};
 use tracing::{debug, error, info, warn};
 use secrecy::ExposeSecret;
 
@@ -18,6 +22,9 @@ pub struct EmbeddingClientV2 {
     http_client: Client,
     cache: Option<Arc<EmbeddingCache>>,
     circuit_breaker: Option<Arc<CircuitBreaker>>,
+    detected_dimension: OnceLock<usize>,
+    jitter: Jitter,
+    request_limit: Option<Arc<Semaphore>>,
 }
 
 impl EmbeddingClientV2 {
@@ -75,6 +82,9 @@ impl EmbeddingClientV2 {
             http_client,
             cache,
             circuit_breaker: None,
+            detected_dimension: OnceLock::new(),
+            jitter: Jitter::default(),
+            request_limit: None,
         })
     }
     
@@ -85,6 +95,11 @@ impl EmbeddingClientV2 {
         self
     }
     
+    /// Circuit breaker guarding API requests, if enabled
+    pub fn circuit_breaker(&self) -> Option<Arc<CircuitBreaker>> {
+        self.circuit_breaker.clone()
+    }
+    
     /// Create client with custom HTTP client
     pub fn with_http_client(config: EmbeddingConfig, http_client: Client) -> Result<Self> {
         // Enforce TLS verification in release builds
@@ -119,6 +134,9 @@ impl EmbeddingClientV2 {
             http_client,
             cache,
             circuit_breaker: None,
+            detected_dimension: OnceLock::new(),
+            jitter: Jitter::default(),
+            request_limit: None,
         })
     }
     
@@ -128,6 +146,66 @@ impl EmbeddingClientV2 {
         self
     }
     
+    /// Use a specific jitter source for rate-limit backoff
+    pub fn with_jitter(mut self, jitter: Jitter) -> Self {
+        self.jitter = jitter;
+        self
+    }
+    
+    /// Cap concurrent API requests with a semaphore that may be shared with
+    /// other clients; a permit is held for the duration of each HTTP call
+    pub fn with_request_limit(mut self, limit: Arc<Semaphore>) -> Self {
+        self.request_limit = Some(limit);
+        self
+    }
+    
+    /// Wait for a request permit if a concurrency cap is configured
+    async fn acquire_request_permit(&self) -> Option<SemaphorePermit<'_>> {
+        match &self.request_limit {
+            // The semaphore is never closed, so acquisition cannot fail
+            Some(limit) => limit.acquire().await.ok(),
+            None => None,
+        }
+    }
+    
+    /// Backoff before retrying after the given (1-based) failed attempt
+    ///
+    /// Rate-limited requests back off longer and add up to a second of jitter.
+    fn retry_delay(&self, attempts: u32, rate_limited: bool) -> Duration {
+        if rate_limited {
+            let backoff = Duration::from_millis(500 * 2_u64.pow(attempts));
+            let jitter = self.jitter.sample()
+                .map_or(0, |fraction| (fraction * 1000.0) as u64);
+            backoff + Duration::from_millis(jitter)
+        } else {
+            Duration::from_millis(100 * 2_u64.pow(attempts))
+        }
+    }
+    
+    /// Record the dimension of a returned embedding when auto-detection is enabled
+    ///
+    /// The first embedding fixes the dimension; any later mismatch is an error.
+    fn check_dimension(&self, embedding: &[f32]) -> Result<()> {
+        if !self.config.auto_detect_dimension {
+            return Ok(());
+        }
+        
+        let detected = *self.detected_dimension.get_or_init(|| {
+            info!("Detected embedding dimension: {}", embedding.len());
+            embedding.len()
+        });
+        
+        if embedding.len() != detected {
+            return Err(ContextError::Embedding(EmbeddingError::ApiError(format!(
+                "Embedding dimension changed from {} to {}",
+                detected,
+                embedding.len()
+            ))));
+        }
+        
+        Ok(())
+    }
+    
     /// Generate cache key for text using SHA-256
     fn cache_key(&self, text: &str) -> String {
         use sha2::{Sha256, Digest};
@@ -137,8 +215,83 @@ impl EmbeddingClientV2 {
         format!("emb_{:x}", hasher.finalize())
     }
     
-    /// Make API request with retry logic and adaptive backoff
+    /// Encode `request` in the configured provider's request format
+    fn request_body(&self, request: &EmbeddingRequest) -> Result<serde_json::Value> {
+        let body = match self.config.provider {
+            EmbeddingProviderType::Chutes => serde_json::to_value(request),
+            // Config validation requires a model for OpenAI
+            EmbeddingProviderType::OpenAI => serde_json::to_value(OpenAIEmbeddingRequest {
+                input: &request.input,
+                model: request.model.as_deref().unwrap_or_default(),
+                encoding_format: "float",
+            }),
+            EmbeddingProviderType::Custom => return Err(unsupported_provider()),
+        };
+        body.map_err(|e| ContextError::Embedding(EmbeddingError::ApiError(format!("Failed to encode request: {}", e))))
+    }
+    
+    /// Decode a successful response in the configured provider's format
+    ///
+    /// OpenAI items must carry their batch index; Chutes items may omit it.
+    fn parse_response(&self, body: &[u8]) -> Result<EmbeddingResponse> {
+        let parsed = match self.config.provider {
+            EmbeddingProviderType::Chutes => serde_json::from_slice::<EmbeddingResponse>(body),
+            EmbeddingProviderType::OpenAI => serde_json::from_slice::<OpenAIEmbeddingResponse>(body).map(Into::into),
+            EmbeddingProviderType::Custom => return Err(unsupported_provider()),
+        };
+        parsed.map_err(|e| ContextError::Embedding(EmbeddingError::ApiError(format!("Failed to parse response: {}", e))))
+    }
+    
+    /// Send one encoded request and read the full response body
+    ///
+    /// Holds a request permit, if a cap is configured, until the body has been
+    /// read so that retries wait for backoff without occupying a slot. Reading
+    /// the body after the headers arrive is bounded by the response timeout.
+    async fn send_request(&self, body: &serde_json::Value) -> Result<(StatusCode, Option<Duration>, Vec<u8>)> {
+        let _permit = self.acquire_request_permit().await;
+        
+        let response = self.http_client
+            .post(&self.config.api_url)
+            .bearer_auth(self.config.api_token.expose_secret())
+            .json(body)
+            .send()
+            .await
+            .map_err(|e| ContextError::Embedding(EmbeddingError::NetworkError(e)))?;
+        let status = response.status();
+        let retry_after = response.headers()
+            .get(RETRY_AFTER)
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| parse_retry_after(value, Utc::now()));
+        
+        let response_timeout = self.config.response_timeout_secs.unwrap_or(self.config.timeout_secs);
+        let body = tokio::time::timeout(Duration::from_secs(response_timeout), response.bytes())
+            .await
+            .map_err(|_| ContextError::Embedding(EmbeddingError::Timeout(response_timeout)))?
+            .map_err(|e| ContextError::Embedding(EmbeddingError::NetworkError(e)))?;
+        
+        Ok((status, retry_after, body.to_vec()))
+    }
+    
+    /// Make API request in the configured provider's format with retry logic,
+    /// bounded by `total_timeout_secs`
+    ///
+    /// Remaining retries are abandoned once the deadline passes.
     async fn make_request(&self, request: &EmbeddingRequest) -> Result<EmbeddingResponse> {
+        let body = self.request_body(request)?;
+        let Some(total_timeout) = self.config.total_timeout_secs else {
+            return self.retry_request(&body).await;
+        };
+        
+        tokio::time::timeout(Duration::from_secs(total_timeout), self.retry_request(&body))
+            .await
+            .unwrap_or_else(|_| {
+                warn!("Embedding request exceeded its {}s deadline", total_timeout);
+                Err(ContextError::Embedding(EmbeddingError::Timeout(total_timeout)))
+            })
+    }
+    
+    /// Make API request with retry logic and adaptive backoff
+    async fn retry_request(&self, request: &serde_json::Value) -> Result<EmbeddingResponse> {
         // Check circuit breaker first
         if let Some(cb) = &self.circuit_breaker {
             if !cb.allow_request().await {
@@ -149,47 +302,43 @@ impl EmbeddingClientV2 {
             }
         }
         
-        let mut attempts = 0;
+        let mut attempts: u32 = 0;
+        let mut parse_failures: u32 = 0;
         let max_retries = self.config.max_retries;
         
         loop {
             attempts += 1;
             
-            match self.http_client
-                .post(&self.config.api_url)
-                .bearer_auth(self.config.api_token.expose_secret())
-                .json(request)
-                .send()
-                .await
-            {
-                Ok(response) => {
+            match self.send_request(request).await {
+                Ok((status, retry_after, body)) => {
                     // Record success for circuit breaker
                     if let Some(cb) = &self.circuit_breaker {
                         cb.record_success().await;
                     }
                     
-                    let status = response.status();
                     if status.is_success() {
-                        match response.json::<EmbeddingResponse>().await {
+                        match self.parse_response(&body) {
                             Ok(embedding_response) => {
                                 debug!("Embedding request successful after {} attempts", attempts);
                                 return Ok(embedding_response);
                             }
                             Err(e) => {
-                                error!("Failed to parse embedding response: {}", e);
+                                error!("Failed to parse embedding response: {} (body: {})", e, body_excerpt(&body));
                                 // Record failure for circuit breaker
                                 if let Some(cb) = &self.circuit_breaker {
                                     cb.record_failure().await;
                                 }
                                 
-                                if attempts <= max_retries {
-                                    let backoff = Duration::from_millis(100 * (2_u64.pow(attempts as u32)));
+                                // Parse failures have their own, usually smaller, budget
+                                parse_failures += 1;
+                                if parse_failures <= self.config.max_parse_retries {
+                                    let backoff = self.retry_delay(attempts, false);
                                     debug!("Retrying embedding request in {:?}", backoff);
                                     tokio::time::sleep(backoff).await;
                                     continue;
                                 }
                                 
-                                return Err(ContextError::Embedding(EmbeddingError::ApiError(format!("Failed to parse response: {}", e))));
+                                return Err(e);
                             }
                         }
                     } else {
@@ -198,16 +347,15 @@ impl EmbeddingClientV2 {
                             cb.record_failure().await;
                         }
                         
-                        let error_text = response.text().await.unwrap_or_else(|_| "Unknown error".to_string());
+                        let error_text = String::from_utf8_lossy(&body).into_owned();
                         error!("Embedding API error {}: {}", status, error_text);
                         
                         match status {
                             StatusCode::TOO_MANY_REQUESTS => {
                                 if attempts <= max_retries {
-                                    // Exponential backoff with jitter for rate limiting
-                                    let backoff = Duration::from_millis(500 * (2_u64.pow(attempts as u32)));
-                                    let jitter = Duration::from_millis(rand::random::<u64>() % 1000);
-                                    let total_backoff = backoff + jitter;
+                                    // Honour the server's Retry-After, else exponential backoff with jitter
+                                    let total_backoff = retry_after
+                                        .unwrap_or_else(|| self.retry_delay(attempts, true));
                                     debug!("Rate limited, retrying in {:?}", total_backoff);
                                     tokio::time::sleep(total_backoff).await;
                                     continue;
@@ -218,7 +366,7 @@ impl EmbeddingClientV2 {
                             }
                             _ => {
                                 if attempts <= max_retries {
-                                    let backoff = Duration::from_millis(100 * (2_u64.pow(attempts as u32)));
+                                    let backoff = self.retry_delay(attempts, false);
                                     debug!("Retrying embedding request in {:?}", backoff);
                                     tokio::time::sleep(backoff).await;
                                     continue;
@@ -235,74 +383,77 @@ impl EmbeddingClientV2 {
                         cb.record_failure().await;
                     }
                     
-                    error!("Network error during embedding request: {}", e);
+                    error!("Error during embedding request: {}", e);
                     
                     if attempts <= max_retries {
-                        let backoff = Duration::from_millis(100 * (2_u64.pow(attempts as u32)));
+                        let backoff = self.retry_delay(attempts, false);
                         debug!("Retrying embedding request in {:?}", backoff);
                         tokio::time::sleep(backoff).await;
                         continue;
                     }
                     
-                    return Err(ContextError::Embedding(EmbeddingError::NetworkError(e)));
+                    return Err(e);
                 }
             }
         }
     }
-}
-
-#[async_trait]
-impl EmbeddingProvider for EmbeddingClientV2 {
-    /// Generate embedding for a single text
-    async fn embed_single(&self, text: &str) -> Result<Vec<f32>> {
+    
+    /// Embed one text, caching the result for `ttl`
+    async fn embed_one(&self, text: &str, ttl: Duration) -> Result<Vec<f32>> {
         // Validate input
         InputValidator::validate_text(text)?;
+        let text = normalize_text(text, &self.config.normalization);
         
         // Check cache first
+        let cache_key = self.cache_key(&text);
         if let Some(cache) = &self.cache {
-            if let Some(cached) = cache.get(text).await {
+            if let Some(cached) = cache.get(&cache_key).await {
                 debug!("Cache hit for embedding");
                 return Ok(cached);
             }
         }
         
-        // Generate cache key
-        let cache_key = self.cache_key(text);
-        
         // Prepare request
-        let request = EmbeddingRequest::single(text.to_string());
+        let request = EmbeddingRequest::single(text.to_string())
+            .with_model(self.config.model.clone());
         
         // Make request
         let response = self.make_request(&request).await?;
         
         // Extract embedding
-        let embedding = response
+        let mut embedding = response
             .data
             .into_iter()
             .next()
             .ok_or_else(|| ContextError::Embedding(EmbeddingError::ApiError("No embedding in response".to_string())))?
             .embedding;
+        self.check_dimension(&embedding)?;
+        check_magnitude(&mut embedding, &self.config)?;
         
         // Store in cache
         if let Some(cache) = &self.cache {
-            cache.put(cache_key, embedding.clone()).await;
+            cache.put_with_ttl(cache_key, embedding.clone(), ttl).await;
         }
         
         Ok(embedding)
     }
     
-    /// Generate embeddings for multiple texts
-    async fn embed_batch(&self, texts: &[String]) -> Result<Vec<Vec<f32>>> {
+    /// Embed several texts, caching new results for `ttl`
+    async fn embed_many(&self, texts: &[String], ttl: Duration) -> Result<Vec<Vec<f32>>> {
         // Validate inputs
         for text in texts {
             InputValidator::validate_text(text)?;
         }
+        let normalized: Vec<String> = texts
+            .iter()
+            .map(|text| normalize_text(text, &self.config.normalization).into_owned())
+            .collect();
+        let texts = normalized.as_slice();
         
-        // Process in batches
+        // Process in batches bounded by both item count and payload size
         let mut results = Vec::new();
-        let batch_size = self.config.batch_size;
         
-        for chunk in texts.chunks(batch_size) {
+        for chunk in batch_chunks(texts, self.config.batch_size, self.config.max_batch_bytes) {
             // Check cache for all items in batch
             let mut batch_results = Vec::new();
             let mut uncached_texts = Vec::new();
@@ -310,7 +461,7 @@ impl EmbeddingProvider for EmbeddingClientV2 {
             
             if let Some(cache) = &self.cache {
                 for (i, text) in chunk.iter().enumerate() {
-                    if let Some(cached) = cache.get(text).await {
+                    if let Some(cached) = cache.get(&self.cache_key(text)).await {
                         batch_results.push((i, cached));
                     } else {
                         uncached_texts.push(text.clone());
@@ -324,15 +475,17 @@ impl EmbeddingProvider for EmbeddingClientV2 {
             
             // Generate embeddings for uncached texts
             if !uncached_texts.is_empty() {
-                let request = EmbeddingRequest::batch(uncached_texts.clone());
+                let request = EmbeddingRequest::batch(uncached_texts.clone())
+                    .with_model(self.config.model.clone());
                 
                 let response = self.make_request(&request).await?;
                 
                 // Extract embeddings and store in cache
-                for (i, embedding_data) in response.data.into_iter().enumerate() {
-                    let embedding = embedding_data.embedding;
+                for (i, mut embedding) in order_by_index(response.data, uncached_texts.len())?.into_iter().enumerate() {
+                    self.check_dimension(&embedding)?;
+                    check_magnitude(&mut embedding, &self.config)?;
                     if let Some(cache) = &self.cache {
-                        cache.put(self.cache_key(&uncached_texts[i]), embedding.clone()).await;
+                        cache.put_with_ttl(self.cache_key(&uncached_texts[i]), embedding.clone(), ttl).await;
                     }
                     batch_results.push((uncached_indices[i], embedding));
                 }
@@ -345,34 +498,569 @@ impl EmbeddingProvider for EmbeddingClientV2 {
         
         Ok(results)
     }
+}
+
+#[async_trait]
+impl EmbeddingProvider for EmbeddingClientV2 {
+    /// Generate embedding for a single text
+    async fn embed_single(&self, text: &str) -> Result<Vec<f32>> {
+        self.embed_one(text, Duration::from_secs(self.config.cache_ttl_secs)).await
+    }
+    
+    /// Generate embedding for a search query, cached with the query TTL
+    async fn embed_query(&self, text: &str) -> Result<Vec<f32>> {
+        self.embed_one(text, Duration::from_secs(self.config.query_cache_ttl_secs)).await
+    }
+    
+    /// Generate embeddings for multiple texts
+    async fn embed_batch(&self, texts: &[String]) -> Result<Vec<Vec<f32>>> {
+        self.embed_many(texts, Duration::from_secs(self.config.cache_ttl_secs)).await
+    }
+    
+    /// Generate embeddings for search queries, cached with the query TTL
+    async fn embed_query_batch(&self, texts: &[String]) -> Result<Vec<Vec<f32>>> {
+        self.embed_many(texts, Duration::from_secs(self.config.query_cache_ttl_secs)).await
+    }
     
     /// Get the dimension of embeddings
     fn embedding_dimension(&self) -> usize {
-        // multilingual-e5-large has 1024 dimensions
-        1024
+        // Prefer a dimension detected from the API, then the configured one;
+        // the default multilingual-e5-large model has 1024 dimensions
+        self.detected_dimension.get().copied()
+            .or(self.config.dimension)
+            .unwrap_or(1024)
+    }
+    
+    fn dimension_known(&self) -> bool {
+        !self.config.auto_detect_dimension || self.detected_dimension.get().is_some()
+    }
+    
+    /// Probe the API with a single authenticated request
+    ///
+    /// Bypasses the cache, retries and circuit breaker so the result reflects
+    /// the current state of the endpoint and credentials.
+    async fn health(&self) -> Result<()> {
+        let request = EmbeddingRequest::single("health check")
+            .with_model(self.config.model.clone());
+        
+        let (status, _, body) = self.send_request(&self.request_body(&request)?).await?;
+        
+        match status {
+            status if status.is_success() => {
+                let parsed = self.parse_response(&body)?;
+                if parsed.data.is_empty() {
+                    return Err(ContextError::Embedding(EmbeddingError::ApiError("No embedding in response".to_string())));
+                }
+                Ok(())
+            }
+            StatusCode::UNAUTHORIZED => Err(ContextError::Embedding(EmbeddingError::AuthenticationFailed)),
+            status => Err(ContextError::Embedding(EmbeddingError::ApiError(format!("Health probe returned {}", status)))),
+        }
+    }
+}
+
+/// Error for the `Custom` provider, which has no wire format of its own
+fn unsupported_provider() -> ContextError {
+    ContextError::Config("Custom embedding providers can't be served by the HTTP client".to_string())
+}
+
+/// Longest prefix of a response body included in parse-failure logs
+const MAX_LOGGED_BODY_CHARS: usize = 512;
+
+/// Lossy, length-limited rendering of a response body for logs
+fn body_excerpt(body: &[u8]) -> String {
+    let text = String::from_utf8_lossy(body);
+    match text.char_indices().nth(MAX_LOGGED_BODY_CHARS) {
+        Some((end, _)) => format!("{}... ({} bytes)", &text[..end], body.len()),
+        None => text.into_owned(),
     }
 }
 
+/// Longest wait honoured from a `Retry-After` header
+const MAX_RETRY_AFTER: Duration = Duration::from_secs(60);
+
+/// Parse a `Retry-After` header value, given as delay-seconds or an HTTP-date
+///
+/// Dates in the past yield a zero wait; the result is capped at `MAX_RETRY_AFTER`.
+fn parse_retry_after(value: &str, now: DateTime<Utc>) -> Option<Duration> {
+    let value = value.trim();
+    let wait = match value.parse::<u64>() {
+        Ok(secs) => Duration::from_secs(secs),
+        Err(_) => {
+            let date = DateTime::parse_from_rfc2822(value).ok()?;
+            (date.with_timezone(&Utc) - now).to_std().unwrap_or(Duration::ZERO)
+        }
+    };
+    Some(wait.min(MAX_RETRY_AFTER))
+}
+
+/// Estimated contribution of a text to the serialized request body
+/// (the text plus its quotes and separating comma)
+fn estimated_bytes(text: &str) -> usize {
+    text.len() + 3
+}
+
+/// Split texts greedily into chunks of at most `max_items` texts and at most
+/// `max_bytes` estimated payload bytes
+///
+/// A single text larger than `max_bytes` is sent on its own.
+fn batch_chunks(texts: &[String], max_items: usize, max_bytes: usize) -> Vec<&[String]> {
+    let mut chunks = Vec::new();
+    let mut start = 0;
+    let mut bytes = 0;
+    
+    for (i, text) in texts.iter().enumerate() {
+        let size = estimated_bytes(text);
+        if i > start && (i - start >= max_items || bytes + size > max_bytes) {
+            chunks.push(&texts[start..i]);
+            start = i;
+            bytes = 0;
+        }
+        bytes += size;
+    }
+    
+    if start < texts.len() {
+        chunks.push(&texts[start..]);
+    }
+    chunks
+}
+
+/// Put batch embeddings back in request order
+///
+/// Uses the reported `index` of each embedding when every entry has one,
+/// since OpenAI-style APIs don't guarantee response order, and falls back to
+/// positional order otherwise.
+fn order_by_index(data: Vec<EmbeddingData>, expected: usize) -> Result<Vec<Vec<f32>>> {
+    if data.len() != expected {
+        return Err(EmbeddingError::ApiError(format!(
+            "Expected {} embeddings in response, got {}", expected, data.len()
+        )).into());
+    }
+    
+    if data.iter().any(|item| item.index.is_none()) {
+        return Ok(data.into_iter().map(|item| item.embedding).collect());
+    }
+    
+    let mut slots: Vec<Option<Vec<f32>>> = vec![None; expected];
+    for item in data {
+        let index = item.index.unwrap_or_default();
+        match slots.get_mut(index) {
+            Some(slot @ None) => *slot = Some(item.embedding),
+            Some(Some(_)) => {
+                return Err(EmbeddingError::ApiError(format!("Duplicate embedding index {} in response", index)).into());
+            }
+            None => {
+                return Err(EmbeddingError::ApiError(format!(
+                    "Embedding index {} out of range for batch of {}", index, expected
+                )).into());
+            }
+        }
+    }
+    // Every slot is filled: there are `expected` distinct in-range indices
+    Ok(slots.into_iter().flatten().collect())
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
     use secrecy::Secret;
     
-    #[tokio::test]
-    async fn test_cache_key_generation() {
-        let config = EmbeddingConfig {
-            api_url: "https://api.example.com".to_string(),
+    fn test_config(api_url: &str) -> EmbeddingConfig {
+        EmbeddingConfig {
+            provider: crate::config::EmbeddingProviderType::Chutes,
+            api_url: api_url.to_string(),
+            model: None,
             api_token: Secret::new("test-token".to_string()),
             batch_size: 32,
+            max_batch_bytes: 1024 * 1024,
             timeout_secs: 30,
-            max_retries: 3,
-            cache_enabled: true,
+            max_retries: 0,
+            cache_enabled: false,
             cache_ttl_secs: 3600,
+            query_cache_ttl_secs: 300,
             cache_size: 1000,
             tls_enabled: false,
             tls_verify: true,
+            auto_detect_dimension: false,
+            dimension: None,
+            max_concurrent_requests: None,
+            normalization: Default::default(),
+            max_vector_norm: None,
+            excessive_norm_policy: Default::default(),
+            total_timeout_secs: None,
+            response_timeout_secs: None,
+            cache_snapshot_path: None,
+            max_parse_retries: 1,
+        }
+    }
+    
+    fn embedding_body(vectors: &[Vec<f32>]) -> String {
+        let data: Vec<_> = vectors.iter().enumerate()
+            .map(|(i, v)| serde_json::json!({"embedding": v, "index": i, "object": "embedding"}))
+            .collect();
+        serde_json::json!({
+            "data": data,
+            "model": "test-model",
+            "usage": {"prompt_tokens": 1, "total_tokens": 1},
+        }).to_string()
+    }
+    
+    #[tokio::test]
+    async fn test_health_probe_succeeds_against_live_endpoint() {
+        let mut server = mockito::Server::new_async().await;
+        let mock = server.mock("POST", "/")
+            .match_header("authorization", "Bearer test-token")
+            .with_status(200)
+            .with_body(embedding_body(&[vec![0.1, 0.2]]))
+            .create_async()
+            .await;
+        
+        let client = EmbeddingClientV2::new(test_config(&server.url())).unwrap();
+        assert!(client.health().await.is_ok());
+        mock.assert_async().await;
+    }
+    
+    #[tokio::test]
+    async fn test_health_probe_reports_auth_failure() {
+        let mut server = mockito::Server::new_async().await;
+        server.mock("POST", "/")
+            .with_status(401)
+            .create_async()
+            .await;
+        
+        let client = EmbeddingClientV2::new(test_config(&server.url())).unwrap();
+        let err = client.health().await.unwrap_err();
+        assert!(matches!(err, ContextError::Embedding(EmbeddingError::AuthenticationFailed)));
+    }
+    
+    #[tokio::test]
+    async fn test_auto_detected_dimension_is_used_after_first_call() {
+        let mut server = mockito::Server::new_async().await;
+        let three = server.mock("POST", "/")
+            .with_status(200)
+            .with_body(embedding_body(&[vec![0.1, 0.2, 0.3]]))
+            .create_async()
+            .await;
+        
+        let mut config = test_config(&server.url());
+        config.auto_detect_dimension = true;
+        let client = EmbeddingClientV2::new(config).unwrap();
+        
+        assert_eq!(client.embedding_dimension(), 1024);
+        client.embed_single("first").await.unwrap();
+        assert_eq!(client.embedding_dimension(), 3);
+        three.remove_async().await;
+        
+        // A later response with a different dimension is rejected
+        server.mock("POST", "/")
+            .with_status(200)
+            .with_body(embedding_body(&[vec![0.1, 0.2, 0.3, 0.4]]))
+            .create_async()
+            .await;
+        assert!(client.embed_single("second").await.is_err());
+        assert_eq!(client.embedding_dimension(), 3);
+    }
+    
+    #[tokio::test]
+    async fn test_excessive_vector_norm_follows_policy() {
+        let mut server = mockito::Server::new_async().await;
+        server.mock("POST", "/")
+            .with_status(200)
+            .with_body(embedding_body(&[vec![1e30, 1e30, 1e30, 1e30]]))
+            .create_async()
+            .await;
+        
+        let mut config = test_config(&server.url());
+        config.max_vector_norm = Some(100.0);
+        let client = EmbeddingClientV2::new(config.clone()).unwrap();
+        let err = client.embed_single("broken").await.unwrap_err();
+        assert!(matches!(err, ContextError::Embedding(EmbeddingError::ApiError(_))));
+        
+        config.excessive_norm_policy = crate::config::ExcessiveNormPolicy::Normalize;
+        let client = EmbeddingClientV2::new(config).unwrap();
+        let embedding = client.embed_single("broken").await.unwrap();
+        assert!(embedding.iter().all(|&x| (x - 0.5).abs() < 1e-6));
+    }
+    
+    #[tokio::test]
+    async fn test_parse_failures_use_their_own_retry_budget() {
+        let mut server = mockito::Server::new_async().await;
+        let mock = server.mock("POST", "/")
+            .with_status(200)
+            .with_body("<html>not an embedding</html>")
+            .expect(2)
+            .create_async()
+            .await;
+        
+        let mut config = test_config(&server.url());
+        config.max_retries = 5;
+        config.max_parse_retries = 1;
+        let client = EmbeddingClientV2::new(config).unwrap();
+        
+        let err = client.embed_single("hello").await.unwrap_err();
+        assert!(err.to_string().contains("Failed to parse response"));
+        mock.assert_async().await;
+    }
+    
+    #[tokio::test]
+    async fn test_rate_limit_waits_for_retry_after() {
+        let mut server = mockito::Server::new_async().await;
+        let limited = server.mock("POST", "/")
+            .with_status(429)
+            .with_header("Retry-After", "2")
+            .expect(1)
+            .create_async()
+            .await;
+        let ok = server.mock("POST", "/")
+            .with_status(200)
+            .with_body(embedding_body(&[vec![0.1, 0.2]]))
+            .expect(1)
+            .create_async()
+            .await;
+        
+        let mut config = test_config(&server.url());
+        config.max_retries = 1;
+        let client = EmbeddingClientV2::new(config).unwrap();
+        
+        let started = std::time::Instant::now();
+        client.embed_single("hello").await.unwrap();
+        let waited = started.elapsed();
+        assert!(waited >= Duration::from_secs(2), "retried after {:?}", waited);
+        assert!(waited < Duration::from_secs(4), "retried after {:?}", waited);
+        limited.assert_async().await;
+        ok.assert_async().await;
+    }
+    
+    #[tokio::test(start_paused = true)]
+    async fn test_total_timeout_cuts_off_remaining_retries() {
+        let mut server = mockito::Server::new_async().await;
+        server.mock("POST", "/")
+            .with_status(500)
+            .create_async()
+            .await;
+        
+        // Backoff alone would add up to 100ms * (2 + 4 + ... + 1024), about 3.4 minutes
+        let mut config = test_config(&server.url());
+        config.max_retries = 10;
+        config.total_timeout_secs = Some(2);
+        let client = EmbeddingClientV2::new(config).unwrap();
+        
+        let started = tokio::time::Instant::now();
+        let err = client.embed_single("hello").await.unwrap_err();
+        assert!(matches!(err, ContextError::Embedding(EmbeddingError::Timeout(2))));
+        assert_eq!(started.elapsed(), Duration::from_secs(2));
+    }
+    
+    #[test]
+    fn test_parse_retry_after() {
+        let now = DateTime::parse_from_rfc2822("Wed, 21 Oct 2015 07:28:00 GMT").unwrap().with_timezone(&Utc);
+        assert_eq!(parse_retry_after("2", now), Some(Duration::from_secs(2)));
+        assert_eq!(parse_retry_after("Wed, 21 Oct 2015 07:28:30 GMT", now), Some(Duration::from_secs(30)));
+        assert_eq!(parse_retry_after("Wed, 21 Oct 2015 07:27:00 GMT", now), Some(Duration::ZERO));
+        assert_eq!(parse_retry_after("3600", now), Some(MAX_RETRY_AFTER));
+        assert_eq!(parse_retry_after("soon", now), None);
+    }
+    
+    #[test]
+    fn test_body_excerpt_is_truncated() {
+        assert_eq!(body_excerpt(b"short"), "short");
+        let long = "x".repeat(MAX_LOGGED_BODY_CHARS + 10);
+        assert!(body_excerpt(long.as_bytes()).ends_with(&format!("... ({} bytes)", long.len())));
+    }
+    
+    #[tokio::test]
+    async fn test_repeated_text_is_served_from_cache() {
+        let mut server = mockito::Server::new_async().await;
+        let mock = server.mock("POST", "/")
+            .with_status(200)
+            .with_body(embedding_body(&[vec![0.1, 0.2]]))
+            .expect(1)
+            .create_async()
+            .await;
+        
+        let mut config = test_config(&server.url());
+        config.cache_enabled = true;
+        config.auto_detect_dimension = true;
+        let client = EmbeddingClientV2::new(config).unwrap();
+        
+        client.embed_single("hello").await.unwrap();
+        client.embed_batch(&["hello".to_string()]).await.unwrap();
+        mock.assert_async().await;
+    }
+    
+    #[tokio::test]
+    async fn test_query_batches_are_cached_with_the_query_ttl() {
+        let mut server = mockito::Server::new_async().await;
+        server.mock("POST", "/")
+            .with_status(200)
+            .with_body(embedding_body(&[vec![0.1, 0.2]]))
+            .create_async()
+            .await;
+        
+        let mut config = test_config(&server.url());
+        config.cache_enabled = true;
+        config.auto_detect_dimension = true;
+        let client = EmbeddingClientV2::new(config).unwrap();
+        let cache = client.cache.clone().unwrap();
+        
+        client.embed_query_batch(&["query".to_string()]).await.unwrap();
+        client.embed_batch(&["passage".to_string()]).await.unwrap();
+        let query = cache.inner().get(&client.cache_key("query")).await.unwrap();
+        let passage = cache.inner().get(&client.cache_key("passage")).await.unwrap();
+        assert_eq!(query.ttl, Duration::from_secs(300));
+        assert_eq!(passage.ttl, Duration::from_secs(3600));
+    }
+    
+    #[tokio::test]
+    async fn test_openai_request_and_response_format() {
+        let mut server = mockito::Server::new_async().await;
+        // OpenAI responses carry a list envelope and may reorder the batch
+        let body = serde_json::json!({
+            "object": "list",
+            "data": [
+                {"object": "embedding", "index": 1, "embedding": [0.2, 0.2]},
+                {"object": "embedding", "index": 0, "embedding": [0.1, 0.1]},
+            ],
+            "model": "text-embedding-3-small",
+            "usage": {"prompt_tokens": 2, "total_tokens": 2},
+        });
+        let mock = server.mock("POST", "/")
+            .match_body(mockito::Matcher::Json(serde_json::json!({
+                "input": ["first", "second"],
+                "model": "text-embedding-3-small",
+                "encoding_format": "float",
+            })))
+            .with_status(200)
+            .with_body(body.to_string())
+            .create_async()
+            .await;
+        
+        let mut config: EmbeddingConfig = serde_json::from_value(serde_json::json!({
+            "api_format": "openai",
+            "api_url": server.url(),
+            "api_token": "test",
+            "model": "text-embedding-3-small",
+            "auto_detect_dimension": true,
+        })).unwrap();
+        config.max_retries = 0;
+        assert_eq!(config.provider, crate::config::EmbeddingProviderType::OpenAI);
+        let client = EmbeddingClientV2::new(config).unwrap();
+        
+        let embeddings = client.embed_batch(&["first".to_string(), "second".to_string()]).await.unwrap();
+        assert_eq!(embeddings, vec![vec![0.1, 0.1], vec![0.2, 0.2]]);
+        mock.assert_async().await;
+    }
+    
+    #[test]
+    fn test_formats_follow_the_configured_provider() {
+        let request = EmbeddingRequest::single("text").with_model(Some("m".to_string()));
+        let unindexed = br#"{"data": [{"embedding": [0.5]}]}"#;
+        
+        let chutes = EmbeddingClientV2::new(test_config("https://api.example.com")).unwrap();
+        assert_eq!(chutes.request_body(&request).unwrap(), serde_json::json!({"input": "text", "model": "m"}));
+        assert_eq!(chutes.parse_response(unindexed).unwrap().data[0].embedding, vec![0.5]);
+        
+        let mut config = test_config("https://api.example.com");
+        config.provider = crate::config::EmbeddingProviderType::OpenAI;
+        let openai = EmbeddingClientV2::new(config).unwrap();
+        assert_eq!(
+            openai.request_body(&request).unwrap(),
+            serde_json::json!({"input": "text", "model": "m", "encoding_format": "float"})
+        );
+        assert!(openai.parse_response(unindexed).is_err());
+        let indexed = openai.parse_response(br#"{"data": [{"embedding": [0.5], "index": 0}]}"#).unwrap();
+        assert_eq!(indexed.data[0].index, Some(0));
+    }
+    
+    #[test]
+    fn test_minimal_response_is_accepted() {
+        let response: EmbeddingResponse = serde_json::from_str(r#"{"data": [{"embedding": [0.5]}]}"#).unwrap();
+        assert_eq!(response.data[0].embedding, vec![0.5]);
+        assert_eq!(response.data[0].index, None);
+    }
+    
+    #[tokio::test]
+    async fn test_batch_embeddings_follow_response_indices() {
+        let mut server = mockito::Server::new_async().await;
+        let body = serde_json::json!({
+            "data": [
+                {"embedding": [0.3, 0.3], "index": 2, "object": "embedding"},
+                {"embedding": [0.1, 0.1], "index": 0, "object": "embedding"},
+                {"embedding": [0.2, 0.2], "index": 1, "object": "embedding"},
+            ],
+            "model": "test-model",
+            "usage": {"prompt_tokens": 3, "total_tokens": 3},
+        });
+        let _mock = server.mock("POST", "/")
+            .with_status(200)
+            .with_body(body.to_string())
+            .create_async()
+            .await;
+        
+        let mut config = test_config(&server.url());
+        config.auto_detect_dimension = true;
+        let client = EmbeddingClientV2::new(config).unwrap();
+        
+        let texts = ["zero", "one", "two"].map(String::from);
+        let embeddings = client.embed_batch(&texts).await.unwrap();
+        assert_eq!(embeddings, vec![vec![0.1, 0.1], vec![0.2, 0.2], vec![0.3, 0.3]]);
+    }
+    
+    #[test]
+    fn test_order_by_index_validates_indices() {
+        let item = |index: Option<usize>, value: f32| EmbeddingData {
+            embedding: vec![value],
+            index,
+            object: "embedding".to_string(),
         };
         
+        // Without indices the response order is kept
+        let ordered = order_by_index(vec![item(None, 1.0), item(None, 2.0)], 2).unwrap();
+        assert_eq!(ordered, vec![vec![1.0], vec![2.0]]);
+        
+        assert!(order_by_index(vec![item(Some(0), 1.0), item(Some(0), 2.0)], 2).is_err());
+        assert!(order_by_index(vec![item(Some(0), 1.0), item(Some(5), 2.0)], 2).is_err());
+        assert!(order_by_index(vec![item(Some(0), 1.0)], 2).is_err());
+    }
+    
+    #[tokio::test]
+    async fn test_normalized_variants_share_cache_entry() {
+        let mut server = mockito::Server::new_async().await;
+        let mock = server.mock("POST", "/")
+            .match_body(mockito::Matcher::PartialJson(serde_json::json!({"input": "hello world"})))
+            .with_status(200)
+            .with_body(embedding_body(&[vec![0.1, 0.2]]))
+            .expect(1)
+            .create_async()
+            .await;
+        
+        let mut config = test_config(&server.url());
+        config.cache_enabled = true;
+        config.auto_detect_dimension = true;
+        config.normalization.enabled = true;
+        config.normalization.lowercase = true;
+        let client = EmbeddingClientV2::new(config).unwrap();
+        
+        client.embed_single("  Hello \n world ").await.unwrap();
+        client.embed_single("hello world").await.unwrap();
+        client.embed_batch(&["HELLO\tWORLD".to_string()]).await.unwrap();
+        mock.assert_async().await;
+    }
+    
+    #[test]
+    fn test_configured_dimension_is_reported() {
+        let mut config = test_config("https://api.example.com");
+        config.dimension = Some(768);
+        let client = EmbeddingClientV2::new(config).unwrap();
+        assert_eq!(client.embedding_dimension(), 768);
+    }
+    
+    #[tokio::test]
+    async fn test_cache_key_generation() {
+        let mut config = test_config("https://api.example.com");
+        config.max_retries = 3;
+        config.cache_enabled = true;
+        
         let client = EmbeddingClientV2::new(config).unwrap();
         let key1 = client.cache_key("test text");
         let key2 = client.cache_key("test text");
@@ -381,4 +1069,123 @@ mod tests {
         assert_eq!(key1, key2);
         assert_ne!(key1, key3);
     }
-}
\ No newline at end of file
+    
+    #[test]
+    fn test_batch_chunks_respect_byte_limit() {
+        let texts: Vec<String> = (0..5).map(|i| i.to_string().repeat(400)).collect();
+        
+        // Item count alone would allow a single chunk
+        let chunks = batch_chunks(&texts, 32, 1000);
+        assert_eq!(chunks.iter().map(|c| c.len()).collect::<Vec<_>>(), vec![2, 2, 1]);
+        for chunk in &chunks {
+            assert!(chunk.iter().map(|t| estimated_bytes(t)).sum::<usize>() <= 1000);
+        }
+        assert_eq!(chunks.concat(), texts);
+    }
+    
+    #[test]
+    fn test_batch_chunks_respect_item_limit() {
+        let texts: Vec<String> = (0..5).map(|i| i.to_string()).collect();
+        let chunks = batch_chunks(&texts, 2, 1000);
+        assert_eq!(chunks.iter().map(|c| c.len()).collect::<Vec<_>>(), vec![2, 2, 1]);
+    }
+    
+    #[test]
+    fn test_batch_chunks_oversized_text_is_sent_alone() {
+        let texts = vec!["a".to_string(), "b".repeat(2000), "c".to_string()];
+        let chunks = batch_chunks(&texts, 32, 1000);
+        assert_eq!(chunks.iter().map(|c| c.len()).collect::<Vec<_>>(), vec![1, 1, 1]);
+    }
+    
+    #[test]
+    fn test_retry_delay_without_jitter_is_exact() {
+        let client = EmbeddingClientV2::new(test_config("http://localhost"))
+            .unwrap()
+            .with_jitter(Jitter::Disabled);
+        
+        assert_eq!(client.retry_delay(1, false), Duration::from_millis(200));
+        assert_eq!(client.retry_delay(2, false), Duration::from_millis(400));
+        assert_eq!(client.retry_delay(1, true), Duration::from_millis(1000));
+        assert_eq!(client.retry_delay(3, true), Duration::from_millis(4000));
+    }
+    
+    #[test]
+    fn test_seeded_retry_delay_is_reproducible() {
+        let client = |seed| EmbeddingClientV2::new(test_config("http://localhost"))
+            .unwrap()
+            .with_jitter(Jitter::seeded(seed));
+        let (a, b) = (client(7), client(7));
+        
+        for attempt in 1..=3 {
+            let delay = a.retry_delay(attempt, true);
+            assert_eq!(delay, b.retry_delay(attempt, true));
+            assert!(delay >= Duration::from_millis(500 * 2_u64.pow(attempt)));
+            assert!(delay < Duration::from_millis(500 * 2_u64.pow(attempt) + 1000));
+        }
+    }
+    
+    #[tokio::test]
+    async fn test_shared_request_limit_serializes_calls() {
+        use std::sync::atomic::{AtomicUsize, Ordering};
+        
+        // Slow endpoint that records the peak number of concurrent requests
+        let in_flight = Arc::new(AtomicUsize::new(0));
+        let peak = Arc::new(AtomicUsize::new(0));
+        let app = axum::Router::new().route("/", axum::routing::post({
+            let (in_flight, peak) = (in_flight.clone(), peak.clone());
+            move || async move {
+                let current = in_flight.fetch_add(1, Ordering::SeqCst) + 1;
+                peak.fetch_max(current, Ordering::SeqCst);
+                tokio::time::sleep(Duration::from_millis(100)).await;
+                in_flight.fetch_sub(1, Ordering::SeqCst);
+                embedding_body(&[vec![0.1, 0.2]])
+            }
+        }));
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}/", listener.local_addr().unwrap());
+        tokio::spawn(async move { axum::serve(listener, app).await });
+        
+        let limit = Arc::new(Semaphore::new(1));
+        let first = EmbeddingClientV2::new(test_config(&url)).unwrap().with_request_limit(limit.clone());
+        let second = EmbeddingClientV2::new(test_config(&url)).unwrap().with_request_limit(limit.clone());
+        
+        let (a, b) = tokio::join!(first.embed_single("first"), second.embed_single("second"));
+        a.unwrap();
+        b.unwrap();
+        
+        assert_eq!(peak.load(Ordering::SeqCst), 1);
+        assert_eq!(limit.available_permits(), 1);
+    }
+    
+    #[tokio::test]
+    async fn test_stalled_response_body_times_out() {
+        use tokio::io::{AsyncReadExt, AsyncWriteExt};
+        
+        // Sends headers promptly, then stalls before the body
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}/", listener.local_addr().unwrap());
+        tokio::spawn(async move {
+            let (mut socket, _) = listener.accept().await.unwrap();
+            let mut buf = [0u8; 4096];
+            let _ = socket.read(&mut buf).await;
+            let body = embedding_body(&[vec![0.1, 0.2]]);
+            let headers = format!(
+                "HTTP/1.1 200 OK\r\ncontent-type: application/json\r\ncontent-length: {}\r\n\r\n",
+                body.len()
+            );
+            socket.write_all(headers.as_bytes()).await.unwrap();
+            socket.flush().await.unwrap();
+            tokio::time::sleep(Duration::from_secs(10)).await;
+            let _ = socket.write_all(body.as_bytes()).await;
+        });
+        
+        let mut config = test_config(&url);
+        config.response_timeout_secs = Some(1);
+        let client = EmbeddingClientV2::new(config).unwrap();
+        
+        let started = std::time::Instant::now();
+        let err = client.embed_single("stalled").await.unwrap_err();
+        assert!(matches!(err, ContextError::Embedding(EmbeddingError::Timeout(1))), "got {:?}", err);
+        assert!(started.elapsed() < Duration::from_secs(5));
+    }
+}
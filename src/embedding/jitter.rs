@@ -0,0 +1,57 @@
+//! Randomness source for retry backoff jitter
+
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use std::sync::Mutex;
+
+/// Source of the random jitter added to retry backoff delays
+///
+/// Production clients use thread-local randomness; tests can disable jitter
+/// or seed it to make retry timing deterministic.
+#[derive(Debug, Default)]
+pub enum Jitter {
+    /// Jitter from the thread-local RNG
+    #[default]
+    Random,
+    /// No jitter, backoff delays are exact
+    Disabled,
+    /// Jitter from a seeded RNG, reproducible across runs
+    Seeded(Box<Mutex<StdRng>>),
+}
+
+impl Jitter {
+    /// Jitter from an RNG seeded with `seed`
+    pub fn seeded(seed: u64) -> Self {
+        Jitter::Seeded(Box::new(Mutex::new(StdRng::seed_from_u64(seed))))
+    }
+
+    /// Sample a fraction in `[0, 1)`, or `None` when jitter is disabled
+    pub fn sample(&self) -> Option<f64> {
+        match self {
+            Jitter::Random => Some(rand::random::<f64>()),
+            Jitter::Disabled => None,
+            Jitter::Seeded(rng) => Some(rng.lock().unwrap().gen::<f64>()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_disabled_jitter_never_samples() {
+        assert_eq!(Jitter::Disabled.sample(), None);
+    }
+
+    #[test]
+    fn test_seeded_jitter_is_reproducible() {
+        let a = Jitter::seeded(42);
+        let b = Jitter::seeded(42);
+        for _ in 0..5 {
+            let sample = a.sample().unwrap();
+            assert!((0.0..1.0).contains(&sample));
+            assert_eq!(Some(sample), b.sample());
+        }
+    }
+}
@@ -0,0 +1,63 @@
+//! Sanity check on the magnitude of returned embeddings
+
+use crate::config::{EmbeddingConfig, ExcessiveNormPolicy};
+use crate::error::{EmbeddingError, Result};
+
+/// Apply `config.max_vector_norm` to an embedding returned by the provider
+///
+/// Vectors within the bound are left untouched. Larger ones are rejected with
+/// an API error or scaled to unit length, per `config.excessive_norm_policy`.
+/// Vectors with non-finite components are always rejected once a bound is set.
+pub fn check_magnitude(embedding: &mut [f32], config: &EmbeddingConfig) -> Result<()> {
+    let Some(max_norm) = config.max_vector_norm else {
+        return Ok(());
+    };
+
+    // Accumulate in f64 so vectors of huge components don't overflow to infinity
+    let norm = embedding.iter().map(|&x| f64::from(x).powi(2)).sum::<f64>().sqrt();
+    if !norm.is_finite() {
+        return Err(EmbeddingError::ApiError("Embedding contains non-finite values".to_string()).into());
+    }
+    if norm <= f64::from(max_norm) {
+        return Ok(());
+    }
+
+    match config.excessive_norm_policy {
+        ExcessiveNormPolicy::Reject => Err(EmbeddingError::ApiError(format!(
+            "Embedding norm {:e} exceeds the configured maximum of {}",
+            norm, max_norm
+        ))
+        .into()),
+        ExcessiveNormPolicy::Normalize => {
+            for x in embedding.iter_mut() {
+                *x = (f64::from(*x) / norm) as f32;
+            }
+            Ok(())
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::Config;
+
+    #[test]
+    fn test_check_magnitude() {
+        let mut config = Config::default_config().embedding;
+        let mut huge = vec![1e30_f32; 4];
+        assert!(check_magnitude(&mut huge, &config).is_ok(), "unchecked without a bound");
+
+        config.max_vector_norm = Some(10.0);
+        let mut small = vec![3.0, 4.0];
+        check_magnitude(&mut small, &config).unwrap();
+        assert_eq!(small, vec![3.0, 4.0]);
+
+        assert!(check_magnitude(&mut huge, &config).is_err());
+        assert!(check_magnitude(&mut [f32::NAN, 1.0], &config).is_err());
+
+        config.excessive_norm_policy = ExcessiveNormPolicy::Normalize;
+        check_magnitude(&mut huge, &config).unwrap();
+        assert!(huge.iter().all(|&x| (x - 0.5).abs() < 1e-6));
+    }
+}
@@ -4,14 +4,22 @@ pub mod client;
 pub mod client_v2;
 pub mod cache;
 pub mod models;
+pub mod jitter;
+pub mod registry;
+pub mod normalize;
+pub mod magnitude;
 
 pub use client::EmbeddingClient;
 pub use client_v2::EmbeddingClientV2;
 pub use models::{EmbeddingRequest, EmbeddingResponse, EmbeddingInput};
 pub use cache::EmbeddingCache;
+pub use jitter::Jitter;
 
 use async_trait::async_trait;
-use crate::error::Result;
+use crate::config::{EmbeddingConfig, EmbeddingProviderType};
+use crate::error::{ContextError, EmbeddingError, Result};
+use std::sync::Arc;
+use std::time::Duration;
 
 /// Trait for embedding providers
 #[async_trait]
@@ -22,6 +30,178 @@ pub trait EmbeddingProvider: Send + Sync {
     /// Generate embeddings for multiple texts
     async fn embed_batch(&self, texts: &[String]) -> Result<Vec<Vec<f32>>>;
     
+    /// Generate embedding for a search query
+    ///
+    /// Providers that cache embeddings can override this to keep query
+    /// embeddings for a shorter time than stored passages.
+    async fn embed_query(&self, text: &str) -> Result<Vec<f32>> {
+        self.embed_single(text).await
+    }
+    
+    /// Generate embeddings for several search queries
+    ///
+    /// Like [`embed_query`](Self::embed_query), providers that cache can
+    /// override this to apply the query TTL to the whole batch.
+    async fn embed_query_batch(&self, texts: &[String]) -> Result<Vec<Vec<f32>>> {
+        self.embed_batch(texts).await
+    }
+    
     /// Get the dimension of embeddings
     fn embedding_dimension(&self) -> usize;
+    
+    /// Whether [`embedding_dimension`](Self::embedding_dimension) is final
+    ///
+    /// Providers that detect the dimension from their first response return
+    /// `false` until then, since the reported value is only a guess.
+    fn dimension_known(&self) -> bool {
+        true
+    }
+    
+    /// Check that the provider is able to serve requests
+    ///
+    /// The default implementation only validates the reported dimension.
+    /// Providers backed by a remote service should override this with a
+    /// minimal live request.
+    async fn health(&self) -> Result<()> {
+        if self.embedding_dimension() == 0 {
+            return Err(EmbeddingError::ApiError("Invalid embedding dimension".to_string()).into());
+        }
+        Ok(())
+    }
+}
+
+/// Construct the embedding provider selected by `config.provider`
+///
+/// `Custom` providers can't be built from configuration alone; construct them
+/// directly and pass them to the manager instead.
+pub fn build_provider(config: &EmbeddingConfig) -> Result<Arc<dyn EmbeddingProvider>> {
+    build_provider_with_cache(config, build_cache(config))
+}
+
+/// Construct the embedding cache described by `config`
+///
+/// Returns `None` when caching is disabled. The cache is tagged with the
+/// configured model (or the API URL when no model is set) so snapshots from a
+/// different model are not reused.
+pub fn build_cache(config: &EmbeddingConfig) -> Option<Arc<EmbeddingCache>> {
+    if !config.cache_enabled {
+        return None;
+    }
+    
+    Some(Arc::new(
+        EmbeddingCache::new(config.cache_size, Duration::from_secs(config.cache_ttl_secs))
+            .with_model_id(config.model_id()),
+    ))
+}
+
+/// Like [`build_provider`], but sharing an existing cache with the provider
+pub fn build_provider_with_cache(
+    config: &EmbeddingConfig,
+    cache: Option<Arc<EmbeddingCache>>,
+) -> Result<Arc<dyn EmbeddingProvider>> {
+    match config.provider {
+        EmbeddingProviderType::Chutes | EmbeddingProviderType::OpenAI => {
+            let mut client = EmbeddingClientV2::new(config.clone())?;
+            if let Some(cache) = cache {
+                client = client.with_cache(cache);
+            }
+            if let Some(limit) = config.max_concurrent_requests {
+                client = client.with_request_limit(Arc::new(tokio::sync::Semaphore::new(limit)));
+            }
+            Ok(Arc::new(client))
+        }
+        EmbeddingProviderType::Custom => Err(ContextError::Config(
+            "Custom embedding providers must be constructed programmatically".to_string()
+        )),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::Config;
+    use secrecy::Secret;
+    
+    struct StaticProvider {
+        dimension: usize,
+    }
+    
+    #[async_trait]
+    impl EmbeddingProvider for StaticProvider {
+        async fn embed_single(&self, _text: &str) -> Result<Vec<f32>> {
+            Ok(vec![0.5; self.dimension])
+        }
+        
+        async fn embed_batch(&self, texts: &[String]) -> Result<Vec<Vec<f32>>> {
+            Ok(vec![vec![0.5; self.dimension]; texts.len()])
+        }
+        
+        fn embedding_dimension(&self) -> usize {
+            self.dimension
+        }
+    }
+    
+    fn provider_config(provider: EmbeddingProviderType, api_url: String) -> EmbeddingConfig {
+        let mut config = Config::default_config().embedding;
+        config.provider = provider;
+        config.api_url = api_url;
+        config.api_token = Secret::new("test-token".to_string());
+        config.max_retries = 0;
+        config.cache_enabled = false;
+        config
+    }
+    
+    fn embedding_body() -> String {
+        serde_json::json!({
+            "data": [{"embedding": [0.1, 0.2], "index": 0, "object": "embedding"}],
+            "model": "test-model",
+            "usage": {"prompt_tokens": 1, "total_tokens": 1},
+        }).to_string()
+    }
+    
+    #[tokio::test]
+    async fn test_build_chutes_provider() {
+        let mut server = mockito::Server::new_async().await;
+        let mock = server.mock("POST", "/")
+            .match_body(mockito::Matcher::Json(serde_json::json!({"input": "hello"})))
+            .with_body(embedding_body())
+            .create_async()
+            .await;
+        
+        let provider = build_provider(&provider_config(EmbeddingProviderType::Chutes, server.url())).unwrap();
+        provider.embed_single("hello").await.unwrap();
+        mock.assert_async().await;
+    }
+    
+    #[tokio::test]
+    async fn test_build_openai_provider_sends_model() {
+        let mut server = mockito::Server::new_async().await;
+        let mock = server.mock("POST", "/")
+            .match_body(mockito::Matcher::Json(serde_json::json!({
+                "input": "hello",
+                "model": "text-embedding-3-small",
+                "encoding_format": "float",
+            })))
+            .with_body(embedding_body())
+            .create_async()
+            .await;
+        
+        let mut config = provider_config(EmbeddingProviderType::OpenAI, server.url());
+        config.model = Some("text-embedding-3-small".to_string());
+        let provider = build_provider(&config).unwrap();
+        provider.embed_single("hello").await.unwrap();
+        mock.assert_async().await;
+    }
+    
+    #[test]
+    fn test_build_custom_provider_is_rejected() {
+        let config = provider_config(EmbeddingProviderType::Custom, "http://localhost".to_string());
+        assert!(matches!(build_provider(&config), Err(ContextError::Config(_))));
+    }
+    
+    #[tokio::test]
+    async fn test_default_health_validates_dimension() {
+        assert!(StaticProvider { dimension: 1024 }.health().await.is_ok());
+        assert!(StaticProvider { dimension: 0 }.health().await.is_err());
+    }
 }
\ No newline at end of file
@@ -28,9 +28,11 @@ pub struct EmbeddingResponse {
     pub data: Vec<EmbeddingData>,
     
     /// Model used for generation
+    #[serde(default)]
     pub model: String,
     
     /// Usage statistics
+    #[serde(default)]
     pub usage: UsageStats,
 }
 
@@ -40,15 +42,72 @@ pub struct EmbeddingData {
     /// Embedding vector
     pub embedding: Vec<f32>,
     
-    /// Index in the batch
-    pub index: usize,
+    /// Index in the batch, if the API reports it
+    #[serde(default)]
+    pub index: Option<usize>,
     
     /// Object type (always "embedding")
+    #[serde(default)]
     pub object: String,
 }
 
+/// Request body of an OpenAI `/v1/embeddings` call, where the model is required
+#[derive(Debug, Clone, Serialize)]
+pub struct OpenAIEmbeddingRequest<'a> {
+    /// Input text(s) to embed
+    pub input: &'a EmbeddingInput,
+    
+    /// Model name
+    pub model: &'a str,
+    
+    /// Encoding of the returned vectors (always "float")
+    pub encoding_format: &'static str,
+}
+
+/// Response of an OpenAI `/v1/embeddings` call
+#[derive(Debug, Clone, Deserialize)]
+pub struct OpenAIEmbeddingResponse {
+    /// Generated embeddings, each tagged with its position in the batch
+    pub data: Vec<OpenAIEmbeddingData>,
+    
+    /// Model used for generation
+    #[serde(default)]
+    pub model: String,
+    
+    /// Usage statistics
+    #[serde(default)]
+    pub usage: UsageStats,
+}
+
+/// Individual OpenAI embedding data
+#[derive(Debug, Clone, Deserialize)]
+pub struct OpenAIEmbeddingData {
+    /// Embedding vector
+    pub embedding: Vec<f32>,
+    
+    /// Index in the batch
+    pub index: usize,
+}
+
+impl From<OpenAIEmbeddingResponse> for EmbeddingResponse {
+    fn from(response: OpenAIEmbeddingResponse) -> Self {
+        Self {
+            data: response.data
+                .into_iter()
+                .map(|item| EmbeddingData {
+                    embedding: item.embedding,
+                    index: Some(item.index),
+                    object: "embedding".to_string(),
+                })
+                .collect(),
+            model: response.model,
+            usage: response.usage,
+        }
+    }
+}
+
 /// Token usage statistics
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
 pub struct UsageStats {
     /// Number of prompt tokens
     pub prompt_tokens: usize,
@@ -73,4 +132,10 @@ impl EmbeddingRequest {
             model: None,
         }
     }
+    
+    /// Set the model to request
+    pub fn with_model(mut self, model: Option<String>) -> Self {
+        self.model = model;
+        self
+    }
 }
\ No newline at end of file
@@ -0,0 +1,50 @@
+//! Text normalization applied before embedding
+
+use crate::config::TextNormalization;
+use std::borrow::Cow;
+use unicode_normalization::{is_nfc, UnicodeNormalization};
+
+/// Normalize `text` according to `settings`
+///
+/// Trims, collapses every run of whitespace to a single space and applies
+/// Unicode NFC, optionally lowercasing the result. Text that is already
+/// normalized is returned borrowed.
+pub fn normalize_text<'a>(text: &'a str, settings: &TextNormalization) -> Cow<'a, str> {
+    if !settings.enabled {
+        return Cow::Borrowed(text);
+    }
+
+    let trimmed = text.trim();
+    let collapsed = trimmed.split_whitespace().eq(trimmed.split(' '));
+    let lowered = !settings.lowercase || !trimmed.chars().any(char::is_uppercase);
+    if collapsed && lowered && is_nfc(trimmed) {
+        return Cow::Borrowed(trimmed);
+    }
+
+    let joined = trimmed.split_whitespace().collect::<Vec<_>>().join(" ");
+    let mut normalized: String = joined.nfc().collect();
+    if settings.lowercase {
+        normalized = normalized.to_lowercase();
+    }
+    Cow::Owned(normalized)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_normalize_text() {
+        let settings = TextNormalization { enabled: true, lowercase: false };
+        assert_eq!(normalize_text("  Hello \n\t world  ", &settings), "Hello world");
+        // "e" followed by a combining acute accent composes to "é"
+        assert_eq!(normalize_text("caf\u{0065}\u{0301}", &settings), "caf\u{00e9}");
+        assert!(matches!(normalize_text("already clean", &settings), Cow::Borrowed(_)));
+
+        let lowercase = TextNormalization { enabled: true, lowercase: true };
+        assert_eq!(normalize_text(" Hello  World", &lowercase), "hello world");
+
+        let disabled = TextNormalization { enabled: false, lowercase: true };
+        assert_eq!(normalize_text(" Hello  World", &disabled), " Hello  World");
+    }
+}
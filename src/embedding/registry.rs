@@ -0,0 +1,48 @@
+//! Built-in registry of embedding models with known output dimensions
+
+/// Known model ids and the dimension of the embeddings they produce
+///
+/// Ids are matched case-insensitively and without any organisation prefix, so
+/// `intfloat/multilingual-e5-large` and `multilingual-e5-large` are equivalent.
+const KNOWN_MODELS: &[(&str, usize)] = &[
+    ("multilingual-e5-large", 1024),
+    ("multilingual-e5-large-instruct", 1024),
+    ("multilingual-e5-base", 768),
+    ("multilingual-e5-small", 384),
+    ("e5-large-v2", 1024),
+    ("e5-base-v2", 768),
+    ("e5-small-v2", 384),
+    ("bge-small", 384),
+    ("bge-small-en-v1.5", 384),
+    ("bge-base-en-v1.5", 768),
+    ("bge-large-en-v1.5", 1024),
+    ("bge-m3", 1024),
+    ("all-minilm-l6-v2", 384),
+    ("nomic-embed-text-v1.5", 768),
+    ("text-embedding-3-small", 1536),
+    ("text-embedding-3-large", 3072),
+    ("text-embedding-ada-002", 1536),
+];
+
+/// Dimension of the embeddings produced by `model`, if it is a known model
+pub fn known_dimension(model: &str) -> Option<usize> {
+    let name = model.trim().rsplit('/').next().unwrap_or_default();
+    KNOWN_MODELS
+        .iter()
+        .find(|(id, _)| id.eq_ignore_ascii_case(name))
+        .map(|(_, dimension)| *dimension)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_known_dimension() {
+        assert_eq!(known_dimension("multilingual-e5-large"), Some(1024));
+        assert_eq!(known_dimension("intfloat/multilingual-e5-large"), Some(1024));
+        assert_eq!(known_dimension("BAAI/BGE-Small-EN-v1.5"), Some(384));
+        assert_eq!(known_dimension("text-embedding-3-small"), Some(1536));
+        assert_eq!(known_dimension("my-finetuned-model"), None);
+    }
+}
@@ -32,6 +32,9 @@ pub enum ContextError {
     #[error("Configuration error: {0}")]
     Config(String),
     
+    #[error("Service unavailable: {0}")]
+    ServiceUnavailable(String),
+    
     #[error("Internal error: {0}")]
     Internal(String),
 }
@@ -102,6 +105,9 @@ pub enum VectorDbError {
     
     #[error("Qdrant client error: {0}")]
     QdrantError(String),
+    
+    #[error("Operation not supported by this vector store: {0}")]
+    Unsupported(String),
 }
 
 /// Errors related to HiRAG operations
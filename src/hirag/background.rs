@@ -1,20 +1,44 @@
 //! Background tasks for context management
 
+use super::{DELETED_AT_KEY, EMBEDDING_MODEL_KEY, NEEDS_EMBEDDING_KEY};
+use crate::clock::{system_clock, Clock};
+use crate::embedding::EmbeddingProvider;
 use crate::error::Result;
+use crate::observability::MetricsCollector;
+use crate::shutdown::ShutdownNotifier;
 use crate::vector_db::{Filter, Condition, VectorStore};
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicU32, Ordering};
 use std::sync::Arc;
 use std::time::Duration;
-use tokio::time::interval;
 use tracing::{debug, error, info, warn};
 
+/// Default maximum number of contexts re-embedded per pass
+const DEFAULT_REEMBED_BATCH_SIZE: usize = 64;
+
 /// Background task manager for garbage collection
 pub struct BackgroundTaskManager {
     vector_db: Arc<dyn VectorStore>,
     gc_interval: Duration,
+    gc_max_backoff: Duration,
+    consecutive_gc_failures: AtomicU32,
     l2_ttl_secs: i64,
+    l3_ttl_secs: i64,
+    l3_gc_enabled: bool,
     l2_collection_name: String,
     l3_collection_name: String,
     vector_size: usize,
+    clock: Arc<dyn Clock>,
+    future_timestamp_tolerance: Option<Duration>,
+    gc_enabled: bool,
+    embedding_provider: Option<Arc<dyn EmbeddingProvider>>,
+    embedding_model: Option<String>,
+    reembed_collections: Vec<String>,
+    reembed_batch_size: usize,
+    reembed_interval: Duration,
+    metrics: Option<Arc<MetricsCollector>>,
+    tombstone_collections: Vec<String>,
+    tombstone_grace: Duration,
 }
 
 impl BackgroundTaskManager {
@@ -23,6 +47,7 @@ impl BackgroundTaskManager {
         vector_db: Arc<dyn VectorStore>,
         gc_interval: Duration,
         l2_ttl_secs: i64,
+        l3_ttl_secs: i64,
         l2_collection_name: String,
         l3_collection_name: String,
         vector_size: usize,
@@ -30,66 +55,417 @@ impl BackgroundTaskManager {
         Self {
             vector_db,
             gc_interval,
+            gc_max_backoff: gc_interval,
+            consecutive_gc_failures: AtomicU32::new(0),
             l2_ttl_secs,
+            l3_ttl_secs,
+            l3_gc_enabled: false,
             l2_collection_name,
             l3_collection_name,
             vector_size,
+            clock: system_clock(),
+            future_timestamp_tolerance: None,
+            gc_enabled: true,
+            embedding_provider: None,
+            embedding_model: None,
+            reembed_collections: Vec::new(),
+            reembed_batch_size: DEFAULT_REEMBED_BATCH_SIZE,
+            reembed_interval: gc_interval,
+            metrics: None,
+            tombstone_collections: Vec::new(),
+            tombstone_grace: Duration::ZERO,
         }
     }
 
+    /// Use a specific clock for TTL cutoffs
+    pub fn with_clock(mut self, clock: Arc<dyn Clock>) -> Self {
+        self.clock = clock;
+        self
+    }
+
+    /// Back off exponentially after consecutive GC failures, up to `max`
+    ///
+    /// Without this the interval stays fixed even while the database is failing.
+    pub fn with_max_gc_backoff(mut self, max: Duration) -> Self {
+        self.gc_max_backoff = max;
+        self
+    }
+
+    /// Also collect contexts timestamped at least `tolerance` in the future
+    ///
+    /// Such timestamps come from clock skew or bad imports and would otherwise
+    /// never fall behind the TTL cutoff.
+    pub fn with_future_timestamp_tolerance(mut self, tolerance: Duration) -> Self {
+        self.future_timestamp_tolerance = Some(tolerance);
+        self
+    }
+
+    /// Enable or disable the garbage collection tasks started by [`Self::start`]
+    pub fn with_gc(mut self, enabled: bool) -> Self {
+        self.gc_enabled = enabled;
+        self
+    }
+
+    /// Also collect L3 contexts older than the L3 TTL (off by default, since
+    /// long-term contexts are usually meant to be kept)
+    pub fn with_l3_gc(mut self, enabled: bool) -> Self {
+        self.l3_gc_enabled = enabled;
+        self
+    }
+
+    /// Re-embed contexts in `collections` that were stored with a deferred
+    /// embedding
+    ///
+    /// Once started, a pass runs every GC interval (see
+    /// [`Self::with_reembed_rate`]) and picks up contexts flagged with
+    /// [`NEEDS_EMBEDDING_KEY`] while the embedding service was unavailable.
+    pub fn with_reembedding(
+        mut self,
+        provider: Arc<dyn EmbeddingProvider>,
+        collections: Vec<String>,
+    ) -> Self {
+        self.embedding_provider = Some(provider);
+        self.reembed_collections = collections;
+        self
+    }
+
+    /// Also re-embed contexts whose [`EMBEDDING_MODEL_KEY`] names a model
+    /// other than `model`
+    ///
+    /// Contexts without a model tag are left alone.
+    pub fn with_embedding_model(mut self, model: impl Into<String>) -> Self {
+        self.embedding_model = Some(model.into());
+        self
+    }
+
+    /// Re-embed at most `batch_size` contexts every `interval`, bounding the
+    /// load the task puts on the embedding API
+    pub fn with_reembed_rate(mut self, batch_size: usize, interval: Duration) -> Self {
+        self.reembed_batch_size = batch_size;
+        self.reembed_interval = interval;
+        self
+    }
+
+    /// Also remove contexts in `collections` that were soft-deleted more than
+    /// `grace` ago, every GC interval whether or not GC is enabled
+    pub fn with_tombstone_purge(mut self, collections: Vec<String>, grace: Duration) -> Self {
+        self.tombstone_collections = collections;
+        self.tombstone_grace = grace;
+        self
+    }
+
+    /// Record GC runs and re-embedding counts in `metrics`
+    pub fn with_metrics(mut self, metrics: Arc<MetricsCollector>) -> Self {
+        self.metrics = Some(metrics);
+        self
+    }
+
+    /// Filter selecting `level` contexts older than `cutoff_time`, plus
+    /// far-future ones when a tolerance is configured
+    fn gc_filter(&self, level: &str, cutoff_time: i64, now: i64) -> Filter {
+        let expired = Condition::Range {
+            key: "timestamp".to_string(),
+            gt: None,
+            gte: None,
+            lte: Some(cutoff_time as f64),
+        };
+        let timestamp = match self.future_timestamp_tolerance {
+            Some(tolerance) => Condition::Group {
+                filter: Filter::new().should(expired).should(Condition::Range {
+                    key: "timestamp".to_string(),
+                    gt: None,
+                    gte: Some(now.saturating_add(tolerance.as_secs() as i64) as f64),
+                    lte: None,
+                }),
+            },
+            None => expired,
+        };
+
+        Filter::new()
+            .must(Condition::Match {
+                key: "level".to_string(),
+                value: serde_json::Value::String(level.to_string()),
+            })
+            .must(timestamp)
+    }
+
+    /// Number of GC runs that have failed in a row
+    pub fn consecutive_gc_failures(&self) -> u32 {
+        self.consecutive_gc_failures.load(Ordering::SeqCst)
+    }
+
+    /// Delay before the next GC run given the current failure streak
+    pub fn next_gc_delay(&self) -> Duration {
+        let failures = self.consecutive_gc_failures();
+        let backoff = self.gc_interval.saturating_mul(2_u32.saturating_pow(failures));
+        backoff.min(self.gc_max_backoff.max(self.gc_interval))
+    }
+
     /// Start all background tasks
-    pub fn start(self: Arc<Self>) {
+    ///
+    /// The tasks run until `shutdown` is signaled; await the returned handles
+    /// to wait for them to finish. A pass already in progress completes first.
+    pub fn start(self: Arc<Self>, shutdown: ShutdownNotifier) -> Vec<tokio::task::JoinHandle<()>> {
+        let mut handles = Vec::new();
+
         // Start L2 garbage collection task
-        let manager = self.clone();
-        tokio::spawn(async move {
-            manager.run_l2_gc().await;
-        });
+        if self.gc_enabled {
+            let manager = self.clone();
+            let l2_shutdown = shutdown.clone();
+            handles.push(tokio::spawn(async move {
+                manager.run_l2_gc(l2_shutdown).await;
+            }));
+            if self.l3_gc_enabled {
+                let manager = self.clone();
+                let l3_shutdown = shutdown.clone();
+                handles.push(tokio::spawn(async move {
+                    manager.run_l3_gc(l3_shutdown).await;
+                }));
+            }
+            info!("Background GC tasks started");
+        }
 
-        info!("Background GC tasks started");
+        // Start tombstone purge task, independent of GC so soft-deleted
+        // contexts are removed even when GC is off
+        if !self.tombstone_collections.is_empty() {
+            let manager = self.clone();
+            let purge_shutdown = shutdown.clone();
+            handles.push(tokio::spawn(async move {
+                manager.run_tombstone_purge(purge_shutdown).await;
+            }));
+            info!("Tombstone purge task started");
+        }
+
+        // Start re-embedding task
+        if self.embedding_provider.is_some() {
+            let manager = self.clone();
+            handles.push(tokio::spawn(async move {
+                manager.run_reembedding(shutdown).await;
+            }));
+            info!("Re-embedding task started");
+        }
+
+        handles
     }
 
-    /// Run L2 garbage collection periodically
-    async fn run_l2_gc(&self) {
-        let mut ticker = interval(self.gc_interval);
+    /// Re-embed flagged and stale contexts periodically until shutdown
+    async fn run_reembedding(&self, shutdown: ShutdownNotifier) {
+        loop {
+            match self.reembed_contexts().await {
+                Ok(0) => debug!("No contexts to re-embed"),
+                Ok(count) => info!("Re-embedded {} contexts", count),
+                Err(e) => {
+                    warn!("Re-embed pass failed: {}", e);
+                    if let Some(metrics) = &self.metrics {
+                        metrics.record_reembed_error();
+                    }
+                }
+            }
+            tokio::select! {
+                _ = tokio::time::sleep(self.reembed_interval) => {}
+                _ = shutdown.wait() => {
+                    debug!("Re-embedding task stopped");
+                    return;
+                }
+            }
+        }
+    }
+
+    /// Filter selecting contexts flagged for embedding, plus those tagged
+    /// with a model other than the current one when it is known
+    fn reembed_filter(&self) -> Filter {
+        let mut filter = Filter::new().should(Condition::Match {
+            key: NEEDS_EMBEDDING_KEY.to_string(),
+            value: serde_json::Value::Bool(true),
+        });
+        if let Some(model) = &self.embedding_model {
+            filter = filter.should(Condition::Group {
+                filter: Filter::new()
+                    .must_not(Condition::IsEmpty { key: EMBEDDING_MODEL_KEY.to_string() })
+                    .must_not(Condition::Match {
+                        key: EMBEDDING_MODEL_KEY.to_string(),
+                        value: serde_json::Value::String(model.clone()),
+                    }),
+            });
+        }
+        filter
+    }
+
+    /// Re-embed one batch of contexts that were stored with a deferred
+    /// embedding or embedded with a different model
+    ///
+    /// Handles at most the configured batch size per pass, across all
+    /// collections; later passes pick up the rest. Returns how many contexts
+    /// were updated.
+    pub async fn reembed_contexts(&self) -> Result<usize> {
+        let Some(provider) = &self.embedding_provider else {
+            return Ok(0);
+        };
+
+        let filter = self.reembed_filter();
+        let mut reembedded = 0;
+        for collection in &self.reembed_collections {
+            let remaining = self.reembed_batch_size - reembedded;
+            if remaining == 0 {
+                break;
+            }
+
+            let page = self.vector_db
+                .scroll(collection, Some(filter.clone()), None, remaining)
+                .await?;
+            if page.points.is_empty() {
+                continue;
+            }
+
+            let texts: Vec<String> = page.points.iter().map(|p| p.payload.text.clone()).collect();
+            let vectors = provider.embed_batch(&texts).await?;
+
+            // Only the vector and the bookkeeping keys are written, so metadata
+            // updated since the scroll is kept; the flag goes last, leaving a
+            // point that fails halfway to the next pass
+            let count = page.points.len();
+            for (point, vector) in page.points.into_iter().zip(vectors) {
+                self.vector_db.set_vector(collection, point.id, vector).await?;
+                if let Some(model) = &self.embedding_model {
+                    let fields = HashMap::from([(
+                        EMBEDDING_MODEL_KEY.to_string(),
+                        serde_json::Value::String(model.clone()),
+                    )]);
+                    self.vector_db.set_payload_fields(collection, point.id, fields).await?;
+                }
+                if point.payload.metadata.contains_key(NEEDS_EMBEDDING_KEY) {
+                    self.vector_db
+                        .delete_payload_fields(collection, point.id, vec![NEEDS_EMBEDDING_KEY.to_string()])
+                        .await?;
+                }
+            }
 
+            debug!("Re-embedded {} contexts in {}", count, collection);
+            if let Some(metrics) = &self.metrics {
+                metrics.record_reembedded(count);
+            }
+            reembedded += count;
+        }
+
+        Ok(reembedded)
+    }
+
+    /// Run L2 garbage collection periodically until shutdown
+    async fn run_l2_gc(&self, shutdown: ShutdownNotifier) {
         loop {
-            ticker.tick().await;
+            let delay = self.run_l2_gc_once().await;
+            tokio::select! {
+                _ = tokio::time::sleep(delay) => {}
+                _ = shutdown.wait() => {
+                    debug!("L2 GC task stopped");
+                    return;
+                }
+            }
+        }
+    }
+
+    /// Run one L2 GC pass and return the delay before the next one
+    async fn run_l2_gc_once(&self) -> Duration {
+        debug!("Running L2 garbage collection");
+
+        let started = std::time::Instant::now();
+        match self.cleanup_expired_l2_contexts().await {
+            Ok(deleted_count) => {
+                if deleted_count > 0 {
+                    info!("L2 GC: Deleted {} expired contexts", deleted_count);
+                } else {
+                    debug!("L2 GC: No expired contexts found");
+                }
+                self.consecutive_gc_failures.store(0, Ordering::SeqCst);
+                if let Some(metrics) = &self.metrics {
+                    metrics.record_gc_run(deleted_count, started.elapsed());
+                }
+            }
+            Err(e) => {
+                let failures = self.consecutive_gc_failures.fetch_add(1, Ordering::SeqCst) + 1;
+                error!("L2 GC error ({} consecutive): {}", failures, e);
+                if let Some(metrics) = &self.metrics {
+                    metrics.record_gc_error();
+                }
+            }
+        }
 
-            debug!("Running L2 garbage collection");
+        let delay = self.next_gc_delay();
+        if delay > self.gc_interval {
+            warn!("L2 GC backing off, next run in {:?}", delay);
+        }
+        delay
+    }
 
-            match self.cleanup_expired_l2_contexts().await {
-                Ok(deleted_count) => {
-                    if deleted_count > 0 {
-                        info!("L2 GC: Deleted {} expired contexts", deleted_count);
-                    } else {
-                        debug!("L2 GC: No expired contexts found");
+    /// Purge tombstones past their grace period every GC interval until shutdown
+    async fn run_tombstone_purge(&self, shutdown: ShutdownNotifier) {
+        loop {
+            match self.purge_tombstones().await {
+                Ok(0) => debug!("No soft-deleted contexts to purge"),
+                Ok(count) => info!("Purged {} soft-deleted contexts", count),
+                Err(e) => {
+                    warn!("Tombstone purge failed: {}", e);
+                    if let Some(metrics) = &self.metrics {
+                        metrics.record_gc_error();
                     }
                 }
-                Err(e) => {
-                    error!("L2 GC error: {}", e);
+            }
+            tokio::select! {
+                _ = tokio::time::sleep(self.gc_interval) => {}
+                _ = shutdown.wait() => {
+                    debug!("Tombstone purge task stopped");
+                    return;
+                }
+            }
+        }
+    }
+
+    /// Run L3 garbage collection periodically until shutdown
+    async fn run_l3_gc(&self, shutdown: ShutdownNotifier) {
+        loop {
+            self.run_l3_gc_once().await;
+            tokio::select! {
+                _ = tokio::time::sleep(self.gc_interval) => {}
+                _ = shutdown.wait() => {
+                    debug!("L3 GC task stopped");
+                    return;
+                }
+            }
+        }
+    }
+
+    /// Run one L3 GC pass
+    async fn run_l3_gc_once(&self) {
+        debug!("Running L3 garbage collection");
+
+        let started = std::time::Instant::now();
+        match self.cleanup_expired_l3_contexts(self.l3_ttl_secs).await {
+            Ok(deleted_count) => {
+                if deleted_count > 0 {
+                    info!("L3 GC: Deleted {} expired contexts", deleted_count);
+                }
+                if let Some(metrics) = &self.metrics {
+                    metrics.record_gc_run(deleted_count, started.elapsed());
+                }
+            }
+            Err(e) => {
+                error!("L3 GC error: {}", e);
+                if let Some(metrics) = &self.metrics {
+                    metrics.record_gc_error();
                 }
             }
         }
     }
 
     /// Clean up expired L2 contexts
-    async fn cleanup_expired_l2_contexts(&self) -> Result<usize> {
-        let now = chrono::Utc::now().timestamp();
+    pub async fn cleanup_expired_l2_contexts(&self) -> Result<usize> {
+        let now = self.clock.now_utc().timestamp();
         let cutoff_time = now - self.l2_ttl_secs;
 
         debug!("Starting L2 GC with cutoff time: {}", cutoff_time);
 
         // Create filter for expired contexts in L2 (short-term) level
-        let filter = Filter::new()
-            .must(Condition::Match {
-                key: "level".to_string(),
-                value: serde_json::Value::String("ShortTerm".to_string()),
-            })
-            .must(Condition::Range {
-                key: "timestamp".to_string(),
-                gte: None,
-                lte: Some(cutoff_time as f64),
-            });
+        let filter = self.gc_filter("ShortTerm", cutoff_time, now);
 
         // Search for expired contexts
         let search_params = crate::vector_db::SearchParams {
@@ -99,6 +475,8 @@ impl BackgroundTaskManager {
             filter: Some(filter),
             with_payload: false,
             with_vector: false,
+            hnsw_ef: None,
+            exact: None,
         };
 
         match self.vector_db.search(&self.l2_collection_name, search_params).await {
@@ -143,24 +521,47 @@ impl BackgroundTaskManager {
         }
     }
 
+    /// Permanently delete soft-deleted contexts whose grace period has passed
+    ///
+    /// Returns how many contexts were removed.
+    pub async fn purge_tombstones(&self) -> Result<usize> {
+        let cutoff_time = self.clock.now_utc().timestamp() - self.tombstone_grace.as_secs() as i64;
+        let filter = Filter::new().must(Condition::Range {
+            key: DELETED_AT_KEY.to_string(),
+            gt: None,
+            gte: None,
+            lte: Some(cutoff_time as f64),
+        });
+
+        let mut purged = 0;
+        for collection in &self.tombstone_collections {
+            let page = self.vector_db
+                .scroll(collection, Some(filter.clone()), None, 1000)
+                .await?;
+            if page.points.is_empty() {
+                continue;
+            }
+
+            let ids: Vec<_> = page.points.iter().map(|p| p.id).collect();
+            let count = ids.len();
+            self.vector_db.delete_points(collection, ids).await?;
+
+            debug!("Purged {} soft-deleted contexts from {}", count, collection);
+            purged += count;
+        }
+
+        Ok(purged)
+    }
+
     /// Clean up expired L3 contexts (long-term)
     /// This is more conservative and only removes contexts that are truly expired
     pub async fn cleanup_expired_l3_contexts(&self, l3_ttl_secs: i64) -> Result<usize> {
-        let now = chrono::Utc::now().timestamp();
+        let now = self.clock.now_utc().timestamp();
         let cutoff_time = now - l3_ttl_secs;
 
         debug!("Starting L3 GC with cutoff time: {}", cutoff_time);
 
-        let filter = Filter::new()
-            .must(Condition::Match {
-                key: "level".to_string(),
-                value: serde_json::Value::String("LongTerm".to_string()),
-            })
-            .must(Condition::Range {
-                key: "timestamp".to_string(),
-                gte: None,
-                lte: Some(cutoff_time as f64),
-            });
+        let filter = self.gc_filter("LongTerm", cutoff_time, now);
 
         let search_params = crate::vector_db::SearchParams {
             vector: vec![0.0; self.vector_size],
@@ -169,6 +570,8 @@ impl BackgroundTaskManager {
             filter: Some(filter),
             with_payload: false,
             with_vector: false,
+            hnsw_ef: None,
+            exact: None,
         };
 
         match self.vector_db.search(&self.l3_collection_name, search_params).await {
@@ -213,12 +616,381 @@ impl BackgroundTaskManager {
 
 #[cfg(test)]
 mod tests {
-    // Note: These tests would require mock implementations
-    // They are placeholders for future implementation
+    use super::*;
+    use crate::clock::FakeClock;
+    use crate::hirag::{ContextManager, ContextRequest};
+    use crate::test_support::{point, test_manager_with_config, MockEmbeddingProvider, MockVectorStore};
+    use crate::vector_db::{CollectionNaming, ContextLevel};
+    use chrono::{TimeZone, Utc};
+    use uuid::Uuid;
+
+    #[tokio::test]
+    async fn test_l2_gc_cutoff_follows_clock() {
+        let store = Arc::new(MockVectorStore::new());
+        store.create_collection("l2").await.unwrap();
+        store.insert_points("l2", vec![
+            point(1, ContextLevel::ShortTerm, 1_000),
+            point(2, ContextLevel::ShortTerm, 1_500),
+        ]).await.unwrap();
+
+        let clock = Arc::new(FakeClock::at(Utc.timestamp_opt(1_100, 0).unwrap()));
+        let manager = BackgroundTaskManager::new(
+            store.clone(),
+            Duration::from_secs(60),
+            100,
+            86_400,
+            "l2".to_string(),
+            "l3".to_string(),
+            2,
+        )
+        .with_clock(clock.clone());
+
+        // Cutoff is 1_000: only the first context has expired
+        assert_eq!(manager.cleanup_expired_l2_contexts().await.unwrap(), 1);
+        assert_eq!(store.point_ids("l2"), vec![Uuid::from_u128(2)]);
+
+        clock.advance(Duration::from_secs(500));
+        assert_eq!(manager.cleanup_expired_l2_contexts().await.unwrap(), 1);
+        assert!(store.point_ids("l2").is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_l3_gc_uses_l3_ttl_and_records_runs() {
+        let store = Arc::new(MockVectorStore::new());
+        store.create_collection("l2").await.unwrap();
+        store.create_collection("l3").await.unwrap();
+        store.insert_points("l3", vec![
+            point(1, ContextLevel::LongTerm, 1_000),
+            point(2, ContextLevel::LongTerm, 1_900),
+        ]).await.unwrap();
+
+        let metrics = Arc::new(MetricsCollector::new());
+        let clock = Arc::new(FakeClock::at(Utc.timestamp_opt(2_000, 0).unwrap()));
+        let manager = BackgroundTaskManager::new(
+            store.clone(),
+            Duration::from_secs(60),
+            100,
+            500,
+            "l2".to_string(),
+            "l3".to_string(),
+            2,
+        )
+        .with_clock(clock)
+        .with_metrics(metrics.clone());
+
+        // Cutoff is 1_500: only the first context has outlived the L3 TTL
+        manager.run_l3_gc_once().await;
+        assert_eq!(store.point_ids("l3"), vec![Uuid::from_u128(2)]);
+
+        manager.run_l2_gc_once().await;
+        assert_eq!(metrics.gc_runs_total(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_gc_counters_are_exported() {
+        let store = Arc::new(MockVectorStore::new());
+        store.create_collection("l2").await.unwrap();
+        store.insert_points("l2", vec![point(1, ContextLevel::ShortTerm, 1_000)]).await.unwrap();
+
+        let metrics = Arc::new(MetricsCollector::new());
+        let clock = Arc::new(FakeClock::at(Utc.timestamp_opt(2_000, 0).unwrap()));
+        let manager = BackgroundTaskManager::new(
+            store.clone(),
+            Duration::from_secs(60),
+            100,
+            500,
+            "l2".to_string(),
+            "l3".to_string(),
+            2,
+        )
+        .with_clock(clock)
+        .with_metrics(metrics.clone());
+
+        manager.run_l2_gc_once().await;
+        store.fail_collection("l2");
+        manager.run_l2_gc_once().await;
+
+        let exported = metrics.export_prometheus();
+        assert!(exported.contains("context_manager_gc_runs_total 1\n"));
+        assert!(exported.contains("context_manager_gc_deleted_total 1\n"));
+        assert!(exported.contains("context_manager_gc_errors_total 1\n"));
+    }
+
+    #[tokio::test]
+    async fn test_background_tasks_stop_on_shutdown() {
+        let store = Arc::new(MockVectorStore::new());
+        store.create_collection("l2").await.unwrap();
+        store.create_collection("l3").await.unwrap();
+
+        let manager = Arc::new(
+            BackgroundTaskManager::new(
+                store.clone(),
+                Duration::from_secs(3600),
+                100,
+                500,
+                "l2".to_string(),
+                "l3".to_string(),
+                2,
+            )
+            .with_l3_gc(true)
+            .with_reembedding(Arc::new(MockEmbeddingProvider::new(2)), vec!["l2".to_string()]),
+        );
+
+        let coordinator = crate::shutdown::ShutdownCoordinator::new();
+        let handles = manager.start(coordinator.subscribe());
+        assert_eq!(handles.len(), 3);
+
+        coordinator.shutdown();
+        for handle in handles {
+            tokio::time::timeout(Duration::from_secs(5), handle).await.unwrap().unwrap();
+        }
+    }
+
+    #[tokio::test]
+    async fn test_l3_gc_is_opt_in() {
+        let store = Arc::new(MockVectorStore::new());
+        let manager = Arc::new(BackgroundTaskManager::new(
+            store,
+            Duration::from_secs(3600),
+            100,
+            500,
+            "l2".to_string(),
+            "l3".to_string(),
+            2,
+        ));
+
+        // Only the L2 GC task starts
+        let coordinator = crate::shutdown::ShutdownCoordinator::new();
+        let handles = manager.start(coordinator.subscribe());
+        assert_eq!(handles.len(), 1);
+
+        coordinator.shutdown();
+        for handle in handles {
+            tokio::time::timeout(Duration::from_secs(5), handle).await.unwrap().unwrap();
+        }
+    }
+
+    #[tokio::test]
+    async fn test_tombstones_purged_after_grace_period() {
+        let store = Arc::new(MockVectorStore::new());
+        store.create_collection("l2").await.unwrap();
+        let tombstone = |id: u128, deleted_at: i64| {
+            let mut point = point(id, ContextLevel::ShortTerm, 1_000);
+            point.payload.metadata.insert(DELETED_AT_KEY.to_string(), deleted_at.into());
+            point
+        };
+        store.insert_points("l2", vec![
+            tombstone(1, 1_000),
+            tombstone(2, 1_080),
+            point(3, ContextLevel::ShortTerm, 1_000),
+        ]).await.unwrap();
+
+        let clock = Arc::new(FakeClock::at(Utc.timestamp_opt(1_100, 0).unwrap()));
+        let manager = BackgroundTaskManager::new(
+            store.clone(),
+            Duration::from_secs(60),
+            100_000,
+            86_400,
+            "l2".to_string(),
+            "l3".to_string(),
+            2,
+        )
+        .with_clock(clock.clone())
+        .with_tombstone_purge(vec!["l2".to_string()], Duration::from_secs(50));
+
+        // Only the first tombstone is past its grace period
+        assert_eq!(manager.purge_tombstones().await.unwrap(), 1);
+        assert_eq!(store.point_ids("l2").len(), 2);
+        assert!(store.point("l2", Uuid::from_u128(1)).is_none());
+
+        clock.advance(Duration::from_secs(60));
+        assert_eq!(manager.purge_tombstones().await.unwrap(), 1);
+        assert_eq!(store.point_ids("l2"), vec![Uuid::from_u128(3)]);
+
+        // Purging runs as its own task even with GC disabled
+        let manager = Arc::new(manager.with_gc(false));
+        let coordinator = crate::shutdown::ShutdownCoordinator::new();
+        let handles = manager.start(coordinator.subscribe());
+        assert_eq!(handles.len(), 1);
+        coordinator.shutdown();
+        for handle in handles {
+            tokio::time::timeout(Duration::from_secs(5), handle).await.unwrap().unwrap();
+        }
+    }
+
+    #[tokio::test]
+    async fn test_gc_interval_backs_off_after_failures() {
+        let store = Arc::new(MockVectorStore::new());
+        store.create_collection("l2").await.unwrap();
+        store.fail_collection("l2");
+
+        let clock = Arc::new(FakeClock::at(Utc.timestamp_opt(1_100, 0).unwrap()));
+        let manager = BackgroundTaskManager::new(
+            store.clone(),
+            Duration::from_secs(60),
+            100,
+            86_400,
+            "l2".to_string(),
+            "l3".to_string(),
+            2,
+        )
+        .with_clock(clock.clone())
+        .with_max_gc_backoff(Duration::from_secs(300));
+
+        let mut delays = Vec::new();
+        for _ in 0..4 {
+            delays.push(manager.run_l2_gc_once().await.as_secs());
+            clock.advance(Duration::from_secs(delays[delays.len() - 1]));
+        }
+        // Capped at the configured maximum
+        assert_eq!(delays, vec![120, 240, 300, 300]);
+        assert_eq!(manager.consecutive_gc_failures(), 4);
+
+        // A successful run resets to the base interval
+        store.recover_collection("l2");
+        assert_eq!(manager.run_l2_gc_once().await, Duration::from_secs(60));
+        assert_eq!(manager.consecutive_gc_failures(), 0);
+    }
 
-    #[test]
-    fn test_background_task_manager_creation() {
-        // This would require a mock VectorStore implementation
-        // Skipping for now
+    #[tokio::test]
+    async fn test_deferred_context_is_embedded_on_next_pass() {
+        let mut config = crate::config::Config::default_config().hirag;
+        config.defer_embedding_on_failure = true;
+        let (manager, store, embedding) = test_manager_with_config(config).await;
+        let collection = CollectionNaming::default().collection(ContextLevel::ShortTerm);
+
+        embedding.set_failing(true);
+        let id = manager
+            .store_context("offline note", ContextLevel::ShortTerm, HashMap::new())
+            .await
+            .unwrap();
+
+        let stored = store.point(&collection, id).unwrap();
+        assert!(stored.vector.iter().all(|v| *v == 0.0));
+        assert_eq!(stored.payload.metadata.get(NEEDS_EMBEDDING_KEY), Some(&serde_json::Value::Bool(true)));
+
+        // The placeholder vector is never searched
+        embedding.set_failing(false);
+        let search = || ContextRequest::new("offline note".to_string(), 1000)
+            .with_levels(vec![ContextLevel::ShortTerm]);
+        assert!(manager.retrieve_context(search()).await.unwrap().contexts.is_empty());
+        embedding.set_failing(true);
+
+        let background = BackgroundTaskManager::new(
+            store.clone(),
+            Duration::from_secs(60),
+            3600,
+            86_400,
+            collection.clone(),
+            CollectionNaming::default().collection(ContextLevel::LongTerm),
+            1024,
+        )
+        .with_reembedding(embedding.clone(), vec![collection.clone()]);
+
+        // Still failing: the context stays deferred
+        assert!(background.reembed_contexts().await.is_err());
+
+        // Only the vector and the flag are written, not the whole point
+        embedding.set_failing(false);
+        let inserts = store.insert_calls();
+        assert_eq!(background.reembed_contexts().await.unwrap(), 1);
+        assert_eq!(store.insert_calls(), inserts);
+        let stored = store.point(&collection, id).unwrap();
+        assert_eq!(stored.vector, embedding.embed_single("offline note").await.unwrap());
+        assert!(!stored.payload.metadata.contains_key(NEEDS_EMBEDDING_KEY));
+        assert_eq!(manager.retrieve_context(search()).await.unwrap().contexts.len(), 1);
+
+        // Nothing left to do on the following pass
+        assert_eq!(background.reembed_contexts().await.unwrap(), 0);
     }
-}
\ No newline at end of file
+
+    #[tokio::test]
+    async fn test_stale_model_contexts_reembedded_in_bounded_batches() {
+        let store = Arc::new(MockVectorStore::new());
+        store.create_collection("l2").await.unwrap();
+        let tagged = |id: u128, model: Option<&str>, needs_embedding: bool| {
+            let mut point = point(id, ContextLevel::ShortTerm, 1_000);
+            if let Some(model) = model {
+                point.payload.metadata.insert(EMBEDDING_MODEL_KEY.to_string(), model.into());
+            }
+            if needs_embedding {
+                point.payload.metadata.insert(NEEDS_EMBEDDING_KEY.to_string(), true.into());
+            }
+            point
+        };
+        store.insert_points("l2", vec![
+            tagged(1, Some("old-model"), false),
+            tagged(2, Some("new-model"), false),
+            tagged(3, None, false),
+            tagged(4, None, true),
+        ]).await.unwrap();
+
+        let embedding = Arc::new(MockEmbeddingProvider::new(2));
+        let metrics = Arc::new(MetricsCollector::new());
+        let manager = BackgroundTaskManager::new(
+            store.clone(),
+            Duration::from_secs(60),
+            100,
+            86_400,
+            "l2".to_string(),
+            "l3".to_string(),
+            2,
+        )
+        .with_reembedding(embedding.clone(), vec!["l2".to_string()])
+        .with_embedding_model("new-model")
+        .with_reembed_rate(1, Duration::from_secs(60))
+        .with_metrics(metrics.clone());
+
+        // One context per pass until the stale and flagged ones are done
+        assert_eq!(manager.reembed_contexts().await.unwrap(), 1);
+        assert_eq!(manager.reembed_contexts().await.unwrap(), 1);
+        assert_eq!(manager.reembed_contexts().await.unwrap(), 0);
+        assert_eq!(metrics.reembedded_total(), 2);
+
+        for id in [1, 4] {
+            let point = store.point("l2", Uuid::from_u128(id)).unwrap();
+            assert_ne!(point.vector, vec![1.0, 0.0]);
+            assert_eq!(point.payload.metadata.get(EMBEDDING_MODEL_KEY), Some(&"new-model".into()));
+            assert!(!point.payload.metadata.contains_key(NEEDS_EMBEDDING_KEY));
+        }
+        // Current and untagged contexts are left alone
+        for id in [2, 3] {
+            assert_eq!(store.point("l2", Uuid::from_u128(id)).unwrap().vector, vec![1.0, 0.0]);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_far_future_contexts_collected_only_when_configured() {
+        let store = Arc::new(MockVectorStore::new());
+        store.create_collection("l2").await.unwrap();
+        store.insert_points("l2", vec![
+            point(1, ContextLevel::ShortTerm, 1_100),
+            point(2, ContextLevel::ShortTerm, 1_000_000),
+        ]).await.unwrap();
+
+        let clock = Arc::new(FakeClock::at(Utc.timestamp_opt(1_100, 0).unwrap()));
+        let manager = |tolerance: Option<u64>| {
+            let manager = BackgroundTaskManager::new(
+                store.clone(),
+                Duration::from_secs(60),
+                100,
+                86_400,
+                "l2".to_string(),
+                "l3".to_string(),
+                2,
+            )
+            .with_clock(clock.clone());
+            match tolerance {
+                Some(secs) => manager.with_future_timestamp_tolerance(Duration::from_secs(secs)),
+                None => manager,
+            }
+        };
+
+        // By default a future timestamp never expires
+        assert_eq!(manager(None).cleanup_expired_l2_contexts().await.unwrap(), 0);
+
+        // With a tolerance it is treated as corrupt; the current context stays
+        assert_eq!(manager(Some(3600)).cleanup_expired_l2_contexts().await.unwrap(), 1);
+        assert_eq!(store.point_ids("l2"), vec![Uuid::from_u128(1)]);
+    }
+}
@@ -0,0 +1,203 @@
+//! Bounded in-memory cache for Immediate (L1) contexts
+
+use super::models::Context;
+use dashmap::DashMap;
+use std::collections::BTreeSet;
+use std::sync::Mutex;
+use uuid::Uuid;
+
+/// L1 context cache with timestamp-ordered eviction
+///
+/// Entries live in a `DashMap` for lock-free reads, alongside a
+/// `(timestamp, id)` index that keeps them in eviction order. Inserts and
+/// evictions are O(log n) instead of collecting and sorting every entry once
+/// the cache is over capacity. All mutations happen under the index lock so
+/// the map and the index never disagree.
+pub struct L1Cache {
+    entries: DashMap<Uuid, Context>,
+    order: Mutex<BTreeSet<(i64, Uuid)>>,
+}
+
+impl L1Cache {
+    /// Create an empty cache
+    pub fn new() -> Self {
+        Self {
+            entries: DashMap::new(),
+            order: Mutex::new(BTreeSet::new()),
+        }
+    }
+
+    /// Insert or replace a context, evicting the oldest entries beyond `capacity`
+    ///
+    /// Returns the IDs of evicted contexts.
+    pub fn insert(&self, context: Context, capacity: usize) -> Vec<Uuid> {
+        let mut order = self.order.lock().unwrap();
+
+        let key = (context.timestamp, context.id);
+        if let Some(previous) = self.entries.insert(context.id, context) {
+            order.remove(&(previous.timestamp, previous.id));
+        }
+        order.insert(key);
+
+        self.evict_beyond(&mut order, capacity)
+    }
+
+    /// Evict the oldest entries beyond `capacity`
+    ///
+    /// Returns the IDs of evicted contexts.
+    pub fn shrink_to(&self, capacity: usize) -> Vec<Uuid> {
+        let mut order = self.order.lock().unwrap();
+        self.evict_beyond(&mut order, capacity)
+    }
+
+    fn evict_beyond(&self, order: &mut BTreeSet<(i64, Uuid)>, capacity: usize) -> Vec<Uuid> {
+        let mut evicted = Vec::new();
+        while order.len() > capacity {
+            let Some((_, id)) = order.pop_first() else { break };
+            self.entries.remove(&id);
+            evicted.push(id);
+        }
+        evicted
+    }
+
+    /// Move a cached context to a new timestamp, if present
+    ///
+    /// Returns whether the context was cached.
+    pub fn touch(&self, id: &Uuid, timestamp: i64) -> bool {
+        let mut order = self.order.lock().unwrap();
+        let Some(mut entry) = self.entries.get_mut(id) else { return false };
+        order.remove(&(entry.timestamp, *id));
+        entry.timestamp = timestamp;
+        order.insert((timestamp, *id));
+        true
+    }
+
+    /// A cached context, if present
+    pub fn get(&self, id: &Uuid) -> Option<Context> {
+        self.entries.get(id).map(|entry| entry.value().clone())
+    }
+
+    /// Set a cached context's access count, if present
+    ///
+    /// Returns whether the context was cached.
+    pub fn set_access_count(&self, id: &Uuid, access_count: u64) -> bool {
+        let Some(mut entry) = self.entries.get_mut(id) else { return false };
+        entry.access_count = access_count;
+        true
+    }
+
+    /// Remove a context
+    pub fn remove(&self, id: &Uuid) -> Option<Context> {
+        let mut order = self.order.lock().unwrap();
+        let (_, removed) = self.entries.remove(id)?;
+        order.remove(&(removed.timestamp, removed.id));
+        Some(removed)
+    }
+
+    /// Remove all contexts
+    pub fn clear(&self) {
+        let mut order = self.order.lock().unwrap();
+        self.entries.clear();
+        order.clear();
+    }
+
+    /// Number of cached contexts
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// Whether the cache is empty
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Snapshot of all cached contexts, newest first
+    pub fn newest_first(&self) -> Vec<Context> {
+        let order = self.order.lock().unwrap();
+        order
+            .iter()
+            .rev()
+            .filter_map(|(_, id)| self.entries.get(id).map(|entry| entry.value().clone()))
+            .collect()
+    }
+}
+
+impl Default for L1Cache {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::vector_db::ContextLevel;
+
+    fn context_at(timestamp: i64) -> Context {
+        Context::new(Uuid::new_v4(), format!("context {}", timestamp), ContextLevel::Immediate, timestamp, 1)
+    }
+
+    #[test]
+    fn test_evicts_oldest_beyond_capacity() {
+        let cache = L1Cache::new();
+        let contexts: Vec<_> = [30, 10, 50, 20, 40].into_iter().map(context_at).collect();
+
+        let mut evicted = Vec::new();
+        for context in &contexts {
+            evicted.extend(cache.insert(context.clone(), 3));
+        }
+
+        // Timestamps 10 and 20 are the oldest and must be the ones evicted
+        assert_eq!(evicted, vec![contexts[1].id, contexts[3].id]);
+        assert_eq!(cache.len(), 3);
+
+        let timestamps: Vec<_> = cache.newest_first().iter().map(|c| c.timestamp).collect();
+        assert_eq!(timestamps, vec![50, 40, 30]);
+    }
+
+    #[test]
+    fn test_reinsert_updates_eviction_order() {
+        let cache = L1Cache::new();
+        let mut first = context_at(10);
+        let second = context_at(20);
+        cache.insert(first.clone(), 2);
+        cache.insert(second.clone(), 2);
+
+        // Refreshing the oldest entry moves it to the back of the eviction queue
+        first.timestamp = 30;
+        assert!(cache.insert(first.clone(), 2).is_empty());
+        assert_eq!(cache.len(), 2);
+
+        let evicted = cache.insert(context_at(40), 2);
+        assert_eq!(evicted, vec![second.id]);
+    }
+
+    #[test]
+    fn test_remove_and_clear_keep_index_consistent() {
+        let cache = L1Cache::new();
+        let context = context_at(10);
+        cache.insert(context.clone(), 10);
+        cache.insert(context_at(20), 10);
+
+        assert!(cache.remove(&context.id).is_some());
+        assert!(cache.remove(&context.id).is_none());
+        assert_eq!(cache.newest_first().len(), 1);
+
+        cache.clear();
+        assert!(cache.is_empty());
+        assert!(cache.newest_first().is_empty());
+    }
+
+    #[test]
+    fn test_shrink_to_evicts_oldest() {
+        let cache = L1Cache::new();
+        let contexts: Vec<_> = [10, 20, 30, 40].into_iter().map(context_at).collect();
+        for context in &contexts {
+            cache.insert(context.clone(), 10);
+        }
+
+        assert_eq!(cache.shrink_to(2), vec![contexts[0].id, contexts[1].id]);
+        assert_eq!(cache.len(), 2);
+        assert!(cache.shrink_to(2).is_empty());
+    }
+}
@@ -1,10 +1,10 @@
 //! HiRAG manager implementation
 
-use super::{ContextManager, models::*, retriever::ContextRetriever, ranker::ContextRanker, token_estimator::TokenEstimator};
+use super::{find_point, is_expired, ContextManager, models::*, retriever::{live_filter, request_filter, ContextRetriever}, ranker::ContextRanker, token_estimator::TokenEstimator};
 use crate::config::HiRAGConfig;
 use crate::embedding::EmbeddingProvider;
 use crate::error::{HiRAGError, Result};
-use crate::vector_db::{ContextLevel, VectorPoint, VectorStore, Payload};
+use crate::vector_db::{CollectionNaming, ContextLevel, VectorPoint, VectorStore, Payload};
 use async_trait::async_trait;
 use chrono::Utc;
 use std::collections::{HashMap, VecDeque};
@@ -22,6 +22,7 @@ pub struct HiRAGManager {
     retriever: ContextRetriever,
     ranker: ContextRanker,
     token_estimator: TokenEstimator,
+    naming: CollectionNaming,
 }
 
 impl HiRAGManager {
@@ -33,13 +34,15 @@ impl HiRAGManager {
     ) -> Result<Self> {
         info!("Initializing HiRAG manager");
         
-        let token_estimator = TokenEstimator::new(config.token_estimator);
+        let token_estimator = TokenEstimator::new(config.token_estimator.clone())
+            .with_level_estimators(&config.level_token_estimators);
         let retriever = ContextRetriever::new(
             vector_db.clone(),
-            TokenEstimator::new(config.token_estimator),
+            token_estimator.clone(),
             config.retrieval_strategy.clone(),
         );
-        let ranker = ContextRanker::new(config.ranking_weights.clone());
+        let ranker = ContextRanker::new(config.ranking_weights.clone())
+            .with_recency_half_life(config.recency_half_life_secs);
         
         Ok(Self {
             config,
@@ -49,9 +52,18 @@ impl HiRAGManager {
             retriever,
             ranker,
             token_estimator,
+            naming: CollectionNaming::default(),
         })
     }
     
+    /// Name level collections with `naming` instead of the default `contexts_` prefix
+    ///
+    /// Use the same naming as the vector store client and GC.
+    pub fn with_collection_naming(mut self, naming: CollectionNaming) -> Self {
+        self.naming = naming;
+        self
+    }
+    
     /// Initialize the manager
     pub async fn initialize(&self) -> Result<()> {
         info!("Initializing HiRAG collections");
@@ -69,7 +81,7 @@ impl HiRAGManager {
     
     /// Get collection name for a context level
     fn collection_name(&self, level: ContextLevel) -> String {
-        format!("contexts_{}", level.as_str().to_lowercase())
+        self.naming.collection(level)
     }
     
     /// Update L1 cache
@@ -88,85 +100,49 @@ impl HiRAGManager {
     }
     
     /// Get contexts from L1 cache
-    async fn get_l1_contexts(&self, max_tokens: usize) -> Vec<Context> {
+    ///
+    /// Returns the contexts that fit `max_tokens` and how many were left out.
+    /// With `session_id` set, contexts from other sessions are skipped.
+    /// Cached vectors are only kept when `include_vectors` is set.
+    async fn get_l1_contexts(
+        &self,
+        max_tokens: usize,
+        session_id: Option<&str>,
+        include_vectors: bool,
+    ) -> (Vec<Context>, usize) {
         let cache = self.l1_cache.read().await;
         let mut contexts = Vec::new();
         let mut total_tokens = 0;
         
-        for context in cache.iter() {
+        let cached: Vec<_> = cache
+            .iter()
+            .filter(|c| session_id.is_none_or(|session_id| c.session_id.as_deref() == Some(session_id)))
+            .collect();
+        for context in cached.iter().copied() {
             if total_tokens + context.token_count <= max_tokens {
-                contexts.push(context.clone());
+                let mut context = context.clone();
+                if !include_vectors {
+                    context.vector = None;
+                }
                 total_tokens += context.token_count;
+                contexts.push(context);
             } else {
                 break;
             }
         }
         
         debug!("Retrieved {} contexts from L1 cache", contexts.len());
-        contexts
-    }
-}
-
-#[async_trait]
-impl ContextManager for HiRAGManager {
-    async fn store_context(
-        &self,
-        text: &str,
-        level: ContextLevel,
-        metadata: HashMap<String, serde_json::Value>,
-    ) -> Result<Uuid> {
-        debug!("Storing context at level: {:?}", level);
-        
-        // Generate embedding
-        let embedding = self.embedding_client.embed_single(text).await?;
-        
-        // Create point
-        let id = Uuid::new_v4();
-        let timestamp = Utc::now().timestamp();
-        let token_count = self.token_estimator.estimate(text);
-        
-        let point = VectorPoint {
-            id,
-            vector: embedding,
-            payload: Payload {
-                text: text.to_string(),
-                level,
-                timestamp,
-                agent_id: "default".to_string(),
-                session_id: None,
-                metadata: metadata.clone(),
-            },
-        };
-        
-        // Store in vector database
-        let collection = self.collection_name(level);
-        self.vector_db.insert_points(&collection, vec![point]).await?;
-        
-        // Update L1 cache if immediate context
-        if level == ContextLevel::Immediate {
-            let context = Context {
-                id,
-                text: text.to_string(),
-                level,
-                relevance_score: 1.0,
-                token_count,
-                timestamp,
-                metadata,
-            };
-            self.update_l1_cache(context).await;
-        }
-        
-        info!("Context stored with id: {}", id);
-        Ok(id)
+        let omitted = cached.len() - contexts.len();
+        (contexts, omitted)
     }
     
-    async fn retrieve_context(&self, request: ContextRequest) -> Result<ContextResponse> {
-        let start_time = std::time::Instant::now();
-        debug!("Retrieving context for query: {}", request.query);
-        
-        // Generate query embedding
-        let query_embedding = self.embedding_client.embed_single(&request.query).await?;
-        
+    /// Retrieve, rank and budget contexts for an already-embedded query
+    async fn retrieve_with_embedding(
+        &self,
+        request: ContextRequest,
+        query_embedding: Vec<f32>,
+        start_time: std::time::Instant,
+    ) -> Result<ContextResponse> {
         // Determine which levels to search
         let levels = if request.levels.is_empty() {
             vec![ContextLevel::Immediate, ContextLevel::ShortTerm, ContextLevel::LongTerm]
@@ -180,6 +156,7 @@ impl ContextManager for HiRAGManager {
         let mut all_contexts = Vec::new();
         let mut cache_hits = 0;
         let mut total_searched = 0;
+        let mut omitted_count = 0;
         
         // Retrieve from each level in parallel
         let mut tasks = Vec::new();
@@ -194,15 +171,18 @@ impl ContextManager for HiRAGManager {
             if level == ContextLevel::Immediate {
                 // Use L1 cache (synchronous)
                 cache_hits += 1;
-                let contexts = self.get_l1_contexts(max_tokens).await;
+                let (contexts, omitted) = self.get_l1_contexts(max_tokens, request.session_id.as_deref(), request.include_vectors).await;
                 total_searched += contexts.len();
+                omitted_count += omitted;
                 all_contexts.extend(contexts);
             } else {
                 // Search vector database in parallel
                 let collection = self.collection_name(level);
                 let retriever = self.retriever.clone();
                 let embedding = query_embedding.clone();
-                let filters = request.filters.clone();
+                let filters = request_filter(&request);
+                let rescore_metric = request.rescore_metric;
+                let include_vectors = request.include_vectors;
                 
                 tasks.push(tokio::spawn(async move {
                     retriever.retrieve_from_level(
@@ -210,6 +190,8 @@ impl ContextManager for HiRAGManager {
                         embedding,
                         max_tokens,
                         filters,
+                        rescore_metric,
+                        include_vectors,
                     ).await
                 }));
             }
@@ -218,8 +200,9 @@ impl ContextManager for HiRAGManager {
         // Wait for all parallel tasks to complete
         for task in tasks {
             match task.await {
-                Ok(Ok(contexts)) => {
+                Ok(Ok((contexts, omitted))) => {
                     total_searched += contexts.len();
+                    omitted_count += omitted;
                     all_contexts.extend(contexts);
                 }
                 Ok(Err(e)) => {
@@ -244,9 +227,13 @@ impl ContextManager for HiRAGManager {
             if total_tokens + context.token_count <= request.max_tokens {
                 total_tokens += context.token_count;
                 final_contexts.push(context);
+            } else {
+                omitted_count += 1;
             }
         }
         
+        request.sort_order.apply(&mut final_contexts);
+        
         // Calculate metadata
         let mut level_distribution = HashMap::new();
         for context in &final_contexts {
@@ -277,9 +264,163 @@ impl ContextManager for HiRAGManager {
                 avg_relevance,
                 cache_hits,
                 total_searched,
+                clamped_max_tokens: None,
+                truncated: omitted_count > 0,
+                omitted_count,
+                failed_levels: Vec::new(),
+                model_mismatches: 0,
             },
         })
     }
+}
+
+#[async_trait]
+impl ContextManager for HiRAGManager {
+    async fn store_context_with(
+        &self,
+        text: &str,
+        level: ContextLevel,
+        metadata: HashMap<String, serde_json::Value>,
+        options: StoreOptions,
+    ) -> Result<Uuid> {
+        debug!("Storing context at level: {:?}", level);
+        
+        // Generate embedding
+        let embedding = self.embedding_client.embed_single(text).await?;
+        
+        // Create point
+        let id = Uuid::new_v4();
+        let timestamp = Utc::now().timestamp();
+        let token_count = self.token_estimator.estimate_for_level(level, text);
+        
+        // L1 entries keep their vector so retrieval can return it on request
+        let cached_vector = (level == ContextLevel::Immediate).then(|| embedding.clone());
+        
+        let point = VectorPoint {
+            id,
+            vector: embedding,
+            payload: Payload {
+                text: text.to_string(),
+                level,
+                timestamp,
+                agent_id: options.agent_id.unwrap_or_else(|| "default".to_string()),
+                session_id: options.session_id.clone(),
+                access_count: 0,
+                token_count: Some(token_count),
+                metadata: metadata.clone(),
+            },
+        };
+        
+        // Store in vector database
+        let collection = self.collection_name(level);
+        self.vector_db.insert_points(&collection, vec![point]).await?;
+        
+        // Update L1 cache if immediate context
+        if level == ContextLevel::Immediate {
+            let context = Context {
+                id,
+                text: text.to_string(),
+                level,
+                relevance_score: 1.0,
+                token_count,
+                timestamp,
+                access_count: 0,
+                session_id: options.session_id,
+                metadata,
+                vector: cached_vector,
+            };
+            self.update_l1_cache(context).await;
+        }
+        
+        info!("Context stored with id: {}", id);
+        Ok(id)
+    }
+    
+    async fn retrieve_context(&self, request: ContextRequest) -> Result<ContextResponse> {
+        let start_time = std::time::Instant::now();
+        debug!("Retrieving context for query: {}", request.query);
+        
+        // Generate query embedding
+        let query_embedding = self.embedding_client.embed_query(&request.query).await?;
+        
+        self.retrieve_with_embedding(request, query_embedding, start_time).await
+    }
+    
+    async fn retrieve_batch(&self, requests: Vec<ContextRequest>) -> Result<Vec<ContextResponse>> {
+        if requests.is_empty() {
+            return Ok(Vec::new());
+        }
+        
+        let start_time = std::time::Instant::now();
+        debug!("Retrieving context for {} queries", requests.len());
+        
+        // Embed every query in a single provider call
+        let queries: Vec<String> = requests.iter().map(|r| r.query.clone()).collect();
+        let embeddings = self.embedding_client.embed_query_batch(&queries).await?;
+        if embeddings.len() != requests.len() {
+            return Err(HiRAGError::RetrievalError(format!(
+                "Expected {} query embeddings, got {}",
+                requests.len(),
+                embeddings.len()
+            )).into());
+        }
+        
+        futures::future::try_join_all(
+            requests
+                .into_iter()
+                .zip(embeddings)
+                .map(|(request, embedding)| self.retrieve_with_embedding(request, embedding, start_time)),
+        ).await
+    }
+    
+    async fn recent(&self, level: ContextLevel, limit: usize, _agent_id: Option<&str>) -> Result<Vec<Context>> {
+        let collection = self.collection_name(level);
+        let live = live_filter(Utc::now().timestamp() as f64);
+        let points = self.vector_db.newest_points(&collection, Some(live), limit).await?;
+        
+        Ok(points
+            .into_iter()
+            .map(|point| {
+                let token_count = self.token_estimator.estimate_payload(&point.payload);
+                let mut context = Context::new(
+                    point.id,
+                    point.payload.text,
+                    point.payload.level,
+                    point.payload.timestamp,
+                    token_count,
+                );
+                context.metadata = point.payload.metadata;
+                context
+            })
+            .collect())
+    }
+    
+    async fn get_context(&self, id: Uuid, _agent_id: Option<&str>) -> Result<Option<Context>> {
+        let now = Utc::now().timestamp() as f64;
+        if let Some(context) = self.l1_cache.read().await.iter().find(|c| c.id == id) {
+            return Ok((!is_expired(&context.metadata, now)).then(|| Context { vector: None, ..context.clone() }));
+        }
+        
+        for level in &[ContextLevel::Immediate, ContextLevel::ShortTerm, ContextLevel::LongTerm] {
+            let collection = self.collection_name(*level);
+            
+            if let Some(point) = find_point(self.vector_db.as_ref(), &collection, id).await? {
+                let token_count = self.token_estimator.estimate_payload(&point.payload);
+                let mut context = Context::new(
+                    point.id,
+                    point.payload.text,
+                    point.payload.level,
+                    point.payload.timestamp,
+                    token_count,
+                );
+                context.access_count = point.payload.access_count;
+                context.metadata = point.payload.metadata;
+                return Ok((!is_expired(&context.metadata, now)).then_some(context));
+            }
+        }
+        
+        Ok(None)
+    }
     
     async fn update_context(
         &self,
@@ -293,24 +434,44 @@ impl ContextManager for HiRAGManager {
             let collection = self.collection_name(*level);
             
             // Try to get the existing point
-            if let Some(mut point) = self.vector_db.get_point(&collection, id).await? {
-                // Update metadata
-                for (key, value) in metadata.iter() {
-                    point.payload.metadata.insert(key.clone(), value.clone());
-                }
+            if find_point(self.vector_db.as_ref(), &collection, id).await?.is_some() {
+                // Only the changed fields are written, so concurrent access
+                // counts, deletes and restores are kept
+                let mut fields = metadata;
+                fields.insert("timestamp".to_string(), Utc::now().timestamp().into());
+                self.vector_db.set_payload_fields(&collection, id, fields).await?;
                 
-                // Update timestamp
-                point.payload.timestamp = chrono::Utc::now().timestamp();
+                info!("Updated context {} in collection {}", id, collection);
+                return Ok(());
+            }
+        }
+        
+        Err(HiRAGError::ContextNotFound(id.to_string()).into())
+    }
+    
+    async fn touch_context(&self, id: Uuid) -> Result<()> {
+        debug!("Touching context: {}", id);
+        
+        for level in &[ContextLevel::Immediate, ContextLevel::ShortTerm, ContextLevel::LongTerm] {
+            let collection = self.collection_name(*level);
+            
+            if find_point(self.vector_db.as_ref(), &collection, id).await?.is_some() {
+                // Only the timestamp is written, so concurrent access counts,
+                // deletes and restores are kept
+                let timestamp = Utc::now().timestamp();
+                let fields = HashMap::from([("timestamp".to_string(), timestamp.into())]);
+                self.vector_db.set_payload_fields(&collection, id, fields).await?;
                 
-                // Re-insert the updated point
-                self.vector_db.insert_points(&collection, vec![point]).await?;
+                let mut cache = self.l1_cache.write().await;
+                if let Some(context) = cache.iter_mut().find(|c| c.id == id) {
+                    context.timestamp = timestamp;
+                }
                 
-                info!("Updated context {} in collection {}", id, collection);
                 return Ok(());
             }
         }
         
-        Err(HiRAGError::StorageError(format!("Context {} not found", id)).into())
+        Err(HiRAGError::ContextNotFound(id.to_string()).into())
     }
     
     async fn delete_context(&self, id: Uuid) -> Result<()> {
@@ -330,6 +491,48 @@ impl ContextManager for HiRAGManager {
         Ok(())
     }
     
+    async fn move_context(&self, id: Uuid, to: ContextLevel) -> Result<()> {
+        debug!("Moving context {} to level {:?}", id, to);
+        
+        for level in &[ContextLevel::Immediate, ContextLevel::ShortTerm, ContextLevel::LongTerm] {
+            let collection = self.collection_name(*level);
+            
+            if let Some(mut point) = find_point(self.vector_db.as_ref(), &collection, id).await? {
+                if *level == to {
+                    return Ok(());
+                }
+                
+                point.payload.level = to;
+                let target = self.collection_name(to);
+                self.vector_db.insert_points(&target, vec![point.clone()]).await?;
+                self.vector_db.delete_points(&collection, vec![id]).await?;
+                
+                self.l1_cache.write().await.retain(|c| c.id != id);
+                if to == ContextLevel::Immediate {
+                    let token_count = self.token_estimator.estimate_payload(&point.payload);
+                    let context = Context {
+                        id,
+                        text: point.payload.text,
+                        level: to,
+                        relevance_score: 1.0,
+                        token_count,
+                        timestamp: point.payload.timestamp,
+                        access_count: point.payload.access_count,
+                        session_id: point.payload.session_id,
+                        metadata: point.payload.metadata,
+                        vector: Some(point.vector),
+                    };
+                    self.update_l1_cache(context).await;
+                }
+                
+                info!("Moved context {} from {} to {}", id, collection, target);
+                return Ok(());
+            }
+        }
+        
+        Err(HiRAGError::ContextNotFound(id.to_string()).into())
+    }
+    
     async fn clear_level(&self, level: ContextLevel) -> Result<()> {
         debug!("Clearing level: {:?}", level);
         
@@ -348,4 +551,17 @@ impl ContextManager for HiRAGManager {
         info!("Level cleared: {:?}", level);
         Ok(())
     }
+    
+    async fn compact_l1(&self) -> Result<usize> {
+        let now = Utc::now().timestamp() as f64;
+        let mut cache = self.l1_cache.write().await;
+        let before = cache.len();
+        
+        cache.retain(|c| !is_expired(&c.metadata, now));
+        cache.truncate(self.config.l1_size);
+        
+        let evicted = before - cache.len();
+        info!("L1 cache compacted: {} evicted, size: {}", evicted, cache.len());
+        Ok(evicted)
+    }
 }
\ No newline at end of file
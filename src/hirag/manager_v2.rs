@@ -1,31 +1,182 @@
 //! Enhanced HiRAG manager with improved concurrency and error handling
 
-use super::{ContextManager, models::*, retriever::ContextRetriever, ranker::ContextRanker, token_estimator::TokenEstimator};
-use crate::config::HiRAGConfig;
+use super::models::*;
+use super::ranker::ContextRanker;
+use super::retriever::{live_filter, request_filter, ContextRetriever};
+use super::token_estimator::TokenEstimator;
+use super::{find_point, is_expired, similarity, strip_internal_metadata, ContextManager, L1Cache};
+use super::{DELETED_AT_KEY, EMBEDDING_MODEL_KEY, HISTORY_KEY, NEEDS_EMBEDDING_KEY};
+use crate::config::{DuplicatePolicy, HiRAGConfig};
 use crate::embedding::EmbeddingProvider;
-use crate::error::{HiRAGError, Result};
-use crate::vector_db::{ContextLevel, VectorPoint, VectorStore, Payload};
-use crate::middleware::InputValidator;
+use crate::error::{ContextError, HiRAGError, Result, VectorDbError};
+use crate::vector_db::{CollectionNaming, Condition, ContextLevel, Filter, PipelineBreaker, VectorPoint, VectorStore, Payload};
+use crate::middleware::{InputValidator, RateLimiter};
 use async_trait::async_trait;
 use chrono::Utc;
 use dashmap::DashMap;
-use std::collections::{HashMap, HashSet};
+use futures::stream::FuturesUnordered;
+use futures::StreamExt;
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::Arc;
-use std::sync::atomic::{AtomicUsize, Ordering};
+use tokio::sync::mpsc;
 use tracing::{debug, info, warn};
 use uuid::Uuid;
 
+/// Agent that operations are attributed to when none is given
+const DEFAULT_AGENT_ID: &str = "default";
+
+/// Metadata key listing the agents allowed to retrieve a context
+const ACL_KEY: &str = "acl";
+
+/// Collections created at once by `initialize` unless configured otherwise
+const DEFAULT_INIT_CONCURRENCY: usize = 4;
+
+/// Filter admitting contexts whose ACL lists `agent_id` or that have no ACL
+fn acl_filter(agent_id: &str) -> Filter {
+    Filter::new()
+        .should(Condition::Match { key: ACL_KEY.to_string(), value: agent_id.into() })
+        .should(Condition::IsEmpty { key: ACL_KEY.to_string() })
+}
+
+/// In-memory equivalent of [`acl_filter`] for L1 cache entries
+fn acl_allows(context: &Context, agent_id: &str) -> bool {
+    match context.metadata.get(ACL_KEY) {
+        None | Some(serde_json::Value::Null) => true,
+        Some(serde_json::Value::Array(agents)) => {
+            agents.is_empty() || agents.iter().any(|a| a.as_str() == Some(agent_id))
+        }
+        Some(agent) => agent.as_str() == Some(agent_id),
+    }
+}
+
+/// Access counts of retrieved contexts waiting to be persisted
+///
+/// Retrievals only record accesses; a single background flush at a time
+/// writes them, coalescing repeated accesses to the same context into one
+/// update of its `access_count` field.
+struct AccessRecorder {
+    vector_db: Arc<dyn VectorStore>,
+    /// Unpersisted accesses by context, with the collection holding it
+    pending: DashMap<Uuid, (String, u64)>,
+    flushing: AtomicBool,
+}
+
+impl AccessRecorder {
+    /// Contexts persisted at once by a flush
+    const FLUSH_CONCURRENCY: usize = 8;
+    
+    fn new(vector_db: Arc<dyn VectorStore>) -> Self {
+        Self { vector_db, pending: DashMap::new(), flushing: AtomicBool::new(false) }
+    }
+    
+    /// Count an access to `id`, starting a flush unless one is running
+    fn record(self: &Arc<Self>, id: Uuid, collection: String) {
+        self.pending.entry(id).or_insert((collection, 0)).1 += 1;
+        if !self.flushing.swap(true, Ordering::AcqRel) {
+            let recorder = self.clone();
+            tokio::spawn(async move { recorder.flush().await });
+        }
+    }
+    
+    async fn flush(&self) {
+        loop {
+            let ids: Vec<Uuid> = self.pending.iter().map(|entry| *entry.key()).collect();
+            if ids.is_empty() {
+                self.flushing.store(false, Ordering::Release);
+                // An access recorded while the flag was still set started no flush
+                if self.pending.is_empty() || self.flushing.swap(true, Ordering::AcqRel) {
+                    return;
+                }
+                continue;
+            }
+            
+            futures::stream::iter(ids)
+                .filter_map(|id| async move { self.pending.remove(&id).map(|(_, pending)| (id, pending)) })
+                .for_each_concurrent(Self::FLUSH_CONCURRENCY, |(id, (collection, accesses))| async move {
+                    if let Err(e) = self.persist(&collection, id, accesses).await {
+                        warn!("Failed to record access to context {}: {}", id, e);
+                    }
+                })
+                .await;
+        }
+    }
+    
+    /// Add `accesses` to the stored count, touching no other field
+    async fn persist(&self, collection: &str, id: Uuid, accesses: u64) -> Result<()> {
+        let Some(point) = self.vector_db.get_point(collection, id).await? else {
+            return Ok(());
+        };
+        let access_count = point.payload.access_count + accesses;
+        self.vector_db
+            .set_payload_fields(collection, id, HashMap::from([("access_count".to_string(), access_count.into())]))
+            .await
+    }
+}
+
+/// Levels searched for `request`, all of them unless it names some
+fn requested_levels(request: &ContextRequest) -> Vec<ContextLevel> {
+    if request.levels.is_empty() {
+        vec![ContextLevel::Immediate, ContextLevel::ShortTerm, ContextLevel::LongTerm]
+    } else {
+        request.levels.clone()
+    }
+}
+
+/// Send a level's candidates to a streaming caller, shaped like the contexts
+/// of a response
+async fn send_candidates(candidates: Option<&mpsc::Sender<Vec<Context>>>, contexts: &[Context], include_vectors: bool) {
+    let Some(sender) = candidates else { return };
+    if contexts.is_empty() {
+        return;
+    }
+    
+    let contexts = contexts
+        .iter()
+        .cloned()
+        .map(|mut context| {
+            strip_internal_metadata(&mut context.metadata);
+            if !include_vectors {
+                context.vector = None;
+            }
+            context
+        })
+        .collect();
+    // A receiver that went away just stops receiving candidates
+    let _ = sender.send(contexts).await;
+}
+
 /// Enhanced HiRAG manager with improved concurrency safety
 pub struct HiRAGManagerV2 {
     config: HiRAGConfig,
     embedding_client: Arc<dyn EmbeddingProvider>,
     vector_db: Arc<dyn VectorStore>,
-    l1_cache: Arc<DashMap<Uuid, Context>>,
-    l1_cache_size: Arc<AtomicUsize>,
+    /// Store serving searches; the primary unless a read replica is set
+    read_db: Arc<dyn VectorStore>,
+    l1_cache: Arc<L1Cache>,
     retriever: ContextRetriever,
     ranker: ContextRanker,
     token_estimator: TokenEstimator,
+    naming: CollectionNaming,
     metrics: Option<Arc<crate::observability::MetricsCollector>>,
+    /// Cached per-level point counts for `max_contexts_per_level`
+    level_counts: DashMap<ContextLevel, usize>,
+    /// Optional per-agent operation quota
+    agent_rate_limiter: Option<Arc<RateLimiter>>,
+    /// Model recorded on stored contexts so stale embeddings can be found
+    embedding_model: Option<String>,
+    /// Fails stores and retrievals fast while the whole pipeline is down
+    pipeline_breaker: Option<Arc<PipelineBreaker>>,
+    /// Maximum number of collections created at once by `initialize`
+    init_concurrency: usize,
+    /// Persists access counts off the retrieval path
+    accesses: Arc<AccessRecorder>,
+    /// Collections known to exist, set once by whichever store or
+    /// `initialize` call creates them first
+    ready_collections: DashMap<String, Arc<tokio::sync::OnceCell<()>>>,
+    /// Set by `initialize` when collections wait for the embedding dimension
+    /// to be detected, so stores create them
+    lazy_collections: AtomicBool,
 }
 
 impl HiRAGManagerV2 {
@@ -37,300 +188,409 @@ impl HiRAGManagerV2 {
     ) -> Result<Self> {
         info!("Initializing enhanced HiRAG manager");
         
-        let token_estimator = TokenEstimator::new(config.token_estimator);
+        let token_estimator = TokenEstimator::new(config.token_estimator.clone())
+            .with_level_estimators(&config.level_token_estimators);
         let retriever = ContextRetriever::new(
             vector_db.clone(),
-            TokenEstimator::new(config.token_estimator),
+            token_estimator.clone(),
             config.retrieval_strategy.clone(),
         );
-        let ranker = ContextRanker::new(config.ranking_weights.clone());
+        let ranker = ContextRanker::new(config.ranking_weights.clone())
+            .with_recency_half_life(config.recency_half_life_secs);
         
         Ok(Self {
+            accesses: Arc::new(AccessRecorder::new(vector_db.clone())),
             config,
             embedding_client,
+            read_db: vector_db.clone(),
             vector_db,
-            l1_cache: Arc::new(DashMap::new()),
-            l1_cache_size: Arc::new(AtomicUsize::new(0)),
+            l1_cache: Arc::new(L1Cache::new()),
             retriever,
             ranker,
             token_estimator,
+            naming: CollectionNaming::default(),
             metrics: None,
+            level_counts: DashMap::new(),
+            agent_rate_limiter: None,
+            embedding_model: None,
+            pipeline_breaker: None,
+            init_concurrency: DEFAULT_INIT_CONCURRENCY,
+            ready_collections: DashMap::new(),
+            lazy_collections: AtomicBool::new(false),
         })
     }
     
+    /// Name level collections with `naming` instead of the default `contexts_` prefix
+    ///
+    /// Use the same naming as the vector store client and GC.
+    pub fn with_collection_naming(mut self, naming: CollectionNaming) -> Self {
+        self.naming = naming;
+        self
+    }
+    
+    /// Serve searches from a read replica while writes go to the primary
+    ///
+    /// Lookups made to modify a context (updates, touches, level caps) still
+    /// use the primary so they never act on replication lag.
+    pub fn with_read_replica(mut self, replica: Arc<dyn VectorStore>) -> Self {
+        self.retriever = ContextRetriever::new(
+            replica.clone(),
+            self.token_estimator.clone(),
+            self.config.retrieval_strategy.clone(),
+        );
+        self.read_db = replica;
+        self
+    }
+    
+    /// Create at most `limit` collections at once in [`initialize`](Self::initialize)
+    pub fn with_init_concurrency(mut self, limit: usize) -> Self {
+        self.init_concurrency = limit.max(1);
+        self
+    }
+    
+    /// Tag stored contexts with the model that embedded them
+    ///
+    /// The re-embedding task uses the tag to find contexts embedded with a
+    /// previous model.
+    pub fn with_embedding_model(mut self, model: impl Into<String>) -> Self {
+        self.embedding_model = Some(model.into());
+        self
+    }
+    
     /// Set metrics collector
     pub fn with_metrics(mut self, metrics: Arc<crate::observability::MetricsCollector>) -> Self {
         self.metrics = Some(metrics);
         self
     }
     
-    /// Initialize the manager
-    pub async fn initialize(&self) -> Result<()> {
-        info!("Initializing HiRAG collections");
-        
-        // Create collections for each level
-        for level in &[ContextLevel::Immediate, ContextLevel::ShortTerm, ContextLevel::LongTerm] {
-            let collection_name = self.collection_name(*level);
-            
-            // Try to create collection (will fail if exists, which is fine)
-            let _ = self.vector_db.create_collection(&collection_name).await;
+    /// Limit stores and retrievals per agent, independently of any HTTP limits
+    ///
+    /// Stores are attributed to `StoreOptions::agent_id` or else the `agent_id`
+    /// metadata value, and retrievals to `ContextRequest::agent_id`, falling
+    /// back to `"default"`.
+    pub fn with_agent_rate_limiter(mut self, rate_limiter: Arc<RateLimiter>) -> Self {
+        self.agent_rate_limiter = Some(rate_limiter);
+        self
+    }
+    
+    /// Reject stores and retrievals while both the embedding and vector
+    /// database breakers are open
+    ///
+    /// The same breaker can be given to the health checker to report the
+    /// pipeline's state.
+    pub fn with_pipeline_breaker(mut self, breaker: Arc<PipelineBreaker>) -> Self {
+        self.pipeline_breaker = Some(breaker);
+        self
+    }
+    
+    /// Fail fast if the embedding and vector database are both unavailable
+    async fn check_pipeline(&self) -> Result<()> {
+        if let Some(breaker) = &self.pipeline_breaker {
+            if breaker.is_open().await {
+                return Err(ContextError::ServiceUnavailable(
+                    "Embedding and vector database circuits are open".to_string(),
+                ));
+            }
         }
-        
         Ok(())
     }
     
-    /// Get collection name for a context level
-    fn collection_name(&self, level: ContextLevel) -> String {
-        format!("contexts_{}", level.as_str().to_lowercase())
+    /// Consume one operation from the agent's quota, if quotas are enabled
+    async fn check_agent_quota(&self, agent_id: Option<&str>) -> Result<()> {
+        if let Some(rate_limiter) = &self.agent_rate_limiter {
+            rate_limiter.check_rate_limit(agent_id.unwrap_or(DEFAULT_AGENT_ID)).await?;
+        }
+        Ok(())
     }
     
-    /// Update L1 cache with lock-free DashMap
-    async fn update_l1_cache(&self, context: Context) {
-        let context_id = context.id;
-        
-        // Insert or update context (atomic operation)
-        self.l1_cache.insert(context_id, context);
-        
-        // Update size counter
-        let current_size = self.l1_cache.len();
-        self.l1_cache_size.store(current_size, Ordering::Relaxed);
-        
-        // Maintain size limit by removing oldest entries
-        if current_size > self.config.l1_size {
-            // Collect IDs to remove (oldest entries)
-            let mut entries: Vec<_> = self.l1_cache.iter()
-                .map(|entry| (*entry.key(), entry.value().timestamp))
-                .collect();
-            
-            // Sort by timestamp (oldest first)
-            entries.sort_by_key(|(_, ts)| *ts);
-            
-            // Remove excess entries
-            let to_remove = current_size - self.config.l1_size;
-            for (id, _) in entries.iter().take(to_remove) {
-                if let Some((_, removed)) = self.l1_cache.remove(id) {
-                    debug!("Evicted context {} from L1 cache", removed.id);
-                }
+    /// Charge one request to each of `agents`, charging none if any is over quota
+    async fn check_agent_quotas(&self, agents: &[Option<String>]) -> Result<()> {
+        for (charged, agent) in agents.iter().enumerate() {
+            if let Err(e) = self.check_agent_quota(agent.as_deref()).await {
+                self.refund_agent_quotas(&agents[..charged]).await;
+                return Err(e);
             }
-            
-            self.l1_cache_size.store(self.l1_cache.len(), Ordering::Relaxed);
         }
-        
-        debug!("L1 cache updated, size: {}", self.l1_cache.len());
+        Ok(())
     }
     
-    /// Get contexts from L1 cache with lock-free access
-    async fn get_l1_contexts(&self, max_tokens: usize) -> Vec<Context> {
-        let mut contexts = Vec::new();
-        let mut total_tokens = 0;
-        
-        // Collect all contexts and sort by timestamp (newest first)
-        let mut all_contexts: Vec<_> = self.l1_cache.iter()
-            .map(|entry| entry.value().clone())
-            .collect();
-        
-        all_contexts.sort_by(|a, b| b.timestamp.cmp(&a.timestamp));
-        
-        for context in all_contexts {
-            if total_tokens + context.token_count <= max_tokens {
-                total_tokens += context.token_count;
-                contexts.push(context);
-            } else {
-                break;
+    /// Give back requests charged by `check_agent_quota` that did no work
+    async fn refund_agent_quotas(&self, agents: &[Option<String>]) {
+        if let Some(rate_limiter) = &self.agent_rate_limiter {
+            for agent in agents {
+                rate_limiter.refund(agent.as_deref().unwrap_or(DEFAULT_AGENT_ID)).await;
             }
         }
-        
-        debug!("Retrieved {} contexts from L1 cache ({} tokens)", contexts.len(), total_tokens);
-        contexts
     }
     
-    /// Deduplicate contexts by ID
-    fn deduplicate_contexts(&self, contexts: Vec<Context>) -> Vec<Context> {
-        let mut seen_ids = HashSet::new();
-        let mut deduplicated = Vec::new();
-        let original_count = contexts.len();
-        
-        for context in contexts {
-            if seen_ids.insert(context.id) {
-                deduplicated.push(context);
+    /// Validate a retrieval request and charge it to the requesting agent's quota
+    async fn admit_request(&self, request: &ContextRequest) -> Result<()> {
+        InputValidator::validate_query(&request.query)?;
+        InputValidator::validate_token_count(request.max_tokens, 100000)?;
+        if let Some(filters) = &request.filters {
+            InputValidator::validate_filter_conditions(filters.condition_count(), self.config.max_filter_conditions)?;
+        }
+        self.check_pipeline().await?;
+        self.check_agent_quota(request.agent_id.as_deref()).await
+    }
+    
+    /// Agent whose ACL applies to `request`, when ACLs are enforced
+    fn acl_agent<'a>(&self, request: &'a ContextRequest) -> Option<&'a str> {
+        self.config.enforce_acl
+            .then(|| request.agent_id.as_deref().unwrap_or(DEFAULT_AGENT_ID))
+    }
+    
+    /// Vector store filter for `request`: caller filters, session scope and ACL
+    ///
+    /// ACLs are enforced in the filter so hidden contexts are never returned.
+    /// Contexts with a deferred embedding are left out too, since their
+    /// placeholder vector can't be scored.
+    fn retrieval_filter(&self, request: &ContextRequest) -> Filter {
+        let embedded = Filter::new().must_not(Condition::Match {
+            key: NEEDS_EMBEDDING_KEY.to_string(),
+            value: serde_json::Value::Bool(true),
+        });
+        [request_filter(request), self.acl_agent(request).map(acl_filter)]
+            .into_iter()
+            .flatten()
+            .fold(embedded, Filter::and)
+    }
+    
+    /// Token budget of each level for a request searching `levels`
+    ///
+    /// Levels that aren't searched or hold no contexts hand their share to
+    /// the others. L1 is sized from the cache; ShortTerm and LongTerm use the
+    /// counts kept for the level cap. Those only see this process's writes, so
+    /// an uncounted or zero-count level is still assumed to hold contexts.
+    fn level_allocations(&self, max_tokens: usize, levels: &[ContextLevel]) -> (usize, usize, usize) {
+        let available = |level: ContextLevel| {
+            if !levels.contains(&level) {
+                0
+            } else if level == ContextLevel::Immediate {
+                self.l1_cache.len()
             } else {
-                debug!("Removed duplicate context: {}", context.id);
+                self.level_counts.get(&level).map_or(1, |count| (*count).max(1))
             }
-        }
+        };
         
-        debug!("Deduplicated {} -> {} contexts", original_count, deduplicated.len());
-        deduplicated
+        self.retriever.calculate_dynamic_allocations(
+            max_tokens,
+            available(ContextLevel::Immediate),
+            available(ContextLevel::ShortTerm),
+            available(ContextLevel::LongTerm),
+        )
     }
-}
-
-#[async_trait]
-impl ContextManager for HiRAGManagerV2 {
-    async fn store_context(
+    
+    /// Embed one request's query and retrieve for it, sending level
+    /// candidates to `candidates` if given
+    async fn retrieve_single(
         &self,
-        text: &str,
-        level: ContextLevel,
-        metadata: HashMap<String, serde_json::Value>,
-    ) -> Result<Uuid> {
-        // Validate input
-        InputValidator::validate_text(text)?;
-        
-        // Validate metadata keys
-        for key in metadata.keys() {
-            InputValidator::validate_metadata_key(key)?;
-        }
-        
-        debug!("Storing context at level: {:?}", level);
-        
-        // Generate embedding
-        let embedding = self.embedding_client.embed_single(text).await?;
+        request: ContextRequest,
+        candidates: Option<&mpsc::Sender<Vec<Context>>>,
+    ) -> Result<ContextResponse> {
+        let start_time = std::time::Instant::now();
         
-        // Validate vector dimension
-        InputValidator::validate_vector_dimension(
-            embedding.len(),
-            1024, // Expected dimension for multilingual-e5-large
-        )?;
+        self.admit_request(&request).await?;
         
-        // Create point
-        let id = Uuid::new_v4();
-        let timestamp = Utc::now().timestamp();
-        let token_count = self.token_estimator.estimate(text);
+        debug!("Retrieving context for query: {}", request.query);
         
-        let point = VectorPoint {
-            id,
-            vector: embedding,
-            payload: Payload {
-                text: text.to_string(),
-                level,
-                timestamp,
-                agent_id: "default".to_string(),
-                session_id: None,
-                metadata: metadata.clone(),
-            },
+        // Generate query embedding, giving the quota back if that fails
+        let query_embedding = match self.embedding_client.embed_query(&request.query).await {
+            Ok(embedding) => embedding,
+            Err(e) => {
+                self.refund_agent_quotas(std::slice::from_ref(&request.agent_id)).await;
+                return Err(e);
+            }
         };
         
-        // Store in vector database
-        let collection = self.collection_name(level);
-        self.vector_db.insert_points(&collection, vec![point]).await?;
-        
-        // Update L1 cache if immediate context
-        if level == ContextLevel::Immediate {
-            let context = Context {
-                id,
-                text: text.to_string(),
-                level,
-                relevance_score: 1.0,
-                token_count,
-                timestamp,
-                metadata,
-            };
-            self.update_l1_cache(context).await;
-        }
-        
-        info!("Context stored with id: {}", id);
-        
-        // Record metrics
-        if let Some(metrics) = &self.metrics {
-            metrics.record_request(std::time::Instant::now().elapsed());
-        }
-        
-        Ok(id)
+        self.retrieve_with_embedding(request, query_embedding, start_time, candidates).await
     }
     
-    async fn retrieve_context(&self, request: ContextRequest) -> Result<ContextResponse> {
-        let start_time = std::time::Instant::now();
-        
-        // Validate input
-        InputValidator::validate_text(&request.query)?;
-        InputValidator::validate_token_count(request.max_tokens, 100000)?;
-        
-        debug!("Retrieving context for query: {}", request.query);
-        
-        // Generate query embedding
-        let query_embedding = self.embedding_client.embed_single(&request.query).await?;
+    /// Retrieve, rank and budget contexts for an already-embedded query
+    ///
+    /// Each level's candidates are sent to `candidates`, if given, as soon as
+    /// that level has been searched.
+    async fn retrieve_with_embedding(
+        &self,
+        request: ContextRequest,
+        query_embedding: Vec<f32>,
+        start_time: std::time::Instant,
+        candidates: Option<&mpsc::Sender<Vec<Context>>>,
+    ) -> Result<ContextResponse> {
+        // Rejected up front, as every level would fail on it alike
+        if similarity::norm(&query_embedding) == 0.0 {
+            return Err(HiRAGError::RetrievalError(
+                "Query embedding is a zero vector and cannot be compared".to_string()
+            ).into());
+        }
         
-        // Determine which levels to search
-        let levels = if request.levels.is_empty() {
-            vec![ContextLevel::Immediate, ContextLevel::ShortTerm, ContextLevel::LongTerm]
-        } else {
-            request.levels.clone()
-        };
+        let levels = requested_levels(&request);
         
         // Calculate token allocations
-        let (l1_tokens, l2_tokens, l3_tokens) = self.retriever.calculate_allocations(request.max_tokens);
+        let (l1_tokens, l2_tokens, l3_tokens) = self.level_allocations(request.max_tokens, &levels);
+        
+        let acl_agent = self.acl_agent(&request);
+        let filters = Some(self.retrieval_filter(&request));
+        // MMR compares candidates by their vectors
+        let fetch_vectors = request.include_vectors || self.config.mmr_lambda.is_some();
         
         let mut all_contexts = Vec::new();
         let mut cache_hits = 0;
         let mut total_searched = 0;
+        let mut omitted_count = 0;
+        let mut failed_levels = Vec::new();
+        
+        let fast_path = self.is_fast_path(&levels);
         
         // Retrieve from each level with partial failure handling
         let mut tasks = Vec::new();
-        
-        for level in levels {
-            let max_tokens = match level {
-                ContextLevel::Immediate => l1_tokens,
-                ContextLevel::ShortTerm => l2_tokens,
-                ContextLevel::LongTerm => l3_tokens,
-            };
-            
-            if level == ContextLevel::Immediate {
-                // Use L1 cache (synchronous)
-                cache_hits += 1;
-                let contexts = self.get_l1_contexts(max_tokens).await;
-                total_searched += contexts.len();
-                all_contexts.extend(contexts);
-            } else {
-                // Search vector database in parallel
-                let collection = self.collection_name(level);
-                let retriever = self.retriever.clone();
-                let embedding = query_embedding.clone();
-                let filters = request.filters.clone();
+        if fast_path {
+            let level = levels[0];
+            let max_tokens = if level == ContextLevel::ShortTerm { l2_tokens } else { l3_tokens };
+            match self.retriever.retrieve_from_level(
+                &self.collection_name(level),
+                query_embedding,
+                max_tokens,
+                filters,
+                request.rescore_metric,
+                fetch_vectors,
+            ).await {
+                Ok((contexts, omitted)) => {
+                    total_searched += contexts.len();
+                    omitted_count += omitted;
+                    send_candidates(candidates, &contexts, request.include_vectors).await;
+                    all_contexts.extend(contexts);
+                }
+                Err(e) => {
+                    warn!("Error retrieving contexts from {:?}: {}", level, e);
+                    failed_levels.push(level);
+                }
+            }
+        } else {
+            for level in levels {
+                let max_tokens = match level {
+                    ContextLevel::Immediate => l1_tokens,
+                    ContextLevel::ShortTerm => l2_tokens,
+                    ContextLevel::LongTerm => l3_tokens,
+                };
+                
+                if level == ContextLevel::Immediate {
+                    // Use L1 cache (synchronous)
+                    cache_hits += 1;
+                    let (contexts, omitted) = self.get_l1_contexts(max_tokens, acl_agent, request.session_id.as_deref(), fetch_vectors).await;
+                    total_searched += contexts.len();
+                    omitted_count += omitted;
+                    send_candidates(candidates, &contexts, request.include_vectors).await;
+                    all_contexts.extend(contexts);
+                } else {
+                    // Search vector database in parallel
+                    let collection = self.collection_name(level);
+                    let retriever = self.retriever.clone();
+                    let embedding = query_embedding.clone();
+                    let filters = filters.clone();
+                    let rescore_metric = request.rescore_metric;
+                    let include_vectors = fetch_vectors;
                 
-                tasks.push(tokio::spawn(async move {
-                    retriever.retrieve_from_level(
-                        &collection,
-                        embedding,
-                        max_tokens,
-                        filters,
-                    ).await
-                }));
+                    tasks.push((level, tokio::spawn(async move {
+                        retriever.retrieve_from_level(
+                            &collection,
+                            embedding,
+                            max_tokens,
+                            filters,
+                            rescore_metric,
+                            include_vectors,
+                        ).await
+                    })));
+                }
             }
         }
         
-        // Wait for all parallel tasks with partial failure handling
-        for task in tasks {
-            match task.await {
-                Ok(Ok(contexts)) => {
+        // Wait for the parallel tasks as they finish, with partial failure
+        // handling, so a level's candidates are sent without waiting for
+        // slower levels
+        let mut pending: FuturesUnordered<_> = tasks
+            .into_iter()
+            .enumerate()
+            .map(|(i, (level, task))| async move { (i, level, task.await) })
+            .collect();
+        let mut outcomes = Vec::new();
+        while let Some((i, level, joined)) = pending.next().await {
+            match joined {
+                Ok(Ok((contexts, omitted))) => {
                     total_searched += contexts.len();
-                    all_contexts.extend(contexts);
+                    omitted_count += omitted;
+                    send_candidates(candidates, &contexts, request.include_vectors).await;
+                    outcomes.push((i, Ok(contexts)));
                 }
                 Ok(Err(e)) => {
-                    warn!("Error retrieving contexts from one level: {}", e);
+                    warn!("Error retrieving contexts from {:?}: {}", level, e);
                     // Continue with other levels instead of failing completely
+                    outcomes.push((i, Err(level)));
                 }
                 Err(e) => {
-                    warn!("Task join error: {}", e);
+                    warn!("Task join error for {:?}: {}", level, e);
                     // Continue with other levels
+                    outcomes.push((i, Err(level)));
                 }
             }
         }
+        // Back in level order, so ties resolve the same however searches finish
+        outcomes.sort_by_key(|(i, _)| *i);
+        for (_, outcome) in outcomes {
+            match outcome {
+                Ok(contexts) => all_contexts.extend(contexts),
+                Err(level) => failed_levels.push(level),
+            }
+        }
         
         // Deduplicate contexts
-        all_contexts = self.deduplicate_contexts(all_contexts);
+        if !fast_path {
+            all_contexts = self.deduplicate_contexts(all_contexts);
+        }
         
         // Rank contexts
-        let ranked_contexts = self.ranker.rank_contexts(all_contexts);
+        let mut ranked_contexts = self.ranker.rank_contexts(all_contexts);
+        if let Some(lambda) = self.config.mmr_lambda {
+            ranked_contexts = self.ranker.apply_mmr(ranked_contexts, lambda, request.max_tokens);
+        }
         
         // Apply token limit
         let mut final_contexts = Vec::new();
         let mut total_tokens = 0;
         
-        for context in ranked_contexts {
+        for mut context in ranked_contexts {
+            if !request.include_vectors {
+                context.vector = None;
+            }
             if total_tokens + context.token_count <= request.max_tokens {
                 total_tokens += context.token_count;
                 final_contexts.push(context);
+            } else {
+                omitted_count += 1;
             }
         }
         
+        request.sort_order.apply(&mut final_contexts);
+        self.record_accesses(&mut final_contexts);
+        
+        // Scores against vectors from another model are meaningless
+        let model_mismatches = self.embedding_model.as_ref().map_or(0, |model| {
+            final_contexts
+                .iter()
+                .filter(|c| c.metadata.get(EMBEDDING_MODEL_KEY).and_then(|m| m.as_str()).is_some_and(|m| m != model))
+                .count()
+        });
+        if model_mismatches > 0 {
+            warn!(
+                "{} retrieved contexts were embedded with a different model than {}; reindex to compare them reliably",
+                model_mismatches,
+                self.embedding_model.as_deref().unwrap_or_default()
+            );
+        }
+        for context in &mut final_contexts {
+            strip_internal_metadata(&mut context.metadata);
+        }
+        
         // Calculate metadata
         let mut level_distribution = HashMap::new();
         for context in &final_contexts {
@@ -370,95 +630,2251 @@ impl ContextManager for HiRAGManagerV2 {
                 avg_relevance,
                 cache_hits,
                 total_searched,
+                clamped_max_tokens: None,
+                truncated: omitted_count > 0,
+                omitted_count,
+                failed_levels,
+                model_mismatches,
             },
         })
     }
     
-    async fn update_context(
+    /// Embed and insert a validated batch owned by `agents`, removing the
+    /// points already inserted if a later level fails
+    async fn insert_batch(
         &self,
-        id: Uuid,
-        metadata: HashMap<String, serde_json::Value>,
-    ) -> Result<()> {
-        // Validate metadata keys
-        for key in metadata.keys() {
-            InputValidator::validate_metadata_key(key)?;
+        items: Vec<(String, ContextLevel, HashMap<String, serde_json::Value>)>,
+        agents: &[Option<String>],
+        session_id: Option<String>,
+    ) -> Result<Vec<Uuid>> {
+        // Embed every text in a single provider call, falling back to placeholders if configured
+        let texts: Vec<String> = items.iter().map(|(text, _, _)| text.clone()).collect();
+        let (embeddings, deferred) = match self.embedding_client.embed_batch(&texts).await {
+            Ok(embeddings) => (embeddings, false),
+            Err(ContextError::Embedding(e)) if self.config.defer_embedding_on_failure => {
+                warn!("Embedding unavailable, deferring embedding of {} new contexts: {}", texts.len(), e);
+                let placeholder = vec![0.0; self.embedding_client.embedding_dimension()];
+                (vec![placeholder; texts.len()], true)
+            }
+            Err(e) => return Err(e),
+        };
+        if embeddings.len() != items.len() {
+            return Err(HiRAGError::StorageError(format!(
+                "Expected {} embeddings, got {}",
+                items.len(),
+                embeddings.len()
+            )).into());
         }
         
-        debug!("Updating context: {}", id);
+        // Group points by level, in input order, so each collection gets a single insert
+        let mut ids = Vec::with_capacity(items.len());
+        let mut by_level: Vec<(ContextLevel, Vec<VectorPoint>)> = Vec::new();
+        let mut cached = Vec::new();
+        for (((text, level, mut metadata), embedding), agent) in items.into_iter().zip(embeddings).zip(agents) {
+            if deferred {
+                metadata.insert(NEEDS_EMBEDDING_KEY.to_string(), serde_json::Value::Bool(true));
+            }
+            let (point, context) = self.new_point(&text, level, metadata, embedding, deferred, StoreOptions {
+                agent_id: agent.clone(),
+                session_id: session_id.clone(),
+            })?;
+            ids.push(point.id);
+            match by_level.iter_mut().find(|(l, _)| *l == level) {
+                Some((_, points)) => points.push(point),
+                None => by_level.push((level, vec![point])),
+            }
+            cached.extend(context);
+        }
         
-        // Try to find and update the context in all collections
-        for level in &[ContextLevel::Immediate, ContextLevel::ShortTerm, ContextLevel::LongTerm] {
-            let collection = self.collection_name(*level);
-            
-            // Try to get the existing point
-            if let Some(mut point) = self.vector_db.get_point(&collection, id).await? {
-                // Update metadata
-                for (key, value) in metadata.iter() {
-                    point.payload.metadata.insert(key.clone(), value.clone());
-                }
-                
-                // Update timestamp
-                point.payload.timestamp = chrono::Utc::now().timestamp();
-                
-                // Re-insert the updated point
-                self.vector_db.insert_points(&collection, vec![point.clone()]).await?;
-                
-                // Update L1 cache if immediate level
-                if *level == ContextLevel::Immediate {
-                    let token_count = self.token_estimator.estimate(&point.payload.text);
-                    let context = Context {
-                        id: point.id,
-                        text: point.payload.text,
-                        level: point.payload.level,
-                        relevance_score: 1.0,
-                        token_count,
-                        timestamp: point.payload.timestamp,
-                        metadata: point.payload.metadata,
-                    };
-                    self.update_l1_cache(context).await;
+        let mut inserted: Vec<(ContextLevel, String, Vec<Uuid>)> = Vec::new();
+        for (level, points) in by_level {
+            let collection = self.collection_name(level);
+            let level_ids = points.iter().map(|p| p.id).collect();
+            if let Err(e) = self.insert_prepared(&collection, points).await {
+                for (_, collection, ids) in inserted {
+                    if let Err(e) = self.vector_db.delete_points(&collection, ids).await {
+                        warn!("Failed to remove partially stored batch from {}: {}", collection, e);
+                    }
                 }
-                
-                info!("Updated context {} in collection {}", id, collection);
-                return Ok(());
+                return Err(e);
             }
+            inserted.push((level, collection, level_ids));
+        }
+        
+        for (level, collection, level_ids) in &inserted {
+            self.enforce_level_cap(*level, collection, level_ids.len()).await;
+        }
+        for context in cached {
+            self.update_l1_cache(context).await;
         }
         
-        Err(HiRAGError::StorageError(format!("Context {} not found", id)).into())
+        Ok(ids)
     }
     
-    async fn delete_context(&self, id: Uuid) -> Result<()> {
-        debug!("Deleting context: {}", id);
+    /// Count an access to each returned context
+    ///
+    /// Returned and L1 counts include this access right away; the stored
+    /// counts are updated in the background and failures are only logged.
+    fn record_accesses(&self, contexts: &mut [Context]) {
+        for context in contexts {
+            context.access_count += 1;
+            self.l1_cache.set_access_count(&context.id, context.access_count);
+            self.accesses.record(context.id, self.collection_name(context.level));
+        }
+    }
+    
+    /// Tombstone a context in every level it is stored in
+    ///
+    /// The context stays in the vector store, hidden from retrieval, until GC
+    /// removes it after the grace period or it is restored.
+    async fn soft_delete_context(&self, id: Uuid) -> Result<()> {
+        let deleted_at = serde_json::Value::from(Utc::now().timestamp());
         
-        // Try to delete from all collections
         for level in &[ContextLevel::Immediate, ContextLevel::ShortTerm, ContextLevel::LongTerm] {
             let collection = self.collection_name(*level);
-            let _ = self.vector_db.delete_points(&collection, vec![id]).await;
+            
+            // Only the tombstone is written, so concurrent updates aren't lost
+            if find_point(self.vector_db.as_ref(), &collection, id).await?.is_some() {
+                let tombstone = HashMap::from([(DELETED_AT_KEY.to_string(), deleted_at.clone())]);
+                self.vector_db.set_payload_fields(&collection, id, tombstone).await?;
+            }
         }
         
-        // Remove from L1 cache (lock-free)
         self.l1_cache.remove(&id);
-        self.l1_cache_size.store(self.l1_cache.len(), Ordering::Relaxed);
         
-        info!("Context deleted: {}", id);
+        info!("Context soft-deleted: {}", id);
         Ok(())
     }
     
-    async fn clear_level(&self, level: ContextLevel) -> Result<()> {
-        debug!("Clearing level: {:?}", level);
+    /// Initialize the manager
+    pub async fn initialize(&self) -> Result<()> {
+        // Collections need the real vector size, so they wait for the first store
+        if !self.embedding_client.dimension_known() {
+            info!("Creating HiRAG collections on first store, once the embedding dimension is detected");
+            self.lazy_collections.store(true, Ordering::Relaxed);
+            return Ok(());
+        }
         
-        let collection = self.collection_name(level);
+        info!("Initializing HiRAG collections");
         
-        // Delete and recreate collection
-        let _ = self.vector_db.delete_collection(&collection).await;
-        self.vector_db.create_collection(&collection).await?;
+        // Create collections for each level concurrently
+        let levels = [ContextLevel::Immediate, ContextLevel::ShortTerm, ContextLevel::LongTerm];
+        futures::stream::iter(levels)
+            .map(|level| async move {
+                let collection_name = self.collection_name(level);
+                
+                match self.prepare_collection(&collection_name).await {
+                    Ok(()) => {
+                        let _ = self.collection_ready(&collection_name).set(());
+                    }
+                    Err(e) => debug!("Not creating collection {}: {}", collection_name, e),
+                }
+            })
+            .buffer_unordered(self.init_concurrency)
+            .collect::<()>()
+            .await;
         
-        // Clear L1 cache if immediate level
-        if level == ContextLevel::Immediate {
-            self.l1_cache.clear();
-            self.l1_cache_size.store(0, Ordering::Relaxed);
+        Ok(())
+    }
+    
+    /// Number of contexts currently held in the L1 cache
+    pub fn l1_cache_len(&self) -> usize {
+        self.l1_cache.len()
+    }
+    
+    /// Get collection name for a context level
+    fn collection_name(&self, level: ContextLevel) -> String {
+        self.naming.collection(level)
+    }
+    
+    fn collection_ready(&self, collection: &str) -> Arc<tokio::sync::OnceCell<()>> {
+        self.ready_collections.entry(collection.to_string()).or_default().clone()
+    }
+    
+    /// Create `collection` before the first store to it when
+    /// `create_collections_on_store` is set or `initialize` left creation to
+    /// the first store
+    ///
+    /// Concurrent first stores wait for a single create; one that finds the
+    /// collection already exists counts as created.
+    async fn ensure_collection(&self, collection: &str) -> Result<()> {
+        if !self.config.create_collections_on_store && !self.lazy_collections.load(Ordering::Relaxed) {
+            return Ok(());
         }
         
-        info!("Level cleared: {:?}", level);
+        self.collection_ready(collection)
+            .get_or_try_init(|| self.prepare_collection(collection))
+            .await?;
         Ok(())
     }
-}
\ No newline at end of file
+    
+    /// Insert `points` into `collection`, creating it first if configured
+    ///
+    /// A collection found missing was deleted behind our back, so the next
+    /// store prepares it again.
+    async fn insert_prepared(&self, collection: &str, points: Vec<VectorPoint>) -> Result<()> {
+        self.ensure_collection(collection).await?;
+        let result = self.vector_db.insert_points(collection, points).await;
+        if let Err(ContextError::VectorDb(VectorDbError::CollectionNotFound(_))) = &result {
+            self.ready_collections.remove(collection);
+        }
+        result
+    }
+    
+    /// Create `collection` unless it exists, then make sure it has its indexes
+    ///
+    /// Collections from older versions, or whose index creation failed, get
+    /// their missing indexes here.
+    async fn prepare_collection(&self, collection: &str) -> Result<()> {
+        let vector_size = self.embedding_client.embedding_dimension();
+        match self.vector_db.create_sized_collection(collection, vector_size).await {
+            Ok(()) => info!("Created collection {}", collection),
+            Err(ContextError::VectorDb(VectorDbError::CollectionExists(_))) => {}
+            Err(e) => return Err(e),
+        }
+        self.vector_db.ensure_indexes(collection).await
+    }
+    
+    /// Update L1 cache, evicting the oldest entries beyond the configured size
+    async fn update_l1_cache(&self, context: Context) {
+        for id in self.l1_cache.insert(context, self.config.l1_size) {
+            debug!("Evicted context {} from L1 cache", id);
+        }
+        
+        debug!("L1 cache updated, size: {}", self.l1_cache.len());
+    }
+    
+    /// Evict the oldest contexts of a level beyond `max_contexts_per_level`
+    ///
+    /// Called after successfully inserting `added` points. The level's count is
+    /// fetched once and then maintained locally; failures only drop the cached
+    /// count.
+    async fn enforce_level_cap(&self, level: ContextLevel, collection: &str, added: usize) {
+        let Some(max) = self.config.max_contexts_per_level else {
+            return;
+        };
+        
+        // The excess is taken off the count under its lock, so concurrent
+        // stores neither lose each other's additions nor evict it twice
+        let claim = |count: &mut usize| {
+            let excess = count.saturating_sub(max);
+            *count -= excess;
+            excess
+        };
+        let cached = self.level_counts.get_mut(&level).map(|mut count| {
+            *count += added;
+            claim(&mut count)
+        });
+        let excess = match cached {
+            Some(excess) => excess,
+            None => match self.vector_db.count(collection).await {
+                Ok(mut count) => {
+                    let excess = claim(&mut count);
+                    self.level_counts.entry(level).or_insert(count);
+                    excess
+                }
+                Err(e) => {
+                    warn!("Failed to count contexts in {}: {}", collection, e);
+                    return;
+                }
+            },
+        };
+        
+        if excess == 0 {
+            return;
+        }
+        
+        let evict = async {
+            let ids = self.vector_db.oldest_points(collection, excess).await?;
+            self.vector_db.delete_points(collection, ids.clone()).await?;
+            Ok::<_, crate::error::ContextError>(ids)
+        };
+        
+        match evict.await {
+            Ok(ids) => {
+                for id in &ids {
+                    self.l1_cache.remove(id);
+                }
+                debug!("Evicted {} contexts from {} (cap: {})", ids.len(), collection, max);
+            }
+            Err(e) => {
+                warn!("Failed to evict contexts from {}: {}", collection, e);
+                self.level_counts.remove(&level);
+            }
+        }
+    }
+    
+    /// Whether retrieving `levels` takes the fast path: a lone vector-db level
+    /// needs no task or cross-level deduplication
+    fn is_fast_path(&self, levels: &[ContextLevel]) -> bool {
+        match levels {
+            [level] => self.config.single_level_fast_path && *level != ContextLevel::Immediate,
+            _ => false,
+        }
+    }
+    
+    /// Convert a stored point to a context with an estimated token count,
+    /// leaving out its vector
+    fn context_from_point(&self, point: VectorPoint) -> Context {
+        let token_count = self.token_estimator.estimate_payload(&point.payload);
+        let mut context = Context::new(
+            point.id,
+            point.payload.text,
+            point.payload.level,
+            point.payload.timestamp,
+            token_count,
+        );
+        context.access_count = point.payload.access_count;
+        context.session_id = point.payload.session_id;
+        context.metadata = point.payload.metadata;
+        context
+    }
+    
+    /// Build the point for a new context, plus its L1 cache entry when it is
+    /// an Immediate context
+    ///
+    /// `owner.agent_id` must already include any metadata fallback.
+    fn new_point(
+        &self,
+        text: &str,
+        level: ContextLevel,
+        mut metadata: HashMap<String, serde_json::Value>,
+        embedding: Vec<f32>,
+        deferred: bool,
+        owner: StoreOptions,
+    ) -> Result<(VectorPoint, Option<Context>)> {
+        if let (Some(model), false) = (&self.embedding_model, deferred) {
+            metadata.insert(EMBEDDING_MODEL_KEY.to_string(), serde_json::Value::String(model.clone()));
+        }
+        
+        // Validate vector dimension
+        InputValidator::validate_vector_dimension(
+            embedding.len(),
+            self.embedding_client.embedding_dimension(),
+        )?;
+        
+        let id = Uuid::new_v4();
+        let timestamp = Utc::now().timestamp();
+        let token_count = self.token_estimator.estimate_for_level(level, text);
+        
+        // L1 entries keep their vector so retrieval can return it on request
+        let cached = (level == ContextLevel::Immediate).then(|| Context {
+            id,
+            text: text.to_string(),
+            level,
+            relevance_score: 1.0,
+            token_count,
+            timestamp,
+            access_count: 0,
+            session_id: owner.session_id.clone(),
+            metadata: metadata.clone(),
+            vector: (!deferred).then(|| embedding.clone()),
+        });
+        
+        let point = VectorPoint {
+            id,
+            vector: embedding,
+            payload: Payload {
+                text: text.to_string(),
+                level,
+                timestamp,
+                agent_id: owner.agent_id.unwrap_or_else(|| DEFAULT_AGENT_ID.to_string()),
+                session_id: owner.session_id,
+                access_count: 0,
+                token_count: Some(token_count),
+                metadata,
+            },
+        };
+        
+        Ok((point, cached))
+    }
+    
+    /// Get contexts from L1 cache with lock-free access
+    ///
+    /// Returns the contexts that fit `max_tokens` and how many were left out.
+    /// With `acl_agent` set, contexts whose ACL excludes that agent are skipped,
+    /// and with `session_id` set, contexts from other sessions are skipped.
+    /// Cached vectors are only kept when `include_vectors` is set.
+    async fn get_l1_contexts(
+        &self,
+        max_tokens: usize,
+        acl_agent: Option<&str>,
+        session_id: Option<&str>,
+        include_vectors: bool,
+    ) -> (Vec<Context>, usize) {
+        let mut contexts = Vec::new();
+        let mut total_tokens = 0;
+        
+        // The cache index is already ordered by timestamp (newest first)
+        let mut cached = self.l1_cache.newest_first();
+        if let Some(agent_id) = acl_agent {
+            cached.retain(|c| acl_allows(c, agent_id));
+        }
+        if let Some(session_id) = session_id {
+            cached.retain(|c| c.session_id.as_deref() == Some(session_id));
+        }
+        let now = Utc::now().timestamp() as f64;
+        cached.retain(|c| !is_expired(&c.metadata, now));
+        let available = cached.len();
+        for mut context in cached {
+            if total_tokens + context.token_count <= max_tokens {
+                if !include_vectors {
+                    context.vector = None;
+                }
+                total_tokens += context.token_count;
+                contexts.push(context);
+            } else {
+                break;
+            }
+        }
+        
+        debug!("Retrieved {} contexts from L1 cache ({} tokens)", contexts.len(), total_tokens);
+        let omitted = available - contexts.len();
+        (contexts, omitted)
+    }
+    
+    /// Deduplicate contexts by ID, resolving each duplicate by the configured policy
+    fn deduplicate_contexts(&self, contexts: Vec<Context>) -> Vec<Context> {
+        let original_count = contexts.len();
+        let mut order = Vec::new();
+        let mut kept: HashMap<Uuid, Context> = HashMap::new();
+        
+        for context in contexts {
+            match kept.get_mut(&context.id) {
+                Some(existing) => {
+                    debug!("Resolving duplicate context: {}", context.id);
+                    if self.prefers(&context, existing) {
+                        *existing = context;
+                    }
+                }
+                None => {
+                    order.push(context.id);
+                    kept.insert(context.id, context);
+                }
+            }
+        }
+        
+        let deduplicated: Vec<Context> = order.into_iter().filter_map(|id| kept.remove(&id)).collect();
+        debug!("Deduplicated {} -> {} contexts", original_count, deduplicated.len());
+        deduplicated
+    }
+    
+    /// Whether `candidate` should replace `existing` under the duplicate policy
+    fn prefers(&self, candidate: &Context, existing: &Context) -> bool {
+        let durability = |level: ContextLevel| match level {
+            ContextLevel::Immediate => 0,
+            ContextLevel::ShortTerm => 1,
+            ContextLevel::LongTerm => 2,
+        };
+        
+        match self.config.duplicate_policy {
+            DuplicatePolicy::HighestLevel => durability(candidate.level) > durability(existing.level),
+            DuplicatePolicy::HighestRelevance => candidate.relevance_score > existing.relevance_score,
+            DuplicatePolicy::Newest => candidate.timestamp > existing.timestamp,
+        }
+    }
+}
+
+#[async_trait]
+impl ContextManager for HiRAGManagerV2 {
+    async fn store_context_with(
+        &self,
+        text: &str,
+        level: ContextLevel,
+        mut metadata: HashMap<String, serde_json::Value>,
+        options: StoreOptions,
+    ) -> Result<Uuid> {
+        let start_time = std::time::Instant::now();
+        
+        // Validate input
+        InputValidator::validate_text(text)?;
+        
+        // Validate metadata keys
+        for key in metadata.keys() {
+            InputValidator::validate_metadata_key(key)?;
+        }
+        InputValidator::validate_expires_at(&metadata)?;
+        
+        self.check_pipeline().await?;
+        let agent = options.agent_id
+            .or_else(|| metadata.get("agent_id").and_then(|v| v.as_str()).map(str::to_string));
+        self.check_agent_quota(agent.as_deref()).await?;
+        
+        debug!("Storing context at level: {:?}", level);
+        
+        let collection = self.collection_name(level);
+        let stored = async {
+            // Generate embedding, falling back to a placeholder if configured
+            let (embedding, deferred) = match self.embedding_client.embed_single(text).await {
+                Ok(embedding) => (embedding, false),
+                Err(ContextError::Embedding(e)) if self.config.defer_embedding_on_failure => {
+                    warn!("Embedding unavailable, deferring embedding of new context: {}", e);
+                    metadata.insert(NEEDS_EMBEDDING_KEY.to_string(), serde_json::Value::Bool(true));
+                    (vec![0.0; self.embedding_client.embedding_dimension()], true)
+                }
+                Err(e) => return Err(e),
+            };
+            let (point, cached) = self.new_point(text, level, metadata, embedding, deferred, StoreOptions {
+                agent_id: agent.clone(),
+                session_id: options.session_id,
+            })?;
+            let id = point.id;
+            
+            // Store in vector database
+            self.insert_prepared(&collection, vec![point]).await?;
+            Ok((id, cached))
+        };
+        let (id, cached) = match stored.await {
+            Ok(stored) => stored,
+            Err(e) => {
+                // Nothing was stored, so nothing is charged
+                self.refund_agent_quotas(std::slice::from_ref(&agent)).await;
+                return Err(e);
+            }
+        };
+        
+        // Update L1 cache if immediate context
+        if let Some(context) = cached {
+            self.update_l1_cache(context).await;
+        }
+        
+        self.enforce_level_cap(level, &collection, 1).await;
+        
+        info!("Context stored with id: {}", id);
+        
+        // Record metrics
+        if let Some(metrics) = &self.metrics {
+            metrics.record_request(start_time.elapsed());
+            metrics.record_context_stored(
+                agent.as_deref().unwrap_or(DEFAULT_AGENT_ID),
+            );
+        }
+        
+        Ok(id)
+    }
+    
+    async fn store_batch_with(
+        &self,
+        items: Vec<(String, ContextLevel, HashMap<String, serde_json::Value>)>,
+        options: StoreOptions,
+    ) -> Result<Vec<Uuid>> {
+        if items.is_empty() {
+            return Ok(Vec::new());
+        }
+        
+        // Validate every item before doing any work
+        for (text, _, metadata) in &items {
+            InputValidator::validate_text(text)?;
+            for key in metadata.keys() {
+                InputValidator::validate_metadata_key(key)?;
+            }
+            InputValidator::validate_expires_at(metadata)?;
+        }
+        
+        self.check_pipeline().await?;
+        let agents: Vec<Option<String>> = items
+            .iter()
+            .map(|(_, _, metadata)| {
+                options.agent_id.clone()
+                    .or_else(|| metadata.get("agent_id").and_then(|v| v.as_str()).map(str::to_string))
+            })
+            .collect();
+        self.check_agent_quotas(&agents).await?;
+        
+        debug!("Storing batch of {} contexts", items.len());
+        
+        match self.insert_batch(items, &agents, options.session_id).await {
+            Ok(ids) => {
+                info!("Stored batch of {} contexts", ids.len());
+                if let Some(metrics) = &self.metrics {
+                    for agent in &agents {
+                        metrics.record_context_stored(agent.as_deref().unwrap_or(DEFAULT_AGENT_ID));
+                    }
+                }
+                Ok(ids)
+            }
+            Err(e) => {
+                // Nothing was stored, so nothing is charged
+                self.refund_agent_quotas(&agents).await;
+                Err(e)
+            }
+        }
+    }
+    
+    async fn retrieve_context(&self, request: ContextRequest) -> Result<ContextResponse> {
+        self.retrieve_single(request, None).await
+    }
+    
+    async fn retrieve_context_streaming(
+        &self,
+        request: ContextRequest,
+        candidates: mpsc::Sender<Vec<Context>>,
+    ) -> Result<ContextResponse> {
+        self.retrieve_single(request, Some(&candidates)).await
+    }
+    
+    async fn retrieve_batch(&self, requests: Vec<ContextRequest>) -> Result<Vec<ContextResponse>> {
+        if requests.is_empty() {
+            return Ok(Vec::new());
+        }
+        
+        let start_time = std::time::Instant::now();
+        
+        InputValidator::validate_batch_size(requests.len())?;
+        // A batch that fails before searching charges no quota, like a single request
+        let agents: Vec<Option<String>> = requests.iter().map(|r| r.agent_id.clone()).collect();
+        for (admitted, request) in requests.iter().enumerate() {
+            if let Err(e) = self.admit_request(request).await {
+                self.refund_agent_quotas(&agents[..admitted]).await;
+                return Err(e);
+            }
+        }
+        
+        debug!("Retrieving context for {} queries", requests.len());
+        
+        // Embed every query in a single provider call
+        let queries: Vec<String> = requests.iter().map(|r| r.query.clone()).collect();
+        let embeddings = match self.embedding_client.embed_query_batch(&queries).await {
+            Ok(embeddings) => embeddings,
+            Err(e) => {
+                self.refund_agent_quotas(&agents).await;
+                return Err(e);
+            }
+        };
+        if embeddings.len() != requests.len() {
+            self.refund_agent_quotas(&agents).await;
+            return Err(HiRAGError::RetrievalError(format!(
+                "Expected {} query embeddings, got {}",
+                requests.len(),
+                embeddings.len()
+            )).into());
+        }
+        
+        futures::future::try_join_all(
+            requests
+                .into_iter()
+                .zip(embeddings)
+                .map(|(request, embedding)| self.retrieve_with_embedding(request, embedding, start_time, None)),
+        ).await
+    }
+    
+    async fn recent(&self, level: ContextLevel, limit: usize, agent_id: Option<&str>) -> Result<Vec<Context>> {
+        let collection = self.collection_name(level);
+        // Filtered in the store, so hidden contexts don't use up the limit
+        let access = self.config.enforce_acl.then(|| acl_filter(agent_id.unwrap_or(DEFAULT_AGENT_ID)));
+        let live = live_filter(Utc::now().timestamp() as f64);
+        let filter = match access {
+            Some(access) => access.and(live),
+            None => live,
+        };
+        let points = self.read_db.newest_points(&collection, Some(filter), limit).await?;
+        
+        Ok(points
+            .into_iter()
+            .map(|point| self.context_from_point(point))
+            .map(|mut context| {
+                strip_internal_metadata(&mut context.metadata);
+                context
+            })
+            .collect())
+    }
+    
+    async fn get_context(&self, id: Uuid, agent_id: Option<&str>) -> Result<Option<Context>> {
+        // Hide what retrieval would hide from the same agent
+        let acl_agent = self.config.enforce_acl.then(|| agent_id.unwrap_or(DEFAULT_AGENT_ID));
+        let now = Utc::now().timestamp() as f64;
+        let visible = |context: &Context| {
+            !is_expired(&context.metadata, now) && acl_agent.is_none_or(|agent| acl_allows(context, agent))
+        };
+        
+        if let Some(mut context) = self.l1_cache.get(&id) {
+            strip_internal_metadata(&mut context.metadata);
+            return Ok(visible(&context).then_some(Context { vector: None, ..context }));
+        }
+        
+        for level in &[ContextLevel::Immediate, ContextLevel::ShortTerm, ContextLevel::LongTerm] {
+            let collection = self.collection_name(*level);
+            
+            if let Some(point) = find_point(self.vector_db.as_ref(), &collection, id).await? {
+                // Tombstoned contexts only come back through `restore_context`
+                if self.config.soft_delete && point.payload.metadata.contains_key(DELETED_AT_KEY) {
+                    return Ok(None);
+                }
+                let mut context = self.context_from_point(point);
+                strip_internal_metadata(&mut context.metadata);
+                return Ok(visible(&context).then_some(context));
+            }
+        }
+        
+        Ok(None)
+    }
+    
+    async fn update_context(
+        &self,
+        id: Uuid,
+        metadata: HashMap<String, serde_json::Value>,
+    ) -> Result<()> {
+        // Validate metadata keys
+        for key in metadata.keys() {
+            InputValidator::validate_metadata_key(key)?;
+        }
+        InputValidator::validate_expires_at(&metadata)?;
+        
+        debug!("Updating context: {}", id);
+        
+        // Try to find and update the context in all collections
+        for level in &[ContextLevel::Immediate, ContextLevel::ShortTerm, ContextLevel::LongTerm] {
+            let collection = self.collection_name(*level);
+            
+            // Try to get the existing point
+            if let Some(mut point) = find_point(self.vector_db.as_ref(), &collection, id).await? {
+                // Record the version being replaced, dropping the oldest past the cap
+                let history = self.config.history_max_entries.map(|max_entries| {
+                    let mut history = match point.payload.metadata.get(HISTORY_KEY) {
+                        Some(serde_json::Value::Array(history)) => history.clone(),
+                        _ => Vec::new(),
+                    };
+                    let mut snapshot = point.payload.metadata.clone();
+                    strip_internal_metadata(&mut snapshot);
+                    history.push(serde_json::json!({
+                        "timestamp": point.payload.timestamp,
+                        "metadata": snapshot,
+                    }));
+                    let excess = history.len().saturating_sub(max_entries);
+                    history.drain(..excess);
+                    history
+                });
+                
+                // Only the changed fields are written, so concurrent access
+                // counts, deletes and restores are kept
+                let mut fields = metadata;
+                if let Some(history) = history {
+                    fields.insert(HISTORY_KEY.to_string(), history.into());
+                }
+                point.payload.metadata.extend(fields.clone());
+                point.payload.timestamp = Utc::now().timestamp();
+                fields.insert("timestamp".to_string(), point.payload.timestamp.into());
+                self.vector_db.set_payload_fields(&collection, id, fields).await?;
+                
+                // Update L1 cache if immediate level
+                if *level == ContextLevel::Immediate {
+                    let token_count = self.token_estimator.estimate_payload(&point.payload);
+                    let context = Context {
+                        id: point.id,
+                        text: point.payload.text,
+                        level: point.payload.level,
+                        relevance_score: 1.0,
+                        token_count,
+                        timestamp: point.payload.timestamp,
+                        access_count: point.payload.access_count,
+                        session_id: point.payload.session_id,
+                        metadata: point.payload.metadata,
+                        vector: Some(point.vector),
+                    };
+                    self.update_l1_cache(context).await;
+                }
+                
+                info!("Updated context {} in collection {}", id, collection);
+                return Ok(());
+            }
+        }
+        
+        Err(HiRAGError::ContextNotFound(id.to_string()).into())
+    }
+    
+    async fn get_context_history(&self, id: Uuid) -> Result<Vec<Context>> {
+        for level in &[ContextLevel::Immediate, ContextLevel::ShortTerm, ContextLevel::LongTerm] {
+            let collection = self.collection_name(*level);
+            
+            if let Some(point) = find_point(self.vector_db.as_ref(), &collection, id).await? {
+                let Some(serde_json::Value::Array(history)) = point.payload.metadata.get(HISTORY_KEY) else {
+                    return Ok(Vec::new());
+                };
+                let token_count = self.token_estimator.estimate_payload(&point.payload);
+                
+                return Ok(history
+                    .iter()
+                    .map(|version| {
+                        let timestamp = version["timestamp"].as_i64().unwrap_or_default();
+                        let mut context = Context::new(id, point.payload.text.clone(), *level, timestamp, token_count);
+                        context.metadata = serde_json::from_value(version["metadata"].clone()).unwrap_or_default();
+                        context
+                    })
+                    .collect());
+            }
+        }
+        
+        Err(HiRAGError::ContextNotFound(id.to_string()).into())
+    }
+    
+    async fn touch_context(&self, id: Uuid) -> Result<()> {
+        debug!("Touching context: {}", id);
+        
+        for level in &[ContextLevel::Immediate, ContextLevel::ShortTerm, ContextLevel::LongTerm] {
+            let collection = self.collection_name(*level);
+            
+            if find_point(self.vector_db.as_ref(), &collection, id).await?.is_some() {
+                // Only the timestamp is written, so concurrent access counts,
+                // deletes and restores are kept
+                let timestamp = Utc::now().timestamp();
+                let fields = HashMap::from([("timestamp".to_string(), timestamp.into())]);
+                self.vector_db.set_payload_fields(&collection, id, fields).await?;
+                self.l1_cache.touch(&id, timestamp);
+                
+                debug!("Touched context {} in collection {}", id, collection);
+                return Ok(());
+            }
+        }
+        
+        Err(HiRAGError::ContextNotFound(id.to_string()).into())
+    }
+    
+    async fn delete_context(&self, id: Uuid) -> Result<()> {
+        debug!("Deleting context: {}", id);
+        
+        if self.config.soft_delete {
+            return self.soft_delete_context(id).await;
+        }
+        
+        // Try to delete from all collections
+        for level in &[ContextLevel::Immediate, ContextLevel::ShortTerm, ContextLevel::LongTerm] {
+            let collection = self.collection_name(*level);
+            let _ = self.vector_db.delete_points(&collection, vec![id]).await;
+        }
+        
+        // Remove from L1 cache (lock-free)
+        self.l1_cache.remove(&id);
+        
+        // The owning level is unknown, so recount on the next store
+        self.level_counts.clear();
+        
+        info!("Context deleted: {}", id);
+        Ok(())
+    }
+    
+    async fn restore_context(&self, id: Uuid) -> Result<()> {
+        debug!("Restoring context: {}", id);
+        
+        // Soft deletes tombstone every level holding the context, so restore them all
+        let mut found = false;
+        for level in &[ContextLevel::Immediate, ContextLevel::ShortTerm, ContextLevel::LongTerm] {
+            let collection = self.collection_name(*level);
+            
+            if let Some(mut point) = find_point(self.vector_db.as_ref(), &collection, id).await? {
+                found = true;
+                if point.payload.metadata.remove(DELETED_AT_KEY).is_none() {
+                    continue;
+                }
+                
+                self.vector_db.delete_payload_fields(&collection, id, vec![DELETED_AT_KEY.to_string()]).await?;
+                
+                if *level == ContextLevel::Immediate {
+                    let token_count = self.token_estimator.estimate_payload(&point.payload);
+                    let mut context = Context::new(id, point.payload.text, *level, point.payload.timestamp, token_count);
+                    context.access_count = point.payload.access_count;
+                    context.metadata = point.payload.metadata;
+                    context.vector = Some(point.vector);
+                    self.update_l1_cache(context).await;
+                }
+                
+                info!("Restored context {} in collection {}", id, collection);
+            }
+        }
+        
+        if !found {
+            return Err(HiRAGError::ContextNotFound(id.to_string()).into());
+        }
+        Ok(())
+    }
+    
+    async fn move_context(&self, id: Uuid, to: ContextLevel) -> Result<()> {
+        debug!("Moving context {} to level {:?}", id, to);
+        
+        for level in &[ContextLevel::Immediate, ContextLevel::ShortTerm, ContextLevel::LongTerm] {
+            let collection = self.collection_name(*level);
+            
+            if let Some(mut point) = find_point(self.vector_db.as_ref(), &collection, id).await? {
+                if self.config.soft_delete && point.payload.metadata.contains_key(DELETED_AT_KEY) {
+                    break;
+                }
+                if *level == to {
+                    return Ok(());
+                }
+                
+                // Insert before deleting so a failure never loses the context
+                point.payload.level = to;
+                point.payload.token_count = Some(self.token_estimator.estimate_for_level(to, &point.payload.text));
+                let target = self.collection_name(to);
+                self.insert_prepared(&target, vec![point.clone()]).await?;
+                self.vector_db.delete_points(&collection, vec![id]).await?;
+                
+                if let Some(mut count) = self.level_counts.get_mut(level) {
+                    *count = count.saturating_sub(1);
+                }
+                
+                if to == ContextLevel::Immediate {
+                    let vector = point.vector.clone();
+                    let context = Context { vector: Some(vector), ..self.context_from_point(point) };
+                    self.update_l1_cache(context).await;
+                } else {
+                    self.l1_cache.remove(&id);
+                }
+                
+                self.enforce_level_cap(to, &target, 1).await;
+                
+                info!("Moved context {} from {} to {}", id, collection, target);
+                return Ok(());
+            }
+        }
+        
+        Err(HiRAGError::ContextNotFound(id.to_string()).into())
+    }
+    
+    async fn clear_level(&self, level: ContextLevel) -> Result<()> {
+        debug!("Clearing level: {:?}", level);
+        
+        let collection = self.collection_name(level);
+        
+        // Delete and recreate the collection behind a fresh guard, so stores
+        // to it wait for the new collection instead of racing the delete
+        let ready = Arc::new(tokio::sync::OnceCell::new());
+        self.ready_collections.insert(collection.clone(), ready.clone());
+        ready
+            .get_or_try_init(|| async {
+                let _ = self.vector_db.delete_collection(&collection).await;
+                self.prepare_collection(&collection).await
+            })
+            .await?;
+        self.level_counts.remove(&level);
+        
+        // Clear L1 cache if immediate level
+        if level == ContextLevel::Immediate {
+            self.l1_cache.clear();
+        }
+        
+        info!("Level cleared: {:?}", level);
+        Ok(())
+    }
+    
+    async fn explain_retrieval(&self, request: ContextRequest) -> Result<RetrievalExplanation> {
+        self.admit_request(&request).await?;
+        let query_embedding = match self.embedding_client.embed_query(&request.query).await {
+            Ok(embedding) => embedding,
+            Err(e) => {
+                self.refund_agent_quotas(std::slice::from_ref(&request.agent_id)).await;
+                return Err(e);
+            }
+        };
+        
+        let levels = requested_levels(&request);
+        let (l1_tokens, l2_tokens, l3_tokens) = self.level_allocations(request.max_tokens, &levels);
+        let acl_agent = self.acl_agent(&request);
+        let filters = Some(self.retrieval_filter(&request));
+        let fast_path = self.is_fast_path(&levels);
+        let mmr_lambda = self.config.mmr_lambda;
+        
+        // Recall every candidate, marking those beyond their level's budget
+        // the same way the retriever and L1 lookup cut them off
+        let mut candidates: Vec<(Context, Option<ExclusionReason>)> = Vec::new();
+        let mut failed_levels = Vec::new();
+        for level in levels {
+            let (max_tokens, recalled) = match level {
+                ContextLevel::Immediate => {
+                    let (contexts, _) = self
+                        .get_l1_contexts(usize::MAX, acl_agent, request.session_id.as_deref(), mmr_lambda.is_some())
+                        .await;
+                    (l1_tokens, contexts)
+                }
+                ContextLevel::ShortTerm | ContextLevel::LongTerm => {
+                    let recalled = self.retriever.retrieve_from_level(
+                        &self.collection_name(level),
+                        query_embedding.clone(),
+                        usize::MAX,
+                        filters.clone(),
+                        request.rescore_metric,
+                        mmr_lambda.is_some(),
+                    ).await;
+                    let contexts = match recalled {
+                        Ok((contexts, _)) => contexts,
+                        Err(e) => {
+                            warn!("Error retrieving contexts from {:?}: {}", level, e);
+                            failed_levels.push(level);
+                            continue;
+                        }
+                    };
+                    let max_tokens = if level == ContextLevel::ShortTerm { l2_tokens } else { l3_tokens };
+                    (max_tokens, contexts)
+                }
+            };
+            
+            let mut used = 0;
+            let mut over_budget = false;
+            for context in recalled {
+                over_budget |= used + context.token_count > max_tokens;
+                if !over_budget {
+                    used += context.token_count;
+                }
+                candidates.push((context, over_budget.then_some(ExclusionReason::LevelBudget)));
+            }
+        }
+        
+        // Mark the copies deduplication drops
+        if !fast_path {
+            let eligible: Vec<Context> = candidates.iter()
+                .filter(|(_, reason)| reason.is_none())
+                .map(|(context, _)| context.clone())
+                .collect();
+            let kept: std::collections::HashSet<_> = self.deduplicate_contexts(eligible)
+                .into_iter()
+                .map(|context| (context.id, context.level))
+                .collect();
+            for (context, reason) in &mut candidates {
+                if reason.is_none() && !kept.contains(&(context.id, context.level)) {
+                    *reason = Some(ExclusionReason::Duplicate);
+                }
+            }
+        }
+        
+        // Score and order everything, then apply the overall budget
+        let now = self.ranker.current_time();
+        let mut scored: Vec<_> = candidates.into_iter()
+            .map(|(context, reason)| (self.ranker.score_breakdown(&context, now), context, reason))
+            .collect();
+        scored.sort_by(|a, b| b.0.total.total_cmp(&a.0.total));
+        
+        // Reorder the remaining candidates by MMR, as retrieval does; excluded
+        // candidates keep their place
+        if let Some(lambda) = mmr_lambda {
+            let slots: Vec<usize> = (0..scored.len()).filter(|&i| scored[i].2.is_none()).collect();
+            let ranked: Vec<Context> = slots.iter()
+                .map(|&i| Context { relevance_score: scored[i].0.total, ..scored[i].1.clone() })
+                .collect();
+            let picked: Vec<(Uuid, ContextLevel)> = self.ranker.apply_mmr(ranked, lambda, request.max_tokens)
+                .into_iter()
+                .map(|context| (context.id, context.level))
+                .collect();
+            let mut eligible: HashMap<_, _> = slots.iter()
+                .map(|&i| ((scored[i].1.id, scored[i].1.level), scored[i].clone()))
+                .collect();
+            for (slot, key) in slots.into_iter().zip(picked) {
+                if let Some(candidate) = eligible.remove(&key) {
+                    scored[slot] = candidate;
+                }
+            }
+        }
+        
+        let mut total_tokens = 0;
+        let candidates = scored.into_iter()
+            .map(|(score, context, mut excluded_reason)| {
+                if excluded_reason.is_none() {
+                    if total_tokens + context.token_count <= request.max_tokens {
+                        total_tokens += context.token_count;
+                    } else {
+                        excluded_reason = Some(ExclusionReason::TokenBudget);
+                    }
+                }
+                CandidateExplanation {
+                    id: context.id,
+                    level: context.level,
+                    token_count: context.token_count,
+                    similarity: context.relevance_score,
+                    score,
+                    included: excluded_reason.is_none(),
+                    excluded_reason,
+                }
+            })
+            .collect();
+        
+        Ok(RetrievalExplanation {
+            query: request.query,
+            max_tokens: request.max_tokens,
+            candidates,
+            failed_levels,
+        })
+    }
+    
+    async fn compact_l1(&self) -> Result<usize> {
+        let now = Utc::now().timestamp() as f64;
+        let mut evicted = 0;
+        for context in self.l1_cache.newest_first() {
+            if is_expired(&context.metadata, now) && self.l1_cache.remove(&context.id).is_some() {
+                evicted += 1;
+            }
+        }
+        evicted += self.l1_cache.shrink_to(self.config.l1_size).len();
+        
+        info!("L1 cache compacted: {} evicted, size: {}", evicted, self.l1_cache.len());
+        Ok(evicted)
+    }
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::Config;
+    use crate::hirag::EXPIRES_AT_KEY;
+    use crate::middleware::RateLimitConfig;
+    use crate::test_support::{point, test_manager_with_config, MockEmbeddingProvider, MockVectorStore};
+    use crate::vector_db::{CircuitBreaker, CircuitBreakerConfig};
+    use std::collections::HashSet;
+    
+    #[tokio::test]
+    async fn test_store_validates_against_provider_dimension() {
+        let vector_db = Arc::new(MockVectorStore::new());
+        let manager = HiRAGManagerV2::new(
+            Config::default_config().hirag,
+            Arc::new(MockEmbeddingProvider::new(8)),
+            vector_db.clone(),
+        ).await.unwrap();
+        manager.initialize().await.unwrap();
+        
+        let id = manager
+            .store_context("eight dimensions", ContextLevel::ShortTerm, HashMap::new())
+            .await
+            .unwrap();
+        assert_eq!(vector_db.point("contexts_shortterm", id).unwrap().vector.len(), 8);
+    }
+    
+    #[tokio::test]
+    async fn test_open_pipeline_fails_fast() {
+        let open_breaker = || async {
+            let breaker = Arc::new(CircuitBreaker::new(CircuitBreakerConfig {
+                failure_threshold: 1,
+                ..Default::default()
+            }));
+            breaker.record_failure().await;
+            breaker
+        };
+        let pipeline = PipelineBreaker::new(open_breaker().await, open_breaker().await);
+        
+        let vector_db = Arc::new(MockVectorStore::new());
+        let embedding = Arc::new(MockEmbeddingProvider::new(8));
+        let manager = HiRAGManagerV2::new(Config::default_config().hirag, embedding.clone(), vector_db.clone())
+            .await
+            .unwrap()
+            .with_pipeline_breaker(Arc::new(pipeline));
+        
+        let err = manager
+            .store_context("unreachable", ContextLevel::ShortTerm, HashMap::new())
+            .await
+            .unwrap_err();
+        assert!(matches!(err, ContextError::ServiceUnavailable(_)));
+        let err = manager
+            .retrieve_context(ContextRequest::new("unreachable".to_string(), 1000))
+            .await
+            .unwrap_err();
+        assert!(matches!(err, ContextError::ServiceUnavailable(_)));
+        
+        assert_eq!(embedding.single_calls(), 0);
+        assert_eq!(vector_db.search_calls(), 0);
+    }
+    
+    #[tokio::test]
+    async fn test_initialize_creates_every_level_collection() {
+        let vector_db = Arc::new(MockVectorStore::new());
+        let manager = HiRAGManagerV2::new(
+            Config::default_config().hirag,
+            Arc::new(MockEmbeddingProvider::new(8)),
+            vector_db.clone(),
+        ).await.unwrap()
+        .with_init_concurrency(2);
+        
+        manager.initialize().await.unwrap();
+        let mut collections = vector_db.collection_names();
+        collections.sort();
+        assert_eq!(collections, vec!["contexts_immediate", "contexts_longterm", "contexts_shortterm"]);
+        
+        // Collections that already exist are tolerated
+        manager.initialize().await.unwrap();
+        assert_eq!(vector_db.create_calls(), 6);
+        assert_eq!(vector_db.collection_names().len(), 3);
+    }
+    
+    #[tokio::test]
+    async fn test_level_token_estimator_is_used_on_store() {
+        let mut config = Config::default_config().hirag;
+        config.level_token_estimators.l3 = Some(crate::config::TokenEstimator::WordBased { words_per_token: 1.0 });
+        let (manager, vector_db, _) = test_manager_with_config(config).await;
+        
+        let text = "Hello world";
+        let short = manager.store_context(text, ContextLevel::ShortTerm, HashMap::new()).await.unwrap();
+        let long = manager.store_context(text, ContextLevel::LongTerm, HashMap::new()).await.unwrap();
+        
+        let token_count = |collection: &str, id| vector_db.point(collection, id).unwrap().payload.token_count;
+        assert_eq!(token_count("contexts_shortterm", short), Some(3));
+        assert_eq!(token_count("contexts_longterm", long), Some(2));
+        
+        // Moving re-estimates with the target level's estimator
+        manager.move_context(short, ContextLevel::LongTerm).await.unwrap();
+        assert_eq!(token_count("contexts_longterm", short), Some(2));
+    }
+    
+    #[tokio::test]
+    async fn test_lookups_skip_missing_collections_but_not_failing_ones() {
+        let (manager, vector_db, _) = test_manager_with_config(Config::default_config().hirag).await;
+        let id = manager
+            .store_context("kept in L2", ContextLevel::ShortTerm, HashMap::new())
+            .await
+            .unwrap();
+        vector_db.delete_collection("contexts_immediate").await.unwrap();
+        
+        // The missing L1 collection is probed first and treated as empty
+        assert_eq!(manager.get_context(id, None).await.unwrap().unwrap().text, "kept in L2");
+        assert!(manager.get_context(Uuid::new_v4(), None).await.unwrap().is_none());
+        let metadata = HashMap::from([("topic".to_string(), serde_json::json!("ui"))]);
+        manager.update_context(id, metadata).await.unwrap();
+        
+        vector_db.fail_collection("contexts_shortterm");
+        assert!(manager.get_context(id, None).await.is_err());
+    }
+    
+    #[tokio::test]
+    async fn test_concurrent_first_stores_create_the_collection_once() {
+        let mut config = Config::default_config().hirag;
+        config.create_collections_on_store = true;
+        let vector_db = Arc::new(MockVectorStore::new());
+        let manager = Arc::new(HiRAGManagerV2::new(
+            config,
+            Arc::new(MockEmbeddingProvider::new(1024)),
+            vector_db.clone(),
+        ).await.unwrap());
+        
+        let stores = (0..32).map(|i| {
+            let manager = manager.clone();
+            tokio::spawn(async move {
+                manager.store_context(&format!("context {}", i), ContextLevel::ShortTerm, HashMap::new()).await
+            })
+        });
+        for result in futures::future::join_all(stores).await {
+            result.unwrap().unwrap();
+        }
+        
+        assert_eq!(vector_db.create_calls(), 1);
+        assert_eq!(vector_db.point_ids("contexts_shortterm").len(), 32);
+        
+        // A collection created elsewhere is adopted rather than failing the store,
+        // and gets its indexes
+        vector_db.create_collection("contexts_longterm").await.unwrap();
+        manager.store_context("late", ContextLevel::LongTerm, HashMap::new()).await.unwrap();
+        assert_eq!(vector_db.point_ids("contexts_longterm").len(), 1);
+        assert!(vector_db.indexed_collections().contains("contexts_longterm"));
+    }
+    
+    #[tokio::test]
+    async fn test_deleted_collection_is_recreated_on_store() {
+        let mut config = Config::default_config().hirag;
+        config.create_collections_on_store = true;
+        let vector_db = Arc::new(MockVectorStore::new());
+        let manager = HiRAGManagerV2::new(
+            config,
+            Arc::new(MockEmbeddingProvider::new(1024)),
+            vector_db.clone(),
+        ).await.unwrap();
+        let store = |text: &'static str| manager.store_context(text, ContextLevel::ShortTerm, HashMap::new());
+        
+        store("first").await.unwrap();
+        vector_db.delete_collection("contexts_shortterm").await.unwrap();
+        
+        // The store that finds the collection gone fails; the next recreates it
+        assert!(store("lost").await.is_err());
+        store("second").await.unwrap();
+        assert_eq!(vector_db.point_ids("contexts_shortterm").len(), 1);
+        
+        manager.clear_level(ContextLevel::ShortTerm).await.unwrap();
+        store("after clear").await.unwrap();
+        assert_eq!(vector_db.point_ids("contexts_shortterm").len(), 1);
+        assert_eq!(vector_db.create_calls(), 3);
+    }
+    
+    #[tokio::test]
+    async fn test_collections_are_sized_by_the_detected_dimension() {
+        let vector_db = Arc::new(MockVectorStore::new());
+        let manager = HiRAGManagerV2::new(
+            Config::default_config().hirag,
+            Arc::new(MockEmbeddingProvider::new(8).detecting()),
+            vector_db.clone(),
+        ).await.unwrap();
+        
+        // Nothing is created while the dimension is still a guess
+        manager.initialize().await.unwrap();
+        assert!(vector_db.collection_names().is_empty());
+        
+        manager.store_context("first", ContextLevel::ShortTerm, HashMap::new()).await.unwrap();
+        assert_eq!(vector_db.collection_size("contexts_shortterm"), Some(8));
+        assert_eq!(vector_db.point_ids("contexts_shortterm").len(), 1);
+    }
+    
+    #[tokio::test]
+    async fn test_initialize_indexes_existing_collections() {
+        let vector_db = Arc::new(MockVectorStore::new());
+        vector_db.create_collection("contexts_shortterm").await.unwrap();
+        let manager = HiRAGManagerV2::new(
+            Config::default_config().hirag,
+            Arc::new(MockEmbeddingProvider::new(1024)),
+            vector_db.clone(),
+        ).await.unwrap();
+        
+        manager.initialize().await.unwrap();
+        
+        let indexed = vector_db.indexed_collections();
+        assert_eq!(indexed.len(), 3);
+        assert!(indexed.contains("contexts_shortterm"));
+    }
+    
+    #[tokio::test]
+    async fn test_failed_levels_are_reported() {
+        let (manager, vector_db, _) = test_manager_with_config(Config::default_config().hirag).await;
+        vector_db.fail_collection("contexts_longterm");
+        
+        let response = manager
+            .retrieve_context(
+                ContextRequest::new("anything".to_string(), 1000)
+                    .with_levels(vec![ContextLevel::ShortTerm, ContextLevel::LongTerm]),
+            )
+            .await
+            .unwrap();
+        assert_eq!(response.metadata.failed_levels, vec![ContextLevel::LongTerm]);
+        
+        vector_db.recover_collection("contexts_longterm");
+        let response = manager
+            .retrieve_context(ContextRequest::new("anything".to_string(), 1000))
+            .await
+            .unwrap();
+        assert!(response.metadata.failed_levels.is_empty());
+    }
+    
+    #[tokio::test]
+    async fn test_searches_use_read_replica() {
+        let primary = Arc::new(MockVectorStore::new());
+        let replica = Arc::new(MockVectorStore::new());
+        let manager = HiRAGManagerV2::new(
+            Config::default_config().hirag,
+            Arc::new(MockEmbeddingProvider::new(8)),
+            primary.clone(),
+        ).await.unwrap()
+        .with_read_replica(replica.clone());
+        manager.initialize().await.unwrap();
+        
+        let id = manager
+            .store_context("replicated", ContextLevel::ShortTerm, HashMap::new())
+            .await
+            .unwrap();
+        assert!(primary.point("contexts_shortterm", id).is_some());
+        assert!(replica.point_ids("contexts_shortterm").is_empty());
+        
+        manager
+            .retrieve_context(
+                ContextRequest::new("replicated".to_string(), 1000).with_levels(vec![ContextLevel::ShortTerm]),
+            )
+            .await
+            .unwrap();
+        assert!(replica.search_calls() > 0);
+        assert_eq!(primary.search_calls(), 0);
+    }
+    
+    #[tokio::test]
+    async fn test_l1_size_matches_cache_under_concurrent_stores_and_deletes() {
+        let mut config = Config::default_config().hirag;
+        config.l1_size = 16;
+        let (manager, _, _) = test_manager_with_config(config).await;
+        
+        let mut handles = Vec::new();
+        for i in 0..64 {
+            let manager = manager.clone();
+            handles.push(tokio::spawn(async move {
+                let id = manager
+                    .store_context(&format!("context {}", i), ContextLevel::Immediate, HashMap::new())
+                    .await
+                    .unwrap();
+                if i % 3 == 0 {
+                    manager.delete_context(id).await.unwrap();
+                }
+            }));
+        }
+        for handle in handles {
+            handle.await.unwrap();
+        }
+        
+        let cached = manager.l1_cache.newest_first();
+        assert_eq!(manager.l1_cache_len(), cached.len());
+        assert!(manager.l1_cache_len() <= 16);
+    }
+    
+    #[tokio::test]
+    async fn test_compact_l1_drops_expired_entries() {
+        let mut config = Config::default_config().hirag;
+        config.l1_size = 10;
+        let (manager, _, _) = test_manager_with_config(config).await;
+        
+        for i in 0..8 {
+            manager
+                .store_context(&format!("context {}", i), ContextLevel::Immediate, HashMap::new())
+                .await
+                .unwrap();
+        }
+        let mut metadata = HashMap::new();
+        metadata.insert(EXPIRES_AT_KEY.to_string(), serde_json::json!(Utc::now().timestamp() - 1));
+        manager.store_context("expired", ContextLevel::Immediate, metadata).await.unwrap();
+        assert_eq!(manager.l1_cache_len(), 9);
+        
+        assert_eq!(manager.compact_l1().await.unwrap(), 1);
+        assert_eq!(manager.l1_cache_len(), 8);
+        assert!(manager.l1_cache.newest_first().iter().all(|c| c.text != "expired"));
+        assert_eq!(manager.compact_l1().await.unwrap(), 0);
+    }
+    
+    #[tokio::test]
+    async fn test_store_past_cap_evicts_oldest() {
+        let mut config = Config::default_config().hirag;
+        config.max_contexts_per_level = Some(2);
+        let (manager, vector_db, _) = test_manager_with_config(config).await;
+        
+        // Two existing contexts with known timestamps
+        let existing: Vec<VectorPoint> = [(Uuid::from_u128(1), 100), (Uuid::from_u128(2), 200)]
+            .into_iter()
+            .map(|(id, timestamp)| point(id.as_u128(), ContextLevel::ShortTerm, timestamp))
+            .collect();
+        vector_db.insert_points("contexts_shortterm", existing).await.unwrap();
+        
+        let id = manager
+            .store_context("newest", ContextLevel::ShortTerm, HashMap::new())
+            .await
+            .unwrap();
+        
+        let mut remaining = vector_db.point_ids("contexts_shortterm");
+        remaining.sort();
+        let mut expected = vec![Uuid::from_u128(2), id];
+        expected.sort();
+        assert_eq!(remaining, expected);
+        
+        // The cached count keeps enforcing the cap
+        manager.store_context("newer still", ContextLevel::ShortTerm, HashMap::new()).await.unwrap();
+        assert_eq!(vector_db.point_ids("contexts_shortterm").len(), 2);
+        assert!(vector_db.point("contexts_shortterm", Uuid::from_u128(2)).is_none());
+    }
+    
+    #[tokio::test]
+    async fn test_updates_are_kept_in_history() {
+        let mut config = Config::default_config().hirag;
+        config.history_max_entries = Some(2);
+        let (manager, _, _) = test_manager_with_config(config).await;
+        
+        let id = manager
+            .store_context("versioned", ContextLevel::ShortTerm, HashMap::from([("rev".to_string(), 0.into())]))
+            .await
+            .unwrap();
+        assert!(manager.get_context_history(id).await.unwrap().is_empty());
+        
+        for rev in 1..=2 {
+            manager.update_context(id, HashMap::from([("rev".to_string(), rev.into())])).await.unwrap();
+        }
+        
+        let history = manager.get_context_history(id).await.unwrap();
+        let revs: Vec<_> = history.iter().map(|c| c.metadata["rev"].clone()).collect();
+        assert_eq!(revs, vec![serde_json::json!(0), serde_json::json!(1)]);
+        assert!(history.iter().all(|c| c.id == id && c.text == "versioned"));
+        
+        // The cap drops the oldest version
+        manager.update_context(id, HashMap::from([("rev".to_string(), 3.into())])).await.unwrap();
+        let history = manager.get_context_history(id).await.unwrap();
+        assert_eq!(history.len(), 2);
+        assert_eq!(history[0].metadata["rev"], 1);
+        
+        // History lives under a reserved key callers neither see nor can write
+        let context = manager.get_context(id, None).await.unwrap().unwrap();
+        assert_eq!(context.metadata.keys().collect::<Vec<_>>(), vec!["rev"]);
+        let forged = HashMap::from([(HISTORY_KEY.to_string(), serde_json::json!([]))]);
+        assert!(manager.update_context(id, forged).await.is_err());
+    }
+    
+    #[tokio::test]
+    async fn test_updates_keep_concurrent_changes() {
+        let (manager, vector_db, _) = test_manager_with_config(Config::default_config().hirag).await;
+        let id = manager
+            .store_context("counted", ContextLevel::ShortTerm, HashMap::new())
+            .await
+            .unwrap();
+        // Another replica bumps the access count after the update reads the point
+        vector_db
+            .set_payload_fields("contexts_shortterm", id, HashMap::from([("access_count".to_string(), 5.into())]))
+            .await
+            .unwrap();
+        
+        let inserts = vector_db.insert_calls();
+        manager.update_context(id, HashMap::from([("topic".to_string(), "ui".into())])).await.unwrap();
+        assert_eq!(vector_db.insert_calls(), inserts);
+        let point = vector_db.point("contexts_shortterm", id).unwrap();
+        assert_eq!(point.payload.access_count, 5);
+        assert_eq!(point.payload.metadata["topic"], "ui");
+        
+        assert!(matches!(
+            manager.update_context(Uuid::new_v4(), HashMap::new()).await,
+            Err(ContextError::HiRAG(HiRAGError::ContextNotFound(_)))
+        ));
+    }
+    
+    #[tokio::test]
+    async fn test_touched_context_survives_gc() {
+        use crate::hirag::background::BackgroundTaskManager;
+        
+        let (manager, vector_db, _) = test_manager_with_config(Config::default_config().hirag).await;
+        let stale = |id: u128| point(id, ContextLevel::ShortTerm, 100);
+        vector_db.insert_points("contexts_shortterm", vec![stale(1), stale(2)]).await.unwrap();
+        
+        // Only the timestamp is updated in place, never the whole point
+        let inserts = vector_db.insert_calls();
+        manager.touch_context(Uuid::from_u128(1)).await.unwrap();
+        assert_eq!(vector_db.insert_calls(), inserts);
+        
+        let gc = BackgroundTaskManager::new(
+            vector_db.clone(),
+            std::time::Duration::from_secs(60),
+            3600,
+            86_400,
+            "contexts_shortterm".to_string(),
+            "contexts_longterm".to_string(),
+            1024,
+        );
+        assert_eq!(gc.cleanup_expired_l2_contexts().await.unwrap(), 1);
+        assert_eq!(vector_db.point_ids("contexts_shortterm"), vec![Uuid::from_u128(1)]);
+        
+        assert!(manager.touch_context(Uuid::from_u128(2)).await.is_err());
+    }
+    
+    #[tokio::test]
+    async fn test_agent_store_quota_is_enforced() {
+        use crate::error::ContextError;
+        
+        let rate_limiter = Arc::new(RateLimiter::new(RateLimitConfig {
+            max_requests: 2,
+            window_duration: std::time::Duration::from_secs(60),
+            enabled: true,
+            ..Default::default()
+        }));
+        let vector_db = Arc::new(MockVectorStore::new());
+        let manager = HiRAGManagerV2::new(
+            Config::default_config().hirag,
+            Arc::new(MockEmbeddingProvider::new(1024)),
+            vector_db.clone(),
+        ).await.unwrap().with_agent_rate_limiter(rate_limiter);
+        manager.initialize().await.unwrap();
+        
+        let agent = |name: &str| HashMap::from([("agent_id".to_string(), serde_json::json!(name))]);
+        for i in 0..2 {
+            manager.store_context(&format!("context {}", i), ContextLevel::ShortTerm, agent("busy")).await.unwrap();
+        }
+        let result = manager.store_context("one too many", ContextLevel::ShortTerm, agent("busy")).await;
+        assert!(matches!(result, Err(ContextError::RateLimit(_))));
+        assert_eq!(vector_db.point_ids("contexts_shortterm").len(), 2);
+        
+        // Other agents keep their own quota
+        manager.store_context("quiet", ContextLevel::ShortTerm, agent("quiet")).await.unwrap();
+    }
+    
+    #[tokio::test]
+    async fn test_failed_store_charges_no_quota() {
+        let rate_limiter = Arc::new(RateLimiter::new(RateLimitConfig {
+            max_requests: 1,
+            window_duration: std::time::Duration::from_secs(60),
+            enabled: true,
+            ..Default::default()
+        }));
+        let vector_db = Arc::new(MockVectorStore::new());
+        let embedding = Arc::new(MockEmbeddingProvider::new(1024));
+        let manager = HiRAGManagerV2::new(Config::default_config().hirag, embedding.clone(), vector_db.clone())
+            .await
+            .unwrap()
+            .with_agent_rate_limiter(rate_limiter);
+        manager.initialize().await.unwrap();
+        let agent = || HashMap::from([("agent_id".to_string(), serde_json::json!("busy"))]);
+        
+        embedding.set_failing(true);
+        assert!(manager.store_context("unembedded", ContextLevel::ShortTerm, agent()).await.is_err());
+        embedding.set_failing(false);
+        vector_db.fail_collection("contexts_shortterm");
+        assert!(manager.store_context("unstored", ContextLevel::ShortTerm, agent()).await.is_err());
+        vector_db.recover_collection("contexts_shortterm");
+        
+        // Neither failure used up the agent's only request
+        manager.store_context("stored", ContextLevel::ShortTerm, agent()).await.unwrap();
+    }
+    
+    #[tokio::test]
+    async fn test_response_flags_truncation() {
+        let (manager, _, _) = test_manager_with_config(Config::default_config().hirag).await;
+        for i in 0..5 {
+            manager
+                .store_context(&format!("short-term context number {}", i), ContextLevel::ShortTerm, HashMap::new())
+                .await
+                .unwrap();
+        }
+        
+        let request = |max_tokens| ContextRequest::new("context".to_string(), max_tokens)
+            .with_levels(vec![ContextLevel::ShortTerm]);
+        
+        let clipped = manager.retrieve_context(request(20)).await.unwrap();
+        assert!(clipped.metadata.truncated);
+        assert!(clipped.metadata.omitted_count > 0);
+        assert_eq!(clipped.contexts.len() + clipped.metadata.omitted_count, 5);
+        
+        let complete = manager.retrieve_context(request(4000)).await.unwrap();
+        assert!(!complete.metadata.truncated);
+        assert_eq!(complete.metadata.omitted_count, 0);
+        assert_eq!(complete.contexts.len(), 5);
+    }
+    
+    #[tokio::test]
+    async fn test_empty_l1_budget_is_redistributed() {
+        let (manager, _, _) = test_manager_with_config(Config::default_config().hirag).await;
+        for i in 0..10 {
+            manager
+                .store_context(&format!("short-term context number {}", i), ContextLevel::ShortTerm, HashMap::new())
+                .await
+                .unwrap();
+        }
+        assert_eq!(manager.l1_cache_len(), 0);
+        
+        // ShortTerm's own 40% share of 100 tokens fits only 5 of the 7-token
+        // contexts; with the empty L1 and unsearched L3 shares it gets all 100
+        let request = ContextRequest::new("context".to_string(), 100)
+            .with_levels(vec![ContextLevel::Immediate, ContextLevel::ShortTerm]);
+        let response = manager.retrieve_context(request).await.unwrap();
+        
+        assert_eq!(response.contexts.len(), 10);
+        assert_eq!(response.total_tokens, 70);
+        assert!(!response.metadata.truncated);
+    }
+    
+    #[tokio::test]
+    async fn test_level_counted_empty_keeps_its_budget() {
+        let mut config = Config::default_config().hirag;
+        config.max_contexts_per_level = Some(10);
+        let (manager, vector_db, _) = test_manager_with_config(config).await;
+        let id = manager.store_context("moved around", ContextLevel::ShortTerm, HashMap::new()).await.unwrap();
+        
+        // Moving out takes the ShortTerm count to 0; another replica then
+        // stores into ShortTerm without this process seeing it
+        manager.move_context(id, ContextLevel::LongTerm).await.unwrap();
+        assert_eq!(manager.level_counts.get(&ContextLevel::ShortTerm).map(|count| *count), Some(0));
+        let mut point = vector_db.point("contexts_longterm", id).unwrap();
+        point.id = Uuid::new_v4();
+        point.payload.level = ContextLevel::ShortTerm;
+        vector_db.insert_points("contexts_shortterm", vec![point]).await.unwrap();
+        
+        let request = ContextRequest::new("moved".to_string(), 100)
+            .with_levels(vec![ContextLevel::ShortTerm]);
+        let response = manager.retrieve_context(request).await.unwrap();
+        assert_eq!(response.contexts.len(), 1);
+    }
+    
+    #[tokio::test]
+    async fn test_store_batch_embeds_and_inserts_once_per_level() {
+        let (manager, vector_db, embedding) = test_manager_with_config(Config::default_config().hirag).await;
+        let inserts = vector_db.insert_calls();
+        let items = vec![
+            ("first note".to_string(), ContextLevel::ShortTerm, HashMap::new()),
+            ("urgent note".to_string(), ContextLevel::Immediate, HashMap::new()),
+            ("second note".to_string(), ContextLevel::ShortTerm, HashMap::new()),
+        ];
+        
+        let ids = manager.store_batch(items).await.unwrap();
+        
+        assert_eq!(ids.len(), 3);
+        assert_eq!(embedding.batch_calls(), 1);
+        assert_eq!(embedding.single_calls(), 0);
+        assert_eq!(vector_db.insert_calls() - inserts, 2);
+        
+        // IDs follow input order
+        assert_eq!(vector_db.point("contexts_shortterm", ids[0]).unwrap().payload.text, "first note");
+        assert_eq!(vector_db.point("contexts_immediate", ids[1]).unwrap().payload.text, "urgent note");
+        assert_eq!(vector_db.point("contexts_shortterm", ids[2]).unwrap().payload.text, "second note");
+        assert_eq!(manager.l1_cache_len(), 1);
+        
+        // Nothing is stored when any item is invalid
+        let invalid = vec![
+            ("valid".to_string(), ContextLevel::ShortTerm, HashMap::new()),
+            (String::new(), ContextLevel::ShortTerm, HashMap::new()),
+        ];
+        assert!(manager.store_batch(invalid).await.is_err());
+        assert_eq!(vector_db.point_ids("contexts_shortterm").len(), 2);
+    }
+    
+    #[tokio::test]
+    async fn test_failed_store_batch_stores_and_charges_nothing() {
+        let rate_limiter = Arc::new(RateLimiter::new(RateLimitConfig {
+            max_requests: 2,
+            window_duration: std::time::Duration::from_secs(60),
+            enabled: true,
+            ..Default::default()
+        }));
+        let (manager, vector_db, _) = test_manager_with_config(Config::default_config().hirag).await;
+        let manager = Arc::into_inner(manager).unwrap().with_agent_rate_limiter(rate_limiter);
+        let items = || vec![
+            ("short-term note".to_string(), ContextLevel::ShortTerm, HashMap::new()),
+            ("long-term note".to_string(), ContextLevel::LongTerm, HashMap::new()),
+        ];
+        let options = StoreOptions { agent_id: Some("importer".to_string()), session_id: Some("s1".to_string()) };
+        
+        // The short-term level is inserted before the long-term one fails
+        vector_db.fail_collection("contexts_longterm");
+        assert!(manager.store_batch_with(items(), options.clone()).await.is_err());
+        assert!(vector_db.point_ids("contexts_shortterm").is_empty());
+        
+        // The failed batch gave its quota back
+        vector_db.recover_collection("contexts_longterm");
+        let ids = manager.store_batch_with(items(), options).await.unwrap();
+        let point = vector_db.point("contexts_shortterm", ids[0]).unwrap();
+        assert_eq!(point.payload.session_id.as_deref(), Some("s1"));
+        assert_eq!(point.payload.agent_id, "importer");
+    }
+    
+    #[tokio::test]
+    async fn test_zero_query_embedding_is_an_error_not_a_failed_level() {
+        let (manager, vector_db, embedding) = test_manager_with_config(Config::default_config().hirag).await;
+        manager.store_context("stored note", ContextLevel::ShortTerm, HashMap::new()).await.unwrap();
+        embedding.set_vector("blank", vec![0.0; 1024]);
+        let searches = vector_db.search_calls();
+        
+        let request = ContextRequest::new("blank".to_string(), 1000);
+        assert!(manager.retrieve_context(request.clone()).await.is_err());
+        assert!(manager.retrieve_batch(vec![request]).await.is_err());
+        assert_eq!(vector_db.search_calls(), searches);
+    }
+    
+    #[tokio::test]
+    async fn test_failed_batch_retrieval_charges_no_quota() {
+        let rate_limiter = Arc::new(RateLimiter::new(RateLimitConfig {
+            max_requests: 2,
+            window_duration: std::time::Duration::from_secs(60),
+            enabled: true,
+            ..Default::default()
+        }));
+        let (manager, _, embedding) = test_manager_with_config(Config::default_config().hirag).await;
+        let manager = Arc::into_inner(manager).unwrap().with_agent_rate_limiter(rate_limiter);
+        let request = || ContextRequest::new("quota".to_string(), 1000).with_agent("busy".to_string());
+        
+        // Admission fails on the third request, after charging the first two
+        assert!(manager.retrieve_batch(vec![request(), request(), request()]).await.is_err());
+        
+        embedding.set_failing(true);
+        assert!(manager.retrieve_batch(vec![request(), request()]).await.is_err());
+        
+        embedding.set_failing(false);
+        assert_eq!(manager.retrieve_batch(vec![request(), request()]).await.unwrap().len(), 2);
+    }
+    
+    #[tokio::test]
+    async fn test_failed_explanation_charges_no_quota() {
+        let rate_limiter = Arc::new(RateLimiter::new(RateLimitConfig {
+            max_requests: 1,
+            window_duration: std::time::Duration::from_secs(60),
+            enabled: true,
+            ..Default::default()
+        }));
+        let (manager, _, embedding) = test_manager_with_config(Config::default_config().hirag).await;
+        let manager = Arc::into_inner(manager).unwrap().with_agent_rate_limiter(rate_limiter);
+        let request = || ContextRequest::new("quota".to_string(), 1000).with_agent("busy".to_string());
+        
+        embedding.set_failing(true);
+        assert!(manager.explain_retrieval(request()).await.is_err());
+        embedding.set_failing(false);
+        assert!(manager.explain_retrieval(request()).await.is_ok());
+    }
+    
+    #[tokio::test]
+    async fn test_import_reports_monotonic_progress() {
+        let (manager, vector_db, embedding) = test_manager_with_config(Config::default_config().hirag).await;
+        let mut items: Vec<_> = (0..250)
+            .map(|i| (format!("imported note {}", i), ContextLevel::ShortTerm, HashMap::new()))
+            .collect();
+        // One bad item is skipped without failing its chunk
+        items[120].0 = String::new();
+        
+        let (progress_tx, mut progress_rx) = tokio::sync::mpsc::channel(16);
+        let imported = manager.import_contexts(items, Some(progress_tx)).await.unwrap();
+        
+        assert_eq!(imported, 249);
+        assert_eq!(vector_db.point_ids("contexts_shortterm").len(), 249);
+        assert_eq!((embedding.batch_calls(), embedding.single_calls()), (3, 0));
+        
+        let mut updates = Vec::new();
+        while let Some(update) = progress_rx.recv().await {
+            updates.push(update);
+        }
+        assert_eq!(updates.len(), 3);
+        assert!(updates.windows(2).all(|w| w[0].processed < w[1].processed && w[0].errors <= w[1].errors));
+        assert!(updates.iter().all(|u| u.total == 250));
+        assert_eq!(updates.last(), Some(&ImportProgress { processed: 250, total: 250, errors: 1 }));
+    }
+    
+    #[tokio::test]
+    async fn test_moved_context_keeps_its_id() {
+        let (manager, vector_db, _) = test_manager_with_config(Config::default_config().hirag).await;
+        let id = manager.store_context("turned out important", ContextLevel::Immediate, HashMap::new()).await.unwrap();
+        assert_eq!(manager.l1_cache_len(), 1);
+        
+        manager.move_context(id, ContextLevel::LongTerm).await.unwrap();
+        assert!(vector_db.point("contexts_immediate", id).is_none());
+        assert_eq!(vector_db.point("contexts_longterm", id).unwrap().payload.level, ContextLevel::LongTerm);
+        assert_eq!(manager.l1_cache_len(), 0);
+        assert_eq!(manager.get_context(id, None).await.unwrap().unwrap().level, ContextLevel::LongTerm);
+        
+        // Moving within the same level is a no-op
+        manager.move_context(id, ContextLevel::LongTerm).await.unwrap();
+        assert!(vector_db.point("contexts_longterm", id).is_some());
+        
+        manager.move_context(id, ContextLevel::Immediate).await.unwrap();
+        assert!(vector_db.point("contexts_longterm", id).is_none());
+        assert_eq!(manager.l1_cache_len(), 1);
+        assert_eq!(manager.get_context(id, None).await.unwrap().unwrap().level, ContextLevel::Immediate);
+        
+        assert!(matches!(
+            manager.move_context(Uuid::new_v4(), ContextLevel::ShortTerm).await,
+            Err(ContextError::HiRAG(HiRAGError::ContextNotFound(_)))
+        ));
+    }
+    
+    #[tokio::test]
+    async fn test_embedding_model_is_recorded_and_mismatches_reported() {
+        let vector_db = Arc::new(MockVectorStore::new());
+        let embedding = Arc::new(MockEmbeddingProvider::new(8));
+        let manager = |model: &str| {
+            let (vector_db, embedding) = (vector_db.clone(), embedding.clone());
+            let model = model.to_string();
+            async move {
+                let manager = HiRAGManagerV2::new(Config::default_config().hirag, embedding, vector_db)
+                    .await
+                    .unwrap()
+                    .with_embedding_model(model);
+                manager.initialize().await.unwrap();
+                manager
+            }
+        };
+        
+        let old = manager("model-a").await;
+        let id = old.store_context("embedded by model a", ContextLevel::ShortTerm, HashMap::new()).await.unwrap();
+        let stored = vector_db.point("contexts_shortterm", id).unwrap();
+        assert_eq!(stored.payload.metadata.get(EMBEDDING_MODEL_KEY), Some(&"model-a".into()));
+        
+        let response = old.retrieve_context(ContextRequest::new("model a".to_string(), 1000)).await.unwrap();
+        assert!(!response.contexts[0].metadata.contains_key(EMBEDDING_MODEL_KEY));
+        assert_eq!(response.metadata.model_mismatches, 0);
+        
+        let new = manager("model-b").await;
+        let response = new.retrieve_context(ContextRequest::new("model a".to_string(), 1000)).await.unwrap();
+        assert_eq!(response.contexts.len(), 1);
+        assert_eq!(response.metadata.model_mismatches, 1);
+    }
+    
+    #[tokio::test]
+    async fn test_single_level_fast_path_matches_general_path() {
+        let retrieve = |fast_path: bool, fail: bool| async move {
+            let mut config = Config::default_config().hirag;
+            config.single_level_fast_path = fast_path;
+            let (manager, vector_db, _) = test_manager_with_config(config).await;
+            for text in ["deploy with blue green", "deploy on fridays", "rollback plan", "dark mode toggle", "on-call rota"] {
+                manager.store_context(text, ContextLevel::LongTerm, HashMap::new()).await.unwrap();
+            }
+            if fail {
+                vector_db.fail_collection("contexts_longterm");
+            }
+            let request = ContextRequest::new("deploy".to_string(), 15).with_levels(vec![ContextLevel::LongTerm]);
+            manager.retrieve_context(request).await.unwrap()
+        };
+        
+        let fast = retrieve(true, false).await;
+        let general = retrieve(false, false).await;
+        let texts = |response: &ContextResponse| response.contexts.iter().map(|c| c.text.clone()).collect::<Vec<_>>();
+        assert!(!fast.contexts.is_empty());
+        assert_eq!(texts(&fast), texts(&general));
+        assert_eq!(fast.total_tokens, general.total_tokens);
+        assert_eq!(fast.metadata.total_searched, general.metadata.total_searched);
+        assert_eq!(fast.metadata.omitted_count, general.metadata.omitted_count);
+        assert!(fast.metadata.truncated);
+        
+        let fast = retrieve(true, true).await;
+        let general = retrieve(false, true).await;
+        assert_eq!(fast.metadata.failed_levels, vec![ContextLevel::LongTerm]);
+        assert_eq!(fast.metadata.failed_levels, general.metadata.failed_levels);
+    }
+    
+    #[tokio::test]
+    async fn test_streaming_retrieval_sends_each_level_as_it_completes() {
+        let (manager, _, _) = test_manager_with_config(Config::default_config().hirag).await;
+        manager.store_context("immediate note", ContextLevel::Immediate, HashMap::new()).await.unwrap();
+        manager.store_context("short term note", ContextLevel::ShortTerm, HashMap::new()).await.unwrap();
+        
+        let (sender, mut receiver) = mpsc::channel(3);
+        let response = manager
+            .retrieve_context_streaming(ContextRequest::new("note".to_string(), 1000), sender)
+            .await
+            .unwrap();
+        
+        let mut batches = Vec::new();
+        while let Some(batch) = receiver.recv().await {
+            batches.push(batch);
+        }
+        let levels: Vec<Vec<ContextLevel>> = batches.iter().map(|b| b.iter().map(|c| c.level).collect()).collect();
+        assert_eq!(levels, vec![vec![ContextLevel::Immediate], vec![ContextLevel::ShortTerm]]);
+        assert!(batches[0][0].vector.is_none());
+        assert_eq!(response.contexts.len(), 2);
+    }
+    
+    #[tokio::test]
+    async fn test_batch_retrieval_embeds_queries_once() {
+        let (manager, _, embedding) = test_manager_with_config(Config::default_config().hirag).await;
+        manager.store_context("dark mode preference", ContextLevel::ShortTerm, HashMap::new()).await.unwrap();
+        manager.store_context("deploy on fridays", ContextLevel::ShortTerm, HashMap::new()).await.unwrap();
+        let single_calls = embedding.single_calls();
+        
+        let requests = vec![
+            ContextRequest::new("dark mode".to_string(), 1000).with_levels(vec![ContextLevel::ShortTerm]),
+            ContextRequest::new("deploy".to_string(), 1000).with_levels(vec![ContextLevel::ShortTerm]),
+        ];
+        let responses = manager.retrieve_batch(requests).await.unwrap();
+        
+        assert_eq!(responses.len(), 2);
+        assert!(responses.iter().all(|r| !r.contexts.is_empty()));
+        assert_eq!(embedding.batch_calls(), 1);
+        assert_eq!(embedding.single_calls(), single_calls);
+    }
+    
+    #[tokio::test]
+    async fn test_vectors_returned_only_on_request() {
+        let (manager, _, _) = test_manager_with_config(Config::default_config().hirag).await;
+        manager.store_context("immediate note", ContextLevel::Immediate, HashMap::new()).await.unwrap();
+        manager.store_context("short-term note", ContextLevel::ShortTerm, HashMap::new()).await.unwrap();
+        let request = || ContextRequest::new("note".to_string(), 4000)
+            .with_levels(vec![ContextLevel::Immediate, ContextLevel::ShortTerm]);
+        
+        let plain = manager.retrieve_context(request()).await.unwrap();
+        assert!(!plain.contexts.is_empty());
+        assert!(plain.contexts.iter().all(|c| c.vector.is_none()));
+        
+        let with_vectors = manager.retrieve_context(request().with_vectors()).await.unwrap();
+        let levels: HashSet<_> = with_vectors.contexts.iter().map(|c| c.level).collect();
+        assert_eq!(levels.len(), 2);
+        assert!(with_vectors.contexts.iter().all(|c| c.vector.as_ref().is_some_and(|v| v.len() == 1024)));
+    }
+    
+    #[tokio::test]
+    async fn test_concurrent_retrievals_count_every_access() {
+        let (manager, vector_db, _) = test_manager_with_config(Config::default_config().hirag).await;
+        let id = manager.store_context("popular note", ContextLevel::ShortTerm, HashMap::new()).await.unwrap();
+        let request = || ContextRequest::new("note".to_string(), 4000)
+            .with_levels(vec![ContextLevel::ShortTerm]);
+        
+        let responses = futures::future::try_join_all((0..8).map(|_| manager.retrieve_context(request())))
+            .await
+            .unwrap();
+        assert!(responses.iter().all(|r| r.contexts.len() == 1));
+        
+        // Counts are persisted in the background
+        let persisted = || vector_db.point("contexts_shortterm", id).unwrap().payload.access_count;
+        for _ in 0..100 {
+            if persisted() == 8 {
+                break;
+            }
+            tokio::time::sleep(std::time::Duration::from_millis(10)).await;
+        }
+        assert_eq!(persisted(), 8);
+        assert!(manager.accesses.pending.is_empty());
+        
+        // The persisted count is returned and feeds the frequency score
+        let response = manager.retrieve_context(request()).await.unwrap();
+        assert_eq!(response.contexts[0].access_count, 9);
+    }
+    
+    #[tokio::test]
+    async fn test_recording_accesses_keeps_concurrent_payload_changes() {
+        let (manager, vector_db, _) = test_manager_with_config(Config::default_config().hirag).await;
+        let id = manager.store_context("touched note", ContextLevel::ShortTerm, HashMap::new()).await.unwrap();
+        let collection = "contexts_shortterm";
+        
+        // A touch landing between the flush's read and write must survive it
+        let stale = vector_db.point(collection, id).unwrap();
+        manager.touch_context(id).await.unwrap();
+        let touched = vector_db.point(collection, id).unwrap().payload.timestamp;
+        manager.accesses.persist(collection, id, 1).await.unwrap();
+        
+        let point = vector_db.point(collection, id).unwrap();
+        assert_eq!(point.payload.access_count, stale.payload.access_count + 1);
+        assert_eq!(point.payload.timestamp, touched);
+    }
+    
+    #[tokio::test]
+    async fn test_soft_deleted_context_is_hidden_until_restored() {
+        let mut config = Config::default_config().hirag;
+        config.soft_delete = true;
+        let (manager, vector_db, _) = test_manager_with_config(config).await;
+        let id = manager.store_context("deleted note", ContextLevel::Immediate, HashMap::new()).await.unwrap();
+        let request = || ContextRequest::new("note".to_string(), 4000)
+            .with_levels(vec![ContextLevel::Immediate, ContextLevel::ShortTerm]);
+        
+        manager.delete_context(id).await.unwrap();
+        
+        // The tombstoned point is kept but never retrieved
+        let point = vector_db.point("contexts_immediate", id).unwrap();
+        assert!(point.payload.metadata.contains_key(DELETED_AT_KEY));
+        assert!(manager.retrieve_context(request()).await.unwrap().contexts.is_empty());
+        assert!(manager.recent(ContextLevel::Immediate, 10, None).await.unwrap().is_empty());
+        
+        manager.restore_context(id).await.unwrap();
+        let restored = manager.retrieve_context(request()).await.unwrap();
+        assert_eq!(restored.contexts.iter().map(|c| c.id).collect::<Vec<_>>(), vec![id]);
+        assert!(!restored.contexts[0].metadata.contains_key(DELETED_AT_KEY));
+        
+        assert!(manager.restore_context(Uuid::new_v4()).await.is_err());
+    }
+    
+    #[tokio::test]
+    async fn test_soft_delete_and_restore_cover_every_level() {
+        let mut config = Config::default_config().hirag;
+        config.soft_delete = true;
+        let (manager, vector_db, _) = test_manager_with_config(config).await;
+        let metadata = HashMap::from([("topic".to_string(), serde_json::json!("ui"))]);
+        let id = manager.store_context("copied note", ContextLevel::ShortTerm, metadata).await.unwrap();
+        let mut copy = vector_db.point("contexts_shortterm", id).unwrap();
+        copy.payload.level = ContextLevel::LongTerm;
+        vector_db.insert_points("contexts_longterm", vec![copy]).await.unwrap();
+        
+        manager.delete_context(id).await.unwrap();
+        for collection in ["contexts_shortterm", "contexts_longterm"] {
+            let point = vector_db.point(collection, id).unwrap();
+            assert!(point.payload.metadata.contains_key(DELETED_AT_KEY));
+            assert_eq!(point.payload.metadata["topic"], "ui");
+        }
+        
+        manager.restore_context(id).await.unwrap();
+        for collection in ["contexts_shortterm", "contexts_longterm"] {
+            let point = vector_db.point(collection, id).unwrap();
+            assert!(!point.payload.metadata.contains_key(DELETED_AT_KEY), "{}", collection);
+            assert_eq!(point.payload.metadata["topic"], "ui");
+        }
+        
+        // Tombstones can't be forged through metadata
+        let forged = HashMap::from([(DELETED_AT_KEY.to_string(), serde_json::json!(0))]);
+        assert!(manager.store_context("forged", ContextLevel::ShortTerm, forged).await.is_err());
+        
+        // A caller's own `deleted_at` entry is plain metadata
+        let metadata = HashMap::from([("deleted_at".to_string(), serde_json::json!("2024-01-01"))]);
+        let id = manager.store_context("archived note", ContextLevel::ShortTerm, metadata).await.unwrap();
+        let request = ContextRequest::new("archived".to_string(), 4000).with_levels(vec![ContextLevel::ShortTerm]);
+        let found = manager.retrieve_context(request).await.unwrap();
+        assert!(found.contexts.iter().any(|c| c.id == id && c.metadata["deleted_at"] == "2024-01-01"));
+    }
+    
+    #[tokio::test]
+    async fn test_duplicate_ids_resolved_by_policy() {
+        let axis = |x: f32, y: f32| {
+            let mut vector = vec![0.0; 1024];
+            vector[0] = x;
+            vector[1] = y;
+            vector
+        };
+        // The short-term copy always matches the query more closely
+        let duplicate = |level: ContextLevel, timestamp: i64| VectorPoint {
+            vector: if level == ContextLevel::ShortTerm { axis(1.0, 0.0) } else { axis(0.6, 0.8) },
+            ..point(7, level, timestamp)
+        };
+        
+        // Under HighestRelevance the better match wins although it is both
+        // older and in the less durable level
+        for (policy, short_term_timestamp, expected) in [
+            (DuplicatePolicy::HighestLevel, 200, ContextLevel::LongTerm),
+            (DuplicatePolicy::Newest, 200, ContextLevel::ShortTerm),
+            (DuplicatePolicy::HighestRelevance, 50, ContextLevel::ShortTerm),
+        ] {
+            let mut config = Config::default_config().hirag;
+            config.duplicate_policy = policy;
+            let (manager, vector_db, embedding) = test_manager_with_config(config).await;
+            embedding.set_vector("copy", axis(1.0, 0.0));
+            vector_db
+                .insert_points("contexts_shortterm", vec![duplicate(ContextLevel::ShortTerm, short_term_timestamp)])
+                .await
+                .unwrap();
+            vector_db.insert_points("contexts_longterm", vec![duplicate(ContextLevel::LongTerm, 100)]).await.unwrap();
+            
+            let response = manager
+                .retrieve_context(ContextRequest::new("copy".to_string(), 4000))
+                .await
+                .unwrap();
+            let copies: Vec<_> = response.contexts.iter().filter(|c| c.id == Uuid::from_u128(7)).collect();
+            assert_eq!(copies.len(), 1);
+            assert_eq!(copies[0].level, expected, "policy {:?}", policy);
+        }
+    }
+    
+    #[tokio::test]
+    async fn test_oversized_filters_are_rejected() {
+        let mut config = Config::default_config().hirag;
+        config.max_filter_conditions = 4;
+        let (manager, _, _) = test_manager_with_config(config).await;
+        manager.store_context("tagged note", ContextLevel::ShortTerm, HashMap::new()).await.unwrap();
+        
+        let tag = |i: usize| Condition::Match { key: "tag".to_string(), value: format!("t{}", i).into() };
+        let grouped = Filter::new().should(tag(1)).should(tag(2));
+        let request = |filter: Filter| ContextRequest { filters: Some(filter), ..ContextRequest::new("note".to_string(), 1000) };
+        
+        // Conditions inside groups count individually
+        let allowed = Filter::new().must_not(tag(0)).must(Condition::Group { filter: grouped.clone() }).should(tag(3));
+        assert_eq!(allowed.condition_count(), 4);
+        assert!(manager.retrieve_context(request(allowed.clone())).await.is_ok());
+        
+        let oversized = allowed.must(tag(4));
+        assert!(matches!(
+            manager.retrieve_context(request(oversized)).await,
+            Err(ContextError::Validation(crate::middleware::ValidationError::TooManyFilterConditions { count: 5, max: 4 }))
+        ));
+    }
+    
+    #[tokio::test]
+    async fn test_acl_hides_contexts_from_other_agents() {
+        let mut config = Config::default_config().hirag;
+        config.enforce_acl = true;
+        let (manager, vector_db, _) = test_manager_with_config(config).await;
+        
+        let acl = |agents: &[&str]| HashMap::from([("acl".to_string(), serde_json::json!(agents))]);
+        let secret_l2 = manager.store_context("alice's plan", ContextLevel::ShortTerm, acl(&["alice"])).await.unwrap();
+        let secret_l1 = manager.store_context("alice's note", ContextLevel::Immediate, acl(&["alice", "carol"])).await.unwrap();
+        let public = manager.store_context("shared plan", ContextLevel::ShortTerm, HashMap::new()).await.unwrap();
+        
+        let visible_to = |agent: &str| {
+            let manager = manager.clone();
+            let request = ContextRequest::new("plan".to_string(), 4000).with_agent(agent.to_string());
+            async move {
+                manager.retrieve_context(request).await.unwrap()
+                    .contexts.into_iter().map(|c| c.id).collect::<HashSet<_>>()
+            }
+        };
+        
+        assert_eq!(visible_to("bob").await, HashSet::from([public]));
+        assert_eq!(visible_to("alice").await, HashSet::from([secret_l2, secret_l1, public]));
+        
+        // Lookups by ID apply the same ACL
+        for id in [secret_l2, secret_l1] {
+            assert!(manager.get_context(id, Some("alice")).await.unwrap().is_some());
+            assert!(manager.get_context(id, Some("bob")).await.unwrap().is_none());
+            assert!(manager.get_context(id, None).await.unwrap().is_none());
+        }
+        assert!(manager.get_context(public, Some("bob")).await.unwrap().is_some());
+        
+        // Listing by age applies it too, before the limit
+        let mut newest = vector_db.point("contexts_shortterm", secret_l2).unwrap();
+        newest.payload.timestamp += 60;
+        vector_db.insert_points("contexts_shortterm", vec![newest]).await.unwrap();
+        let recent = |agent: &'static str| {
+            let manager = manager.clone();
+            async move {
+                manager.recent(ContextLevel::ShortTerm, 1, Some(agent)).await.unwrap()
+                    .into_iter().map(|c| c.id).collect::<Vec<_>>()
+            }
+        };
+        assert_eq!(recent("bob").await, vec![public]);
+        assert_eq!(manager.recent(ContextLevel::ShortTerm, 2, Some("alice")).await.unwrap().len(), 2);
+    }
+    
+    #[tokio::test]
+    async fn test_get_context_hides_expired_contexts() {
+        let (manager, _, _) = test_manager_with_config(Config::default_config().hirag).await;
+        let expired = HashMap::from([(EXPIRES_AT_KEY.to_string(), serde_json::json!(Utc::now().timestamp() - 1))]);
+        
+        for level in [ContextLevel::Immediate, ContextLevel::ShortTerm] {
+            let id = manager.store_context("stale", level, expired.clone()).await.unwrap();
+            assert!(manager.get_context(id, None).await.unwrap().is_none(), "{:?}", level);
+            assert!(manager.recent(level, 10, None).await.unwrap().is_empty(), "{:?}", level);
+        }
+        
+        // Dates that search can't compare are rejected up front
+        let dated = HashMap::from([(EXPIRES_AT_KEY.to_string(), serde_json::json!("2030-01-01"))]);
+        assert!(manager.store_context("dated", ContextLevel::ShortTerm, dated).await.is_err());
+    }
+    
+    #[tokio::test]
+    async fn test_explanation_scores_every_candidate() {
+        let (manager, vector_db, _) = test_manager_with_config(Config::default_config().hirag).await;
+        for i in 0..5 {
+            manager
+                .store_context(&format!("deployment note number {} about the rollout", i), ContextLevel::ShortTerm, HashMap::new())
+                .await
+                .unwrap();
+        }
+        manager.store_context("latest deployment status", ContextLevel::Immediate, HashMap::new()).await.unwrap();
+        
+        let request = ContextRequest::new("deployment".to_string(), 40);
+        let explanation = manager.explain_retrieval(request.clone()).await.unwrap();
+        assert_eq!(explanation.candidates.len(), 6);
+        for candidate in &explanation.candidates {
+            let score = candidate.score;
+            assert!((score.similarity + score.recency + score.level + score.frequency - score.total).abs() < 1e-6);
+            assert_eq!(candidate.included, candidate.excluded_reason.is_none());
+        }
+        assert!(explanation.candidates.windows(2).all(|w| w[0].score.total >= w[1].score.total));
+        assert!(explanation.candidates.iter().any(|c| !c.included));
+        
+        // The explanation matches what a real retrieval returns
+        let included: HashSet<_> = explanation.candidates.iter().filter(|c| c.included).map(|c| c.id).collect();
+        let response = manager.retrieve_context(request.clone()).await.unwrap();
+        assert_eq!(response.contexts.iter().map(|c| c.id).collect::<HashSet<_>>(), included);
+        assert!(explanation.failed_levels.is_empty());
+        
+        // A failing level is reported instead of failing the explanation
+        vector_db.fail_collection("contexts_longterm");
+        let explanation = manager.explain_retrieval(request).await.unwrap();
+        assert_eq!(explanation.candidates.len(), 6);
+        assert_eq!(explanation.failed_levels, vec![ContextLevel::LongTerm]);
+    }
+    
+    #[tokio::test]
+    async fn test_explanation_follows_mmr_order() {
+        let mut config = Config::default_config().hirag;
+        config.mmr_lambda = Some(0.5);
+        let (manager, _, embedding) = test_manager_with_config(config).await;
+        let vector = |x: f32, y: f32| {
+            let mut vector = vec![0.0; 1024];
+            vector[0] = x;
+            vector[1] = y;
+            vector
+        };
+        embedding.set_vector("query", vector(1.0, 0.3));
+        let mut ids = Vec::new();
+        for (text, v) in [("note one", vector(1.0, 0.0)), ("note two", vector(1.0, 0.01)), ("note six", vector(0.6, 0.8))] {
+            embedding.set_vector(text, v);
+            ids.push(manager.store_context(text, ContextLevel::ShortTerm, HashMap::new()).await.unwrap());
+        }
+        
+        // MMR moves the distinct note ahead of the near-duplicate one
+        let request = ContextRequest::new("query".to_string(), 4000);
+        let explanation = manager.explain_retrieval(request.clone()).await.unwrap();
+        let explained: Vec<_> = explanation.candidates.iter().filter(|c| c.included).map(|c| c.id).collect();
+        assert_eq!(explained, vec![ids[1], ids[2], ids[0]]);
+        assert!(explanation.candidates[1].score.total < explanation.candidates[2].score.total);
+        let response = manager.retrieve_context(request).await.unwrap();
+        assert_eq!(response.contexts.iter().map(|c| c.id).collect::<Vec<_>>(), explained);
+    }
+    
+    #[tokio::test]
+    async fn test_session_scoped_retrieval_excludes_other_sessions() {
+        let (manager, _, _) = test_manager_with_config(Config::default_config().hirag).await;
+        
+        let store = |text: &'static str, level: ContextLevel, session: &'static str| {
+            let manager = manager.clone();
+            async move {
+                let options = StoreOptions::default().with_session_id(session);
+                manager.store_context_with(text, level, HashMap::new(), options).await.unwrap()
+            }
+        };
+        let ours_l1 = store("our note", ContextLevel::Immediate, "a").await;
+        let ours_l2 = store("our plan", ContextLevel::ShortTerm, "a").await;
+        store("their note", ContextLevel::Immediate, "b").await;
+        store("their plan", ContextLevel::ShortTerm, "b").await;
+        
+        let request = ContextRequest::new("plan".to_string(), 4000).with_session("a".to_string());
+        let response = manager.retrieve_context(request).await.unwrap();
+        let ids: HashSet<_> = response.contexts.iter().map(|c| c.id).collect();
+        assert_eq!(ids, HashSet::from([ours_l1, ours_l2]));
+        assert!(response.contexts.iter().all(|c| c.session_id.as_deref() == Some("a")));
+        
+        let unscoped = manager.retrieve_context(ContextRequest::new("plan".to_string(), 4000)).await.unwrap();
+        assert_eq!(unscoped.contexts.len(), 4);
+    }
+}
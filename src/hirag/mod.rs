@@ -4,22 +4,54 @@ pub mod manager;
 pub mod manager_v2;
 pub mod retriever;
 pub mod ranker;
+pub mod similarity;
 pub mod models;
 pub mod token_estimator;
 pub mod background;
+pub mod l1_cache;
 
 pub use manager::HiRAGManager;
 pub use manager_v2::HiRAGManagerV2;
-pub use models::{Context, ContextRequest, ContextResponse, Priority};
-pub use ranker::ContextRanker;
+pub use models::{CandidateExplanation, Context, ContextRequest, ContextResponse, ExclusionReason, ImportProgress, Priority, RetrievalExplanation, SortOrder, StoreOptions};
+pub use l1_cache::L1Cache;
+pub use ranker::{ContextRanker, ScoreBreakdown};
 pub use token_estimator::TokenEstimator;
 
 use async_trait::async_trait;
-use crate::error::Result;
-use crate::vector_db::ContextLevel;
+use crate::error::{ContextError, HiRAGError, Result, VectorDbError};
+use crate::middleware::InputValidator;
+use crate::vector_db::{ContextLevel, VectorPoint, VectorStore};
 use std::collections::HashMap;
+use tokio::sync::mpsc;
+use tracing::warn;
 use uuid::Uuid;
 
+/// Prefix of metadata keys the manager keeps for itself; they are rejected in
+/// caller metadata and left out of returned contexts
+pub const INTERNAL_KEY_PREFIX: &str = "_";
+
+/// Metadata flag on contexts stored with a placeholder vector that still need
+/// to be embedded
+pub const NEEDS_EMBEDDING_KEY: &str = "_needs_embedding";
+
+/// Metadata tag naming the model a context was embedded with
+pub const EMBEDDING_MODEL_KEY: &str = "_embedding_model";
+
+/// Metadata entry holding a context's previous versions when history is enabled
+pub const HISTORY_KEY: &str = "_history";
+
+/// Metadata entry holding the time a soft-deleted context was deleted
+pub const DELETED_AT_KEY: &str = "_deleted_at";
+
+/// Metadata entry holding the time after which a context is no longer retrieved
+pub const EXPIRES_AT_KEY: &str = "expires_at";
+
+/// Payload key holding the session a context was stored under
+pub const SESSION_ID_KEY: &str = "session_id";
+
+/// Number of items `import_contexts` stores per batch
+const IMPORT_CHUNK_SIZE: usize = 100;
+
 /// Trait for context management operations
 #[async_trait]
 pub trait ContextManager: Send + Sync {
@@ -29,11 +61,147 @@ pub trait ContextManager: Send + Sync {
         text: &str,
         level: ContextLevel,
         metadata: HashMap<String, serde_json::Value>,
+    ) -> Result<Uuid> {
+        self.store_context_with(text, level, metadata, StoreOptions::default()).await
+    }
+    
+    /// Store new context owned by the agent and session in `options`
+    async fn store_context_with(
+        &self,
+        text: &str,
+        level: ContextLevel,
+        metadata: HashMap<String, serde_json::Value>,
+        options: StoreOptions,
     ) -> Result<Uuid>;
     
+    /// Store several contexts, returning their IDs in input order
+    async fn store_batch(
+        &self,
+        items: Vec<(String, ContextLevel, HashMap<String, serde_json::Value>)>,
+    ) -> Result<Vec<Uuid>> {
+        self.store_batch_with(items, StoreOptions::default()).await
+    }
+    
+    /// Store several contexts owned by the agent and session in `options`,
+    /// returning their IDs in input order
+    ///
+    /// Implementations should embed all texts in a single provider call and
+    /// store either every context or, on error, none of them. The default
+    /// stores each context in turn and keeps those stored before an error.
+    async fn store_batch_with(
+        &self,
+        items: Vec<(String, ContextLevel, HashMap<String, serde_json::Value>)>,
+        options: StoreOptions,
+    ) -> Result<Vec<Uuid>> {
+        let mut ids = Vec::with_capacity(items.len());
+        for (text, level, metadata) in items {
+            ids.push(self.store_context_with(&text, level, metadata, options.clone()).await?);
+        }
+        Ok(ids)
+    }
+    
+    /// Import a large number of contexts, returning how many were stored
+    ///
+    /// Items are stored in chunks via `store_batch`. Invalid items are
+    /// counted as errors and skipped up front so a single bad item doesn't
+    /// sink its neighbours; a chunk that still fails counts every item in it
+    /// as an error. After each chunk the running totals are sent to
+    /// `progress`, if given.
+    async fn import_contexts(
+        &self,
+        items: Vec<(String, ContextLevel, HashMap<String, serde_json::Value>)>,
+        progress: Option<mpsc::Sender<ImportProgress>>,
+    ) -> Result<usize> {
+        let total = items.len();
+        let mut processed = 0;
+        let mut errors = 0;
+        
+        let mut items = items.into_iter().peekable();
+        while items.peek().is_some() {
+            let chunk: Vec<_> = items.by_ref().take(IMPORT_CHUNK_SIZE).collect();
+            processed += chunk.len();
+            
+            let valid: Vec<_> = chunk
+                .into_iter()
+                .filter(|(text, _, metadata)| {
+                    let validation = InputValidator::validate_text(text)
+                        .and_then(|_| metadata.keys().try_for_each(|key| InputValidator::validate_metadata_key(key)))
+                        .and_then(|_| InputValidator::validate_expires_at(metadata));
+                    if let Err(e) = &validation {
+                        warn!("Skipping invalid context in import: {}", e);
+                        errors += 1;
+                    }
+                    validation.is_ok()
+                })
+                .collect();
+            
+            let stored = valid.len();
+            if let Err(e) = self.store_batch(valid).await {
+                warn!("Failed to import batch of {} contexts: {}", stored, e);
+                errors += stored;
+            }
+            
+            if let Some(progress) = &progress {
+                // A receiver that went away just stops receiving updates
+                let _ = progress.send(ImportProgress { processed, total, errors }).await;
+            }
+        }
+        
+        Ok(total - errors)
+    }
+    
     /// Retrieve relevant contexts
     async fn retrieve_context(&self, request: ContextRequest) -> Result<ContextResponse>;
     
+    /// Retrieve relevant contexts for several queries at once
+    ///
+    /// Implementations should embed all queries in a single provider call.
+    /// The default retrieves each request in turn.
+    async fn retrieve_batch(&self, requests: Vec<ContextRequest>) -> Result<Vec<ContextResponse>> {
+        let mut responses = Vec::with_capacity(requests.len());
+        for request in requests {
+            responses.push(self.retrieve_context(request).await?);
+        }
+        Ok(responses)
+    }
+    
+    /// Retrieve relevant contexts, sending each level's candidates to
+    /// `candidates` as soon as that level has been searched
+    ///
+    /// Candidates are sent before cross-level deduplication, ranking and the
+    /// overall token budget, so the returned response may leave some of them
+    /// out. The default sends the response's contexts once retrieval is done.
+    async fn retrieve_context_streaming(
+        &self,
+        request: ContextRequest,
+        candidates: mpsc::Sender<Vec<Context>>,
+    ) -> Result<ContextResponse> {
+        let response = self.retrieve_context(request).await?;
+        // A receiver that went away just stops receiving candidates
+        if !response.contexts.is_empty() {
+            let _ = candidates.send(response.contexts.clone()).await;
+        }
+        Ok(response)
+    }
+    
+    /// The `limit` most recently stored or updated contexts in `level`
+    /// visible to `agent_id`, newest first
+    ///
+    /// No query is embedded, so every context has a relevance score of 0.
+    /// Managers that cannot list contexts by age return an error.
+    async fn recent(&self, _level: ContextLevel, _limit: usize, _agent_id: Option<&str>) -> Result<Vec<Context>> {
+        Err(HiRAGError::RetrievalError("Listing recent contexts is not supported".to_string()).into())
+    }
+    
+    /// Fetch a single context by ID on behalf of `agent_id`
+    ///
+    /// Returns `None` if the context doesn't exist or is hidden from the agent
+    /// the way retrieval would hide it (ACL, expiry, soft delete). Managers
+    /// that cannot look contexts up by ID return an error.
+    async fn get_context(&self, _id: Uuid, _agent_id: Option<&str>) -> Result<Option<Context>> {
+        Err(HiRAGError::RetrievalError("Fetching contexts by ID is not supported".to_string()).into())
+    }
+    
     /// Update context metadata
     async fn update_context(
         &self,
@@ -41,9 +209,73 @@ pub trait ContextManager: Send + Sync {
         metadata: HashMap<String, serde_json::Value>,
     ) -> Result<()>;
     
+    /// Previous versions of a context, oldest first
+    ///
+    /// Managers that do not record history return an empty list.
+    async fn get_context_history(&self, _id: Uuid) -> Result<Vec<Context>> {
+        Ok(Vec::new())
+    }
+    
+    /// Reset a context's timestamp to now without changing its content,
+    /// extending its GC lifetime
+    async fn touch_context(&self, id: Uuid) -> Result<()>;
+    
     /// Delete context
     async fn delete_context(&self, id: Uuid) -> Result<()>;
     
+    /// Undo a soft delete during the grace period
+    ///
+    /// Managers that delete permanently have nothing to restore.
+    async fn restore_context(&self, id: Uuid) -> Result<()> {
+        Err(HiRAGError::ContextNotFound(id.to_string()).into())
+    }
+    
+    /// Move a context to another level, keeping its ID
+    ///
+    /// Moving a context to the level it is already in does nothing. Managers
+    /// that cannot move contexts return an error.
+    async fn move_context(&self, _id: Uuid, _to: ContextLevel) -> Result<()> {
+        Err(HiRAGError::StorageError("Moving contexts between levels is not supported".to_string()).into())
+    }
+    
     /// Clear contexts by level
     async fn clear_level(&self, level: ContextLevel) -> Result<()>;
-}
\ No newline at end of file
+    
+    /// Run a retrieval and report how every candidate was scored and why it
+    /// was returned or left out
+    ///
+    /// Managers that cannot trace their retrieval pipeline return an error.
+    async fn explain_retrieval(&self, _request: ContextRequest) -> Result<RetrievalExplanation> {
+        Err(HiRAGError::RetrievalError("Retrieval explanations are not supported".to_string()).into())
+    }
+    
+    /// Re-apply the L1 size limit and expiry, evicting as needed
+    ///
+    /// Returns how many contexts were evicted. Managers without an L1 cache
+    /// return an error.
+    async fn compact_l1(&self) -> Result<usize> {
+        Err(HiRAGError::RetrievalError("L1 compaction is not supported".to_string()).into())
+    }
+}
+
+/// Drop the manager's internal entries from metadata handed back to callers
+pub(crate) fn strip_internal_metadata(metadata: &mut HashMap<String, serde_json::Value>) {
+    metadata.retain(|key, _| !key.starts_with(INTERNAL_KEY_PREFIX));
+}
+
+/// Whether a context with `metadata` has passed its `expires_at` time at `now`
+pub(crate) fn is_expired(metadata: &HashMap<String, serde_json::Value>, now: f64) -> bool {
+    metadata.get(EXPIRES_AT_KEY).and_then(|t| t.as_f64()).is_some_and(|expires_at| expires_at <= now)
+}
+
+/// Look `id` up in one of the collections a context may be stored in
+///
+/// A collection that doesn't exist (e.g. a level that was never initialized)
+/// can't hold the context, so it is reported as `Ok(None)` like a missing id;
+/// any other failure is returned.
+pub(crate) async fn find_point(vector_db: &dyn VectorStore, collection: &str, id: Uuid) -> Result<Option<VectorPoint>> {
+    match vector_db.get_point(collection, id).await {
+        Err(ContextError::VectorDb(VectorDbError::CollectionNotFound(_))) => Ok(None),
+        result => result,
+    }
+}
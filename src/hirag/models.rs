@@ -3,10 +3,13 @@
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 use uuid::Uuid;
+use crate::config::Distance;
 use crate::vector_db::{ContextLevel, Filter};
+use super::ranker::ScoreBreakdown;
 
 /// Context item with metadata
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
 pub struct Context {
     /// Unique identifier
     pub id: Uuid,
@@ -26,8 +29,45 @@ pub struct Context {
     /// Timestamp
     pub timestamp: i64,
     
+    /// Number of times the context has been returned by a retrieval
+    #[serde(default)]
+    pub access_count: u64,
+    
+    /// Session the context was stored under
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub session_id: Option<String>,
+    
     /// Additional metadata
     pub metadata: HashMap<String, serde_json::Value>,
+    
+    /// Embedding vector, present only when the request set `include_vectors`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub vector: Option<Vec<f32>>,
+}
+
+/// Ownership recorded with a newly stored context
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct StoreOptions {
+    /// Agent the context belongs to, falling back to the `agent_id`
+    /// metadata value and then `"default"`
+    #[serde(default)]
+    pub agent_id: Option<String>,
+    
+    /// Session the context belongs to
+    #[serde(default)]
+    pub session_id: Option<String>,
+}
+
+impl StoreOptions {
+    pub fn with_agent_id(mut self, agent_id: impl Into<String>) -> Self {
+        self.agent_id = Some(agent_id.into());
+        self
+    }
+    
+    pub fn with_session_id(mut self, session_id: impl Into<String>) -> Self {
+        self.session_id = Some(session_id.into());
+        self
+    }
 }
 
 /// Request for context retrieval
@@ -52,10 +92,59 @@ pub struct ContextRequest {
     
     /// Session context
     pub session_id: Option<String>,
+    
+    /// Agent making the request, used for per-agent quotas
+    #[serde(default)]
+    pub agent_id: Option<String>,
+    
+    /// Order of the returned contexts
+    #[serde(default)]
+    pub sort_order: SortOrder,
+    
+    /// Re-score vector-store candidates with this metric instead of the
+    /// collection's own (L1 cache hits keep their original scores)
+    ///
+    /// Dot products are divided by the norms of the query and the longest
+    /// candidate, so they fall within [-1, 1] like cosine scores.
+    #[serde(default)]
+    pub rescore_metric: Option<Distance>,
+    
+    /// Return each context's embedding vector
+    #[serde(default)]
+    pub include_vectors: bool,
+}
+
+/// Ordering applied to retrieved contexts after selection
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub enum SortOrder {
+    /// Ranker order (highest combined score first)
+    #[default]
+    Relevance,
+    /// Chronological order (oldest first), e.g. to replay a conversation
+    Recency,
+    /// Grouped by level (Immediate, ShortTerm, LongTerm), relevance within a level
+    Level,
+}
+
+impl SortOrder {
+    /// Reorder contexts that are already in ranker order
+    pub fn apply(&self, contexts: &mut [Context]) {
+        match self {
+            SortOrder::Relevance => {}
+            SortOrder::Recency => contexts.sort_by_key(|c| c.timestamp),
+            SortOrder::Level => contexts.sort_by_key(|c| match c.level {
+                ContextLevel::Immediate => 0,
+                ContextLevel::ShortTerm => 1,
+                ContextLevel::LongTerm => 2,
+            }),
+        }
+    }
 }
 
 /// Priority levels for context retrieval
 #[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Default)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
 pub enum Priority {
     Low,
     #[default]
@@ -66,8 +155,9 @@ pub enum Priority {
 
 /// Response containing retrieved contexts
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
 pub struct ContextResponse {
-    /// Retrieved contexts (ordered by relevance)
+    /// Retrieved contexts (ordered by the requested sort order)
     pub contexts: Vec<Context>,
     
     /// Total token count
@@ -82,6 +172,7 @@ pub struct ContextResponse {
 
 /// Metadata about context retrieval
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
 pub struct ResponseMetadata {
     /// Number of contexts from each level
     pub level_distribution: HashMap<ContextLevel, usize>,
@@ -94,6 +185,79 @@ pub struct ResponseMetadata {
     
     /// Total contexts searched
     pub total_searched: usize,
+    
+    /// Token budget actually used when the requested one was clamped by the server
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub clamped_max_tokens: Option<usize>,
+    
+    /// Whether candidates were left out because they did not fit the token budget
+    #[serde(default)]
+    pub truncated: bool,
+    
+    /// Number of candidates left out for the token budget
+    #[serde(default)]
+    pub omitted_count: usize,
+    
+    /// Levels whose retrieval failed, leaving the response incomplete
+    #[serde(default)]
+    pub failed_levels: Vec<ContextLevel>,
+    
+    /// Returned contexts embedded with a different model than the query
+    #[serde(default)]
+    pub model_mismatches: usize,
+}
+
+/// Why a retrieval candidate was left out of the response
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ExclusionReason {
+    /// Did not fit its level's share of the token budget
+    LevelBudget,
+    /// Another copy of the same context was kept
+    Duplicate,
+    /// Did not fit the overall token budget after ranking
+    TokenBudget,
+}
+
+/// How a single candidate was scored and whether it was returned
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CandidateExplanation {
+    pub id: Uuid,
+    pub level: ContextLevel,
+    pub token_count: usize,
+    
+    /// Similarity to the query before ranking (1.0 for L1 cache entries)
+    pub similarity: f32,
+    
+    /// Contribution of each ranking factor to the final score
+    pub score: ScoreBreakdown,
+    
+    pub included: bool,
+    
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub excluded_reason: Option<ExclusionReason>,
+}
+
+/// Every candidate considered for a retrieval, in ranked order
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RetrievalExplanation {
+    pub query: String,
+    pub max_tokens: usize,
+    pub candidates: Vec<CandidateExplanation>,
+    
+    /// Levels whose retrieval failed, leaving their candidates out
+    #[serde(default)]
+    pub failed_levels: Vec<ContextLevel>,
+}
+
+/// Progress of a bulk import, reported after each chunk
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ImportProgress {
+    /// Items handled so far, whether stored or failed
+    pub processed: usize,
+    pub total: usize,
+    /// Items that could not be stored
+    pub errors: usize,
 }
 
 /// Statistics about HiRAG system
@@ -133,7 +297,10 @@ impl Context {
             relevance_score: 0.0,
             token_count,
             timestamp,
+            access_count: 0,
+            session_id: None,
             metadata: HashMap::new(),
+            vector: None,
         }
     }
 }
@@ -147,6 +314,10 @@ impl ContextRequest {
             filters: None,
             priority: Priority::Normal,
             session_id: None,
+            agent_id: None,
+            sort_order: SortOrder::default(),
+            rescore_metric: None,
+            include_vectors: false,
         }
     }
     
@@ -159,6 +330,26 @@ impl ContextRequest {
         self.session_id = Some(session_id);
         self
     }
+    
+    pub fn with_agent(mut self, agent_id: String) -> Self {
+        self.agent_id = Some(agent_id);
+        self
+    }
+    
+    pub fn with_sort_order(mut self, sort_order: SortOrder) -> Self {
+        self.sort_order = sort_order;
+        self
+    }
+    
+    pub fn with_rescore_metric(mut self, metric: Distance) -> Self {
+        self.rescore_metric = Some(metric);
+        self
+    }
+    
+    pub fn with_vectors(mut self) -> Self {
+        self.include_vectors = true;
+        self
+    }
 }
 /// Search query for API endpoints
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -188,3 +379,45 @@ pub struct ContextFilter {
     /// Filter by creation date (after)
     pub created_after: Option<chrono::DateTime<chrono::Utc>>,
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    /// Fixed candidate set in ranker order
+    fn ranked_candidates() -> Vec<Context> {
+        vec![
+            Context::new(Uuid::from_u128(1), "a".to_string(), ContextLevel::LongTerm, 200, 1),
+            Context::new(Uuid::from_u128(2), "b".to_string(), ContextLevel::Immediate, 300, 1),
+            Context::new(Uuid::from_u128(3), "c".to_string(), ContextLevel::ShortTerm, 100, 1),
+            Context::new(Uuid::from_u128(4), "d".to_string(), ContextLevel::Immediate, 50, 1),
+        ]
+    }
+    
+    fn order_after(sort_order: SortOrder) -> Vec<String> {
+        let mut contexts = ranked_candidates();
+        sort_order.apply(&mut contexts);
+        contexts.into_iter().map(|c| c.text).collect()
+    }
+    
+    #[test]
+    fn test_relevance_order_keeps_ranker_order() {
+        assert_eq!(order_after(SortOrder::Relevance), vec!["a", "b", "c", "d"]);
+    }
+    
+    #[test]
+    fn test_recency_order_is_chronological() {
+        assert_eq!(order_after(SortOrder::Recency), vec!["d", "c", "a", "b"]);
+    }
+    
+    #[test]
+    fn test_level_order_groups_by_level_keeping_relevance() {
+        assert_eq!(order_after(SortOrder::Level), vec!["b", "d", "c", "a"]);
+    }
+    
+    #[test]
+    fn test_sort_order_defaults_to_relevance() {
+        let request: ContextRequest = serde_json::from_str(r#"{"query": "q", "max_tokens": 10, "filters": null, "session_id": null}"#).unwrap();
+        assert_eq!(request.sort_order, SortOrder::Relevance);
+    }
+}
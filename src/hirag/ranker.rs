@@ -1,57 +1,178 @@
 //! Context ranking and scoring
 
 use super::models::Context;
+use super::similarity;
+use crate::clock::{system_clock, Clock};
 use crate::config::RankingWeights;
-use chrono::Utc;
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+
+/// Recency half-life used unless one is configured
+///
+/// One day times ln 2, so the default decays like the earlier
+/// `exp(-age_hours / 24)` score and existing rankings stay the same.
+const DEFAULT_RECENCY_HALF_LIFE_SECS: u64 = 59888;
+
+/// Weighted contribution of each ranking factor to a context's score
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct ScoreBreakdown {
+    pub similarity: f32,
+    pub recency: f32,
+    pub level: f32,
+    pub frequency: f32,
+    /// Sum of the contributions
+    pub total: f32,
+}
 
 /// Context ranker for scoring and ordering
 pub struct ContextRanker {
     weights: RankingWeights,
+    recency_half_life_secs: u64,
+    clock: Arc<dyn Clock>,
 }
 
 impl ContextRanker {
     pub fn new(weights: RankingWeights) -> Self {
-        Self { weights }
+        Self {
+            weights,
+            recency_half_life_secs: DEFAULT_RECENCY_HALF_LIFE_SECS,
+            clock: system_clock(),
+        }
+    }
+    
+    /// Halve a context's recency score every `secs` seconds of age
+    pub fn with_recency_half_life(mut self, secs: u64) -> Self {
+        self.recency_half_life_secs = secs.max(1);
+        self
+    }
+    
+    /// Use a specific clock for the time contexts are aged against
+    pub fn with_clock(mut self, clock: Arc<dyn Clock>) -> Self {
+        self.clock = clock;
+        self
+    }
+    
+    /// Current time in seconds, as ranking sees it
+    pub fn current_time(&self) -> i64 {
+        self.clock.now_utc().timestamp()
     }
     
     /// Rank contexts based on multiple factors
-    pub fn rank_contexts(&self, mut contexts: Vec<Context>) -> Vec<Context> {
-        let current_time = Utc::now().timestamp();
+    pub fn rank_contexts(&self, contexts: Vec<Context>) -> Vec<Context> {
+        self.rank_contexts_with_scores(contexts)
+            .into_iter()
+            .map(|(context, _)| context)
+            .collect()
+    }
+    
+    /// Rank contexts like `rank_contexts`, keeping each one's score breakdown
+    pub fn rank_contexts_with_scores(&self, contexts: Vec<Context>) -> Vec<(Context, ScoreBreakdown)> {
+        let current_time = self.current_time();
         
-        for context in &mut contexts {
-            context.relevance_score = self.calculate_score(context, current_time);
-        }
+        let mut scored: Vec<_> = contexts
+            .into_iter()
+            .map(|mut context| {
+                let breakdown = self.score_breakdown(&context, current_time);
+                context.relevance_score = breakdown.total;
+                (context, breakdown)
+            })
+            .collect();
         
         // Sort by relevance score (descending)
-        contexts.sort_by(|a, b| {
-            b.relevance_score.partial_cmp(&a.relevance_score).unwrap_or(std::cmp::Ordering::Equal)
+        scored.sort_by(|a, b| {
+            b.1.total.partial_cmp(&a.1.total).unwrap_or(std::cmp::Ordering::Equal)
         });
         
-        contexts
+        scored
+    }
+    
+    /// Reorder ranked contexts by maximal marginal relevance
+    ///
+    /// Each pick maximizes `lambda * relevance - (1 - lambda) * redundancy`,
+    /// where relevance is the ranked score and redundancy is the highest
+    /// cosine similarity to an already picked context. Picking stops once no
+    /// candidate fits in what is left of `max_tokens`; those left over keep
+    /// their ranked order after the picks, followed by contexts without a
+    /// vector, which can't be compared.
+    pub fn apply_mmr(&self, contexts: Vec<Context>, lambda: f32, max_tokens: usize) -> Vec<Context> {
+        let (embedded, unembedded): (Vec<_>, Vec<_>) =
+            contexts.into_iter().partition(|c| c.vector.is_some());
+        
+        let mut remaining: Vec<Option<Context>> = embedded.into_iter().map(Some).collect();
+        // Each candidate's redundancy, updated against every new pick only
+        let mut redundancy = vec![0.0_f32; remaining.len()];
+        let mut budget = max_tokens;
+        let mut selected: Vec<Context> = Vec::with_capacity(remaining.len() + unembedded.len());
+        loop {
+            // Budget only shrinks, so a candidate that doesn't fit never will
+            let best = remaining
+                .iter()
+                .zip(&redundancy)
+                .enumerate()
+                .filter_map(|(i, (candidate, redundancy))| {
+                    let candidate = candidate.as_ref().filter(|c| c.token_count <= budget)?;
+                    Some((i, lambda * candidate.relevance_score - (1.0 - lambda) * redundancy))
+                })
+                .fold(None, |best, (i, score)| match best {
+                    Some((_, best_score)) if best_score >= score => best,
+                    _ => Some((i, score)),
+                });
+            let Some(pick) = best.and_then(|(best, _)| remaining[best].take()) else {
+                break;
+            };
+            budget -= pick.token_count;
+            let picked = pick.vector.as_deref().unwrap_or_default();
+            for (candidate, redundancy) in remaining.iter().zip(&mut redundancy) {
+                let vector = candidate.as_ref()
+                    .filter(|c| c.token_count <= budget)
+                    .and_then(|c| c.vector.as_deref());
+                if let Some(Ok(similarity)) = vector.map(|v| similarity::cosine(v, picked)) {
+                    *redundancy = redundancy.max(similarity);
+                }
+            }
+            selected.push(pick);
+        }
+        
+        selected.extend(remaining.into_iter().flatten());
+        selected.extend(unembedded);
+        selected
     }
     
     /// Calculate composite score for a context
     pub fn calculate_score(&self, context: &Context, current_time: i64) -> f32 {
+        self.score_breakdown(context, current_time).total
+    }
+    
+    /// Composite score for a context, split into each factor's contribution
+    pub fn score_breakdown(&self, context: &Context, current_time: i64) -> ScoreBreakdown {
         let similarity_score = context.relevance_score; // Already set from vector search
         let recency_score = self.calculate_recency_score(context.timestamp, current_time);
         let level_score = self.calculate_level_score(context.level);
         let frequency_score = self.calculate_frequency_score(context);
         
-        similarity_score * self.weights.similarity_weight
-            + recency_score * self.weights.recency_weight
-            + level_score * self.weights.level_weight
-            + frequency_score * self.weights.frequency_weight
+        let similarity = similarity_score * self.weights.similarity_weight;
+        let recency = recency_score * self.weights.recency_weight;
+        let level = level_score * self.weights.level_weight;
+        let frequency = frequency_score * self.weights.frequency_weight;
+        ScoreBreakdown {
+            similarity,
+            recency,
+            level,
+            frequency,
+            total: similarity + recency + level + frequency,
+        }
     }
     
     /// Calculate recency score (more recent = higher score)
+    ///
+    /// Decays exponentially, halving every `recency_half_life_secs`. Future
+    /// timestamps (clock skew, bad imports) are treated as "now" so they
+    /// can't score above a brand-new context.
     fn calculate_recency_score(&self, timestamp: i64, current_time: i64) -> f32 {
-        let age_seconds = (current_time - timestamp).max(0) as f32;
-        let age_hours = age_seconds / 3600.0;
+        let age_seconds = current_time.saturating_sub(timestamp).max(0) as f64;
+        let half_lives = age_seconds / self.recency_half_life_secs as f64;
         
-        // Exponential decay: score = e^(-age_hours / 24)
-        // After 24 hours, score is ~0.37
-        // After 48 hours, score is ~0.14
-        (-age_hours / 24.0).exp()
+        (-std::f64::consts::LN_2 * half_lives).exp() as f32
     }
     
     /// Calculate level score (L1 > L2 > L3)
@@ -65,12 +186,9 @@ impl ContextRanker {
         }
     }
     
-    /// Calculate frequency score based on access count in metadata
+    /// Calculate frequency score based on how often the context was retrieved
     fn calculate_frequency_score(&self, context: &Context) -> f32 {
-        let access_count = context.metadata
-            .get("access_count")
-            .and_then(|v| v.as_u64())
-            .unwrap_or(0) as f32;
+        let access_count = context.access_count as f32;
         
         // Logarithmic scaling: score = log(1 + access_count) / log(101)
         // Max score of 1.0 at 100 accesses
@@ -85,7 +203,9 @@ impl ContextRanker {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::clock::FakeClock;
     use crate::vector_db::ContextLevel;
+    use chrono::Utc;
     
     
     #[test]
@@ -119,4 +239,151 @@ mod tests {
         assert_eq!(ranker.calculate_level_score(ContextLevel::ShortTerm), 0.7);
         assert_eq!(ranker.calculate_level_score(ContextLevel::LongTerm), 0.5);
     }
-}
\ No newline at end of file
+    
+    #[test]
+    fn test_future_timestamp_ranks_as_now() {
+        let ranker = ContextRanker::new(RankingWeights::default());
+        let current_time = Utc::now().timestamp();
+        
+        assert_eq!(ranker.calculate_recency_score(current_time + 86_400 * 365, current_time), 1.0);
+        assert_eq!(ranker.calculate_recency_score(i64::MAX, current_time), 1.0);
+        
+        let fresh = Context::new(uuid::Uuid::new_v4(), "fresh".to_string(), ContextLevel::ShortTerm, current_time, 1);
+        let skewed = Context::new(uuid::Uuid::new_v4(), "skewed".to_string(), ContextLevel::ShortTerm, current_time + 86_400 * 365, 1);
+        assert_eq!(ranker.calculate_score(&fresh, current_time), ranker.calculate_score(&skewed, current_time));
+    }
+    
+    #[test]
+    fn test_frequently_accessed_context_ranks_higher() {
+        let ranker = ContextRanker::new(RankingWeights::default());
+        let current_time = Utc::now().timestamp();
+        
+        let rare = Context::new(uuid::Uuid::new_v4(), "rare".to_string(), ContextLevel::ShortTerm, current_time, 1);
+        let mut popular = rare.clone();
+        popular.access_count = 100;
+        
+        assert_eq!(ranker.calculate_frequency_score(&rare), 0.0);
+        assert!((ranker.calculate_frequency_score(&popular) - 1.0).abs() < 1e-6);
+        assert!(ranker.calculate_score(&popular, current_time) > ranker.calculate_score(&rare, current_time));
+    }
+    
+    #[test]
+    fn test_recency_decays_with_half_life() {
+        let now = Utc::now();
+        let ranker = ContextRanker::new(RankingWeights::default())
+            .with_recency_half_life(60)
+            .with_clock(Arc::new(FakeClock::at(now)));
+        let current_time = ranker.current_time();
+        assert_eq!(current_time, now.timestamp());
+        
+        let approx = |actual: f32, expected: f32| assert!((actual - expected).abs() < 1e-6, "expected {}, got {}", expected, actual);
+        approx(ranker.calculate_recency_score(current_time, current_time), 1.0);
+        approx(ranker.calculate_recency_score(current_time - 60, current_time), 0.5);
+        approx(ranker.calculate_recency_score(current_time - 120, current_time), 0.25);
+        
+        // Identical contexts rank by age, the older falling well behind
+        let context = |text: &str, age: i64| Context::new(uuid::Uuid::new_v4(), text.to_string(), ContextLevel::ShortTerm, current_time - age, 1);
+        let ranked = ranker.rank_contexts_with_scores(vec![context("old", 600), context("new", 10)]);
+        assert_eq!(ranked[0].0.text, "new");
+        assert!(ranked[1].1.recency < ranked[0].1.recency / 100.0);
+        
+        // A longer half-life keeps the same context much fresher
+        let patient = ContextRanker::new(RankingWeights::default()).with_recency_half_life(86400);
+        assert!(patient.calculate_recency_score(current_time - 600, current_time) > 0.99);
+        
+        // The default matches the earlier exp(-age_hours / 24) curve
+        let default = ContextRanker::new(RankingWeights::default());
+        let day_old = default.calculate_recency_score(current_time - 86400, current_time);
+        assert!((day_old - (-1.0f32).exp()).abs() < 1e-4, "got {}", day_old);
+    }
+    
+    #[test]
+    fn test_score_breakdown_terms_sum_to_total() {
+        let weights = RankingWeights {
+            similarity_weight: 0.5,
+            recency_weight: 0.2,
+            level_weight: 0.2,
+            frequency_weight: 0.1,
+        };
+        let ranker = ContextRanker::new(weights.clone());
+        let current_time = Utc::now().timestamp();
+        
+        let mut recent = Context::new(uuid::Uuid::new_v4(), "recent".to_string(), ContextLevel::Immediate, current_time, 1);
+        recent.relevance_score = 0.4;
+        let mut old = Context::new(uuid::Uuid::new_v4(), "old".to_string(), ContextLevel::LongTerm, current_time - 86_400 * 30, 1);
+        old.relevance_score = 0.9;
+        old.access_count = 100;
+        
+        let ranked = ranker.rank_contexts_with_scores(vec![old.clone(), recent.clone()]);
+        assert_eq!(ranked.len(), 2);
+        assert!(ranked[0].1.total >= ranked[1].1.total);
+        
+        for (context, breakdown) in &ranked {
+            let sum = breakdown.similarity + breakdown.recency + breakdown.level + breakdown.frequency;
+            assert!((sum - breakdown.total).abs() < 1e-6);
+            assert_eq!(context.relevance_score, breakdown.total);
+            
+            let original = if context.id == old.id { &old } else { &recent };
+            let approx = |actual: f32, expected: f32| assert!((actual - expected).abs() < 1e-6, "expected {}, got {}", expected, actual);
+            approx(breakdown.similarity, original.relevance_score * weights.similarity_weight);
+            approx(breakdown.recency, ranker.calculate_recency_score(original.timestamp, current_time) * weights.recency_weight);
+            approx(breakdown.level, ranker.calculate_level_score(original.level) * weights.level_weight);
+            approx(breakdown.frequency, ranker.calculate_frequency_score(original) * weights.frequency_weight);
+        }
+        
+        // rank_contexts returns the same order with the breakdown dropped
+        let ids = |contexts: Vec<Context>| contexts.into_iter().map(|c| c.id).collect::<Vec<_>>();
+        assert_eq!(ids(ranker.rank_contexts(vec![old, recent])), ids(ranked.into_iter().map(|(c, _)| c).collect()));
+    }
+    
+    #[test]
+    fn test_zero_weight_zeroes_its_term() {
+        let weights = RankingWeights {
+            similarity_weight: 1.0,
+            recency_weight: 0.0,
+            level_weight: 0.0,
+            frequency_weight: 0.0,
+        };
+        let ranker = ContextRanker::new(weights);
+        let mut context = Context::new(uuid::Uuid::new_v4(), "text".to_string(), ContextLevel::Immediate, Utc::now().timestamp(), 1);
+        context.relevance_score = 0.75;
+        context.access_count = 10;
+        
+        let (_, breakdown) = ranker.rank_contexts_with_scores(vec![context]).remove(0);
+        assert_eq!(breakdown.recency, 0.0);
+        assert_eq!(breakdown.level, 0.0);
+        assert_eq!(breakdown.frequency, 0.0);
+        assert_eq!(breakdown.similarity, 0.75);
+        assert_eq!(breakdown.total, 0.75);
+    }
+    
+    #[test]
+    fn test_mmr_diversifies_duplicate_heavy_results() {
+        let ranker = ContextRanker::new(RankingWeights::default());
+        let context = |text: &str, score: f32, vector: Vec<f32>| {
+            let mut context = Context::new(uuid::Uuid::new_v4(), text.to_string(), ContextLevel::ShortTerm, 0, 1);
+            context.relevance_score = score;
+            context.vector = Some(vector);
+            context
+        };
+        let ranked = vec![
+            context("dup a", 0.90, vec![1.0, 0.0, 0.0]),
+            context("dup b", 0.89, vec![0.99, 0.01, 0.0]),
+            context("dup c", 0.88, vec![0.98, 0.02, 0.0]),
+            context("other", 0.80, vec![0.0, 1.0, 0.0]),
+        ];
+        let top_two = |contexts: &[Context]| contexts[..2].iter().map(|c| c.text.clone()).collect::<Vec<_>>();
+        
+        assert_eq!(top_two(&ranked), vec!["dup a", "dup b"]);
+        let diversified = ranker.apply_mmr(ranked.clone(), 0.5, usize::MAX);
+        assert_eq!(top_two(&diversified), vec!["dup a", "other"]);
+        assert_eq!(diversified.len(), 4);
+        
+        // A lambda of 1 is pure relevance and keeps the ranked order
+        let texts = |contexts: Vec<Context>| contexts.into_iter().map(|c| c.text).collect::<Vec<_>>();
+        assert_eq!(texts(ranker.apply_mmr(ranked.clone(), 1.0, usize::MAX)), texts(ranked.clone()));
+        
+        // Once the budget is spent the rest keep their ranked order
+        assert_eq!(texts(ranker.apply_mmr(ranked, 0.5, 2)), vec!["dup a", "other", "dup b", "dup c"]);
+    }
+}
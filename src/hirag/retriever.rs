@@ -1,13 +1,46 @@
 //! Context retrieval logic for different levels
 
 use super::models::*;
+use super::similarity;
 use super::token_estimator::TokenEstimator;
-use crate::config::RetrievalStrategy;
-use crate::error::Result;
-use crate::vector_db::{SearchParams, VectorStore};
+use super::{DELETED_AT_KEY, EXPIRES_AT_KEY, SESSION_ID_KEY};
+use crate::config::{Distance, RetrievalStrategy};
+use crate::error::{ContextError, HiRAGError, Result, VectorDbError};
+use crate::vector_db::{Condition, Filter, SearchParams, VectorStore};
 use std::sync::Arc;
 use tracing::debug;
 
+/// Filter admitting only contexts that are neither soft-deleted nor expired at
+/// `now`, by the same rule as [`is_expired`](super::is_expired)
+pub(super) fn live_filter(now: f64) -> Filter {
+    Filter::new()
+        .must(Condition::IsEmpty { key: DELETED_AT_KEY.to_string() })
+        .must(Condition::Group {
+            filter: Filter::new()
+                .should(Condition::IsEmpty { key: EXPIRES_AT_KEY.to_string() })
+                .should(Condition::Range {
+                    key: EXPIRES_AT_KEY.to_string(),
+                    gt: Some(now),
+                    gte: None,
+                    lte: None,
+                }),
+        })
+}
+
+/// Caller-supplied filters narrowed to the request's session, if it names one
+pub(super) fn request_filter(request: &ContextRequest) -> Option<Filter> {
+    let session = request.session_id.as_ref().map(|session_id| {
+        Filter::new().must(Condition::Match {
+            key: SESSION_ID_KEY.to_string(),
+            value: session_id.as_str().into(),
+        })
+    });
+    match (request.filters.clone(), session) {
+        (Some(filters), Some(session)) => Some(filters.and(session)),
+        (filters, session) => filters.or(session),
+    }
+}
+
 /// Context retriever for hierarchical retrieval
 #[derive(Clone)]
 pub struct ContextRetriever {
@@ -30,34 +63,88 @@ impl ContextRetriever {
     }
     
     /// Retrieve contexts from a specific level
+    ///
+    /// Returns the contexts that fit `max_tokens` and the number of candidates
+    /// left out because they did not. Soft-deleted and expired contexts are
+    /// always excluded, on top of any caller `filters`. A zero query vector
+    /// has no direction to compare against and is rejected.
     pub async fn retrieve_from_level(
         &self,
         collection: &str,
         query_vector: Vec<f32>,
         max_tokens: usize,
-        filters: Option<crate::vector_db::Filter>,
-    ) -> Result<Vec<Context>> {
+        filters: Option<Filter>,
+        rescore_metric: Option<Distance>,
+        include_vectors: bool,
+    ) -> Result<(Vec<Context>, usize)> {
         debug!("Retrieving from level: {} with max_tokens: {}", collection, max_tokens);
         
+        if similarity::norm(&query_vector) == 0.0 {
+            return Err(HiRAGError::RetrievalError(
+                "Query embedding is a zero vector and cannot be compared".to_string()
+            ).into());
+        }
+        
         // Search with generous limit, we'll filter by tokens later
         let search_params = SearchParams {
-            vector: query_vector,
+            vector: query_vector.clone(),
             limit: 100,
             score_threshold: None,
-            filter: filters,
+            filter: Some(filters.unwrap_or_default().and(live_filter(chrono::Utc::now().timestamp() as f64))),
             with_payload: true,
-            with_vector: false,
+            with_vector: rescore_metric.is_some() || include_vectors,
+            hnsw_ef: None,
+            exact: None,
+        };
+        
+        let mut results = match self.vector_db.search(collection, search_params).await {
+            Ok(results) => results,
+            // Searching before the level's collection exists simply finds nothing
+            Err(ContextError::VectorDb(VectorDbError::CollectionNotFound(name))) => {
+                debug!("Collection {} not found, treating as empty", name);
+                return Ok((Vec::new(), 0));
+            }
+            Err(e) => return Err(e),
         };
         
-        let results = self.vector_db.search(collection, search_params).await?;
+        // Re-rank the recalled candidates under the requested metric
+        if let Some(metric) = rescore_metric {
+            // Dot products are unbounded, so scale them by the query and the
+            // longest candidate: scores stay within [-1, 1] like cosine, in
+            // the same order
+            let scale = match metric {
+                Distance::Dot => {
+                    let longest = results.iter()
+                        .filter_map(|result| result.vector.as_deref())
+                        .filter(|v| v.len() == query_vector.len())
+                        .map(similarity::norm)
+                        .fold(0.0, f32::max);
+                    similarity::norm(&query_vector) * longest
+                }
+                Distance::Cosine | Distance::Euclidean => 1.0,
+            };
+            for result in &mut results {
+                // Vectors of another dimension keep their search score
+                if let Some(score) = result.vector.as_deref().and_then(|v| similarity::score(metric, &query_vector, v).ok()) {
+                    result.score = if scale > 0.0 { score / scale } else { score };
+                }
+            }
+            results.sort_by(|a, b| b.score.total_cmp(&a.score));
+        }
         
         // Convert to Context objects and filter by token budget
         let mut contexts = Vec::new();
         let mut total_tokens = 0;
+        let mut omitted = 0;
         
         for result in results {
             if let Some(payload) = result.payload {
-                let token_count = self.token_estimator.estimate(&payload.text);
+                if omitted > 0 {
+                    omitted += 1;
+                    continue;
+                }
+                
+                let token_count = self.token_estimator.estimate_payload(&payload);
                 
                 if total_tokens + token_count <= max_tokens {
                     contexts.push(Context {
@@ -67,18 +154,21 @@ impl ContextRetriever {
                         relevance_score: result.score,
                         token_count,
                         timestamp: payload.timestamp,
+                        access_count: payload.access_count,
+                        session_id: payload.session_id,
                         metadata: payload.metadata,
+                        vector: if include_vectors { result.vector } else { None },
                     });
                     
                     total_tokens += token_count;
                 } else {
-                    break;
+                    omitted += 1;
                 }
             }
         }
         
-        debug!("Retrieved {} contexts with {} tokens", contexts.len(), total_tokens);
-        Ok(contexts)
+        debug!("Retrieved {} contexts with {} tokens ({} omitted)", contexts.len(), total_tokens, omitted);
+        Ok((contexts, omitted))
     }
     
     /// Calculate token allocation for each level
@@ -142,4 +232,151 @@ impl ContextRetriever {
         
         (l1_tokens, l2_tokens, l3_tokens)
     }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::TokenEstimator as TokenEstimatorConfig;
+    use crate::test_support::{point, MockVectorStore};
+    use crate::vector_db::{ContextLevel, VectorPoint};
+    use uuid::Uuid;
+    
+    fn retriever(store: Arc<MockVectorStore>) -> ContextRetriever {
+        ContextRetriever::new(
+            store,
+            TokenEstimator::new(TokenEstimatorConfig::default()),
+            RetrievalStrategy::default(),
+        )
+    }
+    
+    #[tokio::test]
+    async fn test_missing_collection_is_empty() {
+        let store = Arc::new(MockVectorStore::new());
+        let (contexts, _) = retriever(store.clone())
+            .retrieve_from_level("missing", vec![1.0, 0.0], 100, None, None, false)
+            .await
+            .unwrap();
+        
+        assert!(contexts.is_empty());
+        assert_eq!(store.search_calls(), 1);
+    }
+    
+    #[tokio::test]
+    async fn test_other_search_errors_propagate() {
+        let store = Arc::new(MockVectorStore::new());
+        store.create_collection("broken").await.unwrap();
+        store.fail_collection("broken");
+        
+        let result = retriever(store)
+            .retrieve_from_level("broken", vec![1.0, 0.0], 100, None, None, false)
+            .await;
+        assert!(result.is_err());
+    }
+    
+    #[tokio::test]
+    async fn test_zero_query_vector_is_rejected() {
+        let store = Arc::new(MockVectorStore::new());
+        store.create_collection("l2").await.unwrap();
+        
+        for query in [vec![0.0, 0.0], vec![]] {
+            let result = retriever(store.clone())
+                .retrieve_from_level("l2", query, 100, None, Some(Distance::Cosine), false)
+                .await;
+            assert!(matches!(result, Err(ContextError::HiRAG(HiRAGError::RetrievalError(_)))));
+        }
+        assert_eq!(store.search_calls(), 0);
+    }
+    
+    #[tokio::test]
+    async fn test_stored_token_count_is_used() {
+        let store = Arc::new(MockVectorStore::new());
+        store.create_collection("l2").await.unwrap();
+        let point = |id: u128, token_count: Option<usize>| {
+            let mut point = point(id, ContextLevel::ShortTerm, 0);
+            point.payload.text = "four words of text".to_string();
+            point.payload.token_count = token_count;
+            point
+        };
+        store.insert_points("l2", vec![point(1, Some(42)), point(2, None)]).await.unwrap();
+        
+        let (contexts, _) = retriever(store)
+            .retrieve_from_level("l2", vec![1.0, 0.0], 100, None, None, false)
+            .await
+            .unwrap();
+        let token_count = |id: u128| contexts.iter().find(|c| c.id.as_u128() == id).unwrap().token_count;
+        // 18 characters would estimate to 5 tokens
+        assert_eq!(token_count(1), 42);
+        assert_eq!(token_count(2), 5);
+    }
+    
+    #[tokio::test]
+    async fn test_dot_product_rescoring_reorders_candidates() {
+        let store = Arc::new(MockVectorStore::new());
+        store.create_collection("l2").await.unwrap();
+        let point = |id: u128, vector: Vec<f32>| VectorPoint { vector, ..point(id, ContextLevel::ShortTerm, 0) };
+        // Aligned but short vs. slightly off-axis but long
+        store.insert_points("l2", vec![point(1, vec![1.0, 0.0]), point(2, vec![3.0, 1.0])]).await.unwrap();
+        let retriever = retriever(store);
+        
+        let ids = |contexts: Vec<Context>| contexts.into_iter().map(|c| c.id.as_u128()).collect::<Vec<_>>();
+        let (cosine, _) = retriever.retrieve_from_level("l2", vec![1.0, 0.0], 100, None, None, false).await.unwrap();
+        assert_eq!(ids(cosine), vec![1, 2]);
+        
+        let (dot, _) = retriever.retrieve_from_level("l2", vec![1.0, 0.0], 100, None, Some(Distance::Dot), false).await.unwrap();
+        assert_eq!(ids(dot.clone()), vec![2, 1]);
+        // Scaled by the longest candidate, |(3, 1)| = sqrt(10)
+        let longest = 10.0_f32.sqrt();
+        assert!((dot[0].relevance_score - 3.0 / longest).abs() < 1e-6);
+        assert!((dot[1].relevance_score - 1.0 / longest).abs() < 1e-6);
+    }
+    
+    #[tokio::test]
+    async fn test_deleted_and_expired_contexts_are_excluded() {
+        let store = Arc::new(MockVectorStore::new());
+        store.create_collection("l2").await.unwrap();
+        let now = chrono::Utc::now().timestamp();
+        let point = |id: u128, agent_id: &str, metadata: Vec<(&str, serde_json::Value)>| {
+            let mut point = point(id, ContextLevel::ShortTerm, 0);
+            point.payload.agent_id = agent_id.to_string();
+            point.payload.metadata = metadata.into_iter().map(|(k, v)| (k.to_string(), v)).collect();
+            point
+        };
+        store.insert_points("l2", vec![
+            point(1, "alice", vec![]),
+            point(2, "alice", vec![(DELETED_AT_KEY, now.into())]),
+            point(3, "alice", vec![(EXPIRES_AT_KEY, now.into())]),
+            point(4, "alice", vec![(EXPIRES_AT_KEY, (now + 3600).into())]),
+            point(5, "bob", vec![]),
+            point(6, "carol", vec![("archived", true.into())]),
+        ]).await.unwrap();
+        let retriever = retriever(store);
+        
+        let ids = |contexts: Vec<Context>| {
+            let mut ids: Vec<_> = contexts.into_iter().map(|c| c.id.as_u128()).collect();
+            ids.sort();
+            ids
+        };
+        let (all, _) = retriever.retrieve_from_level("l2", vec![1.0, 0.0], 1000, None, None, false).await.unwrap();
+        assert_eq!(ids(all), vec![1, 4, 5, 6]);
+        
+        // Caller alternatives and exclusions keep their meaning
+        let filter = Filter::new()
+            .should(Condition::Match { key: "agent_id".to_string(), value: "alice".into() })
+            .should(Condition::Match { key: "agent_id".to_string(), value: "carol".into() })
+            .must_not(Condition::Match { key: "archived".to_string(), value: true.into() });
+        let (filtered, _) = retriever.retrieve_from_level("l2", vec![1.0, 0.0], 1000, Some(filter), None, false).await.unwrap();
+        assert_eq!(ids(filtered), vec![1, 4]);
+    }
+    
+    #[test]
+    fn test_live_filter_agrees_with_is_expired() {
+        let now = 1_000.0;
+        for expires_at in [999.5, 1_000.0, 1_000.5, 1_001.0] {
+            let mut payload = point(1, ContextLevel::ShortTerm, 0).payload;
+            payload.metadata.insert(EXPIRES_AT_KEY.to_string(), expires_at.into());
+            let live = live_filter(now).matches(Uuid::nil(), &payload);
+            assert_eq!(live, !crate::hirag::is_expired(&payload.metadata, now), "{}", expires_at);
+        }
+    }
+}
@@ -0,0 +1,126 @@
+//! Vector similarity used for in-process re-ranking
+
+use crate::config::Distance;
+use crate::error::{Result, VectorDbError};
+
+fn check_dimensions(a: &[f32], b: &[f32]) -> Result<()> {
+    if a.len() != b.len() {
+        return Err(VectorDbError::InvalidDimension { expected: a.len(), actual: b.len() }.into());
+    }
+    Ok(())
+}
+
+/// Euclidean length of a vector
+pub fn norm(v: &[f32]) -> f32 {
+    v.iter().map(|x| x * x).sum::<f32>().sqrt()
+}
+
+/// Dot product of two vectors of the same dimension
+pub fn dot(a: &[f32], b: &[f32]) -> Result<f32> {
+    check_dimensions(a, b)?;
+    Ok(a.iter().zip(b).map(|(x, y)| x * y).sum())
+}
+
+/// Cosine similarity of two vectors of the same dimension
+///
+/// A zero vector has no direction, so its similarity to anything is 0.
+pub fn cosine(a: &[f32], b: &[f32]) -> Result<f32> {
+    let dot = dot(a, b)?;
+    let norms = norm(a) * norm(b);
+    Ok(if norms == 0.0 { 0.0 } else { dot / norms })
+}
+
+/// Euclidean distance between two vectors of the same dimension
+pub fn euclidean(a: &[f32], b: &[f32]) -> Result<f32> {
+    check_dimensions(a, b)?;
+    Ok(a.iter().zip(b).map(|(x, y)| (x - y).powi(2)).sum::<f32>().sqrt())
+}
+
+/// Similarity of two vectors under `metric`, higher meaning more similar
+///
+/// Euclidean distance is mapped to `1 / (1 + d)` so it orders like the others.
+pub fn score(metric: Distance, a: &[f32], b: &[f32]) -> Result<f32> {
+    match metric {
+        Distance::Dot => dot(a, b),
+        Distance::Cosine => cosine(a, b),
+        Distance::Euclidean => euclidean(a, b).map(|distance| 1.0 / (1.0 + distance)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::ContextError;
+
+    fn approx(actual: f32, expected: f32) {
+        assert!((actual - expected).abs() < 1e-6, "expected {}, got {}", expected, actual);
+    }
+
+    #[test]
+    fn test_identical_vectors() {
+        let v = [0.6, 0.8, 0.0];
+        approx(cosine(&v, &v).unwrap(), 1.0);
+        approx(dot(&v, &v).unwrap(), 1.0);
+        approx(euclidean(&v, &v).unwrap(), 0.0);
+        approx(score(Distance::Euclidean, &v, &v).unwrap(), 1.0);
+    }
+
+    #[test]
+    fn test_orthogonal_and_opposite_vectors() {
+        let x = [1.0, 0.0];
+        let y = [0.0, 2.0];
+        approx(cosine(&x, &y).unwrap(), 0.0);
+        approx(dot(&x, &y).unwrap(), 0.0);
+        approx(euclidean(&x, &y).unwrap(), 5.0_f32.sqrt());
+
+        approx(cosine(&x, &[-3.0, 0.0]).unwrap(), -1.0);
+    }
+
+    #[test]
+    fn test_cosine_ignores_magnitude_but_dot_does_not() {
+        let a = [1.0, 1.0];
+        let b = [10.0, 10.0];
+        approx(cosine(&a, &b).unwrap(), 1.0);
+        approx(dot(&a, &b).unwrap(), 20.0);
+    }
+
+    #[test]
+    fn test_zero_vectors() {
+        let zero = [0.0, 0.0, 0.0];
+        let v = [1.0, 2.0, 3.0];
+        approx(cosine(&zero, &v).unwrap(), 0.0);
+        approx(cosine(&zero, &zero).unwrap(), 0.0);
+        approx(dot(&zero, &v).unwrap(), 0.0);
+        approx(euclidean(&zero, &v).unwrap(), 14.0_f32.sqrt());
+        assert!(!cosine(&zero, &zero).unwrap().is_nan());
+    }
+
+    #[test]
+    fn test_empty_vectors() {
+        approx(cosine(&[], &[]).unwrap(), 0.0);
+        approx(dot(&[], &[]).unwrap(), 0.0);
+        approx(euclidean(&[], &[]).unwrap(), 0.0);
+    }
+
+    #[test]
+    fn test_dimension_mismatch_is_an_error() {
+        let a = [1.0, 0.0, 0.0];
+        let b = [1.0, 0.0];
+        for result in [cosine(&a, &b), dot(&a, &b), euclidean(&a, &b), score(Distance::Dot, &a, &b)] {
+            assert!(matches!(
+                result,
+                Err(ContextError::VectorDb(VectorDbError::InvalidDimension { expected: 3, actual: 2 }))
+            ));
+        }
+    }
+
+    #[test]
+    fn test_score_orders_by_similarity() {
+        let query = [1.0, 0.0];
+        let near = [0.9, 0.1];
+        let far = [-1.0, 0.5];
+        for metric in [Distance::Cosine, Distance::Dot, Distance::Euclidean] {
+            assert!(score(metric, &query, &near).unwrap() > score(metric, &query, &far).unwrap(), "{:?}", metric);
+        }
+    }
+}
@@ -1,38 +1,100 @@
 //! Token estimation utilities
 
-use crate::config::TokenEstimator as TokenEstimatorConfig;
+use crate::config::{LevelTokenEstimators, TokenEstimator as TokenEstimatorConfig};
+use crate::vector_db::{ContextLevel, Payload};
+use std::collections::HashMap;
+use std::sync::Arc;
+use tiktoken_rs::CoreBPE;
+use tracing::warn;
+
+/// Characters per token assumed when a tiktoken model is unknown
+const FALLBACK_CHARS_PER_TOKEN: f32 = 4.0;
 
 /// Token estimator for calculating token counts
 #[derive(Clone)]
 pub struct TokenEstimator {
     config: TokenEstimatorConfig,
+    /// Tokenizer for the configured tiktoken model, if it is known
+    bpe: Option<Arc<CoreBPE>>,
+    /// Estimators overriding this one for individual levels
+    levels: Arc<HashMap<ContextLevel, TokenEstimator>>,
 }
 
 impl TokenEstimator {
     pub fn new(config: TokenEstimatorConfig) -> Self {
-        Self { config }
+        let bpe = match &config {
+            TokenEstimatorConfig::Tiktoken { model } => match tiktoken_rs::get_bpe_from_model(model) {
+                Ok(bpe) => Some(Arc::new(bpe)),
+                Err(e) => {
+                    warn!("Unknown tiktoken model {}, estimating tokens by characters: {}", model, e);
+                    None
+                }
+            },
+            _ => None,
+        };
+        Self { config, bpe, levels: Arc::default() }
+    }
+    
+    /// Use a different estimator for each level configured in `levels`
+    pub fn with_level_estimators(mut self, levels: &LevelTokenEstimators) -> Self {
+        let overrides = [
+            (ContextLevel::Immediate, &levels.l1),
+            (ContextLevel::ShortTerm, &levels.l2),
+            (ContextLevel::LongTerm, &levels.l3),
+        ];
+        self.levels = Arc::new(
+            overrides
+                .into_iter()
+                .filter_map(|(level, config)| Some((level, TokenEstimator::new(config.clone()?))))
+                .collect(),
+        );
+        self
+    }
+    
+    /// Estimator used for contexts at `level`
+    pub fn for_level(&self, level: ContextLevel) -> &TokenEstimator {
+        self.levels.get(&level).unwrap_or(self)
     }
     
     /// Estimate token count for text
     pub fn estimate(&self, text: &str) -> usize {
         match &self.config {
             TokenEstimatorConfig::CharacterBased { chars_per_token } => {
-                let char_count = text.chars().count();
-                (char_count as f32 / chars_per_token).ceil() as usize
+                estimate_by_characters(text, *chars_per_token)
             }
             TokenEstimatorConfig::WordBased { words_per_token } => {
                 let word_count = text.split_whitespace().count();
                 (word_count as f32 / words_per_token).ceil() as usize
             }
+            TokenEstimatorConfig::Tiktoken { .. } => match &self.bpe {
+                Some(bpe) => bpe.encode_ordinary(text).len(),
+                None => estimate_by_characters(text, FALLBACK_CHARS_PER_TOKEN),
+            },
         }
     }
     
+    /// Estimate token count for text stored at `level`
+    pub fn estimate_for_level(&self, level: ContextLevel, text: &str) -> usize {
+        self.for_level(level).estimate(text)
+    }
+    
+    /// Token count of a stored payload, estimating it only for points stored
+    /// without one
+    pub fn estimate_payload(&self, payload: &Payload) -> usize {
+        payload.token_count.unwrap_or_else(|| self.estimate_for_level(payload.level, &payload.text))
+    }
+    
     /// Estimate total tokens for multiple texts
     pub fn estimate_batch(&self, texts: &[String]) -> Vec<usize> {
         texts.iter().map(|text| self.estimate(text)).collect()
     }
 }
 
+fn estimate_by_characters(text: &str, chars_per_token: f32) -> usize {
+    let char_count = text.chars().count();
+    (char_count as f32 / chars_per_token).ceil() as usize
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -58,4 +120,36 @@ mod tests {
         
         assert_eq!(tokens, 3); // 3 words / 1.3 = 2.3 -> 3
     }
+    
+    #[test]
+    fn test_tiktoken_estimation() {
+        let estimator = TokenEstimator::new(TokenEstimatorConfig::Tiktoken { model: "gpt-4".to_string() });
+        
+        assert_eq!(estimator.estimate("hello world"), 2);
+        assert_eq!(estimator.estimate(""), 0);
+        // Code splits into many more tokens than the character heuristic assumes
+        assert!(estimator.estimate("fn main(){let x=vec![1,2,3];}") > "fn main(){let x=vec![1,2,3];}".len() / 4);
+    }
+    
+    #[test]
+    fn test_unknown_tiktoken_model_falls_back_to_characters() {
+        let estimator = TokenEstimator::new(TokenEstimatorConfig::Tiktoken { model: "not-a-model".to_string() });
+        
+        assert_eq!(estimator.estimate("Hello world"), 3); // 11 chars / 4 = 2.75 -> 3
+    }
+    
+    #[test]
+    fn test_level_estimators_override_the_default() {
+        let levels = LevelTokenEstimators {
+            l3: Some(TokenEstimatorConfig::WordBased { words_per_token: 1.0 }),
+            ..Default::default()
+        };
+        let estimator = TokenEstimator::new(TokenEstimatorConfig::CharacterBased { chars_per_token: 4.0 })
+            .with_level_estimators(&levels);
+        
+        let text = "Hello world";
+        assert_eq!(estimator.estimate_for_level(ContextLevel::ShortTerm, text), 3);
+        assert_eq!(estimator.estimate_for_level(ContextLevel::LongTerm, text), 2);
+        assert_eq!(estimator.estimate(text), 3);
+    }
 }
\ No newline at end of file
@@ -48,6 +48,7 @@
 //! ```
 
 pub mod api;
+pub mod clock;
 pub mod config;
 pub mod embedding;
 pub mod error;
@@ -59,6 +60,11 @@ pub mod vector_db;
 pub mod shutdown;
 pub mod server;
 
+#[cfg(any(test, feature = "testing"))]
+pub mod testing;
+#[cfg(test)]
+pub(crate) mod test_support;
+
 pub use config::Config;
 pub use error::{ContextError, Result};
 
@@ -68,7 +74,7 @@ pub mod prelude {
     pub use crate::embedding::{EmbeddingClient, EmbeddingProvider};
     pub use crate::error::{ContextError, Result};
     pub use crate::hirag::{ContextManager, HiRAGManager, ContextRequest, ContextResponse};
-    pub use crate::middleware::{RateLimiter, RateLimitConfig, AuthMiddleware, AuthConfig, InputValidator};
+    pub use crate::middleware::{RateLimiter, RateLimitAlgorithm, RateLimitConfig, AuthMiddleware, AuthConfig, InputValidator};
     pub use crate::observability::{MetricsCollector, HealthChecker};
     pub use crate::protocol::{Message, MessageHandler, Codec};
     pub use crate::vector_db::{VectorDbClient, VectorStore, ContextLevel};
@@ -1,7 +1,11 @@
 //! Authentication middleware
 
-use std::collections::HashSet;
+use crate::observability::MetricsCollector;
+use jsonwebtoken::{errors::ErrorKind, Algorithm, DecodingKey, Validation};
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
 use std::sync::Arc;
+use std::time::{Duration, Instant};
 use tokio::sync::RwLock;
 use tracing::{debug, warn};
 
@@ -14,6 +18,15 @@ pub struct AuthConfig {
     pub enabled: bool,
     /// Token prefix (e.g., "Bearer")
     pub token_prefix: String,
+    /// Tokens allowed to use the `/admin` routes
+    pub admin_tokens: HashSet<String>,
+    /// Expiry of tokens that were added with a TTL
+    pub token_expiry: HashMap<String, Instant>,
+    /// Validate bearer tokens as JWTs instead of against the static token sets
+    pub jwt: Option<JwtAuth>,
+    /// Permissions of static tokens; tokens without an entry may read and
+    /// write but need an explicit [`Permission::Admin`] grant to delete
+    pub token_permissions: HashMap<String, HashSet<Permission>>,
 }
 
 impl Default for AuthConfig {
@@ -22,13 +35,223 @@ impl Default for AuthConfig {
             valid_tokens: HashSet::new(),
             enabled: true,
             token_prefix: "Bearer".to_string(),
+            admin_tokens: HashSet::new(),
+            token_expiry: HashMap::new(),
+            jwt: None,
+            token_permissions: HashMap::new(),
         }
     }
 }
 
+impl AuthConfig {
+    /// Strip the configured prefix from a presented token
+    fn strip_prefix<'a>(&self, token: &'a str) -> &'a str {
+        token
+            .strip_prefix(self.token_prefix.as_str())
+            .and_then(|rest| rest.strip_prefix(' '))
+            .unwrap_or(token)
+    }
+    
+    /// Whether `token` has passed its expiry
+    fn is_expired(&self, token: &str) -> bool {
+        self.token_expiry.get(token).is_some_and(|expiry| Instant::now() >= *expiry)
+    }
+    
+    /// Check a presented token against `scope`, returning its claims if it is a JWT
+    fn verify(&self, token: &str, scope: TokenScope) -> Result<Option<AuthClaims>, AuthError> {
+        let token = self.strip_prefix(token);
+        
+        if let Some(jwt) = &self.jwt {
+            let claims = jwt.decode(token)?;
+            if scope == TokenScope::Admin && !claims.has_scope(&jwt.admin_scope) {
+                return Err(AuthError::InvalidToken);
+            }
+            return Ok(Some(claims));
+        }
+        
+        let tokens = match scope {
+            TokenScope::Api => &self.valid_tokens,
+            TokenScope::Admin => &self.admin_tokens,
+        };
+        if !tokens.contains(token) {
+            return Err(AuthError::InvalidToken);
+        }
+        if self.is_expired(token) {
+            return Err(AuthError::ExpiredToken);
+        }
+        Ok(None)
+    }
+    
+    /// Permissions of a verified token; JWTs are granted those named in their
+    /// `scope` claim
+    fn permissions(&self, token: &str, claims: Option<&AuthClaims>) -> Permissions {
+        if let (Some(jwt), Some(claims)) = (&self.jwt, claims) {
+            return Permissions(claims.scopes.iter().filter_map(|scope| jwt.permission(scope)).collect());
+        }
+        self.token_permissions
+            .get(self.strip_prefix(token))
+            .map(|permissions| Permissions(permissions.clone()))
+            .unwrap_or_else(Permissions::read_write)
+    }
+}
+
+/// An operation on the `/api/v1` routes a token may be allowed to perform
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Permission {
+    /// Fetch and search contexts
+    Read,
+    /// Store contexts
+    Write,
+    /// Delete contexts and clear levels; implies the other permissions
+    Admin,
+}
+
+/// Permissions granted to an authenticated request, added to its extensions
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Permissions(HashSet<Permission>);
+
+impl Permissions {
+    /// Every permission
+    pub fn all() -> Self {
+        Self([Permission::Read, Permission::Write, Permission::Admin].into_iter().collect())
+    }
+    
+    /// Fetching, searching and storing, granted to static tokens by default
+    pub fn read_write() -> Self {
+        Self([Permission::Read, Permission::Write].into_iter().collect())
+    }
+    
+    /// Whether `permission` was granted
+    pub fn allows(&self, permission: Permission) -> bool {
+        self.0.contains(&permission) || self.0.contains(&Permission::Admin)
+    }
+}
+
+/// Claims of a verified JWT, added to the request extensions for handlers
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+pub struct AuthClaims {
+    /// The `sub` claim
+    #[serde(rename = "sub", default)]
+    pub subject: Option<String>,
+    /// The space-separated `scope` claim
+    #[serde(rename = "scope", default, deserialize_with = "deserialize_scopes")]
+    pub scopes: Vec<String>,
+}
+
+impl AuthClaims {
+    /// Whether the token was granted `scope`
+    pub fn has_scope(&self, scope: &str) -> bool {
+        self.scopes.iter().any(|s| s == scope)
+    }
+}
+
+fn deserialize_scopes<'de, D: serde::Deserializer<'de>>(deserializer: D) -> Result<Vec<String>, D::Error> {
+    let scope = String::deserialize(deserializer)?;
+    Ok(scope.split_whitespace().map(str::to_string).collect())
+}
+
+/// JWT bearer-token verification
+///
+/// Tokens must be signed with the configured key and carry an unexpired
+/// `exp` claim, plus matching `iss` and `aud` claims when those are set.
+#[derive(Clone)]
+pub struct JwtAuth {
+    key: DecodingKey,
+    validation: Validation,
+    /// Scope that grants access to the `/admin` routes
+    admin_scope: String,
+}
+
+impl JwtAuth {
+    fn new(key: DecodingKey, algorithm: Algorithm) -> Self {
+        let mut validation = Validation::new(algorithm);
+        // Only checked once an audience is configured
+        validation.validate_aud = false;
+        Self { key, validation, admin_scope: "admin".to_string() }
+    }
+    
+    /// Verify HS256 tokens signed with a shared secret
+    pub fn hs256(secret: &[u8]) -> Self {
+        Self::new(DecodingKey::from_secret(secret), Algorithm::HS256)
+    }
+    
+    /// Verify RS256 tokens against a PEM-encoded RSA public key
+    pub fn rs256_pem(public_key: &[u8]) -> Result<Self, AuthError> {
+        let key = DecodingKey::from_rsa_pem(public_key)
+            .map_err(|e| AuthError::InvalidKey(e.to_string()))?;
+        Ok(Self::new(key, Algorithm::RS256))
+    }
+    
+    /// Require the `iss` claim to be `issuer`
+    pub fn with_issuer(mut self, issuer: &str) -> Self {
+        self.validation.set_issuer(&[issuer]);
+        self.validation.required_spec_claims.insert("iss".to_string());
+        self
+    }
+    
+    /// Require the `aud` claim to include `audience`
+    pub fn with_audience(mut self, audience: &str) -> Self {
+        self.validation.set_audience(&[audience]);
+        self.validation.validate_aud = true;
+        self.validation.required_spec_claims.insert("aud".to_string());
+        self
+    }
+    
+    /// Use `scope` instead of `admin` as the scope granting admin access
+    pub fn with_admin_scope(mut self, scope: impl Into<String>) -> Self {
+        self.admin_scope = scope.into();
+        self
+    }
+    
+    /// Permission granted by a `scope` claim entry, if any
+    fn permission(&self, scope: &str) -> Option<Permission> {
+        match scope {
+            _ if scope == self.admin_scope => Some(Permission::Admin),
+            "read" => Some(Permission::Read),
+            "write" => Some(Permission::Write),
+            _ => None,
+        }
+    }
+    
+    /// Verify `token` and return its claims
+    pub fn decode(&self, token: &str) -> Result<AuthClaims, AuthError> {
+        jsonwebtoken::decode::<AuthClaims>(token, &self.key, &self.validation)
+            .map(|data| data.claims)
+            .map_err(|e| match e.kind() {
+                ErrorKind::ExpiredSignature => AuthError::ExpiredToken,
+                _ => {
+                    debug!("JWT rejected: {}", e);
+                    AuthError::InvalidToken
+                }
+            })
+    }
+}
+
+impl std::fmt::Debug for JwtAuth {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("JwtAuth")
+            .field("validation", &self.validation)
+            .field("admin_scope", &self.admin_scope)
+            .finish_non_exhaustive()
+    }
+}
+
+/// What a token added at runtime may access
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TokenScope {
+    /// The `/api/v1` routes
+    #[default]
+    Api,
+    /// The `/api/v1` and `/admin` routes
+    Admin,
+}
+
 /// Authentication middleware
 pub struct AuthMiddleware {
     config: Arc<RwLock<AuthConfig>>,
+    metrics: Option<Arc<MetricsCollector>>,
 }
 
 impl AuthMiddleware {
@@ -36,8 +259,29 @@ impl AuthMiddleware {
     pub fn new(config: AuthConfig) -> Self {
         Self {
             config: Arc::new(RwLock::new(config)),
+            metrics: None,
         }
     }
+    
+    /// Set metrics collector for counting rejected requests
+    pub fn with_metrics(mut self, metrics: Arc<MetricsCollector>) -> Self {
+        self.metrics = Some(metrics);
+        self
+    }
+    
+    /// Record a rejected request in the metrics collector, if any
+    fn record_failure(&self) {
+        if let Some(metrics) = &self.metrics {
+            metrics.record_auth_failure();
+        }
+    }
+    
+    /// Reject a request that carried no token
+    pub fn reject_missing_token(&self) -> AuthError {
+        warn!("Authentication failed: missing token");
+        self.record_failure();
+        AuthError::MissingToken
+    }
 
     /// Authenticate a request with token
     pub async fn authenticate(&self, token: &str) -> Result<(), AuthError> {
@@ -47,19 +291,16 @@ impl AuthMiddleware {
             return Ok(());
         }
 
-        // Remove prefix if present
-        let token = if token.starts_with(&format!("{} ", config.token_prefix)) {
-            token.trim_start_matches(&format!("{} ", config.token_prefix))
-        } else {
-            token
-        };
-
-        if config.valid_tokens.contains(token) {
-            debug!("Authentication successful");
-            Ok(())
-        } else {
-            warn!("Authentication failed: invalid token");
-            Err(AuthError::InvalidToken)
+        match config.verify(token, TokenScope::Api) {
+            Ok(_) => {
+                debug!("Authentication successful");
+                Ok(())
+            }
+            Err(e) => {
+                warn!("Authentication failed: {}", e);
+                self.record_failure();
+                Err(e)
+            }
         }
     }
 
@@ -70,11 +311,56 @@ impl AuthMiddleware {
         debug!("Token added to valid tokens");
     }
 
-    /// Remove a token
-    pub async fn remove_token(&self, token: &str) {
+    /// Add a token with the given scope, expiring after `ttl` if set and
+    /// restricted to `permissions` if given
+    ///
+    /// Everything is set under one lock, so the token is never accepted
+    /// with more access than it was added with.
+    pub async fn add_scoped_token(
+        &self,
+        token: String,
+        scope: TokenScope,
+        ttl: Option<Duration>,
+        permissions: Option<HashSet<Permission>>,
+    ) {
         let mut config = self.config.write().await;
-        config.valid_tokens.remove(token);
+        match ttl {
+            Some(ttl) => {
+                config.token_expiry.insert(token.clone(), Instant::now() + ttl);
+            }
+            None => {
+                config.token_expiry.remove(&token);
+            }
+        }
+        match permissions {
+            Some(permissions) => config.token_permissions.insert(token.clone(), permissions),
+            None => config.token_permissions.remove(&token),
+        };
+        // Re-adding an admin token with API scope demotes it
+        match scope {
+            TokenScope::Admin => config.admin_tokens.insert(token.clone()),
+            TokenScope::Api => config.admin_tokens.remove(&token),
+        };
+        config.valid_tokens.insert(token);
+        debug!("Token added to valid tokens ({:?} scope)", scope);
+    }
+    
+    /// Restrict a token to `permissions`
+    pub async fn restrict_token(&self, token: &str, permissions: HashSet<Permission>) {
+        let mut config = self.config.write().await;
+        debug!("Token restricted to {:?}", permissions);
+        config.token_permissions.insert(token.to_string(), permissions);
+    }
+
+    /// Remove a token, returning whether it was known
+    pub async fn remove_token(&self, token: &str) -> bool {
+        let mut config = self.config.write().await;
+        let removed = config.valid_tokens.remove(token);
+        let removed_admin = config.admin_tokens.remove(token);
+        config.token_expiry.remove(token);
+        config.token_permissions.remove(token);
         debug!("Token removed from valid tokens");
+        removed || removed_admin
     }
 
     /// Check if token exists
@@ -83,30 +369,44 @@ impl AuthMiddleware {
         config.valid_tokens.contains(token)
     }
     
-    /// Validate token (synchronous version for middleware)
-    pub fn validate_token(&self, token: &str) -> bool {
-        // Use try_read to avoid blocking
-        if let Ok(config) = self.config.try_read() {
-            if !config.enabled {
-                return true;
-            }
-            
-            let token = if token.starts_with(&format!("{} ", config.token_prefix)) {
-                token.trim_start_matches(&format!("{} ", config.token_prefix))
-            } else {
-                token
-            };
-            
-            config.valid_tokens.contains(token)
-        } else {
-            false
+    /// Validate token
+    pub async fn validate_token(&self, token: &str) -> bool {
+        self.verify_token(token, TokenScope::Api).await.is_ok()
+    }
+    
+    /// Validate a token for the `/admin` routes
+    pub async fn validate_admin_token(&self, token: &str) -> bool {
+        self.verify_token(token, TokenScope::Admin).await.is_ok()
+    }
+    
+    /// Verify a token for routes of `scope`, returning its claims when JWT
+    /// authentication is enabled
+    ///
+    /// Waits for a concurrent token change instead of rejecting the request.
+    pub async fn verify_token(&self, token: &str, scope: TokenScope) -> Result<Option<AuthClaims>, AuthError> {
+        let config = self.config.read().await;
+        // Admin routes always require a token
+        if !config.enabled && scope == TokenScope::Api {
+            return Ok(None);
+        }
+        let result = config.verify(token, scope);
+        if result.is_err() {
+            self.record_failure();
         }
+        result
     }
 
-    /// Get number of valid tokens
+    /// Permissions of a token already verified by [`verify_token`](Self::verify_token),
+    /// or `None` when authentication is disabled
+    pub async fn permissions(&self, token: &str, claims: Option<&AuthClaims>) -> Option<Permissions> {
+        let config = self.config.read().await;
+        config.enabled.then(|| config.permissions(token, claims))
+    }
+
+    /// Get number of tokens accepted on any route, admin-only ones included
     pub async fn token_count(&self) -> usize {
         let config = self.config.read().await;
-        config.valid_tokens.len()
+        config.valid_tokens.union(&config.admin_tokens).count()
     }
 }
 
@@ -121,6 +421,9 @@ pub enum AuthError {
     
     #[error("Token has expired")]
     ExpiredToken,
+    
+    #[error("Invalid JWT verification key: {0}")]
+    InvalidKey(String),
 }
 
 #[cfg(test)]
@@ -174,7 +477,157 @@ mod tests {
         assert!(auth.has_token("new-token").await);
         
         // Remove token
-        auth.remove_token("new-token").await;
+        assert!(auth.remove_token("new-token").await);
         assert!(!auth.has_token("new-token").await);
+        assert!(!auth.remove_token("new-token").await);
+    }
+
+    #[tokio::test]
+    async fn test_scoped_token_with_ttl() {
+        let auth = AuthMiddleware::new(AuthConfig::default());
+        
+        auth.add_scoped_token("admin".to_string(), TokenScope::Admin, None, None).await;
+        auth.add_scoped_token("short-lived".to_string(), TokenScope::Api, Some(Duration::ZERO), None).await;
+        
+        assert!(auth.validate_token("admin").await);
+        assert!(auth.validate_admin_token("admin").await);
+        assert!(!auth.validate_admin_token("short-lived").await);
+        assert!(!auth.validate_token("short-lived").await);
+        assert!(matches!(auth.authenticate("short-lived").await, Err(AuthError::ExpiredToken)));
+        
+        auth.add_scoped_token("admin".to_string(), TokenScope::Api, None, None).await;
+        assert!(auth.validate_token("admin").await);
+        assert!(!auth.validate_admin_token("admin").await);
+    }
+    
+    fn jwt(secret: &[u8], claims: serde_json::Value) -> String {
+        jsonwebtoken::encode(
+            &jsonwebtoken::Header::default(),
+            &claims,
+            &jsonwebtoken::EncodingKey::from_secret(secret),
+        )
+        .unwrap()
+    }
+    
+    #[tokio::test]
+    async fn test_jwt_claims_are_verified() {
+        let auth = AuthMiddleware::new(AuthConfig {
+            valid_tokens: ["static-token".to_string()].into_iter().collect(),
+            jwt: Some(JwtAuth::hs256(b"secret").with_issuer("issuer").with_audience("context-manager")),
+            ..Default::default()
+        });
+        let exp = chrono::Utc::now().timestamp() + 600;
+        let claims = |overrides: serde_json::Value| {
+            let mut claims = serde_json::json!({
+                "sub": "agent-7",
+                "scope": "read admin",
+                "exp": exp,
+                "iss": "issuer",
+                "aud": "context-manager",
+            });
+            claims.as_object_mut().unwrap().extend(overrides.as_object().unwrap().clone());
+            claims
+        };
+        
+        let token = jwt(b"secret", claims(serde_json::json!({})));
+        let verified = auth.verify_token(&token, TokenScope::Api).await.unwrap().unwrap();
+        assert_eq!(verified.subject.as_deref(), Some("agent-7"));
+        assert_eq!(verified.scopes, vec!["read", "admin"]);
+        assert!(auth.validate_admin_token(&token).await);
+        assert!(auth.authenticate(&format!("Bearer {}", token)).await.is_ok());
+        
+        let read_only = jwt(b"secret", claims(serde_json::json!({ "scope": "read" })));
+        assert!(auth.validate_token(&read_only).await);
+        assert!(!auth.validate_admin_token(&read_only).await);
+        
+        let expired = jwt(b"secret", claims(serde_json::json!({ "exp": exp - 3600 })));
+        assert!(matches!(auth.verify_token(&expired, TokenScope::Api).await, Err(AuthError::ExpiredToken)));
+        for rejected in [
+            jwt(b"other-secret", claims(serde_json::json!({}))),
+            jwt(b"secret", claims(serde_json::json!({ "iss": "someone-else" }))),
+            jwt(b"secret", claims(serde_json::json!({ "aud": "another-service" }))),
+            "static-token".to_string(),
+        ] {
+            assert!(matches!(auth.verify_token(&rejected, TokenScope::Api).await, Err(AuthError::InvalidToken)));
+        }
+    }
+    
+    #[tokio::test]
+    async fn test_static_tokens_carry_no_claims() {
+        let mut config = AuthConfig::default();
+        config.valid_tokens.insert("test-token-123".to_string());
+        
+        let auth = AuthMiddleware::new(config);
+        assert_eq!(auth.verify_token("test-token-123", TokenScope::Api).await.unwrap(), None);
+        assert!(matches!(auth.verify_token("test-token-123", TokenScope::Admin).await, Err(AuthError::InvalidToken)));
+    }
+    
+    #[tokio::test]
+    async fn test_token_count_includes_admin_only_tokens() {
+        let auth = Arc::new(AuthMiddleware::new(AuthConfig {
+            valid_tokens: ["api".to_string(), "both".to_string()].into_iter().collect(),
+            admin_tokens: ["both".to_string(), "ops".to_string()].into_iter().collect(),
+            ..Default::default()
+        }));
+        assert_eq!(auth.token_count().await, 3);
+        
+        // Verification waits for a token change in progress instead of failing
+        let rotating = auth.config.write().await;
+        let verified = tokio::spawn({
+            let auth = auth.clone();
+            async move { auth.validate_admin_token("ops").await }
+        });
+        tokio::task::yield_now().await;
+        drop(rotating);
+        assert!(verified.await.unwrap());
     }
-}
\ No newline at end of file
+    
+    #[tokio::test]
+    async fn test_token_permissions() {
+        let mut config = AuthConfig::default();
+        config.valid_tokens.extend(["reader".to_string(), "writer".to_string(), "legacy".to_string()]);
+        config.token_permissions.insert("reader".to_string(), [Permission::Read].into_iter().collect());
+        let auth = AuthMiddleware::new(config);
+        auth.restrict_token("writer", [Permission::Write].into_iter().collect()).await;
+        
+        let reader = auth.permissions("Bearer reader", None).await.unwrap();
+        assert!(reader.allows(Permission::Read));
+        assert!(!reader.allows(Permission::Write));
+        assert!(!reader.allows(Permission::Admin));
+        let writer = auth.permissions("writer", None).await.unwrap();
+        assert!(writer.allows(Permission::Write) && !writer.allows(Permission::Read));
+        assert_eq!(auth.permissions("legacy", None).await.unwrap(), Permissions::read_write());
+        assert!(!auth.permissions("legacy", None).await.unwrap().allows(Permission::Admin));
+        
+        // Re-adding or removing a token resets it to read and write
+        auth.add_scoped_token("writer".to_string(), TokenScope::Api, None, None).await;
+        assert_eq!(auth.permissions("writer", None).await.unwrap(), Permissions::read_write());
+        auth.add_scoped_token("writer".to_string(), TokenScope::Api, None, Some([Permission::Read].into_iter().collect())).await;
+        let writer = auth.permissions("writer", None).await.unwrap();
+        assert!(writer.allows(Permission::Read) && !writer.allows(Permission::Write));
+        auth.remove_token("reader").await;
+        auth.add_token("reader".to_string()).await;
+        assert_eq!(auth.permissions("reader", None).await.unwrap(), Permissions::read_write());
+        
+        let disabled = AuthMiddleware::new(AuthConfig { enabled: false, ..Default::default() });
+        assert_eq!(disabled.permissions("anything", None).await, None);
+    }
+    
+    #[tokio::test]
+    async fn test_jwt_scopes_grant_permissions() {
+        let auth = AuthMiddleware::new(AuthConfig {
+            jwt: Some(JwtAuth::hs256(b"secret").with_admin_scope("ops")),
+            ..Default::default()
+        });
+        let claims = |scopes: &[&str]| AuthClaims {
+            subject: None,
+            scopes: scopes.iter().map(|s| s.to_string()).collect(),
+        };
+        
+        let granted = auth.permissions("token", Some(&claims(&["read", "profile"]))).await.unwrap();
+        assert!(granted.allows(Permission::Read) && !granted.allows(Permission::Write));
+        assert!(auth.permissions("token", Some(&claims(&["ops"]))).await.unwrap().allows(Permission::Write));
+        assert!(!auth.permissions("token", Some(&claims(&["admin"]))).await.unwrap().allows(Permission::Admin));
+        assert!(!auth.permissions("token", Some(&claims(&[]))).await.unwrap().allows(Permission::Read));
+    }
+}
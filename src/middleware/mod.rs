@@ -5,7 +5,7 @@ pub mod auth;
 pub mod validator;
 pub mod body_limit;
 
-pub use rate_limiter::{RateLimiter, RateLimitConfig, RateLimitError};
-pub use auth::{AuthMiddleware, AuthConfig, AuthError};
-pub use validator::{InputValidator, ValidationError};
+pub use rate_limiter::{RateLimiter, RateLimitAlgorithm, RateLimitConfig, RateLimitError};
+pub use auth::{AuthMiddleware, AuthClaims, AuthConfig, AuthError, JwtAuth, Permission, Permissions, TokenScope};
+pub use validator::{InputValidator, ValidationDetail, ValidationError};
 pub use body_limit::{BodyLimiter, BodyLimitConfig};
\ No newline at end of file
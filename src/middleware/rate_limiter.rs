@@ -1,5 +1,8 @@
 //! Rate limiting middleware for API protection
 
+use crate::clock::{system_clock, Clock};
+use crate::observability::MetricsCollector;
+use crate::shutdown::ShutdownNotifier;
 use dashmap::DashMap;
 use std::sync::Arc;
 use std::time::{Duration, Instant};
@@ -14,6 +17,8 @@ pub struct RateLimitConfig {
     pub window_duration: Duration,
     /// Whether to enable rate limiting
     pub enabled: bool,
+    /// How requests are counted against `max_requests`
+    pub algorithm: RateLimitAlgorithm,
 }
 
 impl Default for RateLimitConfig {
@@ -22,21 +27,137 @@ impl Default for RateLimitConfig {
             max_requests: 100,
             window_duration: Duration::from_secs(60),
             enabled: true,
+            algorithm: RateLimitAlgorithm::default(),
         }
     }
 }
 
+/// Rate limiting algorithm
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum RateLimitAlgorithm {
+    /// Allow `max_requests` per `window_duration`, resetting the count when
+    /// the window ends. Up to twice the limit can pass around a window boundary.
+    #[default]
+    FixedWindow,
+    /// Hold up to `max_requests` tokens, refilled continuously at
+    /// `refill_per_sec`; each request takes one token
+    TokenBucket { refill_per_sec: f64 },
+}
+
 /// Request record for tracking
 #[derive(Debug, Clone)]
 struct RequestRecord {
     count: usize,
     window_start: Instant,
+    /// Tokens left in the bucket as of `last_refill` (token bucket only)
+    tokens: f64,
+    last_refill: Instant,
+}
+
+impl RequestRecord {
+    fn new(config: &RateLimitConfig, now: Instant) -> Self {
+        Self {
+            count: 0,
+            window_start: now,
+            tokens: config.max_requests as f64,
+            last_refill: now,
+        }
+    }
+    
+    /// Count a request in the current window, or return the time until the
+    /// window resets if it is full
+    fn take_from_window(&mut self, config: &RateLimitConfig, now: Instant) -> Result<(), Duration> {
+        // Check if window has expired
+        if now.duration_since(self.window_start) >= config.window_duration {
+            // Reset window
+            self.count = 0;
+            self.window_start = now;
+        }
+
+        if self.count >= config.max_requests {
+            return Err(self.retry_after(config, now));
+        }
+        self.count += 1;
+        Ok(())
+    }
+    
+    /// Take a token from the bucket, or return the time until one is available
+    fn take_token(&mut self, config: &RateLimitConfig, refill_per_sec: f64, now: Instant) -> Result<(), Duration> {
+        let capacity = config.max_requests as f64;
+        let refilled = now.duration_since(self.last_refill).as_secs_f64() * refill_per_sec;
+        self.tokens = (self.tokens + refilled).min(capacity);
+        self.last_refill = now;
+
+        if self.tokens < 1.0 {
+            return Err(self.retry_after(config, now));
+        }
+        self.tokens -= 1.0;
+        self.count = (capacity - self.tokens).ceil() as usize;
+        Ok(())
+    }
+    
+    /// Give back a request taken by `take_from_window` or `take_token`
+    fn give_back(&mut self, config: &RateLimitConfig, now: Instant) {
+        match config.algorithm {
+            RateLimitAlgorithm::FixedWindow => {
+                // A request from an earlier window no longer counts anyway
+                if now.duration_since(self.window_start) < config.window_duration {
+                    self.count = self.count.saturating_sub(1);
+                }
+            }
+            RateLimitAlgorithm::TokenBucket { .. } => {
+                let capacity = config.max_requests as f64;
+                self.tokens = (self.tokens + 1.0).min(capacity);
+                self.count = (capacity - self.tokens).ceil() as usize;
+            }
+        }
+    }
+    
+    /// Time until another request would be allowed, zero if one would be now
+    fn retry_after(&self, config: &RateLimitConfig, now: Instant) -> Duration {
+        match config.algorithm {
+            RateLimitAlgorithm::FixedWindow => {
+                let elapsed = now.duration_since(self.window_start);
+                if elapsed >= config.window_duration || self.count < config.max_requests {
+                    Duration::ZERO
+                } else {
+                    config.window_duration - elapsed
+                }
+            }
+            RateLimitAlgorithm::TokenBucket { refill_per_sec } => {
+                let refilled = now.duration_since(self.last_refill).as_secs_f64() * refill_per_sec;
+                let tokens = (self.tokens + refilled).min(config.max_requests as f64);
+                if tokens >= 1.0 {
+                    return Duration::ZERO;
+                }
+                // A bucket that never refills never has a token to wait for
+                Duration::try_from_secs_f64((1.0 - tokens) / refill_per_sec).unwrap_or(Duration::MAX)
+            }
+        }
+    }
+    
+    /// Whether a fresh record would behave the same as this one
+    fn is_expired(&self, config: &RateLimitConfig, now: Instant) -> bool {
+        match config.algorithm {
+            RateLimitAlgorithm::FixedWindow => {
+                now.duration_since(self.window_start) >= config.window_duration
+            }
+            RateLimitAlgorithm::TokenBucket { refill_per_sec } => {
+                let refilled = now.duration_since(self.last_refill).as_secs_f64() * refill_per_sec;
+                self.tokens + refilled >= config.max_requests as f64
+            }
+        }
+    }
 }
 
 /// Rate limiter implementation with lock-free DashMap
 pub struct RateLimiter {
     config: RateLimitConfig,
+    /// Limits for individual clients, overriding `config`
+    client_limits: Arc<DashMap<String, RateLimitConfig>>,
     records: Arc<DashMap<String, RequestRecord>>,
+    metrics: Option<Arc<MetricsCollector>>,
+    clock: Arc<dyn Clock>,
 }
 
 impl RateLimiter {
@@ -44,89 +165,140 @@ impl RateLimiter {
     pub fn new(config: RateLimitConfig) -> Self {
         Self {
             config,
+            client_limits: Arc::new(DashMap::new()),
             records: Arc::new(DashMap::new()),
+            metrics: None,
+            clock: system_clock(),
         }
     }
+    
+    /// Use a specific clock for window timing
+    pub fn with_clock(mut self, clock: Arc<dyn Clock>) -> Self {
+        self.clock = clock;
+        self
+    }
+    
+    /// Set metrics collector for counting rejected requests
+    pub fn with_metrics(mut self, metrics: Arc<MetricsCollector>) -> Self {
+        self.metrics = Some(metrics);
+        self
+    }
+    
+    /// Apply `config` to `client_id` instead of the default limit
+    pub fn set_client_limit(&self, client_id: impl Into<String>, config: RateLimitConfig) {
+        self.client_limits.insert(client_id.into(), config);
+    }
+    
+    /// Return `client_id` to the default limit
+    pub fn remove_client_limit(&self, client_id: &str) {
+        self.client_limits.remove(client_id);
+    }
+    
+    /// Limit that applies to `client_id`
+    pub fn client_limit(&self, client_id: &str) -> RateLimitConfig {
+        self.client_limits
+            .get(client_id)
+            .map(|config| config.value().clone())
+            .unwrap_or_else(|| self.config.clone())
+    }
 
     /// Check if request should be allowed (lock-free)
     pub async fn check_rate_limit(&self, client_id: &str) -> Result<(), RateLimitError> {
-        if !self.config.enabled {
+        let config = self.client_limit(client_id);
+        if !config.enabled {
             return Ok(());
         }
 
-        let now = Instant::now();
+        let now = self.clock.now();
         let client_key = client_id.to_string();
 
         // Get or create record
-        let mut entry = self.records.entry(client_key.clone()).or_insert(RequestRecord {
-            count: 0,
-            window_start: now,
-        });
+        let mut entry = self.records
+            .entry(client_key)
+            .or_insert_with(|| RequestRecord::new(&config, now));
 
         let record = entry.value_mut();
 
-        // Check if window has expired
-        if now.duration_since(record.window_start) >= self.config.window_duration {
-            // Reset window
-            record.count = 0;
-            record.window_start = now;
-        }
-
         // Check rate limit
-        if record.count >= self.config.max_requests {
-            let retry_after = self.config.window_duration
-                .saturating_sub(now.duration_since(record.window_start));
-            
+        let allowed = match config.algorithm {
+            RateLimitAlgorithm::FixedWindow => record.take_from_window(&config, now),
+            RateLimitAlgorithm::TokenBucket { refill_per_sec } => record.take_token(&config, refill_per_sec, now),
+        };
+        if let Err(retry_after) = allowed {
             warn!(
                 "Rate limit exceeded for client: {} ({} requests in window)",
                 client_id, record.count
             );
             
+            if let Some(metrics) = &self.metrics {
+                metrics.record_rate_limited();
+            }
+            
             return Err(RateLimitError::LimitExceeded {
                 retry_after,
-                limit: self.config.max_requests,
+                limit: config.max_requests,
             });
         }
 
-        // Increment counter
-        record.count += 1;
-        debug!("Request allowed for client: {} ({}/{})", client_id, record.count, self.config.max_requests);
+        debug!("Request allowed for client: {} ({}/{})", client_id, record.count, config.max_requests);
 
         Ok(())
     }
 
+    /// Give back a request allowed by `check_rate_limit` that did no work
+    pub async fn refund(&self, client_id: &str) {
+        let config = self.client_limit(client_id);
+        if let Some(mut record) = self.records.get_mut(client_id) {
+            record.give_back(&config, self.clock.now());
+        }
+    }
+
     /// Get current usage for a client
     pub async fn get_usage(&self, client_id: &str) -> Option<(usize, Duration)> {
         self.records.get(client_id).map(|record| {
-            let elapsed = Instant::now().duration_since(record.window_start);
+            let elapsed = self.clock.now().duration_since(record.window_start);
             (record.count, elapsed)
         })
     }
 
+    /// Time until `client_id` may make another request, zero if it may now
+    pub async fn retry_after(&self, client_id: &str) -> Duration {
+        let config = self.client_limit(client_id);
+        self.records
+            .get(client_id)
+            .map_or(Duration::ZERO, |record| record.retry_after(&config, self.clock.now()))
+    }
+
     /// Reset rate limit for a client
     pub async fn reset(&self, client_id: &str) {
         self.records.remove(client_id);
         debug!("Rate limit reset for client: {}", client_id);
     }
 
-    /// Clean up expired records
+    /// Clean up expired records, each against its own client's window
     pub async fn cleanup_expired(&self) {
-        let now = Instant::now();
+        let now = self.clock.now();
         
-        self.records.retain(|_, record| {
-            now.duration_since(record.window_start) < self.config.window_duration
-        });
+        self.records.retain(|client_id, record| !record.is_expired(&self.client_limit(client_id), now));
         
         debug!("Cleaned up expired rate limit records");
     }
     
     /// Start background cleanup task
-    pub fn start_cleanup_task(self: Arc<Self>) -> tokio::task::JoinHandle<()> {
+    ///
+    /// The task runs until `shutdown` is signaled; await the returned handle
+    /// to wait for it to finish.
+    pub fn start_cleanup_task(self: Arc<Self>, shutdown: ShutdownNotifier) -> tokio::task::JoinHandle<()> {
         tokio::spawn(async move {
             let mut interval = tokio::time::interval(self.config.window_duration);
             loop {
-                interval.tick().await;
-                self.cleanup_expired().await;
+                tokio::select! {
+                    _ = interval.tick() => self.cleanup_expired().await,
+                    _ = shutdown.wait() => {
+                        debug!("Rate limiter cleanup task stopped");
+                        return;
+                    }
+                }
             }
         })
     }
@@ -172,6 +344,7 @@ mod tests {
             max_requests: 5,
             window_duration: Duration::from_secs(60),
             enabled: true,
+            ..Default::default()
         };
         let limiter = RateLimiter::new(config);
 
@@ -186,6 +359,7 @@ mod tests {
             max_requests: 3,
             window_duration: Duration::from_secs(60),
             enabled: true,
+            ..Default::default()
         };
         let limiter = RateLimiter::new(config);
 
@@ -204,6 +378,7 @@ mod tests {
             max_requests: 2,
             window_duration: Duration::from_millis(100),
             enabled: true,
+            ..Default::default()
         };
         let limiter = RateLimiter::new(config);
 
@@ -219,12 +394,117 @@ mod tests {
         assert!(limiter.check_rate_limit("client1").await.is_ok());
     }
 
+    #[tokio::test]
+    async fn test_rate_limit_window_reset_with_fake_clock() {
+        let clock = Arc::new(crate::clock::FakeClock::new());
+        let limiter = RateLimiter::new(RateLimitConfig {
+            max_requests: 2,
+            window_duration: Duration::from_secs(60),
+            enabled: true,
+            ..Default::default()
+        })
+        .with_clock(clock.clone());
+
+        limiter.check_rate_limit("client1").await.unwrap();
+        limiter.check_rate_limit("client1").await.unwrap();
+
+        clock.advance(Duration::from_secs(59));
+        match limiter.check_rate_limit("client1").await {
+            Err(RateLimitError::LimitExceeded { retry_after, .. }) => {
+                assert_eq!(retry_after, Duration::from_secs(1));
+            }
+            other => panic!("expected rate limit, got {:?}", other),
+        }
+
+        clock.advance(Duration::from_secs(1));
+        assert!(limiter.check_rate_limit("client1").await.is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_token_bucket_smooths_bursts_across_window_boundary() {
+        async fn allowed(limiter: &RateLimiter, requests: usize) -> usize {
+            let mut allowed = 0;
+            for _ in 0..requests {
+                if limiter.check_rate_limit("client1").await.is_ok() {
+                    allowed += 1;
+                }
+            }
+            allowed
+        }
+
+        let limiter = |algorithm, clock: Arc<crate::clock::FakeClock>| {
+            RateLimiter::new(RateLimitConfig {
+                max_requests: 10,
+                window_duration: Duration::from_secs(10),
+                enabled: true,
+                algorithm,
+            })
+            .with_clock(clock)
+        };
+
+        // Fixed window: a full burst at the end of one window and another at
+        // the start of the next lets twice the limit through in 200ms
+        let clock = Arc::new(crate::clock::FakeClock::new());
+        let fixed = limiter(RateLimitAlgorithm::FixedWindow, clock.clone());
+        allowed(&fixed, 1).await;
+        clock.advance(Duration::from_millis(9_900));
+        assert_eq!(allowed(&fixed, 20).await, 9);
+        clock.advance(Duration::from_millis(200));
+        assert_eq!(allowed(&fixed, 20).await, 10);
+
+        // Token bucket: the same pattern only gets what refilled in between
+        let clock = Arc::new(crate::clock::FakeClock::new());
+        let bucket = limiter(RateLimitAlgorithm::TokenBucket { refill_per_sec: 1.0 }, clock.clone());
+        allowed(&bucket, 1).await;
+        clock.advance(Duration::from_millis(9_900));
+        assert_eq!(allowed(&bucket, 20).await, 10);
+        clock.advance(Duration::from_millis(200));
+        match bucket.check_rate_limit("client1").await {
+            Err(RateLimitError::LimitExceeded { retry_after, .. }) => {
+                assert_eq!(retry_after, Duration::from_millis(800));
+            }
+            other => panic!("expected rate limit, got {:?}", other),
+        }
+
+        clock.advance(Duration::from_secs(3));
+        assert_eq!(allowed(&bucket, 20).await, 3);
+    }
+
+    #[tokio::test]
+    async fn test_refunded_requests_free_their_quota() {
+        for algorithm in [RateLimitAlgorithm::FixedWindow, RateLimitAlgorithm::TokenBucket { refill_per_sec: 0.0 }] {
+            let limiter = RateLimiter::new(RateLimitConfig {
+                max_requests: 2,
+                window_duration: Duration::from_secs(60),
+                enabled: true,
+                algorithm,
+            });
+            
+            limiter.check_rate_limit("client").await.unwrap();
+            limiter.check_rate_limit("client").await.unwrap();
+            assert!(limiter.check_rate_limit("client").await.is_err());
+            
+            limiter.refund("client").await;
+            limiter.check_rate_limit("client").await.unwrap();
+            assert!(limiter.check_rate_limit("client").await.is_err());
+            
+            // Refunds never raise the quota above the limit
+            for _ in 0..5 {
+                limiter.refund("client").await;
+            }
+            limiter.check_rate_limit("client").await.unwrap();
+            limiter.check_rate_limit("client").await.unwrap();
+            assert!(limiter.check_rate_limit("client").await.is_err());
+        }
+    }
+
     #[tokio::test]
     async fn test_rate_limit_per_client() {
         let config = RateLimitConfig {
             max_requests: 2,
             window_duration: Duration::from_secs(60),
             enabled: true,
+            ..Default::default()
         };
         let limiter = RateLimiter::new(config);
 
@@ -237,12 +517,75 @@ mod tests {
         assert!(limiter.check_rate_limit("client2").await.is_ok());
     }
 
+    #[tokio::test]
+    async fn test_client_limit_overrides_default() {
+        let limiter = RateLimiter::new(RateLimitConfig {
+            max_requests: 2,
+            window_duration: Duration::from_secs(60),
+            enabled: true,
+            ..Default::default()
+        });
+        limiter.set_client_limit("premium", RateLimitConfig {
+            max_requests: 5,
+            window_duration: Duration::from_secs(60),
+            enabled: true,
+            ..Default::default()
+        });
+
+        for i in 0..5 {
+            assert!(limiter.check_rate_limit("premium").await.is_ok(), "Request {} should be allowed", i);
+        }
+        match limiter.check_rate_limit("premium").await {
+            Err(RateLimitError::LimitExceeded { limit, .. }) => assert_eq!(limit, 5),
+            other => panic!("expected rate limit, got {:?}", other),
+        }
+
+        limiter.check_rate_limit("free").await.unwrap();
+        limiter.check_rate_limit("free").await.unwrap();
+        assert!(limiter.check_rate_limit("free").await.is_err());
+
+        limiter.remove_client_limit("premium");
+        limiter.reset("premium").await;
+        limiter.check_rate_limit("premium").await.unwrap();
+        limiter.check_rate_limit("premium").await.unwrap();
+        assert!(limiter.check_rate_limit("premium").await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_cleanup_respects_client_windows() {
+        let clock = Arc::new(crate::clock::FakeClock::new());
+        let limiter = RateLimiter::new(RateLimitConfig {
+            max_requests: 1,
+            window_duration: Duration::from_secs(60),
+            enabled: true,
+            ..Default::default()
+        })
+        .with_clock(clock.clone());
+        limiter.set_client_limit("hourly", RateLimitConfig {
+            max_requests: 1,
+            window_duration: Duration::from_secs(3600),
+            enabled: true,
+            ..Default::default()
+        });
+
+        limiter.check_rate_limit("default").await.unwrap();
+        limiter.check_rate_limit("hourly").await.unwrap();
+
+        clock.advance(Duration::from_secs(120));
+        limiter.cleanup_expired().await;
+        assert!(limiter.get_usage("default").await.is_none());
+        assert!(limiter.get_usage("hourly").await.is_some());
+        // Cleanup must not hand the long-window client a fresh window early
+        assert!(limiter.check_rate_limit("hourly").await.is_err());
+    }
+
     #[tokio::test]
     async fn test_rate_limit_disabled() {
         let config = RateLimitConfig {
             max_requests: 1,
             window_duration: Duration::from_secs(60),
             enabled: false,
+            ..Default::default()
         };
         let limiter = RateLimiter::new(config);
 
@@ -251,4 +594,22 @@ mod tests {
             assert!(limiter.check_rate_limit("client1").await.is_ok());
         }
     }
-}
\ No newline at end of file
+
+    #[tokio::test]
+    async fn test_cleanup_task_exits_on_shutdown() {
+        let limiter = Arc::new(RateLimiter::new(RateLimitConfig {
+            max_requests: 5,
+            window_duration: Duration::from_secs(3600),
+            enabled: true,
+            ..Default::default()
+        }));
+        let coordinator = crate::shutdown::ShutdownCoordinator::new();
+        let handle = limiter.start_cleanup_task(coordinator.subscribe());
+
+        coordinator.shutdown();
+        tokio::time::timeout(Duration::from_secs(1), handle)
+            .await
+            .expect("cleanup task did not stop")
+            .unwrap();
+    }
+}
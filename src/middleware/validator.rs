@@ -1,5 +1,7 @@
 //! Input validation middleware
 
+use crate::hirag::{EXPIRES_AT_KEY, INTERNAL_KEY_PREFIX};
+use std::collections::HashMap;
 use tracing::{debug, warn};
 
 /// Maximum text length (8KB)
@@ -39,6 +41,18 @@ impl InputValidator {
         Ok(())
     }
 
+    /// Validate a search query
+    ///
+    /// Same rules as [`Self::validate_text`], but an empty query is reported
+    /// as [`ValidationError::EmptyQuery`] so it can't be confused with empty
+    /// context text.
+    pub fn validate_query(query: &str) -> Result<(), ValidationError> {
+        match Self::validate_text(query) {
+            Err(ValidationError::EmptyInput) => Err(ValidationError::EmptyQuery),
+            result => result,
+        }
+    }
+
     /// Sanitize text input
     pub fn sanitize_text(text: &str) -> String {
         text.chars()
@@ -67,6 +81,17 @@ impl InputValidator {
         Ok(())
     }
 
+    /// Validate the number of conditions in a request filter
+    pub fn validate_filter_conditions(count: usize, max: usize) -> Result<(), ValidationError> {
+        if count > max {
+            warn!("Validation failed: too many filter conditions ({} > {})", count, max);
+            return Err(ValidationError::TooManyFilterConditions { count, max });
+        }
+
+        debug!("Filter validation passed");
+        Ok(())
+    }
+
     /// Validate token count
     pub fn validate_token_count(count: usize, max: usize) -> Result<(), ValidationError> {
         if count == 0 {
@@ -128,10 +153,25 @@ impl InputValidator {
         if !key.chars().all(|c| c.is_alphanumeric() || c == '_' || c == '-') {
             return Err(ValidationError::InvalidMetadataKey);
         }
+        
+        if key.starts_with(INTERNAL_KEY_PREFIX) {
+            return Err(ValidationError::ReservedMetadataKey(key.to_string()));
+        }
 
         Ok(())
     }
     
+    /// Validate the `expires_at` metadata entry, if present
+    ///
+    /// Only numeric Unix timestamps can be compared when filtering, so any
+    /// other value would hide a context from search but not from lookups.
+    pub fn validate_expires_at(metadata: &HashMap<String, serde_json::Value>) -> Result<(), ValidationError> {
+        match metadata.get(EXPIRES_AT_KEY) {
+            Some(value) if !value.is_number() => Err(ValidationError::InvalidExpiry),
+            _ => Ok(()),
+        }
+    }
+    
     /// Validate metadata value
     pub fn validate_metadata_value(value: &serde_json::Value) -> Result<(), ValidationError> {
         // Serialize to check size
@@ -163,12 +203,30 @@ impl InputValidator {
     }
 }
 
+/// Machine-readable description of a validation failure
+#[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct ValidationDetail {
+    /// Stable error code
+    pub code: String,
+
+    /// Offending request field
+    pub field: String,
+
+    /// Limit that was exceeded, if any
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max: Option<usize>,
+}
+
 /// Validation errors
 #[derive(Debug, Clone, thiserror::Error)]
 pub enum ValidationError {
     #[error("Input text is empty")]
     EmptyInput,
 
+    #[error("Search query is empty")]
+    EmptyQuery,
+
     #[error("Text too long: {length} characters (max: {max_length})")]
     TextTooLong { length: usize, max_length: usize },
 
@@ -202,11 +260,56 @@ pub enum ValidationError {
     #[error("Invalid metadata key (must contain only alphanumeric, underscore, or hyphen)")]
     InvalidMetadataKey,
     
+    #[error("Metadata key '{0}' is reserved")]
+    ReservedMetadataKey(String),
+    
     #[error("Invalid metadata value")]
     InvalidMetadataValue,
     
     #[error("Metadata value too large: {size} bytes (max: {max_size})")]
     MetadataValueTooLarge { size: usize, max_size: usize },
+    
+    #[error("expires_at must be a Unix timestamp")]
+    InvalidExpiry,
+    
+    #[error("Too many filter conditions: {count} (max: {max})")]
+    TooManyFilterConditions { count: usize, max: usize },
+    
+    #[error("Invalid request body: {0}")]
+    InvalidBody(String),
+}
+
+impl ValidationError {
+    /// Stable code, field and limit for this error
+    pub fn to_detail(&self) -> ValidationDetail {
+        let (code, field, max) = match self {
+            ValidationError::EmptyInput => ("EMPTY_INPUT", "text", None),
+            ValidationError::EmptyQuery => ("EMPTY_QUERY", "query", None),
+            ValidationError::TextTooLong { max_length, .. } => ("TEXT_TOO_LONG", "text", Some(*max_length)),
+            ValidationError::InvalidCharacters => ("INVALID_CHARACTERS", "text", None),
+            ValidationError::EmptyBatch => ("EMPTY_BATCH", "batch", None),
+            ValidationError::BatchTooLarge { max_size, .. } => ("BATCH_TOO_LARGE", "batch", Some(*max_size)),
+            ValidationError::InvalidTokenCount => ("INVALID_TOKEN_COUNT", "max_tokens", None),
+            ValidationError::TokenLimitExceeded { max, .. } => ("TOKEN_LIMIT_EXCEEDED", "max_tokens", Some(*max)),
+            ValidationError::InvalidVectorDimension { expected, .. } => ("INVALID_VECTOR_DIMENSION", "vector", Some(*expected)),
+            ValidationError::InvalidRelevanceScore { .. } => ("INVALID_RELEVANCE_SCORE", "relevance_score", None),
+            ValidationError::EmptyMetadataKey => ("METADATA_KEY_EMPTY", "metadata.key", None),
+            ValidationError::MetadataKeyTooLong { max_length, .. } => ("METADATA_KEY_TOO_LONG", "metadata.key", Some(*max_length)),
+            ValidationError::InvalidMetadataKey => ("METADATA_KEY_INVALID", "metadata.key", None),
+            ValidationError::ReservedMetadataKey(_) => ("METADATA_KEY_RESERVED", "metadata.key", None),
+            ValidationError::InvalidMetadataValue => ("METADATA_VALUE_INVALID", "metadata.value", None),
+            ValidationError::MetadataValueTooLarge { max_size, .. } => ("METADATA_VALUE_TOO_LARGE", "metadata.value", Some(*max_size)),
+            ValidationError::InvalidExpiry => ("EXPIRES_AT_INVALID", "metadata.expires_at", None),
+            ValidationError::TooManyFilterConditions { max, .. } => ("TOO_MANY_FILTER_CONDITIONS", "filters", Some(*max)),
+            ValidationError::InvalidBody(_) => ("INVALID_BODY", "body", None),
+        };
+
+        ValidationDetail {
+            code: code.to_string(),
+            field: field.to_string(),
+            max,
+        }
+    }
 }
 
 #[cfg(test)]
@@ -224,6 +327,12 @@ mod tests {
         assert!(InputValidator::validate_text("   ").is_err());
     }
 
+    #[test]
+    fn test_validate_query_empty() {
+        assert!(matches!(InputValidator::validate_query(" "), Err(ValidationError::EmptyQuery)));
+        assert!(InputValidator::validate_query("dark mode").is_ok());
+    }
+
     #[test]
     fn test_validate_text_too_long() {
         let long_text = "a".repeat(MAX_TEXT_LENGTH + 1);
@@ -274,5 +383,38 @@ mod tests {
         assert!(InputValidator::validate_metadata_key("").is_err());
         assert!(InputValidator::validate_metadata_key("invalid key").is_err());
         assert!(InputValidator::validate_metadata_key("invalid@key").is_err());
+        assert!(InputValidator::validate_metadata_key("deleted_at").is_ok());
+        assert!(matches!(
+            InputValidator::validate_metadata_key("_deleted_at"),
+            Err(ValidationError::ReservedMetadataKey(_))
+        ));
+        assert!(matches!(
+            InputValidator::validate_metadata_key("_history"),
+            Err(ValidationError::ReservedMetadataKey(_))
+        ));
+    }
+
+    #[test]
+    fn test_validate_expires_at() {
+        let metadata = |value: serde_json::Value| HashMap::from([(EXPIRES_AT_KEY.to_string(), value)]);
+        assert!(InputValidator::validate_expires_at(&HashMap::new()).is_ok());
+        assert!(InputValidator::validate_expires_at(&metadata(serde_json::json!(1_700_000_000))).is_ok());
+        assert!(InputValidator::validate_expires_at(&metadata(serde_json::json!(1_700_000_000.5))).is_ok());
+        assert!(matches!(
+            InputValidator::validate_expires_at(&metadata(serde_json::json!("2030-01-01T00:00:00Z"))),
+            Err(ValidationError::InvalidExpiry)
+        ));
+    }
+
+    #[test]
+    fn test_to_detail() {
+        let detail = ValidationError::MetadataKeyTooLong { length: 300, max_length: 256 }.to_detail();
+        assert_eq!(detail.code, "METADATA_KEY_TOO_LONG");
+        assert_eq!(detail.field, "metadata.key");
+        assert_eq!(detail.max, Some(256));
+
+        let detail = ValidationError::EmptyInput.to_detail();
+        assert_eq!(detail.code, "EMPTY_INPUT");
+        assert_eq!(detail.max, None);
     }
 }
\ No newline at end of file
@@ -3,11 +3,12 @@
 use serde::{Deserialize, Serialize};
 use std::sync::Arc;
 use std::time::{Duration, Instant};
-use tokio::sync::RwLock;
-use tracing::debug;
+use tokio::sync::{watch, RwLock};
+use tracing::{debug, warn};
 
 /// Health status
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
 #[serde(rename_all = "lowercase")]
 pub enum HealthStatus {
     Healthy,
@@ -17,6 +18,7 @@ pub enum HealthStatus {
 
 /// Component health
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
 pub struct ComponentHealth {
     /// Component name
     pub name: String,
@@ -33,6 +35,7 @@ pub struct ComponentHealth {
 
 /// Overall system health
 #[derive(Debug, Clone, Serialize, Deserialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
 pub struct SystemHealth {
     /// Overall status
     pub status: HealthStatus,
@@ -47,6 +50,19 @@ pub struct SystemHealth {
     pub timestamp: i64,
 }
 
+/// Change of the overall status between two consecutive health checks
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+pub struct HealthTransition {
+    /// Status reported by the previous check
+    pub from: HealthStatus,
+    
+    /// Status reported by the latest check
+    pub to: HealthStatus,
+    
+    /// Timestamp of the check that observed the change
+    pub timestamp: i64,
+}
+
 /// Cached health check result
 #[derive(Debug, Clone)]
 struct CachedHealth {
@@ -54,6 +70,13 @@ struct CachedHealth {
     cached_at: Instant,
 }
 
+/// Cached result of a live embedding probe
+#[derive(Debug, Clone)]
+struct CachedProbe {
+    result: ComponentHealth,
+    probed_at: Instant,
+}
+
 /// Health checker with caching
 pub struct HealthChecker {
     start_time: Instant,
@@ -61,8 +84,13 @@ pub struct HealthChecker {
     embedding_client: Option<std::sync::Arc<dyn crate::embedding::EmbeddingProvider>>,
     cache: Option<std::sync::Arc<crate::embedding::EmbeddingCache>>,
     circuit_breaker: Option<std::sync::Arc<crate::vector_db::CircuitBreaker>>,
+    pipeline_breaker: Option<std::sync::Arc<crate::vector_db::PipelineBreaker>>,
     cached_result: Arc<RwLock<Option<CachedHealth>>>,
     cache_ttl: Duration,
+    embedding_probe_ttl: Option<Duration>,
+    embedding_probe: Arc<RwLock<Option<CachedProbe>>>,
+    last_status: Arc<RwLock<Option<HealthStatus>>>,
+    transitions: watch::Sender<Option<HealthTransition>>,
 }
 
 impl HealthChecker {
@@ -79,8 +107,13 @@ impl HealthChecker {
             embedding_client: None,
             cache: None,
             circuit_breaker: None,
+            pipeline_breaker: None,
             cached_result: Arc::new(RwLock::new(None)),
             cache_ttl,
+            embedding_probe_ttl: None,
+            embedding_probe: Arc::new(RwLock::new(None)),
+            last_status: Arc::new(RwLock::new(None)),
+            transitions: watch::channel(None).0,
         }
     }
     
@@ -96,6 +129,15 @@ impl HealthChecker {
         self
     }
     
+    /// Enable deep embedding checks that call the embedding API
+    ///
+    /// The probe result is cached for `probe_ttl` so frequent health checks
+    /// don't translate into per-check API cost.
+    pub fn with_deep_embedding_check(mut self, probe_ttl: Duration) -> Self {
+        self.embedding_probe_ttl = Some(probe_ttl);
+        self
+    }
+    
     /// Set cache for health checks
     pub fn with_cache(mut self, cache: std::sync::Arc<crate::embedding::EmbeddingCache>) -> Self {
         self.cache = Some(cache);
@@ -108,6 +150,35 @@ impl HealthChecker {
         self
     }
     
+    /// Report the state of the aggregate embedding + vector database breaker
+    pub fn with_pipeline_breaker(mut self, pipeline_breaker: std::sync::Arc<crate::vector_db::PipelineBreaker>) -> Self {
+        self.pipeline_breaker = Some(pipeline_breaker);
+        self
+    }
+    
+    /// Subscribe to changes of the overall status
+    ///
+    /// The receiver holds the most recent transition, or `None` until the
+    /// status has changed at least once.
+    pub fn subscribe_transitions(&self) -> watch::Receiver<Option<HealthTransition>> {
+        self.transitions.subscribe()
+    }
+    
+    /// Remember the latest overall status and announce it if it changed
+    async fn record_status(&self, health: &SystemHealth) {
+        let mut last = self.last_status.write().await;
+        let previous = last.replace(health.status.clone());
+        
+        if let Some(from) = previous.filter(|from| *from != health.status) {
+            warn!("Health status changed from {:?} to {:?}", from, health.status);
+            self.transitions.send_replace(Some(HealthTransition {
+                from,
+                to: health.status.clone(),
+                timestamp: health.timestamp,
+            }));
+        }
+    }
+    
     /// Check overall system health with caching
     pub async fn check_health(&self) -> SystemHealth {
         // Check if we have a valid cached result
@@ -153,6 +224,11 @@ impl HealthChecker {
         // Check circuit breaker
         components.push(self.check_circuit_breaker().await);
         
+        // Check the aggregate pipeline breaker, if configured
+        if let Some(pipeline) = &self.pipeline_breaker {
+            components.push(Self::check_pipeline(pipeline).await);
+        }
+        
         // Determine overall status
         let status = if components.iter().all(|c| c.status == HealthStatus::Healthy) {
             HealthStatus::Healthy
@@ -162,12 +238,15 @@ impl HealthChecker {
             HealthStatus::Degraded
         };
         
-        SystemHealth {
+        let health = SystemHealth {
             status,
             uptime_secs: self.start_time.elapsed().as_secs(),
             components,
             timestamp: chrono::Utc::now().timestamp(),
-        }
+        };
+        
+        self.record_status(&health).await;
+        health
     }
     
     /// Force refresh health check (bypass cache)
@@ -188,6 +267,10 @@ impl HealthChecker {
     async fn check_embedding_service(&self) -> ComponentHealth {
         let start = Instant::now();
         
+        if let (Some(client), Some(probe_ttl)) = (&self.embedding_client, self.embedding_probe_ttl) {
+            return self.probe_embedding_service(client.as_ref(), probe_ttl).await;
+        }
+        
         if let Some(client) = &self.embedding_client {
             // Try to get embedding dimension as a health check
             match tokio::time::timeout(
@@ -223,6 +306,55 @@ impl HealthChecker {
         }
     }
     
+    /// Live embedding probe, cached for `probe_ttl`
+    async fn probe_embedding_service(
+        &self,
+        client: &dyn crate::embedding::EmbeddingProvider,
+        probe_ttl: Duration,
+    ) -> ComponentHealth {
+        {
+            let cached = self.embedding_probe.read().await;
+            if let Some(probe) = &*cached {
+                if probe.probed_at.elapsed() < probe_ttl {
+                    return probe.result.clone();
+                }
+            }
+        }
+        
+        let start = Instant::now();
+        let result = match tokio::time::timeout(
+            std::time::Duration::from_secs(5),
+            client.health()
+        ).await {
+            Ok(Ok(_)) => ComponentHealth {
+                name: "embedding_service".to_string(),
+                status: HealthStatus::Healthy,
+                message: Some("Live probe succeeded".to_string()),
+                response_time_ms: Some(start.elapsed().as_millis() as u64),
+            },
+            Ok(Err(e)) => ComponentHealth {
+                name: "embedding_service".to_string(),
+                status: HealthStatus::Unhealthy,
+                message: Some(format!("Live probe failed: {}", e)),
+                response_time_ms: Some(start.elapsed().as_millis() as u64),
+            },
+            Err(_) => ComponentHealth {
+                name: "embedding_service".to_string(),
+                status: HealthStatus::Unhealthy,
+                message: Some("Health check timeout".to_string()),
+                response_time_ms: Some(5000),
+            },
+        };
+        
+        let mut cached = self.embedding_probe.write().await;
+        *cached = Some(CachedProbe {
+            result: result.clone(),
+            probed_at: Instant::now(),
+        });
+        
+        result
+    }
+    
     /// Check vector database health
     async fn check_vector_db(&self) -> ComponentHealth {
         let start = Instant::now();
@@ -286,11 +418,7 @@ impl HealthChecker {
         if let Some(cache) = &self.cache {
             // Get cache statistics
             let stats = cache.stats().await;
-            let hit_rate = if stats.hits + stats.misses > 0 {
-                stats.hits as f64 / (stats.hits + stats.misses) as f64
-            } else {
-                0.0
-            };
+            let hit_rate = stats.hit_rate;
             
             let status = if hit_rate > 0.5 {
                 HealthStatus::Healthy
@@ -350,6 +478,28 @@ impl HealthChecker {
         }
     }
     
+    /// Check the aggregate pipeline breaker
+    async fn check_pipeline(pipeline: &crate::vector_db::PipelineBreaker) -> ComponentHealth {
+        let (status, message) = match pipeline.state().await {
+            crate::vector_db::CircuitState::Closed => {
+                (HealthStatus::Healthy, "Embedding and vector database circuits closed")
+            }
+            crate::vector_db::CircuitState::Open => {
+                (HealthStatus::Unhealthy, "Embedding and vector database circuits open - requests rejected")
+            }
+            crate::vector_db::CircuitState::HalfOpen => {
+                (HealthStatus::Degraded, "Embedding or vector database circuit not closed")
+            }
+        };
+        
+        ComponentHealth {
+            name: "pipeline".to_string(),
+            status,
+            message: Some(message.to_string()),
+            response_time_ms: Some(0),
+        }
+    }
+    
     /// Simple liveness check
     pub fn liveness(&self) -> bool {
         true
@@ -371,6 +521,7 @@ impl Default for HealthChecker {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::test_support::MockEmbeddingProvider;
     
     #[tokio::test]
     async fn test_health_check() {
@@ -394,4 +545,78 @@ mod tests {
         let checker = HealthChecker::new();
         assert!(checker.readiness().await);
     }
-}
\ No newline at end of file
+    
+    #[tokio::test]
+    async fn test_cache_health_follows_hit_rate() {
+        let cache = Arc::new(crate::embedding::EmbeddingCache::new(10, Duration::from_secs(60)));
+        let checker = HealthChecker::new().with_cache(cache.clone());
+        
+        cache.get("missing").await;
+        assert_eq!(checker.check_cache().await.status, HealthStatus::Degraded);
+        
+        cache.put("cached".to_string(), vec![1.0]).await;
+        for _ in 0..3 {
+            cache.get("cached").await;
+        }
+        assert_eq!(checker.check_cache().await.status, HealthStatus::Healthy);
+    }
+    
+    #[tokio::test]
+    async fn test_deep_embedding_check_distinguishes_live_failures() {
+        let healthy = Arc::new(MockEmbeddingProvider::new(8));
+        let failing = Arc::new(MockEmbeddingProvider::new(8));
+        failing.set_failing(true);
+        
+        let healthy_checker = HealthChecker::new()
+            .with_embedding_client(healthy.clone())
+            .with_deep_embedding_check(Duration::from_secs(60));
+        let failing_checker = HealthChecker::new()
+            .with_embedding_client(failing.clone())
+            .with_deep_embedding_check(Duration::from_secs(60));
+        
+        assert_eq!(healthy_checker.check_embedding_service().await.status, HealthStatus::Healthy);
+        assert_eq!(failing_checker.check_embedding_service().await.status, HealthStatus::Unhealthy);
+        
+        // Shallow checks only look at the dimension and miss the failure
+        let shallow = HealthChecker::new().with_embedding_client(failing);
+        assert_eq!(shallow.check_embedding_service().await.status, HealthStatus::Healthy);
+    }
+    
+    #[tokio::test]
+    async fn test_deep_embedding_probe_is_cached() {
+        let provider = Arc::new(MockEmbeddingProvider::new(8));
+        let checker = HealthChecker::new()
+            .with_embedding_client(provider.clone())
+            .with_deep_embedding_check(Duration::from_secs(60));
+        
+        checker.check_embedding_service().await;
+        provider.set_failing(true);
+        let health = checker.check_embedding_service().await;
+        
+        assert_eq!(health.status, HealthStatus::Healthy);
+        assert_eq!(provider.health_calls(), 1);
+    }
+    
+    #[tokio::test]
+    async fn test_status_change_emits_one_transition() {
+        let provider = Arc::new(MockEmbeddingProvider::new(8));
+        let checker = HealthChecker::new()
+            .with_embedding_client(provider.clone())
+            .with_deep_embedding_check(Duration::ZERO);
+        let mut transitions = checker.subscribe_transitions();
+        
+        assert_eq!(checker.check_health_fresh().await.status, HealthStatus::Degraded);
+        assert!(!transitions.has_changed().unwrap());
+        
+        provider.set_failing(true);
+        assert_eq!(checker.check_health_fresh().await.status, HealthStatus::Unhealthy);
+        assert!(transitions.has_changed().unwrap());
+        let transition = transitions.borrow_and_update().clone().unwrap();
+        assert_eq!(transition.from, HealthStatus::Degraded);
+        assert_eq!(transition.to, HealthStatus::Unhealthy);
+        
+        // An unchanged status is not reported again
+        checker.check_health_fresh().await;
+        assert!(!transitions.has_changed().unwrap());
+    }
+}
@@ -1,7 +1,8 @@
 //! Metrics collection and reporting
 
+use std::collections::BTreeMap;
 use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
-use std::sync::Arc;
+use std::sync::{Arc, Mutex};
 use std::time::{Duration, Instant};
 
 /// System metrics
@@ -92,6 +93,113 @@ impl Histogram {
     }
 }
 
+/// Default cap on distinct label values per labeled metric
+pub const DEFAULT_LABEL_CARDINALITY_LIMIT: usize = 100;
+
+/// Counter partitioned by the value of a single label
+///
+/// At most `max_values` distinct label values are tracked; counts for any
+/// further values are collapsed into a single overflow series so that
+/// unbounded label spaces (such as agent IDs) can't explode Prometheus
+/// cardinality. The overflow series is exported with an `overflow="true"`
+/// label instead of a label value, so it never collides with a real value.
+#[derive(Debug, Clone)]
+pub struct LabeledCounter {
+    label: &'static str,
+    max_values: usize,
+    values: Arc<Mutex<LabeledValues>>,
+}
+
+#[derive(Debug, Default)]
+struct LabeledValues {
+    tracked: BTreeMap<String, u64>,
+    overflow: u64,
+}
+
+impl LabeledCounter {
+    /// Create a counter labeled by `label`, tracking up to `max_values` values
+    pub fn new(label: &'static str, max_values: usize) -> Self {
+        Self {
+            label,
+            max_values,
+            values: Arc::new(Mutex::new(LabeledValues::default())),
+        }
+    }
+    
+    /// Add `count` to the series for `value`
+    pub fn add(&self, value: &str, count: u64) {
+        let mut values = self.values.lock().unwrap();
+        if let Some(total) = values.tracked.get_mut(value) {
+            *total += count;
+        } else if values.tracked.len() < self.max_values {
+            values.tracked.insert(value.to_string(), count);
+        } else {
+            values.overflow += count;
+        }
+    }
+    
+    /// Increment the series for `value`
+    pub fn increment(&self, value: &str) {
+        self.add(value, 1);
+    }
+    
+    /// Current count for `value`
+    pub fn get(&self, value: &str) -> u64 {
+        self.values.lock().unwrap().tracked.get(value).copied().unwrap_or(0)
+    }
+    
+    /// Current count of the values beyond the cardinality limit
+    pub fn overflow(&self) -> u64 {
+        self.values.lock().unwrap().overflow
+    }
+    
+    fn export_prometheus(&self, name: &str, help: &str) -> String {
+        let mut output = String::new();
+        
+        output.push_str(&format!("# HELP {} {}\n", name, help));
+        output.push_str(&format!("# TYPE {} counter\n", name));
+        
+        let values = self.values.lock().unwrap();
+        for (value, count) in &values.tracked {
+            output.push_str(&format!(
+                "{}{{{}=\"{}\"}} {}\n",
+                name,
+                self.label,
+                escape_label_value(value),
+                count
+            ));
+        }
+        if values.overflow > 0 {
+            output.push_str(&format!("{}{{overflow=\"true\"}} {}\n", name, values.overflow));
+        }
+        
+        output
+    }
+}
+
+/// Escape a label value for the Prometheus text format
+fn escape_label_value(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+}
+
+/// An active connection, released when dropped
+///
+/// Because the count is released in `Drop`, it stays balanced when the
+/// request future is cancelled or its handler panics.
+#[must_use = "the connection is released as soon as the guard is dropped"]
+pub struct ConnectionGuard {
+    active_connections: Arc<AtomicUsize>,
+}
+
+impl Drop for ConnectionGuard {
+    fn drop(&mut self) {
+        self.active_connections.fetch_sub(1, Ordering::Relaxed);
+    }
+}
+
 /// Metrics collector
 pub struct MetricsCollector {
     start_time: Instant,
@@ -111,6 +219,17 @@ pub struct MetricsCollector {
     gc_runs: Arc<AtomicU64>,
     gc_deleted_total: Arc<AtomicU64>,
     gc_errors: Arc<AtomicU64>,
+    
+    // Re-embedding metrics
+    reembedded_total: Arc<AtomicU64>,
+    reembed_errors: Arc<AtomicU64>,
+    
+    // Request rejection metrics
+    rate_limited_total: Arc<AtomicU64>,
+    auth_failures_total: Arc<AtomicU64>,
+    
+    // Labeled metrics
+    contexts_stored_by_agent: LabeledCounter,
 }
 
 impl MetricsCollector {
@@ -130,9 +249,22 @@ impl MetricsCollector {
             gc_runs: Arc::new(AtomicU64::new(0)),
             gc_deleted_total: Arc::new(AtomicU64::new(0)),
             gc_errors: Arc::new(AtomicU64::new(0)),
+            reembedded_total: Arc::new(AtomicU64::new(0)),
+            reembed_errors: Arc::new(AtomicU64::new(0)),
+            rate_limited_total: Arc::new(AtomicU64::new(0)),
+            auth_failures_total: Arc::new(AtomicU64::new(0)),
+            contexts_stored_by_agent: LabeledCounter::new("agent", DEFAULT_LABEL_CARDINALITY_LIMIT),
         }
     }
     
+    /// Cap the number of distinct label values tracked per labeled metric
+    ///
+    /// Values beyond the cap are counted in a single overflow series.
+    pub fn with_label_cardinality_limit(mut self, limit: usize) -> Self {
+        self.contexts_stored_by_agent = LabeledCounter::new("agent", limit);
+        self
+    }
+    
     /// Record a request
     pub fn record_request(&self, response_time: Duration) {
         self.total_requests.fetch_add(1, Ordering::Relaxed);
@@ -163,6 +295,56 @@ impl MetricsCollector {
         self.gc_errors.fetch_add(1, Ordering::Relaxed);
     }
     
+    /// Total GC runs across all levels
+    pub fn gc_runs_total(&self) -> u64 {
+        self.gc_runs.load(Ordering::Relaxed)
+    }
+    
+    /// Record contexts re-embedded by the background task
+    pub fn record_reembedded(&self, count: usize) {
+        self.reembedded_total.fetch_add(count as u64, Ordering::Relaxed);
+    }
+    
+    /// Record a failed re-embedding pass
+    pub fn record_reembed_error(&self) {
+        self.reembed_errors.fetch_add(1, Ordering::Relaxed);
+    }
+    
+    /// Total contexts re-embedded by the background task
+    pub fn reembedded_total(&self) -> u64 {
+        self.reembedded_total.load(Ordering::Relaxed)
+    }
+    
+    /// Record a context stored on behalf of `agent_id`
+    pub fn record_context_stored(&self, agent_id: &str) {
+        self.contexts_stored_by_agent.increment(agent_id);
+    }
+    
+    /// Contexts stored per agent
+    pub fn contexts_stored_by_agent(&self) -> &LabeledCounter {
+        &self.contexts_stored_by_agent
+    }
+    
+    /// Record a request rejected by the rate limiter
+    pub fn record_rate_limited(&self) {
+        self.rate_limited_total.fetch_add(1, Ordering::Relaxed);
+    }
+    
+    /// Record a request rejected for a missing or invalid token
+    pub fn record_auth_failure(&self) {
+        self.auth_failures_total.fetch_add(1, Ordering::Relaxed);
+    }
+    
+    /// Total requests rejected by the rate limiter
+    pub fn rate_limited_total(&self) -> u64 {
+        self.rate_limited_total.load(Ordering::Relaxed)
+    }
+    
+    /// Total requests rejected by authentication
+    pub fn auth_failures_total(&self) -> u64 {
+        self.auth_failures_total.load(Ordering::Relaxed)
+    }
+    
     /// Record an error
     pub fn record_error(&self) {
         self.total_errors.fetch_add(1, Ordering::Relaxed);
@@ -178,6 +360,14 @@ impl MetricsCollector {
         self.active_connections.fetch_sub(1, Ordering::Relaxed);
     }
     
+    /// Count an active connection until the returned guard is dropped
+    pub fn track_connection(&self) -> ConnectionGuard {
+        self.increment_connections();
+        ConnectionGuard {
+            active_connections: self.active_connections.clone(),
+        }
+    }
+    
     /// Record cache hit
     pub fn record_cache_hit(&self) {
         self.cache_hits.fetch_add(1, Ordering::Relaxed);
@@ -232,6 +422,10 @@ impl MetricsCollector {
         let gc_runs = self.gc_runs.load(Ordering::Relaxed);
         let gc_deleted = self.gc_deleted_total.load(Ordering::Relaxed);
         let gc_errors = self.gc_errors.load(Ordering::Relaxed);
+        let reembedded = self.reembedded_total();
+        let reembed_errors = self.reembed_errors.load(Ordering::Relaxed);
+        let rate_limited = self.rate_limited_total();
+        let auth_failures = self.auth_failures_total();
         
         let mut output = format!(
             "# HELP context_manager_requests_total Total number of requests\n\
@@ -273,6 +467,22 @@ impl MetricsCollector {
              # HELP context_manager_gc_errors_total Total GC errors\n\
              # TYPE context_manager_gc_errors_total counter\n\
              context_manager_gc_errors_total {}\n\
+             \n\
+             # HELP context_manager_reembedded_total Total contexts re-embedded in the background\n\
+             # TYPE context_manager_reembedded_total counter\n\
+             context_manager_reembedded_total {}\n\
+             \n\
+             # HELP context_manager_reembed_errors_total Total failed re-embedding passes\n\
+             # TYPE context_manager_reembed_errors_total counter\n\
+             context_manager_reembed_errors_total {}\n\
+             \n\
+             # HELP context_manager_rate_limited_total Total requests rejected by rate limiting\n\
+             # TYPE context_manager_rate_limited_total counter\n\
+             context_manager_rate_limited_total {}\n\
+             \n\
+             # HELP context_manager_auth_failures_total Total requests rejected by authentication\n\
+             # TYPE context_manager_auth_failures_total counter\n\
+             context_manager_auth_failures_total {}\n\
              \n",
             metrics.total_requests,
             metrics.total_errors,
@@ -284,8 +494,19 @@ impl MetricsCollector {
             gc_runs,
             gc_deleted,
             gc_errors,
+            reembedded,
+            reembed_errors,
+            rate_limited,
+            auth_failures,
         );
         
+        // Add labeled counters
+        output.push_str(&self.contexts_stored_by_agent.export_prometheus(
+            "context_manager_contexts_stored_total",
+            "Contexts stored, by agent"
+        ));
+        output.push('\n');
+        
         // Add histograms
         output.push_str(&self.request_latency.export_prometheus(
             "context_manager_request_duration_ms",
@@ -355,4 +576,64 @@ mod tests {
         assert!(prometheus.contains("context_manager_requests_total 1"));
         assert!(prometheus.contains("context_manager_avg_response_time_ms 100.00"));
     }
-}
\ No newline at end of file
+    
+    #[test]
+    fn test_rejection_counters_exported() {
+        let collector = MetricsCollector::new();
+        collector.record_rate_limited();
+        collector.record_auth_failure();
+        collector.record_auth_failure();
+        
+        let prometheus = collector.export_prometheus();
+        
+        assert!(prometheus.contains("context_manager_rate_limited_total 1"));
+        assert!(prometheus.contains("context_manager_auth_failures_total 2"));
+    }
+    
+    #[test]
+    fn test_labels_beyond_cardinality_limit_collapse_into_other() {
+        let collector = MetricsCollector::new().with_label_cardinality_limit(2);
+        for agent in ["alice", "other", "carol", "dave", "alice", "other"] {
+            collector.record_context_stored(agent);
+        }
+        
+        let stored = collector.contexts_stored_by_agent();
+        assert_eq!(stored.get("alice"), 2);
+        assert_eq!(stored.get("carol"), 0);
+        // An agent named "other" keeps its own series
+        assert_eq!(stored.get("other"), 2);
+        assert_eq!(stored.overflow(), 2);
+        
+        let prometheus = collector.export_prometheus();
+        assert!(prometheus.contains("context_manager_contexts_stored_total{agent=\"other\"} 2"));
+        assert!(prometheus.contains("context_manager_contexts_stored_total{overflow=\"true\"} 2"));
+        assert!(!prometheus.contains("carol"));
+    }
+    
+    #[tokio::test]
+    async fn test_connection_guard_released_on_cancellation() {
+        let collector = Arc::new(MetricsCollector::new());
+        
+        let guard = collector.track_connection();
+        assert_eq!(collector.get_metrics().active_connections, 1);
+        drop(guard);
+        assert_eq!(collector.get_metrics().active_connections, 0);
+        
+        // A request future dropped mid-flight still releases its connection
+        let (started_tx, started_rx) = tokio::sync::oneshot::channel();
+        let request = tokio::spawn({
+            let collector = collector.clone();
+            async move {
+                let _guard = collector.track_connection();
+                let _ = started_tx.send(());
+                std::future::pending::<()>().await;
+            }
+        });
+        started_rx.await.unwrap();
+        assert_eq!(collector.get_metrics().active_connections, 1);
+        
+        request.abort();
+        assert!(request.await.unwrap_err().is_cancelled());
+        assert_eq!(collector.get_metrics().active_connections, 0);
+    }
+}
@@ -5,8 +5,8 @@ pub mod health;
 
 use tracing_subscriber::{layer::SubscriberExt, util::SubscriberInitExt, EnvFilter};
 
-pub use metrics::{MetricsCollector, SystemMetrics};
-pub use health::{HealthChecker, SystemHealth, HealthStatus, ComponentHealth};
+pub use metrics::{ConnectionGuard, LabeledCounter, MetricsCollector, SystemMetrics};
+pub use health::{HealthChecker, SystemHealth, HealthStatus, ComponentHealth, HealthTransition};
 
 /// Initialize logging and tracing
 pub fn init_observability(log_level: &str, format: &str) {
@@ -10,6 +10,9 @@ type HmacSha256 = Hmac<Sha256>;
 /// Maximum message age in seconds (5 minutes)
 const MAX_MESSAGE_AGE: i64 = 300;
 
+/// Tolerated clock skew for future timestamps in seconds
+const CLOCK_SKEW_TOLERANCE: i64 = 30;
+
 /// Authentication configuration
 #[derive(Debug, Clone)]
 pub struct AuthConfig {
@@ -21,6 +24,9 @@ pub struct AuthConfig {
     
     /// Maximum message age in seconds
     pub max_age_secs: i64,
+    
+    /// How far in the future a timestamp may be, to absorb clock skew between agents
+    pub clock_skew_tolerance_secs: i64,
 }
 
 impl Default for AuthConfig {
@@ -29,6 +35,7 @@ impl Default for AuthConfig {
             secret: String::new(),
             validate_timestamp: true,
             max_age_secs: MAX_MESSAGE_AGE,
+            clock_skew_tolerance_secs: CLOCK_SKEW_TOLERANCE,
         }
     }
 }
@@ -67,7 +74,9 @@ pub fn verify_signature(
 }
 
 /// Validate message timestamp
-pub fn validate_timestamp(timestamp: i64, max_age_secs: i64) -> Result<()> {
+///
+/// Timestamps up to `clock_skew_tolerance_secs` in the future are accepted.
+pub fn validate_timestamp(timestamp: i64, max_age_secs: i64, clock_skew_tolerance_secs: i64) -> Result<()> {
     let now = SystemTime::now()
         .duration_since(UNIX_EPOCH)
         .map_err(|e| ProtocolError::ValidationFailed(format!("System time error: {}", e)))?
@@ -75,8 +84,11 @@ pub fn validate_timestamp(timestamp: i64, max_age_secs: i64) -> Result<()> {
     
     let age = now - timestamp;
     
-    if age < 0 {
-        return Err(ProtocolError::ValidationFailed("Message timestamp is in the future".to_string()).into());
+    if -age > clock_skew_tolerance_secs {
+        return Err(ProtocolError::ValidationFailed(format!(
+            "Message timestamp is in the future: {} seconds (tolerance: {})",
+            -age, clock_skew_tolerance_secs
+        )).into());
     }
     
     if age > max_age_secs {
@@ -99,7 +111,7 @@ pub fn authenticate_message(
 ) -> Result<()> {
     // Validate timestamp if enabled
     if config.validate_timestamp {
-        validate_timestamp(timestamp, config.max_age_secs)?;
+        validate_timestamp(timestamp, config.max_age_secs, config.clock_skew_tolerance_secs)?;
     }
     
     // Verify signature if provided
@@ -154,13 +166,30 @@ mod tests {
             .as_secs() as i64;
         
         // Current timestamp should be valid
-        assert!(validate_timestamp(now, MAX_MESSAGE_AGE).is_ok());
+        assert!(validate_timestamp(now, MAX_MESSAGE_AGE, 0).is_ok());
         
         // Old timestamp should fail
-        assert!(validate_timestamp(now - MAX_MESSAGE_AGE - 1, MAX_MESSAGE_AGE).is_err());
+        assert!(validate_timestamp(now - MAX_MESSAGE_AGE - 1, MAX_MESSAGE_AGE, 0).is_err());
         
         // Future timestamp should fail
-        assert!(validate_timestamp(now + 100, MAX_MESSAGE_AGE).is_err());
+        assert!(validate_timestamp(now + 100, MAX_MESSAGE_AGE, 0).is_err());
+    }
+    
+    #[test]
+    fn test_timestamp_clock_skew_tolerance() {
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap()
+            .as_secs() as i64;
+        
+        // Slightly future timestamp within the tolerance should pass
+        assert!(validate_timestamp(now + 10, MAX_MESSAGE_AGE, CLOCK_SKEW_TOLERANCE).is_ok());
+        
+        // Timestamp beyond the tolerance should fail
+        assert!(validate_timestamp(now + CLOCK_SKEW_TOLERANCE + 60, MAX_MESSAGE_AGE, CLOCK_SKEW_TOLERANCE).is_err());
+        
+        // Tolerance does not relax the max age check
+        assert!(validate_timestamp(now - MAX_MESSAGE_AGE - 1, MAX_MESSAGE_AGE, CLOCK_SKEW_TOLERANCE).is_err());
     }
     
     #[test]
@@ -169,6 +198,7 @@ mod tests {
             secret: "test_secret".to_string(),
             validate_timestamp: false, // Disable for testing
             max_age_secs: MAX_MESSAGE_AGE,
+            clock_skew_tolerance_secs: CLOCK_SKEW_TOLERANCE,
         };
         
         let message_id = "test_id";
@@ -2,14 +2,19 @@
 
 use crate::observability::{HealthChecker, MetricsCollector};
 use axum::{
-    extract::State,
+    extract::{Request, State},
     http::StatusCode,
+    middleware::Next,
     response::{IntoResponse, Response},
     routing::get,
     Json, Router,
 };
+use std::future::{Future, IntoFuture};
+use std::sync::atomic::{AtomicUsize, Ordering};
 use std::sync::Arc;
-use tracing::info;
+use std::time::Duration;
+use tokio::net::TcpListener;
+use tracing::{info, warn};
 
 /// Server state
 #[derive(Clone)]
@@ -81,6 +86,67 @@ pub async fn start_server(
     Ok(())
 }
 
+/// Serve `app` until `signal` resolves, then drain in-flight requests for at
+/// most `timeout`
+///
+/// Returns the number of requests still in flight when the timeout expired;
+/// their connections are dropped rather than waited on.
+pub async fn serve_with_shutdown_timeout<F>(
+    listener: TcpListener,
+    app: Router,
+    signal: F,
+    timeout: Duration,
+) -> std::io::Result<usize>
+where
+    F: Future<Output = ()> + Send + 'static,
+{
+    let in_flight = Arc::new(AtomicUsize::new(0));
+    let app = app.layer(axum::middleware::from_fn_with_state(in_flight.clone(), track_in_flight));
+    
+    let (signalled_tx, mut signalled_rx) = tokio::sync::watch::channel(false);
+    let server = axum::serve(listener, app)
+        .with_graceful_shutdown(async move {
+            signal.await;
+            let _ = signalled_tx.send(true);
+        })
+        .into_future();
+    
+    let deadline = async move {
+        // A closed channel means the server finished on its own
+        if signalled_rx.wait_for(|signalled| *signalled).await.is_err() {
+            std::future::pending::<()>().await;
+        }
+        tokio::time::sleep(timeout).await;
+    };
+    
+    tokio::select! {
+        result = server => result.map(|_| 0),
+        _ = deadline => {
+            let dropped = in_flight.load(Ordering::SeqCst);
+            warn!("Shutdown timeout of {:?} elapsed, dropping {} in-flight request(s)", timeout, dropped);
+            Ok(dropped)
+        }
+    }
+}
+
+/// Count requests currently being handled
+async fn track_in_flight(
+    State(in_flight): State<Arc<AtomicUsize>>,
+    req: Request,
+    next: Next,
+) -> Response {
+    struct Guard(Arc<AtomicUsize>);
+    impl Drop for Guard {
+        fn drop(&mut self) {
+            self.0.fetch_sub(1, Ordering::SeqCst);
+        }
+    }
+    
+    in_flight.fetch_add(1, Ordering::SeqCst);
+    let _guard = Guard(in_flight);
+    next.run(req).await
+}
+
 /// Application error wrapper
 struct AppError(anyhow::Error);
 
@@ -117,4 +183,56 @@ mod tests {
         let _router = create_router(state);
         // Just verify router can be created
     }
-}
\ No newline at end of file
+    
+    #[tokio::test]
+    async fn test_shutdown_timeout_drops_stuck_request() {
+        let started_tx = Arc::new(tokio::sync::Notify::new());
+        let started_rx = started_tx.clone();
+        let app = Router::new().route("/slow", get(move || async move {
+            started_tx.notify_one();
+            tokio::time::sleep(Duration::from_secs(30)).await;
+            "done"
+        }));
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let addr = listener.local_addr().unwrap();
+        
+        let (shutdown_tx, shutdown_rx) = tokio::sync::oneshot::channel::<()>();
+        let server = tokio::spawn(serve_with_shutdown_timeout(
+            listener,
+            app,
+            async { let _ = shutdown_rx.await; },
+            Duration::from_millis(200),
+        ));
+        
+        // Start a request that will not finish before the timeout
+        let request = tokio::spawn(reqwest::get(format!("http://{}/slow", addr)));
+        tokio::time::timeout(Duration::from_secs(5), started_rx.notified())
+            .await
+            .expect("request never reached the handler");
+        
+        let started = std::time::Instant::now();
+        shutdown_tx.send(()).unwrap();
+        let dropped = tokio::time::timeout(Duration::from_secs(5), server)
+            .await
+            .expect("server did not exit within the shutdown timeout")
+            .unwrap()
+            .unwrap();
+        
+        assert_eq!(dropped, 1);
+        assert!(started.elapsed() < Duration::from_secs(2));
+        request.abort();
+    }
+    
+    #[tokio::test]
+    async fn test_shutdown_without_in_flight_requests_is_clean() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let dropped = serve_with_shutdown_timeout(
+            listener,
+            Router::new(),
+            async {},
+            Duration::from_secs(5),
+        ).await.unwrap();
+        
+        assert_eq!(dropped, 0);
+    }
+}
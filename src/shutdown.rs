@@ -1,27 +1,30 @@
 //! Graceful shutdown handling
 
 use std::sync::Arc;
-use tokio::sync::Notify;
+use tokio::sync::watch;
 use tokio::signal;
 use tracing::info;
 
 /// Shutdown coordinator
+///
+/// Shutdown is latched: notifiers subscribed or waiting after it was
+/// triggered still observe it.
 pub struct ShutdownCoordinator {
-    notify: Arc<Notify>,
+    sender: watch::Sender<bool>,
 }
 
 impl ShutdownCoordinator {
     /// Create a new shutdown coordinator
     pub fn new() -> Self {
         Self {
-            notify: Arc::new(Notify::new()),
+            sender: watch::Sender::new(false),
         }
     }
     
     /// Get a shutdown notifier
     pub fn subscribe(&self) -> ShutdownNotifier {
         ShutdownNotifier {
-            notify: self.notify.clone(),
+            receiver: self.sender.subscribe(),
         }
     }
     
@@ -54,13 +57,13 @@ impl ShutdownCoordinator {
         }
         
         // Notify all subscribers
-        self.notify.notify_waiters();
+        self.sender.send_replace(true);
     }
     
     /// Trigger shutdown manually
     pub fn shutdown(&self) {
         info!("Manual shutdown triggered");
-        self.notify.notify_waiters();
+        self.sender.send_replace(true);
     }
 }
 
@@ -73,20 +76,22 @@ impl Default for ShutdownCoordinator {
 /// Shutdown notifier for components
 #[derive(Clone)]
 pub struct ShutdownNotifier {
-    notify: Arc<Notify>,
+    receiver: watch::Receiver<bool>,
 }
 
 impl ShutdownNotifier {
     /// Wait for shutdown signal
+    ///
+    /// Returns immediately if shutdown was already signaled. Also returns if
+    /// the coordinator is dropped, since nothing can signal shutdown anymore.
     pub async fn wait(&self) {
-        self.notify.notified().await;
+        let mut receiver = self.receiver.clone();
+        let _ = receiver.wait_for(|shutdown| *shutdown).await;
     }
     
     /// Check if shutdown has been signaled (non-blocking)
     pub fn is_shutdown(&self) -> bool {
-        // This is a simplified check
-        // In production, you might want to use an AtomicBool
-        false
+        *self.receiver.borrow()
     }
 }
 
@@ -114,7 +119,6 @@ mod tests {
     use super::*;
     
     #[tokio::test]
-    #[ignore] // Hangs in test environment
     async fn test_shutdown_coordinator() {
         let coordinator = ShutdownCoordinator::new();
         let notifier = coordinator.subscribe();
@@ -134,7 +138,6 @@ mod tests {
     }
     
     #[tokio::test]
-    #[ignore] // Hangs in test environment
     async fn test_multiple_subscribers() {
         let coordinator = ShutdownCoordinator::new();
         let notifier1 = coordinator.subscribe();
@@ -158,4 +161,19 @@ mod tests {
         assert_eq!(result1, 1);
         assert_eq!(result2, 2);
     }
-}
\ No newline at end of file
+    
+    #[tokio::test]
+    async fn test_late_subscriber_sees_shutdown() {
+        let coordinator = ShutdownCoordinator::new();
+        let notifier = coordinator.subscribe();
+        assert!(!notifier.is_shutdown());
+        
+        coordinator.shutdown();
+        
+        assert!(notifier.is_shutdown());
+        assert!(coordinator.subscribe().is_shutdown());
+        tokio::time::timeout(std::time::Duration::from_secs(1), coordinator.subscribe().wait())
+            .await
+            .unwrap();
+    }
+}
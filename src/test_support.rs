@@ -0,0 +1,66 @@
+//! Shared test helpers for unit tests
+
+#![allow(dead_code)]
+
+use crate::api::handlers::{AppState, TokenBudget};
+use crate::config::{Config, HiRAGConfig};
+use crate::hirag::HiRAGManagerV2;
+use crate::observability::HealthChecker;
+use crate::vector_db::{ContextLevel, Payload, VectorPoint};
+use std::collections::HashMap;
+use std::sync::Arc;
+use uuid::Uuid;
+
+pub use crate::testing::{MockEmbeddingProvider, MockVectorStore};
+
+/// HiRAG V2 manager over in-memory mocks with default configuration
+pub async fn test_manager() -> (Arc<HiRAGManagerV2>, Arc<MockVectorStore>, Arc<MockEmbeddingProvider>) {
+    test_manager_with_config(Config::default_config().hirag).await
+}
+
+/// HiRAG V2 manager over in-memory mocks
+pub async fn test_manager_with_config(
+    config: HiRAGConfig,
+) -> (Arc<HiRAGManagerV2>, Arc<MockVectorStore>, Arc<MockEmbeddingProvider>) {
+    let vector_db = Arc::new(MockVectorStore::new());
+    let embedding = Arc::new(MockEmbeddingProvider::new(1024));
+    let manager = HiRAGManagerV2::new(config, embedding.clone(), vector_db.clone())
+        .await
+        .unwrap();
+    manager.initialize().await.unwrap();
+    (Arc::new(manager), vector_db, embedding)
+}
+
+/// Point `id` at `level` stored at `timestamp`, with text `context {id}`, a
+/// two-dimensional vector and no metadata
+///
+/// Override other fields with struct update syntax.
+pub fn point(id: u128, level: ContextLevel, timestamp: i64) -> VectorPoint {
+    VectorPoint {
+        id: Uuid::from_u128(id),
+        vector: vec![1.0, 0.0],
+        payload: Payload {
+            text: format!("context {}", id),
+            level,
+            timestamp,
+            agent_id: "default".to_string(),
+            session_id: None,
+            access_count: 0,
+            token_count: None,
+            metadata: HashMap::new(),
+        },
+    }
+}
+
+/// API state backed by in-memory mocks
+pub async fn test_app_state() -> AppState {
+    let (manager, vector_db, _) = test_manager().await;
+    AppState {
+        context_manager: manager,
+        vector_db,
+        health_checker: Arc::new(HealthChecker::new()),
+        circuit_breaker: None,
+        token_budget: TokenBudget::from_config(&Config::default_config().server),
+        empty_result_policy: Default::default(),
+    }
+}
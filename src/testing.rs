@@ -0,0 +1,350 @@
+//! In-memory test doubles for the embedding provider and vector store
+//!
+//! Available to downstream crates with the `testing` feature, so managers,
+//! retrievers and GC can be exercised without Qdrant or an embedding API.
+
+use crate::embedding::EmbeddingProvider;
+use crate::error::{EmbeddingError, Result, VectorDbError};
+use crate::hirag::similarity;
+use crate::vector_db::{Filter, ScrollPage, SearchParams, SearchResult, VectorPoint, VectorStore};
+use async_trait::async_trait;
+use std::collections::{HashMap, HashSet};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::Mutex;
+use uuid::Uuid;
+
+/// Deterministic in-memory embedding provider
+///
+/// Unless overridden with [`MockEmbeddingProvider::set_vector`], every text maps
+/// to a stable pseudo-random non-zero vector derived from its bytes.
+pub struct MockEmbeddingProvider {
+    dimension: usize,
+    detecting: bool,
+    detected: AtomicBool,
+    failing: AtomicBool,
+    vectors: Mutex<HashMap<String, Vec<f32>>>,
+    single_calls: AtomicUsize,
+    batch_calls: AtomicUsize,
+    health_calls: AtomicUsize,
+}
+
+impl MockEmbeddingProvider {
+    pub fn new(dimension: usize) -> Self {
+        Self {
+            dimension,
+            detecting: false,
+            detected: AtomicBool::new(false),
+            failing: AtomicBool::new(false),
+            vectors: Mutex::new(HashMap::new()),
+            single_calls: AtomicUsize::new(0),
+            batch_calls: AtomicUsize::new(0),
+            health_calls: AtomicUsize::new(0),
+        }
+    }
+
+    /// Report a 1024 dimension as a guess until the first embedding, like a
+    /// client with `auto_detect_dimension` set
+    pub fn detecting(mut self) -> Self {
+        self.detecting = true;
+        self
+    }
+
+    /// Make every subsequent call fail with a service error
+    pub fn set_failing(&self, failing: bool) {
+        self.failing.store(failing, Ordering::SeqCst);
+    }
+
+    /// Pin the vector returned for a given text
+    pub fn set_vector(&self, text: &str, vector: Vec<f32>) {
+        self.vectors.lock().unwrap().insert(text.to_string(), vector);
+    }
+
+    pub fn single_calls(&self) -> usize {
+        self.single_calls.load(Ordering::SeqCst)
+    }
+
+    pub fn batch_calls(&self) -> usize {
+        self.batch_calls.load(Ordering::SeqCst)
+    }
+
+    pub fn health_calls(&self) -> usize {
+        self.health_calls.load(Ordering::SeqCst)
+    }
+
+    fn vector_for(&self, text: &str) -> Result<Vec<f32>> {
+        if self.failing.load(Ordering::SeqCst) {
+            return Err(EmbeddingError::ServiceUnavailable("mock provider failing".to_string()).into());
+        }
+        self.detected.store(true, Ordering::SeqCst);
+
+        if let Some(vector) = self.vectors.lock().unwrap().get(text) {
+            return Ok(vector.clone());
+        }
+
+        let seed = text
+            .bytes()
+            .fold(0xcbf29ce484222325_u64, |acc, b| (acc ^ b as u64).wrapping_mul(0x100000001b3));
+        Ok((0..self.dimension)
+            .map(|i| {
+                let x = seed.wrapping_add(i as u64).wrapping_mul(0x9e3779b97f4a7c15) >> 40;
+                (x % 1000) as f32 / 1000.0 + 0.001
+            })
+            .collect())
+    }
+}
+
+#[async_trait]
+impl EmbeddingProvider for MockEmbeddingProvider {
+    async fn embed_single(&self, text: &str) -> Result<Vec<f32>> {
+        self.single_calls.fetch_add(1, Ordering::SeqCst);
+        self.vector_for(text)
+    }
+
+    async fn embed_batch(&self, texts: &[String]) -> Result<Vec<Vec<f32>>> {
+        self.batch_calls.fetch_add(1, Ordering::SeqCst);
+        texts.iter().map(|t| self.vector_for(t)).collect()
+    }
+
+    fn embedding_dimension(&self) -> usize {
+        if self.dimension_known() { self.dimension } else { 1024 }
+    }
+
+    fn dimension_known(&self) -> bool {
+        !self.detecting || self.detected.load(Ordering::SeqCst)
+    }
+
+    async fn health(&self) -> Result<()> {
+        self.health_calls.fetch_add(1, Ordering::SeqCst);
+        if self.failing.load(Ordering::SeqCst) {
+            return Err(EmbeddingError::ServiceUnavailable("mock provider failing".to_string()).into());
+        }
+        Ok(())
+    }
+}
+
+/// In-memory vector store with brute-force cosine search
+///
+/// Collections must be created before use, mirroring Qdrant: operations on a
+/// missing collection fail with `CollectionNotFound`.
+#[derive(Default)]
+pub struct MockVectorStore {
+    collections: Mutex<HashMap<String, HashMap<Uuid, VectorPoint>>>,
+    failing_collections: Mutex<HashSet<String>>,
+    indexed_collections: Mutex<HashSet<String>>,
+    collection_sizes: Mutex<HashMap<String, usize>>,
+    create_calls: AtomicUsize,
+    search_calls: AtomicUsize,
+    insert_calls: AtomicUsize,
+}
+
+impl MockVectorStore {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Make every operation on `collection` fail
+    pub fn fail_collection(&self, collection: &str) {
+        self.failing_collections.lock().unwrap().insert(collection.to_string());
+    }
+
+    /// Undo [`MockVectorStore::fail_collection`]
+    pub fn recover_collection(&self, collection: &str) {
+        self.failing_collections.lock().unwrap().remove(collection);
+    }
+
+    /// IDs stored in a collection
+    pub fn point_ids(&self, collection: &str) -> Vec<Uuid> {
+        self.collections.lock().unwrap()
+            .get(collection)
+            .map(|points| points.keys().copied().collect())
+            .unwrap_or_default()
+    }
+
+    /// Fetch a stored point without going through the trait
+    pub fn point(&self, collection: &str, id: Uuid) -> Option<VectorPoint> {
+        self.collections.lock().unwrap().get(collection)?.get(&id).cloned()
+    }
+
+    /// Names of the collections that exist
+    pub fn collection_names(&self) -> Vec<String> {
+        self.collections.lock().unwrap().keys().cloned().collect()
+    }
+
+    /// Names of the collections `ensure_indexes` was called on
+    pub fn indexed_collections(&self) -> HashSet<String> {
+        self.indexed_collections.lock().unwrap().clone()
+    }
+
+    /// Vector size a collection was created with, if it was created sized
+    pub fn collection_size(&self, collection: &str) -> Option<usize> {
+        self.collection_sizes.lock().unwrap().get(collection).copied()
+    }
+
+    pub fn create_calls(&self) -> usize {
+        self.create_calls.load(Ordering::SeqCst)
+    }
+
+    pub fn search_calls(&self) -> usize {
+        self.search_calls.load(Ordering::SeqCst)
+    }
+
+    pub fn insert_calls(&self) -> usize {
+        self.insert_calls.load(Ordering::SeqCst)
+    }
+
+    fn check_failing(&self, collection: &str) -> Result<()> {
+        if self.failing_collections.lock().unwrap().contains(collection) {
+            return Err(VectorDbError::ConnectionError(format!("mock failure for {}", collection)).into());
+        }
+        Ok(())
+    }
+
+    fn with_collection<T>(
+        &self,
+        collection: &str,
+        f: impl FnOnce(&mut HashMap<Uuid, VectorPoint>) -> T,
+    ) -> Result<T> {
+        self.check_failing(collection)?;
+        let mut collections = self.collections.lock().unwrap();
+        let points = collections
+            .get_mut(collection)
+            .ok_or_else(|| VectorDbError::CollectionNotFound(collection.to_string()))?;
+        Ok(f(points))
+    }
+}
+
+fn cosine(a: &[f32], b: &[f32]) -> f32 {
+    similarity::cosine(a, b).unwrap_or(0.0)
+}
+
+#[async_trait]
+impl VectorStore for MockVectorStore {
+    async fn create_collection(&self, name: &str) -> Result<()> {
+        self.create_calls.fetch_add(1, Ordering::SeqCst);
+        self.check_failing(name)?;
+        let mut collections = self.collections.lock().unwrap();
+        if collections.contains_key(name) {
+            return Err(VectorDbError::CollectionExists(name.to_string()).into());
+        }
+        collections.insert(name.to_string(), HashMap::new());
+        Ok(())
+    }
+
+    async fn create_sized_collection(&self, name: &str, vector_size: usize) -> Result<()> {
+        self.create_collection(name).await?;
+        self.collection_sizes.lock().unwrap().insert(name.to_string(), vector_size);
+        Ok(())
+    }
+
+    async fn ensure_indexes(&self, name: &str) -> Result<()> {
+        self.with_collection(name, |_| ())?;
+        self.indexed_collections.lock().unwrap().insert(name.to_string());
+        Ok(())
+    }
+
+    async fn delete_collection(&self, name: &str) -> Result<()> {
+        self.check_failing(name)?;
+        self.collections.lock().unwrap().remove(name);
+        Ok(())
+    }
+
+    async fn insert_points(&self, collection: &str, points: Vec<VectorPoint>) -> Result<()> {
+        self.insert_calls.fetch_add(1, Ordering::SeqCst);
+        self.with_collection(collection, |stored| {
+            for point in points {
+                stored.insert(point.id, point);
+            }
+        })
+    }
+
+    async fn search(&self, collection: &str, params: SearchParams) -> Result<Vec<SearchResult>> {
+        self.search_calls.fetch_add(1, Ordering::SeqCst);
+        self.with_collection(collection, |stored| {
+            let mut results: Vec<SearchResult> = stored
+                .values()
+                .filter(|p| params.filter.as_ref().is_none_or(|f| f.matches(p.id, &p.payload)))
+                .map(|p| SearchResult {
+                    id: p.id,
+                    score: cosine(&params.vector, &p.vector),
+                    payload: params.with_payload.then(|| p.payload.clone()),
+                    vector: params.with_vector.then(|| p.vector.clone()),
+                })
+                .filter(|r| params.score_threshold.is_none_or(|t| r.score >= t))
+                .collect();
+            results.sort_by(|a, b| b.score.total_cmp(&a.score));
+            results.truncate(params.limit);
+            results
+        })
+    }
+
+    async fn delete_points(&self, collection: &str, ids: Vec<Uuid>) -> Result<()> {
+        self.with_collection(collection, |stored| {
+            for id in ids {
+                stored.remove(&id);
+            }
+        })
+    }
+
+    async fn get_point(&self, collection: &str, id: Uuid) -> Result<Option<VectorPoint>> {
+        self.with_collection(collection, |stored| stored.get(&id).cloned())
+    }
+
+    async fn set_payload_fields(
+        &self,
+        collection: &str,
+        id: Uuid,
+        fields: HashMap<String, serde_json::Value>,
+    ) -> Result<()> {
+        self.with_collection(collection, |stored| {
+            let point = stored
+                .get_mut(&id)
+                .ok_or_else(|| VectorDbError::InsertError(format!("Point {} not found", id)))?;
+            point.payload = point.payload.with_fields(fields)
+                .map_err(|e| VectorDbError::InsertError(format!("Invalid payload field: {}", e)))?;
+            Ok(())
+        })?
+    }
+
+    async fn delete_payload_fields(&self, collection: &str, id: Uuid, keys: Vec<String>) -> Result<()> {
+        self.with_collection(collection, |stored| {
+            let point = stored
+                .get_mut(&id)
+                .ok_or_else(|| VectorDbError::InsertError(format!("Point {} not found", id)))?;
+            point.payload = point.payload.without_fields(&keys)
+                .map_err(|e| VectorDbError::InsertError(format!("Cannot remove payload field: {}", e)))?;
+            Ok(())
+        })?
+    }
+
+    async fn set_vector(&self, collection: &str, id: Uuid, vector: Vec<f32>) -> Result<()> {
+        self.with_collection(collection, |stored| {
+            let point = stored
+                .get_mut(&id)
+                .ok_or_else(|| VectorDbError::InsertError(format!("Point {} not found", id)))?;
+            point.vector = vector;
+            Ok(())
+        })?
+    }
+
+    // `count` and `oldest_points` use the trait defaults built on this
+    async fn scroll(
+        &self,
+        collection: &str,
+        filter: Option<Filter>,
+        offset: Option<Uuid>,
+        limit: usize,
+    ) -> Result<ScrollPage> {
+        self.with_collection(collection, |stored| {
+            let mut points: Vec<_> = stored
+                .values()
+                .filter(|p| offset.is_none_or(|o| p.id >= o))
+                .filter(|p| filter.as_ref().is_none_or(|f| f.matches(p.id, &p.payload)))
+                .cloned()
+                .collect();
+            points.sort_by_key(|p| p.id);
+            let next_offset = points.get(limit).map(|p| p.id);
+            points.truncate(limit);
+            ScrollPage { points, next_offset }
+        })
+    }
+}
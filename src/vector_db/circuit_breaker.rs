@@ -1,5 +1,6 @@
 //! Circuit breaker for vector database operations
 
+use crate::clock::{system_clock, Clock};
 use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
 use std::sync::Arc;
 use std::time::{Duration, Instant};
@@ -55,6 +56,7 @@ pub struct CircuitBreaker {
     last_failure_time: Arc<RwLock<Option<Instant>>>,
     total_calls: Arc<AtomicU64>,
     total_failures: Arc<AtomicU64>,
+    clock: Arc<dyn Clock>,
 }
 
 impl CircuitBreaker {
@@ -68,9 +70,16 @@ impl CircuitBreaker {
             last_failure_time: Arc::new(RwLock::new(None)),
             total_calls: Arc::new(AtomicU64::new(0)),
             total_failures: Arc::new(AtomicU64::new(0)),
+            clock: system_clock(),
         }
     }
     
+    /// Use a specific clock for the open-state timeout
+    pub fn with_clock(mut self, clock: Arc<dyn Clock>) -> Self {
+        self.clock = clock;
+        self
+    }
+    
     /// Check if request should be allowed
     pub async fn allow_request(&self) -> bool {
         self.total_calls.fetch_add(1, Ordering::Relaxed);
@@ -82,7 +91,7 @@ impl CircuitBreaker {
             CircuitState::Open => {
                 // Check if timeout has elapsed
                 if let Some(last_failure) = *self.last_failure_time.read().await {
-                    if last_failure.elapsed() >= self.config.timeout {
+                    if self.clock.now().duration_since(last_failure) >= self.config.timeout {
                         // Transition to half-open
                         *self.state.write().await = CircuitState::HalfOpen;
                         self.success_count.store(0, Ordering::Relaxed);
@@ -133,14 +142,14 @@ impl CircuitBreaker {
                 if failures >= self.config.failure_threshold {
                     // Transition to open
                     *self.state.write().await = CircuitState::Open;
-                    *self.last_failure_time.write().await = Some(Instant::now());
+                    *self.last_failure_time.write().await = Some(self.clock.now());
                     warn!("Circuit breaker opened after {} failures", failures);
                 }
             }
             CircuitState::HalfOpen => {
                 // Transition back to open
                 *self.state.write().await = CircuitState::Open;
-                *self.last_failure_time.write().await = Some(Instant::now());
+                *self.last_failure_time.write().await = Some(self.clock.now());
                 self.success_count.store(0, Ordering::Relaxed);
                 warn!("Circuit breaker reopened after failure in half-open state");
             }
@@ -153,6 +162,20 @@ impl CircuitBreaker {
         *self.state.read().await
     }
     
+    /// Whether the circuit is open and would still reject a request
+    ///
+    /// Unlike [`allow_request`](Self::allow_request) this doesn't move an
+    /// expired open circuit to half-open or count as a call.
+    pub async fn is_rejecting(&self) -> bool {
+        if *self.state.read().await != CircuitState::Open {
+            return false;
+        }
+        match *self.last_failure_time.read().await {
+            Some(last_failure) => self.clock.now().duration_since(last_failure) < self.config.timeout,
+            None => true,
+        }
+    }
+    
     /// Get statistics
     pub async fn stats(&self) -> CircuitBreakerStats {
         CircuitBreakerStats {
@@ -206,6 +229,38 @@ impl CircuitBreaker {
     }
 }
 
+/// Aggregate breaker over the embedding and vector database breakers
+///
+/// The pipeline is open only while both breakers reject requests, in which
+/// case neither storing nor retrieving a context can succeed.
+pub struct PipelineBreaker {
+    embedding: Arc<CircuitBreaker>,
+    vector_db: Arc<CircuitBreaker>,
+}
+
+impl PipelineBreaker {
+    pub fn new(embedding: Arc<CircuitBreaker>, vector_db: Arc<CircuitBreaker>) -> Self {
+        Self { embedding, vector_db }
+    }
+    
+    /// Whether both breakers are open and rejecting requests
+    pub async fn is_open(&self) -> bool {
+        self.embedding.is_rejecting().await && self.vector_db.is_rejecting().await
+    }
+    
+    /// Combined state: open when both breakers reject requests, closed when
+    /// both are closed and half-open otherwise
+    pub async fn state(&self) -> CircuitState {
+        if self.is_open().await {
+            return CircuitState::Open;
+        }
+        match (self.embedding.state().await, self.vector_db.state().await) {
+            (CircuitState::Closed, CircuitState::Closed) => CircuitState::Closed,
+            _ => CircuitState::HalfOpen,
+        }
+    }
+}
+
 /// Circuit breaker statistics
 #[derive(Debug, Clone)]
 pub struct CircuitBreakerStats {
@@ -306,4 +361,55 @@ mod tests {
         // Should be closed now
         assert_eq!(cb.state().await, CircuitState::Closed);
     }
-}
\ No newline at end of file
+    
+    #[tokio::test]
+    async fn test_circuit_breaker_timeout_with_fake_clock() {
+        let clock = Arc::new(crate::clock::FakeClock::new());
+        let cb = CircuitBreaker::new(CircuitBreakerConfig {
+            failure_threshold: 2,
+            success_threshold: 1,
+            timeout: Duration::from_secs(30),
+            window_size: Duration::from_secs(60),
+        })
+        .with_clock(clock.clone());
+        
+        cb.record_failure().await;
+        cb.record_failure().await;
+        
+        clock.advance(Duration::from_secs(29));
+        assert!(!cb.allow_request().await);
+        assert_eq!(cb.state().await, CircuitState::Open);
+        
+        clock.advance(Duration::from_secs(1));
+        assert!(cb.allow_request().await);
+        assert_eq!(cb.state().await, CircuitState::HalfOpen);
+        
+        cb.record_success().await;
+        assert_eq!(cb.state().await, CircuitState::Closed);
+    }
+    
+    #[tokio::test]
+    async fn test_pipeline_opens_only_when_both_breakers_open() {
+        let clock = Arc::new(crate::clock::FakeClock::new());
+        let breaker = || Arc::new(CircuitBreaker::new(CircuitBreakerConfig {
+            failure_threshold: 1,
+            success_threshold: 1,
+            timeout: Duration::from_secs(30),
+            window_size: Duration::from_secs(60),
+        }).with_clock(clock.clone()));
+        let (embedding, vector_db) = (breaker(), breaker());
+        let pipeline = PipelineBreaker::new(embedding.clone(), vector_db.clone());
+        
+        embedding.record_failure().await;
+        assert!(!pipeline.is_open().await);
+        assert_eq!(pipeline.state().await, CircuitState::HalfOpen);
+        
+        vector_db.record_failure().await;
+        assert!(pipeline.is_open().await);
+        assert_eq!(pipeline.state().await, CircuitState::Open);
+        
+        // Once the open timeout passes, requests are let through to probe recovery
+        clock.advance(Duration::from_secs(30));
+        assert!(!pipeline.is_open().await);
+    }
+}
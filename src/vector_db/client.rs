@@ -1,25 +1,96 @@
 //! Qdrant client implementation
 
-        use super::VectorStore;
-        use super::models::{ContextLevel, Payload, VectorPoint, SearchParams, SearchResult, Filter as ModelFilter, Condition as ModelCondition};
-        use crate::config::{VectorDbConfig, Distance};
+        use super::{CollectionNaming, VectorStore};
+        use super::models::{ContextLevel, Payload, VectorPoint, ScrollPage, SearchParams, SearchResult, Filter as ModelFilter, Condition as ModelCondition};
+        use crate::config::{VectorDbConfig, Distance, ProductCompression, Quantization};
         use crate::error::{VectorDbError, Result};
         use async_trait::async_trait;
-        use qdrant_client::Qdrant;
+        use qdrant_client::{Qdrant, QdrantError};
         use qdrant_client::qdrant::{
             CreateCollectionBuilder, VectorParamsBuilder, VectorsConfig, PointStruct,
+            CountPointsBuilder, CreateFieldIndexCollectionBuilder, DeletePayloadPointsBuilder, Direction, FieldType,
+            OrderByBuilder, PointsIdsList, RetrievedPoint, ScrollPointsBuilder, SetPayloadPointsBuilder,
             SearchPoints, WithPayloadSelector, PointId, Value, Filter as QdrantFilter, 
-            Condition as QdrantCondition, Range,
+            Condition as QdrantCondition, Range, SearchParams as QdrantSearchParams,
+            CompressionRatio, ProductQuantization, QuantizationType, ScalarQuantization, VectorsOutput,
+            PointVectors, UpdatePointVectorsBuilder,
         };
+        use qdrant_client::qdrant::vector_output::Vector as QdrantVector;
+        use qdrant_client::qdrant::quantization_config::Quantization as QdrantQuantization;
+        use qdrant_client::qdrant::point_id::PointIdOptions;
         use qdrant_client::qdrant::vectors_config::Config;
         use qdrant_client::qdrant::with_payload_selector::SelectorOptions;
+        use futures::{StreamExt, TryStreamExt};
         use std::collections::HashMap;
-        use tracing::{debug, info};
+        use std::future::Future;
+        use std::time::Duration;
+        use tracing::{debug, info, warn};
         use uuid::Uuid;
 
+        /// Map a Qdrant search failure, surfacing a missing collection as `CollectionNotFound`
+        fn search_error(collection: &str, error: QdrantError) -> VectorDbError {
+            match &error {
+                QdrantError::ResponseError { status } if is_missing_collection(status.message()) => {
+                    VectorDbError::CollectionNotFound(collection.to_string())
+                }
+                _ => VectorDbError::SearchError(error.to_string()),
+            }
+        }
+        
+        /// Qdrant reports a missing collection as "Not found: Collection `name` doesn't exist!"
+        fn is_missing_collection(message: &str) -> bool {
+            message.contains("Collection") && message.contains("doesn't exist")
+        }
+        
+        /// Qdrant rejects a duplicate create with "Wrong input: Collection `name` already exists!"
+        fn is_existing_collection(message: &str) -> bool {
+            message.contains("Collection") && message.contains("already exists")
+        }
+
+        /// gRPC `Unavailable` status code
+        const GRPC_UNAVAILABLE: i32 = 14;
+
+        /// Qdrant answers `Unavailable` for a collection whose shards are still
+        /// initializing, e.g. just after it was created or while it loads from disk
+        fn is_not_ready(code: i32) -> bool {
+            code == GRPC_UNAVAILABLE
+        }
+
+        /// Payload key marking points whose metadata is stored as native values
+        ///
+        /// Points written before it existed JSON-encoded every metadata value
+        /// into a string, so only those are decoded on read.
+        const PAYLOAD_VERSION_KEY: &str = "payload_version";
+        
+        /// Payload layout written by this client
+        const PAYLOAD_VERSION: i64 = 2;
+
+        /// Longest backoff between retries of a collection that isn't ready
+        const MAX_NOT_READY_BACKOFF: Duration = Duration::from_secs(5);
+
+        /// Parse a Qdrant point ID; this crate only ever stores UUIDs
+        fn parse_point_id(id: PointIdOptions) -> std::result::Result<Uuid, VectorDbError> {
+            match id {
+                PointIdOptions::Uuid(uuid) => Uuid::parse_str(&uuid)
+                    .map_err(|e| VectorDbError::SearchError(format!("Invalid UUID: {}", e))),
+                PointIdOptions::Num(num) => Err(
+                    VectorDbError::SearchError(format!("Unexpected numeric point ID {}", num))
+                ),
+            }
+        }
+
+        /// The default dense vector of a point, if it was returned
+        fn dense_vector(vectors: VectorsOutput) -> Option<Vec<f32>> {
+            match vectors.get_vector()? {
+                QdrantVector::Dense(dense) => Some(dense.data),
+                _ => None,
+            }
+        }
+
         /// Client for Qdrant vector database
         pub struct VectorDbClient {
             config: VectorDbConfig,
+            naming: CollectionNaming,
             client: Qdrant,
         }
 
@@ -32,28 +103,74 @@
                     .build()
                     .map_err(|e| VectorDbError::ConnectionError(e.to_string()))?;
 
-                Ok(Self { config, client })
+                let naming = CollectionNaming::from_config(&config);
+                Ok(Self { config, naming, client })
+            }
+            
+            /// Run `operation` on `request`, retrying with exponential backoff while
+            /// Qdrant reports `collection` as not ready; any other error is returned at once
+            ///
+            /// The request is only copied while a failed attempt can still be
+            /// retried; the last attempt takes it by value.
+            async fn retry_not_ready<R, T, F, Fut>(
+                &self,
+                collection: &str,
+                request: R,
+                mut operation: F,
+            ) -> std::result::Result<T, QdrantError>
+            where
+                R: Clone,
+                F: FnMut(R) -> Fut,
+                Fut: Future<Output = std::result::Result<T, QdrantError>>,
+            {
+                let mut attempts = 0;
+                loop {
+                    if attempts >= self.config.not_ready_retries {
+                        return operation(request).await;
+                    }
+                    match operation(request.clone()).await {
+                        Err(QdrantError::ResponseError { status }) if is_not_ready(status.code() as i32) => {
+                            attempts += 1;
+                            let delay = Duration::from_millis(
+                                self.config.not_ready_backoff_ms.saturating_mul(1 << (attempts - 1).min(16)),
+                            )
+                            .min(MAX_NOT_READY_BACKOFF);
+                            warn!(
+                                "Collection {} not ready (attempt {}), retrying in {}ms: {}",
+                                collection, attempts, delay.as_millis(), status.message()
+                            );
+                            tokio::time::sleep(delay).await;
+                        }
+                        result => return result,
+                    }
+                }
             }
             
             /// Initialize collections for all context levels
             pub async fn initialize_collections(&self) -> Result<()> {
                 info!("Initializing collections for all context levels");
                 
-                for level in &[ContextLevel::Immediate, ContextLevel::ShortTerm, ContextLevel::LongTerm] {
-                    let collection_name = self.collection_name(*level);
-                    
-                    // Check if collection exists
-                    let exists = self.client
-                        .collection_info(collection_name.clone())
-                        .await
-                        .is_ok();
-                    
-                    if !exists {
-                        info!("Creating collection: {}", collection_name);
-                        self.create_collection(&collection_name).await?;
-                    } else {
-                        debug!("Collection already exists: {}", collection_name);
-                    }
+                let levels = [ContextLevel::Immediate, ContextLevel::ShortTerm, ContextLevel::LongTerm];
+                futures::stream::iter(levels)
+                    .map(|level| self.ensure_collection(self.collection_name(level)))
+                    .buffer_unordered(self.config.max_concurrent_collection_creates.max(1))
+                    .try_collect::<()>()
+                    .await
+            }
+            
+            /// Create `collection_name` unless it already exists
+            async fn ensure_collection(&self, collection_name: String) -> Result<()> {
+                // Check if collection exists
+                let exists = self.client
+                    .collection_info(collection_name.clone())
+                    .await
+                    .is_ok();
+                
+                if !exists {
+                    info!("Creating collection: {}", collection_name);
+                    self.create_collection(&collection_name).await?;
+                } else {
+                    debug!("Collection already exists: {}", collection_name);
                 }
                 
                 Ok(())
@@ -61,7 +178,12 @@
             
             /// Get collection name for a context level
             pub fn collection_name(&self, level: ContextLevel) -> String {
-                format!("{}_{}", self.config.collection_prefix, level.as_str().to_lowercase())
+                self.naming.collection(level)
+            }
+            
+            /// Naming used for this client's level collections
+            pub fn naming(&self) -> &CollectionNaming {
+                &self.naming
             }
             
             /// Convert Distance enum to Qdrant Distance
@@ -73,9 +195,36 @@
                 }
             }
             
+            /// Convert the configured quantization to Qdrant's
+            fn to_qdrant_quantization(quantization: Quantization) -> QdrantQuantization {
+                match quantization {
+                    Quantization::Scalar { quantile } => ScalarQuantization {
+                        r#type: QuantizationType::Int8.into(),
+                        quantile,
+                        ..Default::default()
+                    }
+                    .into(),
+                    Quantization::Product { compression } => {
+                        let compression = match compression {
+                            ProductCompression::X4 => CompressionRatio::X4,
+                            ProductCompression::X8 => CompressionRatio::X8,
+                            ProductCompression::X16 => CompressionRatio::X16,
+                            ProductCompression::X32 => CompressionRatio::X32,
+                            ProductCompression::X64 => CompressionRatio::X64,
+                        };
+                        ProductQuantization { compression: compression.into(), ..Default::default() }.into()
+                    }
+                }
+            }
+            
             /// Convert Payload to Qdrant payload
-            fn to_qdrant_payload(&self, payload: &Payload) -> HashMap<String, Value> {
-                let mut map = HashMap::new();
+            fn to_qdrant_payload(payload: &Payload) -> HashMap<String, Value> {
+                // Store metadata natively so arrays and objects are filterable,
+                // then the system fields so metadata can never override them
+                let mut map: HashMap<String, Value> = payload.metadata
+                    .iter()
+                    .map(|(key, value)| (key.clone(), Value::from(value.clone())))
+                    .collect();
                 
                 map.insert("text".to_string(), Value::from(payload.text.clone()));
                 map.insert("level".to_string(), Value::from(payload.level.as_str().to_string()));
@@ -84,20 +233,24 @@
                 
                 if let Some(session_id) = &payload.session_id {
                     map.insert("session_id".to_string(), Value::from(session_id.clone()));
+                } else {
+                    map.remove("session_id");
                 }
                 
-                // Add additional metadata
-                for (key, value) in &payload.metadata {
-                    if let Ok(v) = serde_json::to_string(value) {
-                        map.insert(key.clone(), Value::from(v));
-                    }
+                map.insert("access_count".to_string(), Value::from(payload.access_count as i64));
+                
+                if let Some(token_count) = payload.token_count {
+                    map.insert("token_count".to_string(), Value::from(token_count as i64));
+                } else {
+                    map.remove("token_count");
                 }
+                map.insert(PAYLOAD_VERSION_KEY.to_string(), Value::from(PAYLOAD_VERSION));
                 
                 map
             }
             
             /// Convert Qdrant payload to Payload
-            fn parse_qdrant_payload(&self, payload: HashMap<String, Value>) -> Result<Payload> {
+            fn parse_qdrant_payload(payload: HashMap<String, Value>) -> Result<Payload> {
                 let text = payload.get("text")
                     .and_then(|v| v.kind.as_ref())
                     .and_then(|kind| match kind {
@@ -144,29 +297,36 @@
                         _ => None,
                     });
                 
+                let access_count = payload.get("access_count")
+                    .and_then(|v| v.kind.as_ref())
+                    .and_then(|kind| match kind {
+                        qdrant_client::qdrant::value::Kind::IntegerValue(i) => u64::try_from(*i).ok(),
+                        _ => None,
+                    })
+                    .unwrap_or(0);
+                
+                let token_count = payload.get("token_count")
+                    .and_then(|v| v.kind.as_ref())
+                    .and_then(|kind| match kind {
+                        qdrant_client::qdrant::value::Kind::IntegerValue(i) => usize::try_from(*i).ok(),
+                        _ => None,
+                    });
+                
+                let legacy = !payload.contains_key(PAYLOAD_VERSION_KEY);
                 let mut metadata = HashMap::new();
                 for (key, value) in payload {
-                    if !["text", "level", "timestamp", "agent_id", "session_id"].contains(&key.as_str()) {
-                        if let Some(kind) = value.kind.as_ref() {
-                            match kind {
-                                qdrant_client::qdrant::value::Kind::StringValue(s) => {
-                                    if let Ok(json_value) = serde_json::from_str(s) {
-                                        metadata.insert(key, json_value);
-                                    }
-                                }
-                                qdrant_client::qdrant::value::Kind::IntegerValue(i) => {
-                                    metadata.insert(key, serde_json::Value::Number((*i).into()));
-                                }
-                                qdrant_client::qdrant::value::Kind::DoubleValue(d) => {
-                                    metadata.insert(key, serde_json::Value::Number(serde_json::Number::from_f64(*d).unwrap_or_else(|| serde_json::Number::from(0))));
-                                }
-                                qdrant_client::qdrant::value::Kind::BoolValue(b) => {
-                                    metadata.insert(key, serde_json::Value::Bool(*b));
-                                }
-                                _ => {}
-                            }
-                        }
+                    if ["text", "level", "timestamp", "agent_id", "session_id", "access_count", "token_count", PAYLOAD_VERSION_KEY].contains(&key.as_str()) {
+                        continue;
                     }
+                    let json_value = match value.kind {
+                        // Older points stored every metadata value JSON-encoded as a string
+                        Some(qdrant_client::qdrant::value::Kind::StringValue(s)) if legacy => {
+                            serde_json::from_str(&s).unwrap_or(serde_json::Value::String(s))
+                        }
+                        None => continue,
+                        _ => serde_json::Value::from(value),
+                    };
+                    metadata.insert(key, json_value);
                 }
                 
                 Ok(Payload {
@@ -175,10 +335,33 @@
                     timestamp,
                     agent_id,
                     session_id,
+                    access_count,
+                    token_count,
                     metadata,
                 })
             }
             
+            /// Convert a retrieved Qdrant point (with payload and vector) to a VectorPoint
+            fn to_vector_point(&self, mut point: RetrievedPoint) -> Result<VectorPoint> {
+                let vector = point.vectors
+                    .take()
+                    .and_then(dense_vector)
+                    .ok_or_else(|| VectorDbError::SearchError("Missing vector".to_string()))?;
+                
+                Ok(VectorPoint { vector, ..self.to_payload_point(point)? })
+            }
+            
+            /// Convert a point fetched without its vector, leaving the vector empty
+            fn to_payload_point(&self, point: RetrievedPoint) -> Result<VectorPoint> {
+                let id = point.id
+                    .and_then(|id| id.point_id_options)
+                    .ok_or_else(|| VectorDbError::SearchError("Missing point ID".to_string()))
+                    .and_then(parse_point_id)?;
+                let payload = Self::parse_qdrant_payload(point.payload)?;
+                
+                Ok(VectorPoint { id, vector: Vec::new(), payload })
+            }
+            
             /// Convert Filter to Qdrant Filter
             fn to_qdrant_filter(&self, filter: &ModelFilter) -> QdrantFilter {
                 let mut must_conditions = Vec::new();
@@ -220,12 +403,17 @@
                     ModelCondition::Match { key, value } => {
                         if let Some(s) = value.as_str() {
                             Some(QdrantCondition::matches(key.clone(), s.to_string()))
+                        } else if let Some(b) = value.as_bool() {
+                            Some(QdrantCondition::matches(key.clone(), b))
                         } else {
                             value.as_i64().map(|i| QdrantCondition::matches(key.clone(), i))
                         }
                     }
-                    ModelCondition::Range { key, gte, lte } => {
+                    ModelCondition::Range { key, gt, gte, lte } => {
                         let mut range_builder = Range::default();
+                        if let Some(gt_val) = gt {
+                            range_builder.gt = Some(*gt_val);
+                        }
                         if let Some(gte_val) = gte {
                             range_builder.gte = Some(*gte_val);
                         }
@@ -242,6 +430,8 @@
                         
                         Some(QdrantCondition::has_id(point_ids))
                     }
+                    ModelCondition::IsEmpty { key } => Some(QdrantCondition::is_empty(key.clone())),
+                    ModelCondition::Group { filter } => Some(QdrantCondition::from(self.to_qdrant_filter(filter))),
                 }
             }
         }
@@ -249,24 +439,52 @@
         #[async_trait]
         impl VectorStore for VectorDbClient {
             async fn create_collection(&self, name: &str) -> Result<()> {
-                debug!("Creating collection: {}", name);
+                self.create_sized_collection(name, self.config.vector_size).await
+            }
+            
+            async fn create_sized_collection(&self, name: &str, vector_size: usize) -> Result<()> {
+                debug!("Creating collection: {} ({} dimensions)", name, vector_size);
                 
                 let vector_params = VectorParamsBuilder::new(
-                    self.config.vector_size as u64,
+                    vector_size as u64,
                     self.to_qdrant_distance(),
                 ).build();
 
+                let mut collection = CreateCollectionBuilder::new(name)
+                    .vectors_config(VectorsConfig {
+                        config: Some(Config::Params(vector_params)),
+                    });
+                if let Some(quantization) = self.config.quantization {
+                    collection = collection.quantization_config(Self::to_qdrant_quantization(quantization));
+                }
+                
+                self.client
+                    .create_collection(collection)
+                    .await
+                    .map_err(|e| match &e {
+                        QdrantError::ResponseError { status } if is_existing_collection(status.message()) => {
+                            VectorDbError::CollectionExists(name.to_string())
+                        }
+                        _ => VectorDbError::ConnectionError(e.to_string()),
+                    })?;
+                
+                self.ensure_indexes(name).await?;
+                
+                info!("Collection created: {}", name);
+                Ok(())
+            }
+            
+            async fn ensure_indexes(&self, name: &str) -> Result<()> {
+                // Ordering by timestamp (used for eviction) requires a range index;
+                // creating an index that already exists is a no-op
                 self.client
-                    .create_collection(
-                        CreateCollectionBuilder::new(name)
-                            .vectors_config(VectorsConfig {
-                                config: Some(Config::Params(vector_params)),
-                            })
+                    .create_field_index(
+                        CreateFieldIndexCollectionBuilder::new(name, "timestamp", FieldType::Integer)
+                            .wait(true)
                     )
                     .await
                     .map_err(|e| VectorDbError::ConnectionError(e.to_string()))?;
                 
-                info!("Collection created: {}", name);
                 Ok(())
             }
             
@@ -295,7 +513,7 @@
                         PointStruct::new(
                             point.id.to_string(),
                             point.vector,
-                            self.to_qdrant_payload(&point.payload),
+                            Self::to_qdrant_payload(&point.payload),
                         )
                     })
                     .collect();
@@ -303,10 +521,11 @@
                 let upsert_points = qdrant_client::qdrant::UpsertPointsBuilder::new(
                     collection.to_string(),
                     qdrant_points,
-                ).build();
+                )
+                .wait(self.config.wait_for_indexing)
+                .build();
 
-                self.client
-                    .upsert_points(upsert_points)
+                self.retry_not_ready(collection, upsert_points, |request| self.client.upsert_points(request))
                     .await
                     .map_err(|e| VectorDbError::InsertError(e.to_string()))?;
                 
@@ -333,10 +552,19 @@
                     search_points.filter = Some(self.to_qdrant_filter(&filter));
                 }
                 
-                let results = self.client
-                    .search_points(search_points)
+                let hnsw_ef = params.hnsw_ef.or(self.config.search_hnsw_ef);
+                let exact = params.exact.or(self.config.search_exact);
+                if hnsw_ef.is_some() || exact.is_some() {
+                    search_points.params = Some(QdrantSearchParams {
+                        hnsw_ef: hnsw_ef.map(|ef| ef as u64),
+                        exact,
+                        ..Default::default()
+                    });
+                }
+                
+                let results = self.retry_not_ready(collection, search_points, |request| self.client.search_points(request))
                     .await
-                    .map_err(|e| VectorDbError::SearchError(e.to_string()))?;
+                    .map_err(|e| search_error(collection, e))?;
                 
                 let search_results: Result<Vec<SearchResult>> = results
                     .result
@@ -358,19 +586,13 @@
                             })?;
                         
                         let payload = if params.with_payload && !point.payload.is_empty() {
-                            Some(self.parse_qdrant_payload(point.payload)?)
+                            Some(Self::parse_qdrant_payload(point.payload)?)
                         } else {
                             None
                         };
                         
                         let vector = if params.with_vector {
-                            point.vectors.and_then(|v| {
-                                if let Some(qdrant_client::qdrant::vectors_output::VectorsOptions::Vector(vec)) = v.vectors_options {
-                                    Some(vec.data)
-                                } else {
-                                    None
-                                }
-                            })
+                            point.vectors.and_then(dense_vector)
                         } else {
                             None
                         };
@@ -402,6 +624,7 @@
                 
                 let delete_points = qdrant_client::qdrant::DeletePointsBuilder::new(collection.to_string())
                     .points(point_ids)
+                    .wait(self.config.wait_for_indexing)
                     .build();
 
                 self.client
@@ -416,9 +639,91 @@
             async fn get_point(&self, collection: &str, id: Uuid) -> Result<Option<VectorPoint>> {
                 debug!("Getting point {} from collection: {}", id, collection);
                 
-                let point_id = PointId::from(id.to_string());
+                Ok(self.get_points(collection, vec![id]).await?.into_iter().next())
+            }
+            
+            async fn set_payload(&self, collection: &str, id: Uuid, payload: Payload) -> Result<()> {
+                debug!("Setting payload of point {} in collection: {}", id, collection);
+                
+                let set_payload = SetPayloadPointsBuilder::new(collection, Self::to_qdrant_payload(&payload))
+                    .points_selector(PointsIdsList { ids: vec![PointId::from(id.to_string())] })
+                    .wait(self.config.wait_for_indexing);
+                
+                self.client
+                    .set_payload(set_payload)
+                    .await
+                    .map_err(|e| VectorDbError::InsertError(e.to_string()))?;
+                
+                Ok(())
+            }
+            
+            async fn set_payload_fields(
+                &self,
+                collection: &str,
+                id: Uuid,
+                fields: HashMap<String, serde_json::Value>,
+            ) -> Result<()> {
+                debug!("Setting {} payload fields of point {} in collection: {}", fields.len(), id, collection);
+                
+                let payload: HashMap<String, Value> = fields
+                    .into_iter()
+                    .map(|(key, value)| (key, Value::from(value)))
+                    .collect();
+                let set_payload = SetPayloadPointsBuilder::new(collection, payload)
+                    .points_selector(PointsIdsList { ids: vec![PointId::from(id.to_string())] })
+                    .wait(self.config.wait_for_indexing);
+                
+                self.client
+                    .set_payload(set_payload)
+                    .await
+                    .map_err(|e| VectorDbError::InsertError(e.to_string()))?;
+                
+                Ok(())
+            }
+            
+            async fn delete_payload_fields(&self, collection: &str, id: Uuid, keys: Vec<String>) -> Result<()> {
+                debug!("Removing {} payload fields of point {} in collection: {}", keys.len(), id, collection);
+                
+                let delete_payload = DeletePayloadPointsBuilder::new(collection, keys)
+                    .points_selector(PointsIdsList { ids: vec![PointId::from(id.to_string())] })
+                    .wait(self.config.wait_for_indexing);
+                
+                self.client
+                    .delete_payload(delete_payload)
+                    .await
+                    .map_err(|e| VectorDbError::InsertError(e.to_string()))?;
+                
+                Ok(())
+            }
+            
+            async fn set_vector(&self, collection: &str, id: Uuid, vector: Vec<f32>) -> Result<()> {
+                debug!("Updating the vector of point {} in collection: {}", id, collection);
+                
+                let point = PointVectors {
+                    id: Some(PointId::from(id.to_string())),
+                    vectors: Some(vector.into()),
+                };
+                let update = UpdatePointVectorsBuilder::new(collection, vec![point])
+                    .wait(self.config.wait_for_indexing);
+                
+                self.client
+                    .update_vectors(update)
+                    .await
+                    .map_err(|e| VectorDbError::InsertError(e.to_string()))?;
+                
+                Ok(())
+            }
+            
+            async fn get_points(&self, collection: &str, ids: Vec<Uuid>) -> Result<Vec<VectorPoint>> {
+                if ids.is_empty() {
+                    return Ok(Vec::new());
+                }
+                
+                let point_ids: Vec<PointId> = ids.iter()
+                    .map(|uuid| PointId::from(uuid.to_string()))
+                    .collect();
                 
-                let get_points = qdrant_client::qdrant::GetPointsBuilder::new(collection.to_string(), vec![point_id])
+                let get_points = qdrant_client::qdrant::GetPointsBuilder::new(collection.to_string(), point_ids)
                     .with_payload(true)
                     .with_vectors(true)
                     .build();
@@ -426,26 +731,237 @@
                 let points = self.client
                     .get_points(get_points)
                     .await
-                    .map_err(|e| VectorDbError::SearchError(e.to_string()))?;
-                
-                if let Some(point) = points.result.first() {
-                    let payload = self.parse_qdrant_payload(point.payload.clone())?;
-                    
-                    let vector = point.vectors.as_ref().and_then(|v| {
-                        if let Some(qdrant_client::qdrant::vectors_output::VectorsOptions::Vector(vec)) = &v.vectors_options {
-                            Some(vec.data.clone())
-                        } else {
-                            None
-                        }
-                    }).ok_or_else(|| VectorDbError::SearchError("Missing vector".to_string()))?;
-                    
-                    Ok(Some(VectorPoint {
-                        id,
-                        vector,
-                        payload,
-                    }))
-                } else {
-                    Ok(None)
+                    .map_err(|e| search_error(collection, e))?;
+                
+                points.result
+                    .into_iter()
+                    .map(|point| self.to_vector_point(point))
+                    .collect()
+            }
+            
+            async fn scroll(
+                &self,
+                collection: &str,
+                filter: Option<ModelFilter>,
+                offset: Option<Uuid>,
+                limit: usize,
+            ) -> Result<ScrollPage> {
+                let mut scroll = ScrollPointsBuilder::new(collection)
+                    .limit(limit as u32)
+                    .with_payload(true)
+                    .with_vectors(true);
+                if let Some(filter) = filter {
+                    scroll = scroll.filter(self.to_qdrant_filter(&filter));
+                }
+                if let Some(offset) = offset {
+                    scroll = scroll.offset(PointId::from(offset.to_string()));
+                }
+                
+                let response = self.client
+                    .scroll(scroll)
+                    .await
+                    .map_err(|e| search_error(collection, e))?;
+                
+                let points = response.result
+                    .into_iter()
+                    .map(|point| self.to_vector_point(point))
+                    .collect::<Result<Vec<_>>>()?;
+                let next_offset = response.next_page_offset
+                    .and_then(|id| id.point_id_options)
+                    .map(parse_point_id)
+                    .transpose()?;
+                
+                Ok(ScrollPage { points, next_offset })
+            }
+            
+            async fn delete_by_filter(&self, collection: &str, filter: ModelFilter) -> Result<usize> {
+                let filter = self.to_qdrant_filter(&filter);
+                // Qdrant doesn't report what a delete removed, so the count is
+                // taken first and misses points written in between
+                let matching = self.client
+                    .count(CountPointsBuilder::new(collection).filter(filter.clone()).exact(true))
+                    .await
+                    .map_err(|e| search_error(collection, e))?
+                    .result
+                    .map(|r| r.count as usize)
+                    .unwrap_or(0);
+                
+                let delete_points = qdrant_client::qdrant::DeletePointsBuilder::new(collection.to_string())
+                    .points(filter)
+                    .wait(self.config.wait_for_indexing)
+                    .build();
+                
+                self.client
+                    .delete_points(delete_points)
+                    .await
+                    .map_err(|e| VectorDbError::DeleteError(e.to_string()))?;
+                
+                debug!("Deleted {} points by filter from collection: {}", matching, collection);
+                Ok(matching)
+            }
+            
+            async fn count(&self, collection: &str) -> Result<usize> {
+                let response = self.client
+                    .count(CountPointsBuilder::new(collection).exact(true))
+                    .await
+                    .map_err(|e| search_error(collection, e))?;
+                
+                Ok(response.result.map(|r| r.count as usize).unwrap_or(0))
+            }
+            
+            async fn oldest_points(&self, collection: &str, limit: usize) -> Result<Vec<Uuid>> {
+                debug!("Fetching {} oldest points from collection: {}", limit, collection);
+                
+                let scroll = ScrollPointsBuilder::new(collection)
+                    .order_by(OrderByBuilder::new("timestamp").direction(Direction::Asc.into()))
+                    .limit(limit as u32)
+                    .with_payload(false);
+                
+                let response = self.client
+                    .scroll(scroll)
+                    .await
+                    .map_err(|e| search_error(collection, e))?;
+                
+                response.result
+                    .into_iter()
+                    .filter_map(|point| point.id.and_then(|id| id.point_id_options))
+                    .map(|id| parse_point_id(id).map_err(Into::into))
+                    .collect()
+            }
+            
+            async fn newest_points(&self, collection: &str, filter: Option<ModelFilter>, limit: usize) -> Result<Vec<VectorPoint>> {
+                debug!("Fetching {} newest points from collection: {}", limit, collection);
+                
+                let mut scroll = ScrollPointsBuilder::new(collection)
+                    .order_by(OrderByBuilder::new("timestamp").direction(Direction::Desc.into()))
+                    .limit(limit as u32)
+                    .with_payload(true)
+                    .with_vectors(false);
+                if let Some(filter) = filter {
+                    scroll = scroll.filter(self.to_qdrant_filter(&filter));
+                }
+                
+                let response = self.client
+                    .scroll(scroll)
+                    .await
+                    .map_err(|e| search_error(collection, e))?;
+                
+                response.result
+                    .into_iter()
+                    .map(|point| self.to_payload_point(point))
+                    .collect()
+            }
+        }
+
+        #[cfg(test)]
+        mod tests {
+            use super::*;
+            
+            #[test]
+            fn test_missing_collection_message_is_detected() {
+                assert!(is_missing_collection("Not found: Collection `contexts_immediate` doesn't exist!"));
+                assert!(!is_missing_collection("Wrong input: Vector dimension error: expected dim: 1024, got 3"));
+                assert!(is_existing_collection("Wrong input: Collection `contexts_immediate` already exists!"));
+                assert!(!is_existing_collection("Not found: Collection `contexts_immediate` doesn't exist!"));
+            }
+            
+            #[test]
+            fn test_only_unavailable_counts_as_not_ready() {
+                assert!(is_not_ready(GRPC_UNAVAILABLE));
+                // Internal, NotFound and InvalidArgument fail at once
+                for code in [13, 5, 3] {
+                    assert!(!is_not_ready(code));
                 }
             }
-        }
\ No newline at end of file
+            
+            #[test]
+            fn test_quantization_converts_to_qdrant() {
+                match VectorDbClient::to_qdrant_quantization(Quantization::Scalar { quantile: Some(0.99) }) {
+                    QdrantQuantization::Scalar(scalar) => {
+                        assert_eq!(scalar.r#type, QuantizationType::Int8 as i32);
+                        assert_eq!(scalar.quantile, Some(0.99));
+                    }
+                    other => panic!("expected scalar quantization, got {:?}", other),
+                }
+                match VectorDbClient::to_qdrant_quantization(Quantization::Product { compression: ProductCompression::X16 }) {
+                    QdrantQuantization::Product(product) => assert_eq!(product.compression, CompressionRatio::X16 as i32),
+                    other => panic!("expected product quantization, got {:?}", other),
+                }
+            }
+            
+            #[test]
+            fn test_agent_and_session_round_trip_through_qdrant_payload() {
+                let payload = Payload {
+                    text: "owned note".to_string(),
+                    level: ContextLevel::ShortTerm,
+                    timestamp: 1_700_000_000,
+                    agent_id: "agent-7".to_string(),
+                    session_id: Some("session-42".to_string()),
+                    access_count: 3,
+                    token_count: Some(12),
+                    metadata: HashMap::from([("topic".to_string(), serde_json::json!("ui"))]),
+                };
+                
+                let parsed = VectorDbClient::parse_qdrant_payload(VectorDbClient::to_qdrant_payload(&payload)).unwrap();
+                assert_eq!(parsed.agent_id, "agent-7");
+                assert_eq!(parsed.session_id.as_deref(), Some("session-42"));
+                assert_eq!(parsed.access_count, 3);
+                assert_eq!(parsed.token_count, Some(12));
+                assert_eq!(parsed.metadata, payload.metadata);
+            }
+            
+            #[test]
+            fn test_metadata_cannot_override_system_fields() {
+                let payload = Payload {
+                    text: "a very long text ".repeat(100),
+                    level: ContextLevel::ShortTerm,
+                    timestamp: 1_700_000_000,
+                    agent_id: "agent-7".to_string(),
+                    session_id: None,
+                    access_count: 0,
+                    token_count: Some(400),
+                    metadata: HashMap::from([
+                        ("token_count".to_string(), serde_json::json!(1)),
+                        ("timestamp".to_string(), serde_json::json!(0)),
+                        ("session_id".to_string(), serde_json::json!("spoofed")),
+                    ]),
+                };
+                
+                let parsed = VectorDbClient::parse_qdrant_payload(VectorDbClient::to_qdrant_payload(&payload)).unwrap();
+                assert_eq!(parsed.token_count, Some(400));
+                assert_eq!(parsed.timestamp, 1_700_000_000);
+                assert_eq!(parsed.session_id, None);
+            }
+            
+            #[test]
+            fn test_only_legacy_payloads_have_string_metadata_decoded() {
+                let metadata: HashMap<_, _> = ["123", "true", "null", "[1]", "plain"]
+                    .iter()
+                    .enumerate()
+                    .map(|(i, value)| (format!("field{}", i), serde_json::json!(value)))
+                    .collect();
+                let payload = Payload {
+                    text: "note".to_string(),
+                    level: ContextLevel::ShortTerm,
+                    timestamp: 1_700_000_000,
+                    agent_id: "default".to_string(),
+                    session_id: None,
+                    access_count: 0,
+                    token_count: None,
+                    metadata: metadata.clone(),
+                };
+                
+                let mut stored = VectorDbClient::to_qdrant_payload(&payload);
+                let parsed = VectorDbClient::parse_qdrant_payload(stored.clone()).unwrap();
+                assert_eq!(parsed.metadata, metadata);
+                
+                // Points written before native metadata JSON-encoded every value
+                stored.remove(PAYLOAD_VERSION_KEY);
+                stored.insert("acl".to_string(), Value::from(r#"["agent-7"]"#.to_string()));
+                let parsed = VectorDbClient::parse_qdrant_payload(stored).unwrap();
+                assert_eq!(parsed.metadata["field0"], serde_json::json!(123));
+                assert_eq!(parsed.metadata["field3"], serde_json::json!([1]));
+                assert_eq!(parsed.metadata["field4"], serde_json::json!("plain"));
+                assert_eq!(parsed.metadata["acl"], serde_json::json!(["agent-7"]));
+            }
+        }
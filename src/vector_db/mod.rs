@@ -5,21 +5,52 @@ pub mod client_v2;
 pub mod models;
 pub mod search;
 pub mod circuit_breaker;
+pub mod naming;
 
 pub use client::VectorDbClient;
-pub use models::{VectorPoint, Payload, SearchParams, SearchResult, Filter, Condition, ContextLevel};
-pub use circuit_breaker::{CircuitBreaker, CircuitBreakerConfig, CircuitState};
+pub use models::{VectorPoint, Payload, SearchParams, SearchResult, ScrollPage, Filter, Condition, ContextLevel};
+pub use circuit_breaker::{CircuitBreaker, CircuitBreakerConfig, CircuitState, PipelineBreaker};
+pub use naming::CollectionNaming;
 
 use async_trait::async_trait;
-use crate::error::Result;
+use crate::error::{Result, VectorDbError};
+use std::collections::HashMap;
 use uuid::Uuid;
 
+/// Page size used by the scroll-based default implementations
+const DEFAULT_SCROLL_PAGE: usize = 256;
+
 /// Trait for vector storage operations
+///
+/// Implementors must provide the core methods: `create_collection`,
+/// `delete_collection`, `insert_points`, `search`, `delete_points` and
+/// `get_point`. Everything else has a default derived from those (or from
+/// `scroll`) and only needs overriding for performance.
+///
+/// `scroll` cannot be derived from the core methods; its default returns
+/// [`VectorDbError::Unsupported`], and so do `count`, `oldest_points` and
+/// `delete_by_filter` unless either they or `scroll` are overridden.
 #[async_trait]
 pub trait VectorStore: Send + Sync {
     /// Create a new collection
     async fn create_collection(&self, name: &str) -> Result<()>;
     
+    /// Create a new collection for vectors of `vector_size` dimensions
+    ///
+    /// Stores with a fixed vector size ignore `vector_size`; the default calls
+    /// [`create_collection`](Self::create_collection).
+    async fn create_sized_collection(&self, name: &str, _vector_size: usize) -> Result<()> {
+        self.create_collection(name).await
+    }
+    
+    /// Create the payload indexes queries rely on, keeping any that exist
+    ///
+    /// `create_collection` already creates them; this repairs collections
+    /// created before an index was added or whose index creation failed.
+    async fn ensure_indexes(&self, _name: &str) -> Result<()> {
+        Ok(())
+    }
+    
     /// Delete a collection
     async fn delete_collection(&self, name: &str) -> Result<()>;
     
@@ -33,5 +64,263 @@ pub trait VectorStore: Send + Sync {
     async fn delete_points(&self, collection: &str, ids: Vec<Uuid>) -> Result<()>;
     
     /// Get point by ID
+    ///
+    /// A missing ID is `Ok(None)`; a missing collection is an error
+    /// ([`VectorDbError::CollectionNotFound`]).
     async fn get_point(&self, collection: &str, id: Uuid) -> Result<Option<VectorPoint>>;
-}
\ No newline at end of file
+    
+    /// Replace the payload of an existing point, keeping its vector
+    async fn set_payload(&self, collection: &str, id: Uuid, payload: Payload) -> Result<()> {
+        let mut point = self.get_point(collection, id).await?
+            .ok_or_else(|| VectorDbError::InsertError(format!("Point {} not found", id)))?;
+        point.payload = payload;
+        self.insert_points(collection, vec![point]).await
+    }
+    
+    /// Set the payload fields in `fields`, keeping the point's other fields
+    /// and its vector
+    ///
+    /// Unlike [`set_payload`](Self::set_payload), fields changed concurrently
+    /// by someone else are left alone. The default reads and re-inserts the
+    /// point, so stores should override it with an update in place.
+    async fn set_payload_fields(
+        &self,
+        collection: &str,
+        id: Uuid,
+        fields: HashMap<String, serde_json::Value>,
+    ) -> Result<()> {
+        let mut point = self.get_point(collection, id).await?
+            .ok_or_else(|| VectorDbError::InsertError(format!("Point {} not found", id)))?;
+        point.payload = point.payload.with_fields(fields)
+            .map_err(|e| VectorDbError::InsertError(format!("Invalid payload field: {}", e)))?;
+        self.insert_points(collection, vec![point]).await
+    }
+    
+    /// Remove the payload fields `keys`, keeping the point's other fields and
+    /// its vector
+    ///
+    /// Like [`set_payload_fields`](Self::set_payload_fields), the default
+    /// reads and re-inserts the point.
+    async fn delete_payload_fields(&self, collection: &str, id: Uuid, keys: Vec<String>) -> Result<()> {
+        let mut point = self.get_point(collection, id).await?
+            .ok_or_else(|| VectorDbError::InsertError(format!("Point {} not found", id)))?;
+        point.payload = point.payload.without_fields(&keys)
+            .map_err(|e| VectorDbError::InsertError(format!("Cannot remove payload field: {}", e)))?;
+        self.insert_points(collection, vec![point]).await
+    }
+    
+    /// Replace the vector of an existing point, keeping its payload
+    ///
+    /// Like [`set_payload_fields`](Self::set_payload_fields), the default
+    /// reads and re-inserts the point, so payload changes made in between
+    /// are lost; stores should override it with an update in place.
+    async fn set_vector(&self, collection: &str, id: Uuid, vector: Vec<f32>) -> Result<()> {
+        let mut point = self.get_point(collection, id).await?
+            .ok_or_else(|| VectorDbError::InsertError(format!("Point {} not found", id)))?;
+        point.vector = vector;
+        self.insert_points(collection, vec![point]).await
+    }
+    
+    /// Get several points by ID, skipping IDs that don't exist
+    async fn get_points(&self, collection: &str, ids: Vec<Uuid>) -> Result<Vec<VectorPoint>> {
+        let mut points = Vec::with_capacity(ids.len());
+        for id in ids {
+            if let Some(point) = self.get_point(collection, id).await? {
+                points.push(point);
+            }
+        }
+        Ok(points)
+    }
+    
+    /// Page through the points matching `filter`, starting at `offset`
+    async fn scroll(
+        &self,
+        _collection: &str,
+        _filter: Option<Filter>,
+        _offset: Option<Uuid>,
+        _limit: usize,
+    ) -> Result<ScrollPage> {
+        Err(VectorDbError::Unsupported("scroll".to_string()).into())
+    }
+    
+    /// Delete every point matching `filter`, returning how many were removed
+    ///
+    /// The count is approximate when the collection is written concurrently:
+    /// stores may count the matches separately from deleting them.
+    async fn delete_by_filter(&self, collection: &str, filter: Filter) -> Result<usize> {
+        let mut ids = Vec::new();
+        let mut offset = None;
+        loop {
+            let page = self.scroll(collection, Some(filter.clone()), offset, DEFAULT_SCROLL_PAGE).await?;
+            ids.extend(page.points.into_iter().map(|p| p.id));
+            match page.next_offset {
+                Some(next) => offset = Some(next),
+                None => break,
+            }
+        }
+        
+        let deleted = ids.len();
+        self.delete_points(collection, ids).await?;
+        Ok(deleted)
+    }
+    
+    /// Count the points in a collection
+    async fn count(&self, collection: &str) -> Result<usize> {
+        let mut count = 0;
+        let mut offset = None;
+        loop {
+            let page = self.scroll(collection, None, offset, DEFAULT_SCROLL_PAGE).await?;
+            count += page.points.len();
+            match page.next_offset {
+                Some(next) => offset = Some(next),
+                None => return Ok(count),
+            }
+        }
+    }
+    
+    /// IDs of the `limit` points with the oldest timestamps, oldest first
+    async fn oldest_points(&self, collection: &str, limit: usize) -> Result<Vec<Uuid>> {
+        let mut all = Vec::new();
+        let mut offset = None;
+        loop {
+            let page = self.scroll(collection, None, offset, DEFAULT_SCROLL_PAGE).await?;
+            all.extend(page.points.into_iter().map(|p| (p.payload.timestamp, p.id)));
+            match page.next_offset {
+                Some(next) => offset = Some(next),
+                None => break,
+            }
+        }
+        
+        all.sort();
+        Ok(all.into_iter().take(limit).map(|(_, id)| id).collect())
+    }
+    
+    /// The `limit` points matching `filter` with the newest timestamps,
+    /// newest first
+    ///
+    /// Stores may leave the returned points' vectors empty.
+    async fn newest_points(&self, collection: &str, filter: Option<Filter>, limit: usize) -> Result<Vec<VectorPoint>> {
+        let mut all = Vec::new();
+        let mut offset = None;
+        loop {
+            let page = self.scroll(collection, filter.clone(), offset, DEFAULT_SCROLL_PAGE).await?;
+            all.extend(page.points);
+            match page.next_offset {
+                Some(next) => offset = Some(next),
+                None => break,
+            }
+        }
+        
+        all.sort_by(|a, b| b.payload.timestamp.cmp(&a.payload.timestamp).then(a.id.cmp(&b.id)));
+        all.truncate(limit);
+        Ok(all)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::ContextError;
+    use crate::test_support::{point, MockVectorStore};
+    use std::sync::Mutex;
+    
+    /// Store implementing only the core methods
+    #[derive(Default)]
+    struct MinimalStore {
+        points: Mutex<HashMap<Uuid, VectorPoint>>,
+    }
+    
+    #[async_trait]
+    impl VectorStore for MinimalStore {
+        async fn create_collection(&self, _name: &str) -> Result<()> {
+            Ok(())
+        }
+        
+        async fn delete_collection(&self, _name: &str) -> Result<()> {
+            Ok(())
+        }
+        
+        async fn insert_points(&self, _collection: &str, points: Vec<VectorPoint>) -> Result<()> {
+            self.points.lock().unwrap().extend(points.into_iter().map(|p| (p.id, p)));
+            Ok(())
+        }
+        
+        async fn search(&self, _collection: &str, _params: SearchParams) -> Result<Vec<SearchResult>> {
+            Ok(Vec::new())
+        }
+        
+        async fn delete_points(&self, _collection: &str, ids: Vec<Uuid>) -> Result<()> {
+            let mut points = self.points.lock().unwrap();
+            for id in ids {
+                points.remove(&id);
+            }
+            Ok(())
+        }
+        
+        async fn get_point(&self, _collection: &str, id: Uuid) -> Result<Option<VectorPoint>> {
+            Ok(self.points.lock().unwrap().get(&id).cloned())
+        }
+    }
+    
+    #[tokio::test]
+    async fn test_minimal_store_gets_batch_defaults() {
+        let store = MinimalStore::default();
+        let (a, b) = (point(1, ContextLevel::ShortTerm, 1), point(2, ContextLevel::ShortTerm, 2));
+        store.insert_points("c", vec![a.clone(), b.clone()]).await.unwrap();
+        
+        let found = store.get_points("c", vec![a.id, Uuid::from_u128(99), b.id]).await.unwrap();
+        assert_eq!(found.iter().map(|p| p.id).collect::<Vec<_>>(), vec![a.id, b.id]);
+        
+        store.set_payload_fields("c", a.id, HashMap::from([
+            ("access_count".to_string(), serde_json::json!(4)),
+            ("topic".to_string(), serde_json::json!("ui")),
+        ])).await.unwrap();
+        let updated = store.get_point("c", a.id).await.unwrap().unwrap();
+        assert_eq!(updated.payload.access_count, 4);
+        assert_eq!(updated.payload.metadata["topic"], "ui");
+        assert_eq!((updated.payload.text, updated.vector), (a.payload.text.clone(), a.vector.clone()));
+        
+        store.delete_payload_fields("c", a.id, vec!["topic".to_string()]).await.unwrap();
+        let updated = store.get_point("c", a.id).await.unwrap().unwrap();
+        assert!(!updated.payload.metadata.contains_key("topic"));
+        assert_eq!(updated.payload.access_count, 4);
+        // Required fields can't be removed
+        assert!(store.delete_payload_fields("c", a.id, vec!["text".to_string()]).await.is_err());
+        
+        // Without scroll there is nothing to derive a count from
+        match store.count("c").await {
+            Err(ContextError::VectorDb(VectorDbError::Unsupported(op))) => assert_eq!(op, "scroll"),
+            other => panic!("expected unsupported, got {:?}", other),
+        }
+    }
+    
+    #[tokio::test]
+    async fn test_get_point_distinguishes_missing_id_from_missing_collection() {
+        let store = MockVectorStore::new();
+        store.create_collection("c").await.unwrap();
+        store.insert_points("c", vec![point(1, ContextLevel::ShortTerm, 1)]).await.unwrap();
+        
+        assert!(store.get_point("c", Uuid::from_u128(1)).await.unwrap().is_some());
+        assert!(store.get_point("c", Uuid::from_u128(2)).await.unwrap().is_none());
+        match store.get_point("missing", Uuid::from_u128(1)).await {
+            Err(ContextError::VectorDb(VectorDbError::CollectionNotFound(name))) => assert_eq!(name, "missing"),
+            other => panic!("expected missing collection, got {:?}", other),
+        }
+    }
+    
+    #[tokio::test]
+    async fn test_scroll_derived_defaults() {
+        let store = MockVectorStore::new();
+        store.create_collection("c").await.unwrap();
+        let points: Vec<_> = (0..600).map(|i| point(i, ContextLevel::ShortTerm, 1000 - i as i64)).collect();
+        let newest = points[599].id;
+        store.insert_points("c", points).await.unwrap();
+        
+        assert_eq!(store.count("c").await.unwrap(), 600);
+        assert_eq!(store.oldest_points("c", 1).await.unwrap(), vec![newest]);
+        
+        let filter = Filter::new().must(Condition::Range { key: "timestamp".to_string(), gt: None, gte: None, lte: Some(500.0) });
+        assert_eq!(store.delete_by_filter("c", filter).await.unwrap(), 100);
+        assert_eq!(store.count("c").await.unwrap(), 500);
+    }
+}
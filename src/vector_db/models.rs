@@ -6,6 +6,7 @@ use uuid::Uuid;
 
 /// Context hierarchy levels
 #[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Hash)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
 pub enum ContextLevel {
     Immediate,  // L1
     ShortTerm,  // L2
@@ -53,11 +54,58 @@ pub struct Payload {
     /// Session identifier
     pub session_id: Option<String>,
     
+    /// Number of times the context has been returned by a retrieval
+    #[serde(default)]
+    pub access_count: u64,
+    
+    /// Token count estimated when the context was stored, so retrieval
+    /// doesn't re-estimate it (absent on older points)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub token_count: Option<usize>,
+    
     /// Additional metadata
     #[serde(flatten)]
     pub metadata: HashMap<String, serde_json::Value>,
 }
 
+impl Payload {
+    /// Look up a filterable field as it is stored in the vector database
+    pub fn field(&self, key: &str) -> Option<serde_json::Value> {
+        match key {
+            "text" => Some(self.text.clone().into()),
+            "level" => Some(self.level.as_str().into()),
+            "timestamp" => Some(self.timestamp.into()),
+            "agent_id" => Some(self.agent_id.clone().into()),
+            "session_id" => self.session_id.clone().map(Into::into),
+            "access_count" => Some(self.access_count.into()),
+            "token_count" => self.token_count.map(Into::into),
+            _ => self.metadata.get(key).cloned(),
+        }
+    }
+    
+    /// Copy of this payload with `fields` assigned, addressing each field by
+    /// the name it is stored under (see [`field`](Self::field))
+    pub fn with_fields(&self, fields: HashMap<String, serde_json::Value>) -> serde_json::Result<Payload> {
+        let mut payload = serde_json::to_value(self)?;
+        if let serde_json::Value::Object(map) = &mut payload {
+            map.extend(fields);
+        }
+        serde_json::from_value(payload)
+    }
+    
+    /// Copy of this payload without `keys`, addressed like in
+    /// [`with_fields`](Self::with_fields)
+    pub fn without_fields(&self, keys: &[String]) -> serde_json::Result<Payload> {
+        let mut payload = serde_json::to_value(self)?;
+        if let serde_json::Value::Object(map) = &mut payload {
+            for key in keys {
+                map.remove(key);
+            }
+        }
+        serde_json::from_value(payload)
+    }
+}
+
 /// Search parameters
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct SearchParams {
@@ -78,6 +126,12 @@ pub struct SearchParams {
     
     /// Include vectors in results
     pub with_vector: bool,
+    
+    /// HNSW beam size for this search (store default if unset)
+    pub hnsw_ef: Option<usize>,
+    
+    /// Search exhaustively instead of using the HNSW index (store default if unset)
+    pub exact: Option<bool>,
 }
 
 /// Search result
@@ -96,6 +150,16 @@ pub struct SearchResult {
     pub vector: Option<Vec<f32>>,
 }
 
+/// One page of points returned by a scroll
+#[derive(Debug, Clone, Default)]
+pub struct ScrollPage {
+    /// Points in this page
+    pub points: Vec<VectorPoint>,
+    
+    /// Offset to pass to fetch the next page, `None` once exhausted
+    pub next_offset: Option<Uuid>,
+}
+
 /// Filter for metadata-based search
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Filter {
@@ -116,9 +180,45 @@ pub struct Filter {
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(tag = "type")]
 pub enum Condition {
+    /// Field equals `value`, or contains it when the field is an array
     Match { key: String, value: serde_json::Value },
-    Range { key: String, gte: Option<f64>, lte: Option<f64> },
+    /// Numeric field within the given bounds; a non-numeric field never matches
+    Range {
+        key: String,
+        /// Exclusive lower bound
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        gt: Option<f64>,
+        gte: Option<f64>,
+        lte: Option<f64>,
+    },
     HasId { ids: Vec<Uuid> },
+    /// Field is missing, null or an empty array
+    IsEmpty { key: String },
+    /// A nested filter, so that `should` groups can be combined
+    Group { filter: Filter },
+}
+
+impl Condition {
+    /// Evaluate the condition against a point the way Qdrant would
+    pub fn matches(&self, id: Uuid, payload: &Payload) -> bool {
+        match self {
+            Condition::Match { key, value } => match payload.field(key) {
+                Some(serde_json::Value::Array(items)) => items.contains(value),
+                field => field.as_ref() == Some(value),
+            },
+            Condition::Range { key, gt, gte, lte } => match payload.field(key).and_then(|v| v.as_f64()) {
+                Some(v) => gt.is_none_or(|g| v > g) && gte.is_none_or(|g| v >= g) && lte.is_none_or(|l| v <= l),
+                None => false,
+            },
+            Condition::HasId { ids } => ids.contains(&id),
+            Condition::IsEmpty { key } => match payload.field(key) {
+                None | Some(serde_json::Value::Null) => true,
+                Some(serde_json::Value::Array(items)) => items.is_empty(),
+                Some(_) => false,
+            },
+            Condition::Group { filter } => filter.matches(id, payload),
+        }
+    }
 }
 
 impl SearchParams {
@@ -130,6 +230,8 @@ impl SearchParams {
             filter: None,
             with_payload: true,
             with_vector: false,
+            hnsw_ef: None,
+            exact: None,
         }
     }
     
@@ -142,6 +244,16 @@ impl SearchParams {
         self.filter = Some(filter);
         self
     }
+    
+    pub fn with_hnsw_ef(mut self, hnsw_ef: usize) -> Self {
+        self.hnsw_ef = Some(hnsw_ef);
+        self
+    }
+    
+    pub fn with_exact(mut self, exact: bool) -> Self {
+        self.exact = Some(exact);
+        self
+    }
 }
 
 impl Filter {
@@ -167,6 +279,45 @@ impl Filter {
         self.must_not.push(condition);
         self
     }
+    
+    /// Number of conditions in the filter, counting those nested in groups
+    /// rather than the groups themselves
+    pub fn condition_count(&self) -> usize {
+        self.must
+            .iter()
+            .chain(&self.should)
+            .chain(&self.must_not)
+            .map(|condition| match condition {
+                Condition::Group { filter } => filter.condition_count(),
+                _ => 1,
+            })
+            .sum()
+    }
+    
+    /// Require points to match both this filter and `other`
+    ///
+    /// `other`'s `must` conditions are appended to this filter's; its
+    /// `should` and `must_not` conditions are nested in a group so neither
+    /// filter's alternatives or exclusions change meaning.
+    pub fn and(mut self, other: Filter) -> Self {
+        let Filter { must, should, must_not } = other;
+        self.must.extend(must);
+        if !should.is_empty() || !must_not.is_empty() {
+            self.must.push(Condition::Group {
+                filter: Filter { must: Vec::new(), should, must_not },
+            });
+        }
+        self
+    }
+}
+
+impl Filter {
+    /// Evaluate the filter against a point the way Qdrant would
+    pub fn matches(&self, id: Uuid, payload: &Payload) -> bool {
+        self.must.iter().all(|c| c.matches(id, payload))
+            && (self.should.is_empty() || self.should.iter().any(|c| c.matches(id, payload)))
+            && !self.must_not.iter().any(|c| c.matches(id, payload))
+    }
 }
 
 impl Default for Filter {
@@ -0,0 +1,97 @@
+//! Collection naming shared by the vector store, managers and GC
+
+use super::models::ContextLevel;
+use crate::config::VectorDbConfig;
+
+/// Prefix used when no configuration is supplied, matching the config default
+const DEFAULT_PREFIX: &str = "contexts";
+
+/// Produces the collection name for each context level
+///
+/// Every component that addresses a level's collection should derive the
+/// name from the same `CollectionNaming` so a custom `collection_prefix`
+/// can't leave them pointing at different collections.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CollectionNaming {
+    prefix: String,
+}
+
+impl CollectionNaming {
+    /// Name collections `<prefix>_<level>`
+    pub fn new(prefix: impl Into<String>) -> Self {
+        Self { prefix: prefix.into() }
+    }
+    
+    /// Use the configured `collection_prefix`
+    pub fn from_config(config: &VectorDbConfig) -> Self {
+        Self::new(config.collection_prefix.clone())
+    }
+    
+    /// Collection prefix
+    pub fn prefix(&self) -> &str {
+        &self.prefix
+    }
+    
+    /// Collection name for a context level
+    pub fn collection(&self, level: ContextLevel) -> String {
+        format!("{}_{}", self.prefix, level.as_str().to_lowercase())
+    }
+}
+
+impl Default for CollectionNaming {
+    fn default() -> Self {
+        Self::new(DEFAULT_PREFIX)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::Config;
+    use crate::hirag::{ContextManager, HiRAGManager, HiRAGManagerV2};
+    use crate::test_support::{MockEmbeddingProvider, MockVectorStore};
+    use crate::vector_db::VectorDbClient;
+    use std::collections::HashMap;
+    use std::sync::Arc;
+    
+    const LEVELS: [ContextLevel; 3] = [ContextLevel::Immediate, ContextLevel::ShortTerm, ContextLevel::LongTerm];
+    
+    #[test]
+    fn test_default_matches_config_default() {
+        assert_eq!(CollectionNaming::default(), CollectionNaming::from_config(&Config::default_config().vector_db));
+    }
+    
+    #[tokio::test]
+    async fn test_client_and_managers_agree_on_names() {
+        let mut config = Config::default_config();
+        config.vector_db.collection_prefix = "tenant_a".to_string();
+        let naming = CollectionNaming::from_config(&config.vector_db);
+        
+        // Building the client doesn't connect, so no Qdrant is needed
+        let client = VectorDbClient::new(config.vector_db.clone()).await.unwrap();
+        for level in LEVELS {
+            assert_eq!(client.collection_name(level), naming.collection(level));
+        }
+        
+        let store = Arc::new(MockVectorStore::new());
+        let embedding = Arc::new(MockEmbeddingProvider::new(8));
+        let v1 = HiRAGManager::new(config.hirag.clone(), embedding.clone(), store.clone())
+            .await
+            .unwrap()
+            .with_collection_naming(naming.clone());
+        let v2 = HiRAGManagerV2::new(config.hirag.clone(), embedding, store.clone())
+            .await
+            .unwrap()
+            .with_collection_naming(naming.clone());
+        v1.initialize().await.unwrap();
+        v2.initialize().await.unwrap();
+        
+        for level in LEVELS {
+            let first = v1.store_context("from v1", level, HashMap::new()).await.unwrap();
+            let second = v2.store_context("from v2", level, HashMap::new()).await.unwrap();
+            let collection = naming.collection(level);
+            assert!(store.point(&collection, first).is_some(), "v1 missed {}", collection);
+            assert!(store.point(&collection, second).is_some(), "v2 missed {}", collection);
+        }
+    }
+}
@@ -10,6 +10,8 @@ pub struct SearchQueryBuilder {
     filter: Option<Filter>,
     with_payload: bool,
     with_vector: bool,
+    hnsw_ef: Option<usize>,
+    exact: Option<bool>,
 }
 
 impl SearchQueryBuilder {
@@ -21,6 +23,8 @@ impl SearchQueryBuilder {
             filter: None,
             with_payload: true,
             with_vector: false,
+            hnsw_ef: None,
+            exact: None,
         }
     }
     
@@ -49,6 +53,16 @@ impl SearchQueryBuilder {
         self
     }
     
+    pub fn hnsw_ef(mut self, hnsw_ef: usize) -> Self {
+        self.hnsw_ef = Some(hnsw_ef);
+        self
+    }
+    
+    pub fn exact(mut self, exact: bool) -> Self {
+        self.exact = Some(exact);
+        self
+    }
+    
     pub fn build(self) -> SearchParams {
         SearchParams {
             vector: self.vector,
@@ -57,6 +71,8 @@ impl SearchQueryBuilder {
             filter: self.filter,
             with_payload: self.with_payload,
             with_vector: self.with_vector,
+            hnsw_ef: self.hnsw_ef,
+            exact: self.exact,
         }
     }
 }
\ No newline at end of file
@@ -209,6 +209,10 @@ async fn test_hirag_manager_end_to_end() {
         filters: None,
         priority: context_manager::hirag::Priority::Normal,
         session_id: None,
+        agent_id: None,
+        sort_order: context_manager::hirag::SortOrder::Relevance,
+        rescore_metric: None,
+        include_vectors: false,
     };
 
     match manager.retrieve_context(request).await {
@@ -0,0 +1,35 @@
+//! Tests running the HiRAG manager against the in-memory test doubles
+//!
+//! Unlike the other integration tests these need no external services.
+//! Run: `cargo test --features testing --test mock_store_test`
+
+use context_manager::{
+    Config,
+    hirag::{ContextManager, ContextRequest, HiRAGManagerV2},
+    testing::{MockEmbeddingProvider, MockVectorStore},
+    vector_db::ContextLevel,
+};
+use std::collections::HashMap;
+use std::sync::Arc;
+
+#[tokio::test]
+async fn test_store_and_retrieve_without_external_services() {
+    let vector_db = Arc::new(MockVectorStore::new());
+    let embedding = Arc::new(MockEmbeddingProvider::new(1024));
+    let manager = HiRAGManagerV2::new(Config::default_config().hirag, embedding, vector_db.clone())
+        .await
+        .unwrap();
+    manager.initialize().await.unwrap();
+
+    let id = manager
+        .store_context("User prefers dark mode", ContextLevel::LongTerm, HashMap::new())
+        .await
+        .unwrap();
+    assert_eq!(vector_db.point_ids("contexts_longterm"), vec![id]);
+
+    let response = manager
+        .retrieve_context(ContextRequest::new("User prefers dark mode".to_string(), 1000))
+        .await
+        .unwrap();
+    assert!(response.contexts.iter().any(|c| c.id == id));
+}
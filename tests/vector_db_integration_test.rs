@@ -0,0 +1,204 @@
+//! Integration tests for the Qdrant-backed vector store
+//!
+//! These tests require a running Qdrant instance:
+//! - gRPC on http://localhost:6334 (used by the client)
+//! - REST on http://localhost:6333 (used for the availability probe)
+//!
+//! To run these tests:
+//! 1. Start Qdrant: `docker run -p 6333:6333 -p 6334:6334 qdrant/qdrant`
+//! 2. Run: `cargo test --test vector_db_integration_test -- --ignored`
+
+use context_manager::{
+    config::Quantization,
+    Config,
+    vector_db::{ContextLevel, Payload, SearchParams, VectorDbClient, VectorPoint, VectorStore},
+};
+use std::collections::HashMap;
+
+/// Helper to check if Qdrant is available
+async fn is_qdrant_available() -> bool {
+    reqwest::get("http://localhost:6333/healthz")
+        .await
+        .map(|r| r.status().is_success())
+        .unwrap_or(false)
+}
+
+/// Helper to create a client against a fresh, uniquely named collection
+async fn create_test_client(wait_for_indexing: bool) -> (VectorDbClient, String) {
+    let mut config = Config::default_config();
+    config.vector_db.vector_size = 4;
+    config.vector_db.wait_for_indexing = wait_for_indexing;
+
+    let client = VectorDbClient::new(config.vector_db)
+        .await
+        .expect("Failed to create vector DB client");
+
+    let collection = format!("it_{}", uuid::Uuid::new_v4().simple());
+    client
+        .create_collection(&collection)
+        .await
+        .expect("Failed to create collection");
+
+    (client, collection)
+}
+
+fn test_point(vector: Vec<f32>, text: &str) -> VectorPoint {
+    VectorPoint {
+        id: uuid::Uuid::new_v4(),
+        vector,
+        payload: Payload {
+            text: text.to_string(),
+            level: ContextLevel::ShortTerm,
+            timestamp: chrono::Utc::now().timestamp(),
+            agent_id: "integration".to_string(),
+            session_id: None,
+            access_count: 0,
+            token_count: None,
+            metadata: HashMap::new(),
+        },
+    }
+}
+
+#[tokio::test]
+#[ignore] // Requires Qdrant running
+async fn test_wait_for_indexing_makes_writes_immediately_visible() {
+    if !is_qdrant_available().await {
+        eprintln!("Skipping test: Qdrant not available at localhost:6333");
+        return;
+    }
+
+    let (client, collection) = create_test_client(true).await;
+
+    let point = test_point(vec![0.1, 0.2, 0.3, 0.4], "read your writes");
+    let id = point.id;
+    client
+        .insert_points(&collection, vec![point])
+        .await
+        .expect("Failed to insert point");
+
+    // No sleep: with wait=true the upsert has been applied before returning
+    let results = client
+        .search(&collection, SearchParams::new(vec![0.1, 0.2, 0.3, 0.4], 5))
+        .await
+        .expect("Search failed");
+    assert!(results.iter().any(|r| r.id == id));
+
+    client
+        .delete_points(&collection, vec![id])
+        .await
+        .expect("Failed to delete point");
+    assert!(client.get_point(&collection, id).await.unwrap().is_none());
+
+    client.delete_collection(&collection).await.ok();
+}
+
+#[tokio::test]
+#[ignore] // Requires Qdrant running
+async fn test_exact_and_approximate_search_agree_on_small_collection() {
+    if !is_qdrant_available().await {
+        eprintln!("Skipping test: Qdrant not available at localhost:6333");
+        return;
+    }
+
+    let (client, collection) = create_test_client(true).await;
+
+    let points: Vec<VectorPoint> = (0..20)
+        .map(|i| {
+            let x = i as f32 / 20.0;
+            test_point(vec![x, 1.0 - x, x * x, 0.5], &format!("point {}", i))
+        })
+        .collect();
+    client
+        .insert_points(&collection, points)
+        .await
+        .expect("Failed to insert points");
+
+    let query = vec![0.3, 0.7, 0.09, 0.5];
+    let exact = client
+        .search(&collection, SearchParams::new(query.clone(), 5).with_exact(true))
+        .await
+        .expect("Exact search failed");
+    let approximate = client
+        .search(&collection, SearchParams::new(query, 5).with_exact(false).with_hnsw_ef(16))
+        .await
+        .expect("Approximate search failed");
+
+    // A collection this small is well within HNSW's recall, so both agree
+    assert_eq!(exact.len(), 5);
+    let exact_ids: Vec<_> = exact.iter().map(|r| r.id).collect();
+    let approximate_ids: Vec<_> = approximate.iter().map(|r| r.id).collect();
+    assert_eq!(exact_ids, approximate_ids);
+
+    client.delete_collection(&collection).await.ok();
+}
+
+#[tokio::test]
+#[ignore] // Requires Qdrant running
+async fn test_insert_immediately_after_create_is_retried_until_ready() {
+    if !is_qdrant_available().await {
+        eprintln!("Skipping test: Qdrant not available at localhost:6333");
+        return;
+    }
+
+    // No sleep between create and insert: a collection that is still
+    // initializing is retried rather than reported as a failure
+    let (client, collection) = create_test_client(true).await;
+
+    let point = test_point(vec![0.4, 0.3, 0.2, 0.1], "written right after create");
+    let id = point.id;
+    client
+        .insert_points(&collection, vec![point])
+        .await
+        .expect("Insert right after create failed");
+
+    let results = client
+        .search(&collection, SearchParams::new(vec![0.4, 0.3, 0.2, 0.1], 1))
+        .await
+        .expect("Search right after create failed");
+    assert_eq!(results.first().map(|r| r.id), Some(id));
+
+    client.delete_collection(&collection).await.ok();
+}
+
+#[tokio::test]
+#[ignore] // Requires Qdrant running
+async fn test_quantized_collection_still_finds_nearest_vectors() {
+    if !is_qdrant_available().await {
+        eprintln!("Skipping test: Qdrant not available at localhost:6333");
+        return;
+    }
+
+    let mut config = Config::default_config();
+    config.vector_db.vector_size = 4;
+    config.vector_db.wait_for_indexing = true;
+    config.vector_db.quantization = Some(Quantization::Scalar { quantile: Some(0.99) });
+    let client = VectorDbClient::new(config.vector_db)
+        .await
+        .expect("Failed to create vector DB client");
+
+    let collection = format!("it_{}", uuid::Uuid::new_v4().simple());
+    client
+        .create_collection(&collection)
+        .await
+        .expect("Failed to create quantized collection");
+
+    let points = vec![
+        test_point(vec![1.0, 0.0, 0.0, 0.0], "x"),
+        test_point(vec![0.0, 1.0, 0.0, 0.0], "y"),
+        test_point(vec![0.0, 0.0, 1.0, 0.0], "z"),
+        test_point(vec![0.7, 0.7, 0.0, 0.0], "xy"),
+    ];
+    let expected: Vec<_> = [0, 3].iter().map(|&i| points[i].id).collect();
+    client
+        .insert_points(&collection, points)
+        .await
+        .expect("Failed to insert points");
+
+    let results = client
+        .search(&collection, SearchParams::new(vec![0.9, 0.3, 0.0, 0.0], 2))
+        .await
+        .expect("Search failed");
+    assert_eq!(results.iter().map(|r| r.id).collect::<Vec<_>>(), expected);
+
+    client.delete_collection(&collection).await.ok();
+}